@@ -1,6 +1,6 @@
 use node_template_runtime::{
-    AccountId, AuraConfig, BalancesConfig, GenesisConfig, GrandpaConfig, Signature, SudoConfig,
-    SystemConfig, WASM_BINARY,
+    AccountId, AuraConfig, BalancesConfig, GenesisConfig, GrandpaConfig, KittiesConfig, Signature,
+    SudoConfig, SystemConfig, WASM_BINARY,
 };
 use sc_service::ChainType;
 use sp_consensus_aura::sr25519::AuthorityId as AuraId;
@@ -160,5 +160,10 @@ fn testnet_genesis(
             // Assign network admin rights.
             key: root_key,
         }),
+        pallet_kitties: Some(KittiesConfig {
+            deterministic_seed: 0,
+            // No founder kitties; add (owner, DNA, price) entries for chains that want them.
+            genesis_kitties: vec![],
+        }),
     }
 }
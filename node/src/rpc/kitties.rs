@@ -0,0 +1,93 @@
+//! RPC methods of the kitties pallet, answered through its runtime API.
+
+use std::sync::Arc;
+
+use jsonrpc_core::{Error as RpcError, ErrorCode, Result};
+use jsonrpc_derive::rpc;
+use node_template_runtime::{opaque::Block, AccountId, Balance, BlockNumber};
+use pallet_kitties_runtime_api::KittiesApi as KittiesRuntimeApi;
+use serde::{Deserialize, Serialize};
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_core::Bytes;
+use sp_runtime::{generic::BlockId, traits::Block as BlockT};
+
+/// Most kitties returned by one `kitties_getKittiesOfOwner` call, whatever the limit asked for.
+pub const MAX_KITTIES_PER_CALL: u32 = 1_000;
+
+/// Error code of a failed runtime API call.
+const RUNTIME_ERROR: i64 = 1;
+
+/// Kitty of an owner, with its DNA hex-encoded.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OwnedKitty {
+    /// Kitty id.
+    pub id: u32,
+    /// DNA of the kitty.
+    pub dna: Bytes,
+}
+
+/// Kitty queries, at the best block unless `at` is given.
+#[rpc(server)]
+pub trait KittiesApi<BlockHash> {
+    /// Up to `limit` kitties of `owner`, by id.
+    #[rpc(name = "kitties_getKittiesOfOwner")]
+    fn kitties_of_owner(&self, owner: AccountId, limit: u32, at: Option<BlockHash>) -> Result<Vec<OwnedKitty>>;
+
+    /// Current owner of a kitty, `null` if there is no such kitty.
+    #[rpc(name = "kitties_getOwner")]
+    fn owner(&self, kitty_id: u32, at: Option<BlockHash>) -> Result<Option<AccountId>>;
+}
+
+/// Serves `KittiesApi` from the runtime API of a client.
+pub struct Kitties<C> {
+    client: Arc<C>,
+}
+
+impl<C> Kitties<C> {
+    /// Create a new `Kitties` over `client`.
+    pub fn new(client: Arc<C>) -> Self {
+        Kitties { client }
+    }
+}
+
+impl<C> KittiesApi<<Block as BlockT>::Hash> for Kitties<C>
+where
+    C: ProvideRuntimeApi<Block> + HeaderBackend<Block> + Send + Sync + 'static,
+    C::Api: KittiesRuntimeApi<Block, AccountId, u32, Balance, BlockNumber>,
+{
+    fn kitties_of_owner(
+        &self,
+        owner: AccountId,
+        limit: u32,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<Vec<OwnedKitty>> {
+        let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+        let kitties = self
+            .client
+            .runtime_api()
+            .kitties_of(&at, owner, limit.min(MAX_KITTIES_PER_CALL))
+            .map_err(|e| runtime_error("Unable to query the kitties of the owner.", e))?;
+        Ok(kitties
+            .into_iter()
+            .map(|(id, dna)| OwnedKitty { id, dna: dna.to_vec().into() })
+            .collect())
+    }
+
+    fn owner(&self, kitty_id: u32, at: Option<<Block as BlockT>::Hash>) -> Result<Option<AccountId>> {
+        let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+        self.client
+            .runtime_api()
+            .owner_of(&at, kitty_id)
+            .map_err(|e| runtime_error("Unable to query the owner of the kitty.", e))
+    }
+}
+
+fn runtime_error(message: &str, error: impl std::fmt::Debug) -> RpcError {
+    RpcError {
+        code: ErrorCode::ServerError(RUNTIME_ERROR),
+        message: message.into(),
+        data: Some(format!("{:?}", error).into()),
+    }
+}
@@ -0,0 +1,46 @@
+//! Runtime API of the kitties pallet, for clients that need derived pallet state.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::Codec;
+use pallet_kitties::{ActivityKind, CostBreakdown, GeneBuckets, KittyCallKind, KittyTrait, RarityTier, TaxReport};
+use sp_runtime::Permill;
+use sp_std::vec::Vec;
+
+sp_api::decl_runtime_apis! {
+	pub trait KittiesApi<AccountId, KittyIndex, Balance, BlockNumber> where
+		AccountId: Codec,
+		KittyIndex: Codec,
+		Balance: Codec,
+		BlockNumber: Codec,
+	{
+		/// Kitty counts per value bucket of each DNA byte position, see `GenePool`.
+		fn gene_scarcity() -> Vec<GeneBuckets>;
+		/// Number of successful calls per kind of activity, see `ActivityCounters`.
+		fn activity_counters() -> Vec<(ActivityKind, u64)>;
+		/// Pallet-level charges of a call, without the transaction fee. `None` if a bought
+		/// kitty isn't listed.
+		fn operation_cost(call_kind: KittyCallKind<KittyIndex>) -> Option<CostBreakdown<Balance>>;
+		/// Acquisitions and disposals of an account in blocks `from..=to`, with totals. See
+		/// `TaxReport::truncated_until` for events lost to the bounded history.
+		fn account_tax_report(
+			account: AccountId,
+			from: BlockNumber,
+			to: BlockNumber,
+		) -> TaxReport<AccountId, KittyIndex, Balance, BlockNumber>;
+		/// Chance of each rarity tier for a kitten of parents with the given DNA, against the
+		/// current gene pool and mutation chance. Computed analytically, there is no sampling.
+		fn offspring_rarity_distribution(first: [u8; 16], second: [u8; 16]) -> Vec<(RarityTier, Permill)>;
+		/// Traits of a kitty as numbers, one per DNA position. `None` if there is no such kitty.
+		fn kitty_traits(kitty_id: KittyIndex) -> Option<Vec<KittyTrait>>;
+		/// Number of kitties in existence, see `MaxKittySupply`.
+		fn total_kitties() -> u32;
+		/// Price a kitty on Dutch auction can be bought at in the current block. `None` if the
+		/// kitty isn't on Dutch auction.
+		fn current_dutch_price(kitty_id: KittyIndex) -> Option<Balance>;
+		/// Up to `limit` kitties of `owner` with their DNA, by id.
+		fn kitties_of(owner: AccountId, limit: u32) -> Vec<(KittyIndex, [u8; 16])>;
+		/// Current owner of a kitty. `None` if there is no such kitty.
+		fn owner_of(kitty_id: KittyIndex) -> Option<AccountId>;
+	}
+}
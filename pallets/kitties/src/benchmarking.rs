@@ -0,0 +1,108 @@
+//! Benchmarks of the kitty calls, each set up for its most expensive path.
+
+use super::*;
+
+use frame_benchmarking::{account, benchmarks};
+use frame_system::RawOrigin;
+// `benchmarks!` of frame-benchmarking 2.0 bounds the runtime by a pallet trait named `Trait`
+use crate::Config as Trait;
+
+const SEED: u32 = 0;
+
+/// An account with funds for every fee and deposit of the benchmarked calls.
+fn funded_account<T: Config>(name: &'static str) -> T::AccountId {
+	let who = account(name, 0, SEED);
+	T::Currency::make_free_balance_be(&who, BalanceOf::<T>::max_value() / 4u32.into());
+	who
+}
+
+/// Age every kitty minted so far past `MaturityPeriod`.
+fn grow_up<T: Config>() {
+	let now = <frame_system::Module<T>>::block_number();
+	<frame_system::Module<T>>::set_block_number(now.saturating_add(T::MaturityPeriod::get()));
+}
+
+benchmarks! {
+	_ { }
+
+	create {
+		let caller = funded_account::<T>("caller");
+	}: _(RawOrigin::Signed(caller.clone()))
+	verify {
+		assert_eq!(Module::<T>::owned_kitties_count(&caller), 1);
+	}
+
+	// with a preferred gender, which is charged for and rolled for
+	breed {
+		let caller = funded_account::<T>("caller");
+		let father = Module::<T>::do_mint(&caller, Kitty([0; 16]), None)?;
+		let mother = Module::<T>::do_mint(&caller, Kitty([1; 16]), None)?;
+		grow_up::<T>();
+	}: _(RawOrigin::Signed(caller), father, mother, Some(KittyGender::Female))
+	verify {
+		assert_eq!(Module::<T>::sire_stats(father).offspring, 1);
+	}
+
+	// of a listed kitty, which is delisted
+	transfer {
+		let caller = funded_account::<T>("caller");
+		let recipient = funded_account::<T>("recipient");
+		let kitty_id = Module::<T>::do_mint(&caller, Kitty([0; 16]), None)?;
+		Module::<T>::insert_listing(&caller, kitty_id, 1_000_000u32.into(), None, None)?;
+	}: _(RawOrigin::Signed(caller), kitty_id, recipient.clone())
+	verify {
+		assert_eq!(Module::<T>::owner_of(kitty_id), Some(recipient));
+	}
+
+	// a new listing that expires
+	set_price {
+		let caller = funded_account::<T>("caller");
+		let kitty_id = Module::<T>::do_mint(&caller, Kitty([0; 16]), None)?;
+		grow_up::<T>();
+		let price: BalanceOf<T> = 1_000_000u32.into();
+		let expires_at = <frame_system::Module<T>>::block_number().saturating_add(100u32.into());
+	}: _(RawOrigin::Signed(caller), kitty_id, Some(price), Some(expires_at), None)
+	verify {
+		assert!(Module::<T>::listing(kitty_id).is_some());
+	}
+
+	// from a seller who didn't create the kitty, so a royalty is paid
+	buy {
+		let creator = funded_account::<T>("creator");
+		let seller = funded_account::<T>("seller");
+		let caller = funded_account::<T>("caller");
+		let kitty_id = Module::<T>::do_mint(&creator, Kitty([0; 16]), None)?;
+		Module::<T>::do_transfer(&creator, &seller, kitty_id)?;
+		let price: BalanceOf<T> = 1_000_000u32.into();
+		Module::<T>::insert_listing(&seller, kitty_id, price, None, None)?;
+	}: _(RawOrigin::Signed(caller.clone()), kitty_id, price, None, None)
+	verify {
+		assert_eq!(Module::<T>::owner_of(kitty_id), Some(caller));
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::tests::{new_test_ext, Test};
+	use frame_support::assert_ok;
+
+	#[test]
+	fn benchmarks_run_on_the_mock() {
+		new_test_ext().execute_with(|| {
+			assert_ok!(test_benchmark_create::<Test>());
+		});
+		new_test_ext().execute_with(|| {
+			assert_ok!(test_benchmark_breed::<Test>());
+		});
+		new_test_ext().execute_with(|| {
+			assert_ok!(test_benchmark_transfer::<Test>());
+		});
+		new_test_ext().execute_with(|| {
+			assert_ok!(test_benchmark_set_price::<Test>());
+		});
+		new_test_ext().execute_with(|| {
+			assert_ok!(test_benchmark_buy::<Test>());
+		});
+	}
+}
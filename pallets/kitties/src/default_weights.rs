@@ -0,0 +1,32 @@
+//! Default weights of the kitty calls, for runtimes that don't benchmark them. Regenerate the
+//! runtime weights with the `benchmark` subcommand of the node instead of editing these.
+
+use frame_support::weights::{constants::RocksDbWeight as DbWeight, Weight};
+
+impl crate::WeightInfo for () {
+	fn create() -> Weight {
+		(96_000_000 as Weight)
+			.saturating_add(DbWeight::get().reads(14 as Weight))
+			.saturating_add(DbWeight::get().writes(15 as Weight))
+	}
+	fn breed() -> Weight {
+		(164_000_000 as Weight)
+			.saturating_add(DbWeight::get().reads(27 as Weight))
+			.saturating_add(DbWeight::get().writes(20 as Weight))
+	}
+	fn transfer() -> Weight {
+		(72_000_000 as Weight)
+			.saturating_add(DbWeight::get().reads(11 as Weight))
+			.saturating_add(DbWeight::get().writes(12 as Weight))
+	}
+	fn set_price() -> Weight {
+		(41_000_000 as Weight)
+			.saturating_add(DbWeight::get().reads(6 as Weight))
+			.saturating_add(DbWeight::get().writes(5 as Weight))
+	}
+	fn buy() -> Weight {
+		(124_000_000 as Weight)
+			.saturating_add(DbWeight::get().reads(18 as Weight))
+			.saturating_add(DbWeight::get().writes(16 as Weight))
+	}
+}
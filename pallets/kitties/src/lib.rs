@@ -1,26 +1,159 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
-use codec::{Decode, Encode};
+use codec::{Decode, Encode, EncodeLike, Error as CodecError, Input, Output};
 use frame_support::{
-	decl_error, decl_event, decl_module, decl_storage,
-	dispatch::DispatchResult,
+	debug, decl_error, decl_event, decl_module, decl_storage,
+	dispatch::{DispatchResult, DispatchResultWithPostInfo},
 	ensure,
-	traits::{Currency, Randomness, ExistenceRequirement},
-	RuntimeDebug, StorageDoubleMap,
+	storage::StoragePrefixedMap,
+	traits::{
+		BalanceStatus, Contains, Currency, EnsureOrigin, ExistenceRequirement, Get, Imbalance, OnNewAccount,
+		OnUnbalanced, Randomness, ReservableCurrency, WithdrawReason,
+	},
+	weights::{Weight, WithPostDispatchInfo},
+	IterableStorageDoubleMap, ReversibleStorageHasher, RuntimeDebug, StorageDoubleMap, StorageMap, Twox64Concat,
+};
+use frame_system::{
+	ensure_none, ensure_signed,
+	offchain::{SendTransactionTypes, SubmitTransaction},
 };
-use frame_system::ensure_signed;
 use sp_io::hashing::blake2_128;
+use sp_runtime::{
+	traits::{AccountIdConversion, AtLeast32BitUnsigned, Bounded, Hash as HashT, One, Saturating, UniqueSaturatedInto, Zero},
+	transaction_validity::{
+		InvalidTransaction, TransactionPriority, TransactionSource, TransactionValidity, ValidTransaction,
+	},
+	DispatchError, ModuleId, Perbill, Permill,
+};
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "std")]
+use sp_runtime::SaturatedConversion;
 
-use sp_std::vec::Vec;
+use sp_std::{marker::PhantomData, vec, vec::Vec};
 use orml_utilities::with_transaction_result;
 use orml_nft::Module as NftModule;
+use orml_traits::MultiCurrency;
 
+pub mod migrations;
+mod default_weights;
+#[cfg(feature = "runtime-benchmarks")]
+mod benchmarking;
 #[cfg(test)]
 mod tests;
 
 #[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
 pub struct Kitty([u8; 16]);
 
+/// Randomness that is only safe to use once it couldn't have been known when the outcome was
+/// committed to, such as BABE epoch randomness.
+pub trait KnownSinceRandomness<Output, BlockNumber> {
+	/// Random value for `subject` and the first block it could be known in.
+	fn random(subject: &[u8]) -> (Output, BlockNumber);
+}
+
+/// Serves a plain `Randomness` source, such as collective flip, as known since the current
+/// block.
+pub struct ImmediateRandomness<T, R>(PhantomData<(T, R)>);
+
+impl<T: frame_system::Trait, R: Randomness<T::Hash>> KnownSinceRandomness<T::Hash, T::BlockNumber>
+	for ImmediateRandomness<T, R>
+{
+	fn random(subject: &[u8]) -> (T::Hash, T::BlockNumber) {
+		(R::random(subject), <frame_system::Module<T>>::block_number())
+	}
+}
+
+/// Source of kitty prices for features that value kitties, such as fees or collateral.
+pub trait KittyPriceOracle<Balance> {
+	/// Price the cheapest kitties can be expected to sell for.
+	fn floor_price() -> Option<Balance>;
+	/// Expected sale price of `kitty`.
+	fn estimate(kitty: &Kitty) -> Option<Balance>;
+}
+
+/// Callbacks on the lifecycle of kitties, for other pallets to react to. An error vetoes the
+/// mint, transfer or burn it was called for, which is then reverted. Hooks run under the
+/// `ExecutionLock`, so they can't move kitties or funds through the pallet themselves.
+pub trait KittyLifecycleHooks<AccountId, KittyId> {
+	fn on_minted(_owner: &AccountId, _kitty_id: KittyId, _kitty: &Kitty) -> DispatchResult {
+		Ok(())
+	}
+
+	fn on_transferred(_from: &AccountId, _to: &AccountId, _kitty_id: KittyId) -> DispatchResult {
+		Ok(())
+	}
+
+	fn on_burned(_owner: &AccountId, _kitty_id: KittyId) -> DispatchResult {
+		Ok(())
+	}
+}
+
+impl<AccountId, KittyId> KittyLifecycleHooks<AccountId, KittyId> for () {}
+
+/// Kitties as plain NFTs, for pallets that hold or move them without knowing what a kitty is,
+/// such as an escrow or a bridge. Goes through the same helpers as the extrinsics, so listings,
+/// counters, locks and hooks are kept the same way.
+pub trait KittyProvider<AccountId> {
+	type KittyId;
+
+	fn owner(kitty_id: Self::KittyId) -> Option<AccountId>;
+	/// Move a kitty to `dest` on behalf of its owner.
+	fn transfer(kitty_id: Self::KittyId, dest: &AccountId) -> DispatchResult;
+	/// Mint `kitty` to `dest`, free of the creation fee and deposit. Fails with `WrongDNA` if a
+	/// kitty of the same DNA exists.
+	fn mint_into(dest: &AccountId, kitty: Kitty) -> Result<Self::KittyId, DispatchError>;
+	/// Burn a kitty on behalf of its owner.
+	fn burn(kitty_id: Self::KittyId) -> DispatchResult;
+}
+
+/// Identifies who holds a lock on a kitty, in the way of `LockIdentifier`.
+pub type LockReason = [u8; 8];
+
+/// Locking of kitties by other pallets, e.g. as collateral. A locked kitty can't be moved,
+/// listed or sold, nor bred if `LocksBlockBreeding` says so.
+///
+/// A kitty holds one lock at a time: locking a locked kitty fails, even for the same reason,
+/// so a holder never has its lock released by someone else's `unlock`.
+pub trait LockableKitties<AccountId> {
+	type KittyId;
+
+	/// Owner of `kitty_id`, whom the lock holds the kitty for.
+	fn owner_of(kitty_id: Self::KittyId) -> Option<AccountId>;
+	/// Lock an unlocked kitty for `reason`.
+	fn lock(kitty_id: Self::KittyId, reason: LockReason) -> DispatchResult;
+	/// Release the lock of `kitty_id` taken for `reason`.
+	fn unlock(kitty_id: Self::KittyId, reason: LockReason) -> DispatchResult;
+	fn is_locked(kitty_id: Self::KittyId) -> bool;
+}
+
+/// Prices kitties from the latest `SALE_PRICE_WINDOW` sales on chain: the floor is the
+/// median of all of them, an estimate the median of the sales of the same `RarityTier`.
+pub struct SaleMedianOracle<T, I = DefaultInstance>(PhantomData<(T, I)>);
+
+impl<T: Config<I>, I: Instance> KittyPriceOracle<BalanceOf<T, I>> for SaleMedianOracle<T, I> {
+	fn floor_price() -> Option<BalanceOf<T, I>> {
+		median(Module::<T, I>::recent_sales().into_iter().map(|(_, price)| price).collect())
+	}
+
+	fn estimate(kitty: &Kitty) -> Option<BalanceOf<T, I>> {
+		let tier = RarityTier::from_scarce_genes(Module::<T, I>::scarce_genes(kitty));
+		median(Module::<T, I>::recent_sales().into_iter().filter(|(sold, _)| *sold == tier).map(|(_, price)| price).collect())
+	}
+}
+
+/// Median of `values`, the mean of the middle two rounded down for an even count.
+pub fn median<B: AtLeast32BitUnsigned + Copy>(mut values: Vec<B>) -> Option<B> {
+	values.sort();
+	let middle = values.len() / 2;
+	let upper = *values.get(middle)?;
+	if values.len() % 2 == 1 {
+		return Some(upper);
+	}
+	let lower = values[middle - 1];
+	Some(lower + (upper - lower) / B::from(2u32))
+}
+
 #[derive(Encode, Decode, Clone, Copy, RuntimeDebug, PartialEq, Eq)]
 pub enum KittyGender {
 	Male,
@@ -41,180 +174,5235 @@ impl Kitty {
 	}
 }
 
+/// A kitty as stored in the `orml_nft` token data. Kitties written before `V2` are `V1`; they
+/// are rewritten as `V2` when moved or by `upgrade_kitties`. Only used as token data, other
+/// code goes through `kitty()`.
+#[derive(Clone, PartialEq, Eq, RuntimeDebug)]
+pub enum VersionedKitty {
+	V1([u8; 16]),
+	V2 {
+		dna: [u8; 16],
+		/// Block the kitty was minted in.
+		birth: u64,
+		/// Reserved, zero.
+		flags: u8,
+	},
+}
+
+/// Leading byte of an encoded `VersionedKitty::V1`. Kitties stored as bare DNA, before the
+/// tag existed, get it from `migrations::tag_v1_kitties`.
+const KITTY_V1_TAG: u8 = 1;
+/// Leading byte of an encoded `VersionedKitty::V2`.
+const KITTY_V2_TAG: u8 = 2;
+
+impl VersionedKitty {
+	pub fn kitty(&self) -> Kitty {
+		match self {
+			VersionedKitty::V1(dna) | VersionedKitty::V2 { dna, .. } => Kitty(*dna),
+		}
+	}
+
+	pub fn is_v1(&self) -> bool {
+		matches!(self, VersionedKitty::V1(_))
+	}
+}
+
+impl Encode for VersionedKitty {
+	fn encode_to<W: Output>(&self, dest: &mut W) {
+		match self {
+			VersionedKitty::V1(dna) => {
+				dest.push_byte(KITTY_V1_TAG);
+				dna.encode_to(dest);
+			}
+			VersionedKitty::V2 { dna, birth, flags } => {
+				dest.push_byte(KITTY_V2_TAG);
+				(dna, birth, flags).encode_to(dest);
+			}
+		}
+	}
+}
+
+impl EncodeLike for VersionedKitty {}
+
+impl Decode for VersionedKitty {
+	fn decode<I: Input>(input: &mut I) -> Result<Self, CodecError> {
+		match input.read_byte()? {
+			KITTY_V1_TAG => Ok(VersionedKitty::V1(Decode::decode(input)?)),
+			KITTY_V2_TAG => {
+				let (dna, birth, flags) = Decode::decode(input)?;
+				Ok(VersionedKitty::V2 { dna, birth, flags })
+			}
+			_ => Err("unknown kitty version".into()),
+		}
+	}
+}
+
+/// A price as returned to UIs: the raw integer as a string, so it survives JSON number
+/// precision, together with what is needed to display it.
+#[cfg(feature = "std")]
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct PriceDisplay {
+	pub raw: String,
+	pub decimals: u8,
+	pub symbol: String,
+}
+
+/// A kitty for sale. Only valid while `seller` still owns the kitty.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct Listing<AccountId, Balance, BlockNumber, AssetId> {
+	pub seller: AccountId,
+	pub price: Balance,
+	/// Last block the kitty can be bought in, if any.
+	pub expires_at: Option<BlockNumber>,
+	/// Reserved from `seller` until the listing is removed.
+	pub deposit: Balance,
+	/// Asset `price` is in, `None` for `Currency`.
+	pub asset: Option<AssetId>,
+}
+
+pub type ListingOf<T, I = DefaultInstance> = Listing<
+	<T as frame_system::Trait>::AccountId,
+	BalanceOf<T, I>,
+	<T as frame_system::Trait>::BlockNumber,
+	AssetIdOf<T, I>,
+>;
+
+/// Maximum number of expired listings removed, plus blocks checked for them, in one
+/// `on_initialize`.
+pub const LISTING_SWEEP_LIMIT: u32 = 50;
+
+/// Maximum number of `expire_listing` calls the off-chain worker submits per block.
+pub const OFFCHAIN_EXPIRE_LIMIT: usize = 20;
+
+/// Blocks an unsigned `expire_listing` stays valid in the pool.
+const EXPIRE_LISTING_LONGEVITY: u64 = 5;
+
+/// Kitty sent by `sender` and held by its gift account until `recipient` claims it.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct PendingGift<AccountId, BlockNumber> {
+	pub sender: AccountId,
+	pub recipient: AccountId,
+	/// Last block the gift can be claimed in.
+	pub expiry: BlockNumber,
+}
+
+/// Offer to buy a kitty at `amount`, reserved from the bidder until the owner accepts it or it
+/// is cancelled.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct Offer<Balance, BlockNumber> {
+	pub amount: Balance,
+	/// Last block the offer can be accepted in.
+	pub expiry: BlockNumber,
+	/// Never reused, so an acceptance can't match an offer made after it was sent.
+	pub nonce: u64,
+}
+
+/// Maximum number of auctions ending in one block, queued for settlement in its `on_finalize`.
+pub const MAX_AUCTIONS_PER_BLOCK: usize = 16;
+
+/// English auction of a kitty, locked with its seller until the auction is settled. Once
+/// `end_block` is over the auction waits in the `SettlementQueue`, see `AuctionStatus`.
+/// The best bid is reserved from its bidder.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct Auction<AccountId, Balance, BlockNumber> {
+	pub seller: AccountId,
+	/// Lowest bid accepted.
+	pub reserve_price: Balance,
+	/// Last block bids are accepted in.
+	pub end_block: BlockNumber,
+	/// Best bidder and bid.
+	pub best_bid: Option<(AccountId, Balance)>,
+}
+
+/// Stage of an auction still in `Auctions`. A settled auction is removed.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub enum AuctionStatus {
+	/// Taking bids, up to and including its end block.
+	Open,
+	/// Over and taking no more bids, queued for settlement.
+	AwaitingSettlement,
+}
+
+/// Maximum number of rentals ending in one block, returned in its `on_initialize`.
+pub const MAX_RENTALS_PER_BLOCK: usize = 16;
+
+/// Terms a kitty can be rented on, see `list_for_rent`. Durations are in `RentalPeriod`s.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct RentalTerms<Balance> {
+	pub price_per_period: Balance,
+	pub max_duration: u32,
+}
+
+/// Kitty leased to `renter`, who can breed it until `ends_at`. Its owner can't move it meanwhile.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct Rental<AccountId, BlockNumber> {
+	pub renter: AccountId,
+	/// First block the kitty is back with its owner.
+	pub ends_at: BlockNumber,
+}
+
+/// Fee of a streamed rental, reserved on the renter and earned by the owner block by block.
+/// See `Module::accrued_rent`.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct RentEscrow<AccountId, Balance, BlockNumber> {
+	pub owner: AccountId,
+	pub renter: AccountId,
+	/// The whole fee, reserved when the rental started.
+	pub deposit: Balance,
+	/// Part of `deposit` the owner was paid so far.
+	pub claimed: Balance,
+	pub started_at: BlockNumber,
+	pub ends_at: BlockNumber,
+}
+
+pub type RentEscrowOf<T, I = DefaultInstance> =
+	RentEscrow<<T as frame_system::Trait>::AccountId, BalanceOf<T, I>, <T as frame_system::Trait>::BlockNumber>;
+
+/// Listing whose price falls linearly from `start_price` to `end_price` over `duration`
+/// blocks, then stays at `end_price` until the kitty is bought or the auction cancelled.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct DutchAuction<AccountId, Balance, BlockNumber> {
+	pub seller: AccountId,
+	pub start_price: Balance,
+	pub end_price: Balance,
+	pub start_block: BlockNumber,
+	pub duration: BlockNumber,
+}
+
+/// Two-sided quote of a market maker on a kitty it doesn't own. The bid is reserved from the
+/// quoter and can be hit by the owner; the ask becomes a listing once the quoter owns the kitty.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct Quote<Balance, BlockNumber> {
+	pub bid: Balance,
+	pub ask: Balance,
+	/// Last block the quote can be acted upon.
+	pub expiry: BlockNumber,
+	/// Unique per posted quote, so a retried `hit_bid` can't match a replaced quote.
+	pub nonce: u64,
+	/// The bid is held in the quoter's bidding balance instead of being reserved.
+	pub from_wallet: bool,
+}
+
+/// Funds an account moved to the pallet account to bid from, see `deposit_bidding_funds`.
+#[derive(Encode, Decode, Clone, Default, PartialEq, Eq, RuntimeDebug)]
+pub struct BiddingBalance<Balance> {
+	/// Available for new bids and withdrawal.
+	pub free: Balance,
+	/// Held by open bids.
+	pub encumbered: Balance,
+}
+
+/// Request to burn a kitty in exchange for a physical item, pending fulfillment.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct Redemption<AccountId, Hash, BlockNumber> {
+	pub owner: AccountId,
+	/// Commitment to the off-chain shipping details.
+	pub shipping_commitment: Hash,
+	/// Last block the request can be confirmed; the kitty unlocks afterwards.
+	pub expires_at: BlockNumber,
+}
+
+/// Kitty holder poll. A voter's power is the number of kitties it owns that didn't vote in
+/// the poll yet, so kitties handed to another account after voting can't vote again.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct Poll<Hash, BlockNumber> {
+	pub description_hash: Hash,
+	/// Last block votes are accepted.
+	pub end_block: BlockNumber,
+	/// Voting power per option.
+	pub tally: Vec<u32>,
+	pub closed: bool,
+}
+
+pub type PollId = u32;
+
+/// How `trigger_switch` prices the kitties of a dormant owner.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub enum PriceStrategy<Balance> {
+	/// The price the kitty last sold for. Kitties never sold stay unlisted.
+	LastSale,
+	/// The same price for every kitty.
+	Fixed(Balance),
+	/// The base price times one plus the number of DNA positions where the kitty's gene sits
+	/// in a below-average `GenePool` bucket.
+	Rarity(Balance),
+}
+
+/// Auto-listing of an owner's kitties after a period of inactivity, see `trigger_switch`.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct DeadMansSwitch<AccountId, Balance, BlockNumber> {
+	pub inactivity_blocks: BlockNumber,
+	/// Receives the proceeds of sales once the switch triggered.
+	pub beneficiary: AccountId,
+	pub price_strategy: PriceStrategy<Balance>,
+	/// Last block the owner called the pallet.
+	pub last_active: BlockNumber,
+}
+
+/// Pending two-phase creation, see `commit_create`.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct CreateCommitment<Hash, Balance, BlockNumber> {
+	/// Hash of the committer and the salt to be revealed.
+	pub commitment: Hash,
+	pub committed_at: BlockNumber,
+	/// Reserved at commit, charged on reveal.
+	pub fee: Balance,
+}
+
+/// Blocks `[start, end)` in which `create` waives the creation fee, within the quotas.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct PromoWindow<BlockNumber> {
+	pub start: BlockNumber,
+	pub end: BlockNumber,
+	pub max_per_account: u32,
+	pub max_total: u32,
+	/// Free mints made in the window so far.
+	pub minted: u32,
+}
+
+/// Kind of user activity counted in `ActivityCounters`.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub enum ActivityKind {
+	Creates,
+	Breeds,
+	Transfers,
+	Sales,
+	/// Only counted when `VerboseMetrics` is on.
+	FailedBuys,
+}
+
+impl ActivityKind {
+	pub const ALL: [ActivityKind; 5] = [
+		ActivityKind::Creates,
+		ActivityKind::Breeds,
+		ActivityKind::Transfers,
+		ActivityKind::Sales,
+		ActivityKind::FailedBuys,
+	];
+}
+
+/// Call whose pallet-level cost is estimated by `Module::operation_cost`.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub enum KittyCallKind<KittyIndex> {
+	Create,
+	CommitCreate,
+	Breed { gender_preference: bool },
+	/// Buy of a kitty at its current listed price.
+	Buy { kitty_id: KittyIndex },
+}
+
+/// Pallet-level charges of a call, without the transaction fee.
+#[derive(Encode, Decode, Clone, Default, PartialEq, Eq, RuntimeDebug)]
+pub struct CostBreakdown<Balance> {
+	/// Withdrawn from the caller and handed to `FeeHandler`.
+	pub fee: Balance,
+	/// Reserved from the caller.
+	pub reserved: Balance,
+	/// Paid by the caller to the seller.
+	pub price: Balance,
+	/// Charged to the seller out of `price`.
+	pub market_fee: Balance,
+	/// Paid by the seller to the creator out of `price`.
+	pub royalty: Balance,
+}
+
+/// How an account acquired or disposed of a kitty. Prices are gross, before the marketplace
+/// fee and royalty.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub enum TaxEventKind<AccountId, Balance> {
+	Minted,
+	Bought { seller: AccountId, price: Balance },
+	GiftReceived { from: AccountId },
+	Sold { buyer: AccountId, price: Balance },
+	GiftSent { to: AccountId },
+	/// Swapped in a bundle trade, see `TradeProposal`.
+	TradeReceived { from: AccountId },
+	TradeSent { to: AccountId },
+	Burned,
+}
+
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct TaxEvent<AccountId, KittyIndex, Balance, BlockNumber> {
+	pub block: BlockNumber,
+	pub kitty_id: KittyIndex,
+	pub kind: TaxEventKind<AccountId, Balance>,
+}
+
+/// Acquisitions and disposals of an account over a block range, see
+/// `Module::account_tax_report`.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct TaxReport<AccountId, KittyIndex, Balance, BlockNumber> {
+	pub events: Vec<TaxEvent<AccountId, KittyIndex, Balance, BlockNumber>>,
+	pub acquisitions: u32,
+	pub disposals: u32,
+	/// Sum of the purchase prices.
+	pub acquisition_cost: Balance,
+	/// Sum of the sale prices.
+	pub disposal_proceeds: Balance,
+	/// Events up to and including this block may be missing from the report because the
+	/// account's history outgrew `MAX_TAX_HISTORY`.
+	pub truncated_until: Option<BlockNumber>,
+}
+
+/// Why the pallet moved funds, see `Module::record_flow`.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub enum FlowKind {
+	/// Buyer paying a seller.
+	Sale,
+	/// Fee charged by the pallet, handed to `FeeHandler`.
+	Fee,
+	/// Pallet account paying out the refunds ledger.
+	Refund,
+	/// Pallet account paying a breeding bonus.
+	Bonus,
+	/// Seller paying the creator of the kitty.
+	Royalty,
+	/// Account funding or withdrawing its bidding balance.
+	Bidding,
+	/// Breeder paying the owner of a stud.
+	StudFee,
+	/// Renter paying the owner of a kitty.
+	Rent,
+}
+
+/// How many distinct partners are remembered per kitty when counting `BreedingStats::partners`.
+/// A partner that dropped out of this window is counted again on the next breed, so the
+/// counter is an upper bound of the real number of distinct partners.
+const MAX_RECENT_PARTNERS: usize = 8;
+
+/// Number of coarse value buckets per DNA byte in `GenePool`. A byte falls into bucket
+/// `byte >> 4`.
+pub const GENE_BUCKETS: usize = 16;
+
+/// Kitty counts per value bucket of one DNA byte position.
+pub type GeneBuckets = [u32; GENE_BUCKETS];
+
+/// Rarity of a kitty by the number of its scarce genes, see `Module::is_scarce_gene`.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub enum RarityTier {
+	/// No scarce gene.
+	Common,
+	/// One or two.
+	Uncommon,
+	/// Three to five.
+	Rare,
+	/// Six or more.
+	Legendary,
+}
+
+impl RarityTier {
+	pub const ALL: [RarityTier; 4] = [RarityTier::Common, RarityTier::Uncommon, RarityTier::Rare, RarityTier::Legendary];
+
+	pub fn from_scarce_genes(count: usize) -> Self {
+		match count {
+			0 => RarityTier::Common,
+			1..=2 => RarityTier::Uncommon,
+			3..=5 => RarityTier::Rare,
+			_ => RarityTier::Legendary,
+		}
+	}
+}
+
+/// One trait of a kitty, in numbers only: names are left to clients, see the
+/// `kitties-traits-names` crate.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub struct KittyTrait {
+	/// DNA position the trait comes from.
+	pub slot: u8,
+	/// `GenePool` bucket of the gene.
+	pub value: u8,
+	/// 1 if the gene is scarce in the current gene pool, 0 otherwise.
+	pub tier: u8,
+}
+
+/// What `do_burn` keeps of a burned kitty.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub enum BurnPolicy {
+	/// Remove everything.
+	Delete,
+	/// Keep an `ArchivedKitty` in `BurnedKitties`, remove everything else.
+	Archive,
+}
+
+/// Pallet feature holding a kitty on behalf of the account that put it there.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub enum CustodyKind {
+	/// Contributed to a breeding pool, held by the pool account.
+	Pool,
+	/// Pending gift, held by the gift account until claimed or cancelled.
+	Gift,
+}
+
+/// Custody of a kitty by a pallet account. `owner` is its beneficial owner.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct Custody<AccountId> {
+	pub kind: CustodyKind,
+	pub owner: AccountId,
+}
+
+/// Per custody feature, whether the beneficial owner of a kitty in that custody can still take
+/// the owner actions that don't move it: breeding it, offering it as a stud and approving
+/// breeding partners for it.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, Default, RuntimeDebug)]
+pub struct CustodyPolicy {
+	pub pool: bool,
+	pub gift: bool,
+}
+
+impl CustodyPolicy {
+	pub fn allows(&self, kind: CustodyKind) -> bool {
+		match kind {
+			CustodyKind::Pool => self.pool,
+			CustodyKind::Gift => self.gift,
+		}
+	}
+}
+
+/// Read-only record of a burned kitty, for explorers.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct ArchivedKitty<Hash, BlockNumber> {
+	pub kitty: Kitty,
+	pub generation: u32,
+	/// The kitty's `Ancestries` entry, a digest of its provenance.
+	pub ancestry: Hash,
+	pub burned_at: BlockNumber,
+}
+
+pub type TradeId = u32;
+
+/// Package swap offered by `proposer` to `counterparty`. The offered kitties are locked and
+/// the offered tokens reserved until the trade is accepted or closed.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct TradeProposal<AccountId, KittyIndex, Balance, BlockNumber> {
+	pub proposer: AccountId,
+	pub counterparty: AccountId,
+	pub give_kitties: Vec<KittyIndex>,
+	pub give_tokens: Balance,
+	pub want_kitties: Vec<KittyIndex>,
+	pub want_tokens: Balance,
+	/// Last block the trade can be accepted in.
+	pub expires_at: BlockNumber,
+}
+
+pub type BreedingProposalId = u32;
+
+/// Breeding of a kitty of `proposer` with a kitty of `partner`, offered by `proposer`. Once
+/// `partner` accepts, the kitten goes to `child_to`.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct BreedingProposal<AccountId, KittyIndex> {
+	pub proposer: AccountId,
+	pub kitty_id: KittyIndex,
+	pub partner: AccountId,
+	pub partner_kitty_id: KittyIndex,
+	pub child_to: AccountId,
+}
+
+pub type SwapId = u32;
+
+/// Swap of a kitty of `proposer` for a kitty of `counterparty`, offered by `proposer`. The
+/// tokens are paid on top by either side once `counterparty` accepts.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct SwapProposal<AccountId, KittyIndex, Balance> {
+	pub proposer: AccountId,
+	pub kitty_id: KittyIndex,
+	pub counterparty: AccountId,
+	pub counterparty_kitty_id: KittyIndex,
+	pub give_tokens: Balance,
+	pub want_tokens: Balance,
+}
+
+pub type PoolId = u32;
+
+/// A kitty contributed to a breeding pool.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct PoolContribution<AccountId, KittyIndex, BlockNumber> {
+	pub contributor: AccountId,
+	pub kitty_id: KittyIndex,
+	/// Share of the offspring, one plus the scarce genes of the kitty when it joined.
+	pub weight: u32,
+	/// First block the kitty can breed or be withdrawn again.
+	pub resting_until: BlockNumber,
+}
+
+/// Kitties pooled by their contributors for `operator` to breed. Kittens go to the
+/// contributors by weight, see `Module::pool_recipient`.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct BreedingPool<AccountId, KittyIndex, BlockNumber> {
+	pub operator: AccountId,
+	pub contributions: Vec<PoolContribution<AccountId, KittyIndex, BlockNumber>>,
+	/// Number of kittens distributed so far.
+	pub cursor: u32,
+}
+
+/// Maximum number of steps of the marketplace fee schedule.
+const MAX_FEE_STEPS: usize = 16;
+
+/// Maximum number of promotional windows that haven't ended yet.
+const MAX_PROMO_WINDOWS: usize = 8;
+
+/// Number of acquisitions and disposals remembered per account in `TaxHistories`. Older
+/// entries are dropped, see `TaxHistoryTruncated`.
+pub const MAX_TAX_HISTORY: usize = 64;
+
+/// Number of latest sales `SaleMedianOracle` prices from.
+pub const SALE_PRICE_WINDOW: usize = 32;
+
+/// Number of times a genome another kitty has already is re-derived before minting fails
+/// with `WrongDNA`.
+pub const MAX_DNA_RETRIES: u8 = 5;
+
+/// Breeding outcomes of a kitty, used by stud owners to advertise their kitty.
+#[derive(Encode, Decode, Clone, Default, PartialEq, Eq, RuntimeDebug)]
+pub struct BreedingStats {
+	/// Number of kittens this kitty was a parent of.
+	pub offspring: u32,
+	/// Number of distinct partner kitties, approximated via a bounded recent partners window.
+	pub partners: u32,
+	/// Number of kittens with mutated genes this kitty was a parent of.
+	pub mewtations: u32,
+}
+
+/// State of a kitty at the moment it was sold, so a buyer can check afterwards that it
+/// wasn't bred right before the sale.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct KittyFreshness<BlockNumber> {
+	/// Number of kittens the kitty was a parent of.
+	pub breed_count: u32,
+	/// Block the kitty was minted in.
+	pub born_at: BlockNumber,
+}
+
+/// A parent kitty as loaded by `BreedContext`.
+struct BreedParent<T: orml_nft::Trait> {
+	id: KittyIndexOf<T>,
+	kitty: Kitty,
+	generation: u32,
+	ancestry: T::Hash,
+}
+
+/// Everything `breed` reads about its parents, loaded and validated once so the rest of the
+/// call doesn't read it again.
+struct BreedContext<T: orml_nft::Trait> {
+	class_id: T::ClassId,
+	first: BreedParent<T>,
+	second: BreedParent<T>,
+}
+
+impl<T: orml_nft::Trait> BreedContext<T> {
+	/// Load both parents, checking they exist, belong to the given owners and are unlocked. A
+	/// rented parent belongs to its renter instead, a parent in custody also to its beneficial
+	/// owner if the `CustodyPolicy` allows.
+	fn load<I: Instance>(
+		first_owner: &T::AccountId,
+		first: KittyIndexOf<T>,
+		second_owner: &T::AccountId,
+		second: KittyIndexOf<T>,
+	) -> Result<Self, DispatchError>
+	where
+		T: Config<I>,
+	{
+		let class_id = Module::<T, I>::class_id();
+		let load_parent = |owner: &T::AccountId, kitty_id| -> Result<BreedParent<T>, DispatchError> {
+			let rental = Module::<T, I>::active_rental(kitty_id);
+			let token = NftModule::<T>::tokens(class_id, kitty_id)
+				.filter(|token| match &rental {
+					Some(rental) => rental.renter == *owner,
+					None => token.owner == *owner || Module::<T, I>::acts_for_custody(owner, kitty_id),
+				})
+				.ok_or(Error::<T, I>::KittenNotFound)?;
+			ensure!(Module::<T, I>::remaining_breeds(kitty_id) > 0, Error::<T, I>::KittyExhausted);
+			Module::<T, I>::ensure_mature(kitty_id)?;
+			Ok(BreedParent {
+				id: kitty_id,
+				kitty: token.data.kitty(),
+				generation: Module::<T, I>::generation(kitty_id),
+				ancestry: Module::<T, I>::ancestry(kitty_id),
+			})
+		};
+		let first_parent = load_parent(first_owner, first)?;
+		let second_parent = load_parent(second_owner, second)?;
+		ensure!(!Module::<T, I>::related(first, second), Error::<T, I>::RelatedKitties);
+		Module::<T, I>::ensure_unlocked_but_rented(first)?;
+		Module::<T, I>::ensure_unlocked_but_rented(second)?;
+		if T::LocksBlockBreeding::get() {
+			ensure!(!KittyLocks::<T, I>::contains_key(first), Error::<T, I>::KittyLocked);
+			ensure!(!KittyLocks::<T, I>::contains_key(second), Error::<T, I>::KittyLocked);
+		}
+
+		Ok(BreedContext { class_id, first: first_parent, second: second_parent })
+	}
+
+	/// Generation of a kitten of the two parents.
+	fn child_generation(&self) -> u32 {
+		self.first.generation.max(self.second.generation).saturating_add(1)
+	}
+}
+
+/// Mints a soulbound starter kitty of the most common genes to every new account, up to
+/// `MaxStarterKittiesPerBlock` per block. Meant for testnets whose accounts come from a faucet:
+/// set it as `OnNewAccount` of `frame_system`.
+pub struct MintStarterKitty<T, I = DefaultInstance>(PhantomData<(T, I)>);
+
+impl<T: Config<I>, I: Instance> OnNewAccount<T::AccountId> for MintStarterKitty<T, I> {
+	fn on_new_account(who: &T::AccountId) {
+		let now = <frame_system::Module<T>>::block_number();
+		let (block, minted) = StarterKittiesMinted::<T, I>::get();
+		let minted = if block == now { minted } else { 0 };
+		if minted >= T::MaxStarterKittiesPerBlock::get() {
+			return;
+		}
+
+		if Module::<T, I>::paused() || Module::<T, I>::ensure_can_mint(who).is_err() {
+			Module::<T, I>::deposit_event(RawEvent::AutoMintSkipped(who.clone(), Module::<T, I>::next_event_seq()));
+			return;
+		}
+
+		let dna = Module::<T, I>::unique_dna(Module::<T, I>::common_dna(who));
+		if let Ok(kitty_id) = dna.and_then(|dna| Module::<T, I>::do_mint(who, Kitty(dna), None)) {
+			SoulboundKitties::<T, I>::insert(kitty_id, ());
+			StarterKittiesMinted::<T, I>::put((now, minted + 1));
+			Module::<T, I>::deposit_event(RawEvent::StarterKittyMinted(who.clone(), kitty_id, Module::<T, I>::next_event_seq()));
+		}
+	}
+}
+
+/// Weights of the calls, measured by the benchmarks in `benchmarking.rs`. Calls not listed
+/// here still have a flat weight.
+pub trait WeightInfo {
+	fn create() -> Weight;
+	fn breed() -> Weight;
+	fn transfer() -> Weight;
+	fn set_price() -> Weight;
+	fn buy() -> Weight;
+}
+
 /// This one defines types used by this exact pallet. After this, in Runtime lib.rs we may define
 /// what types are given to this pallet.
-/// We may use same pallet for several times using different input types. Later this is renamed to
-/// Confid instead of Trait to display actual usage.
-pub trait Trait: orml_nft::Trait<TokenData = Kitty, ClassData=()> {
-	type Event: From<Event<Self>> + Into<<Self as frame_system::Trait>::Event>;
+/// We may use same pallet for several times using different input types, each `I` being a
+/// separate instance with its own storage and NFT class. Named `Config` rather than `Trait` as
+/// it is what the runtime configures the pallet with.
+pub trait Config<I: Instance = DefaultInstance>:
+	orml_nft::Trait<TokenData = VersionedKitty, ClassData=()> + SendTransactionTypes<Call<Self, I>>
+{
+	type Event: From<Event<Self, I>> + Into<<Self as frame_system::Trait>::Event>;
 	type Randomness: Randomness<Self::Hash>;
-	type Currency: Currency<Self::AccountId>;
+	/// Randomness of the flows that need it unpredictable at commit time.
+	type SecureRandomness: KnownSinceRandomness<Self::Hash, Self::BlockNumber>;
+	/// Let `breed` use `Randomness` instead of `SecureRandomness`.
+	type ImmediateBreedRandomness: Get<bool>;
+	/// Maximum number of starter kitties minted by `MintStarterKitty` per block.
+	type MaxStarterKittiesPerBlock: Get<u32>;
+	/// Maximum number of `create`s per block, breeds included unless `MaxBreedPerBlock` is set.
+	type MaxCreatePerBlock: Get<u32>;
+	/// Maximum number of breeds per block, `None` to count them against `MaxCreatePerBlock`.
+	type MaxBreedPerBlock: Get<Option<u32>>;
+	/// Maximum number of kitties an account can hold.
+	type MaxKittiesPerOwner: Get<u32>;
+	/// Maximum number of kitties in existence at once, `None` for no cap.
+	type MaxKittySupply: Get<Option<u32>>;
+	/// Accounts verified as unique persons, who may each claim one starter kitty.
+	type PersonhoodProvider: Contains<Self::AccountId>;
+	/// Whether burned kitties are archived or deleted.
+	type BurnPolicy: Get<BurnPolicy>;
+	/// Owner actions left to the beneficial owner of a kitty held in custody, see
+	/// `Module::beneficial_owner`.
+	type CustodyPolicy: Get<CustodyPolicy>;
+	/// Number of blocks a trade proposal can be accepted in.
+	type TradeTtl: Get<Self::BlockNumber>;
+	/// Maximum number of kitties on each side of a trade proposal.
+	type MaxTradeKitties: Get<u32>;
+	/// Number of blocks a pooled kitty rests after breeding before it can breed or be
+	/// withdrawn again.
+	type PoolBreedCooldown: Get<Self::BlockNumber>;
+	/// Maximum number of kitties in a breeding pool.
+	type MaxPoolKitties: Get<u32>;
+	/// Kitty valuation for the features that need one, `SaleMedianOracle` or an outside oracle.
+	type PriceOracle: KittyPriceOracle<BalanceOf<Self, I>>;
+	type Currency: ReservableCurrency<Self::AccountId>;
+	/// Assets kitties can be listed in besides `Currency`, with the same balance type. Market
+	/// fees on sales in an asset are burned.
+	type Assets: MultiCurrency<Self::AccountId, Balance = BalanceOf<Self, I>>;
+	/// Where the fees charged by the pallet go: burned, treasury, block author, ...
+	type FeeHandler: OnUnbalanced<NegativeImbalanceOf<Self, I>>;
+	/// Told of every kitty minted, transferred or burned, and able to veto it.
+	type KittyHooks: KittyLifecycleHooks<Self::AccountId, KittyIndexOf<Self>>;
+	/// Age in blocks a kitty must reach before it can breed or be listed.
+	type MaturityPeriod: Get<Self::BlockNumber>;
+	/// Number of kittens a kitty can parent over its lifetime.
+	type MaxBreedsPerKitty: Get<u32>;
+	/// Fee of `breed` and `breed_in_pool`, waived by a rebreed voucher.
+	type BreedingFee: Get<BalanceOf<Self, I>>;
+	/// Fee for asking `breed` to steer the kitten gender, charged whether or not it works out.
+	type GenderPreferenceFee: Get<BalanceOf<Self, I>>;
+	/// Probability that a gene of a bred kitten is replaced by a random value instead of
+	/// being inherited.
+	type MutationChance: Get<Perbill>;
+	/// Probability that a gender preference given to `breed` is enforced.
+	type GenderPreferenceChance: Get<Perbill>;
+	/// Number of decimals of `Currency`, for displaying prices.
+	type TokenDecimals: Get<u8>;
+	/// Symbol of `Currency`, for displaying prices.
+	type TokenSymbol: Get<&'static str>;
+	/// Origin allowed to change the pallet's governance settings.
+	type ForceOrigin: EnsureOrigin<Self::Origin>;
+	/// Origin allowed to pause and unpause the pallet.
+	type AdminOrigin: EnsureOrigin<Self::Origin>;
+	/// Maximum number of breeding seasons in the schedule.
+	type MaxSeasons: Get<u32>;
+	/// Origin confirming or rejecting physical redemptions.
+	type FulfillmentOrigin: EnsureOrigin<Self::Origin>;
+	/// Number of blocks a redemption request stays open.
+	type RedemptionTtl: Get<Self::BlockNumber>;
+	/// Maximum number of open redemption requests.
+	type MaxOpenRedemptions: Get<u32>;
+	/// Minimum number of DNA bits a kitten must differ by from each parent. Below that the
+	/// breeder gets a rebreed voucher for the pair.
+	type MinNovelty: Get<u32>;
+	/// Number of blocks a rebreed voucher stays valid.
+	type RebreedWindow: Get<Self::BlockNumber>;
+	/// Length in blocks of a marketplace fee era. The fee rate of an era is derived from the
+	/// sales volume of the previous one.
+	type EraLength: Get<Self::BlockNumber>;
+	/// Maximum number of polls open at the same time.
+	type MaxOpenPolls: Get<u32>;
+	/// Maximum number of kitties that can vote in a poll, which bounds what `close_poll` clears.
+	type MaxPollVotes: Get<u32>;
+	/// Paid from the pallet account for a kitten carrying a gene from the rarest bucket of
+	/// its position.
+	type RareGeneBonus: Get<BalanceOf<Self, I>>;
+	/// Blocks between `commit_create` and the first block `reveal_create` is accepted in.
+	/// Must be at least one, so the hash the DNA is drawn from doesn't exist at commit time.
+	type RevealDelay: Get<Self::BlockNumber>;
+	/// Blocks after the reveal delay during which a commitment can be revealed. Must stay
+	/// below `BlockHashCount`.
+	type RevealWindow: Get<Self::BlockNumber>;
+	/// Fee of `create`, waived for promotional mints.
+	type CreationFee: Get<BalanceOf<Self, I>>;
+	/// With a bonding curve, the fee of `create` grows by `CreationFee` every this many kitties
+	/// minted, see `creation_price`. Zero keeps it flat.
+	type CreationPriceStep: Get<u32>;
+	/// Fee of a two-phase creation.
+	type CommitFee: Get<BalanceOf<Self, I>>;
+	/// Reserved from whoever creates or breeds a kitty, until the kitty is burned.
+	type KittyDeposit: Get<BalanceOf<Self, I>>;
+	/// Reserved from the seller of each listing, until the listing is removed.
+	type ListingDeposit: Get<BalanceOf<Self, I>>;
+	/// Part of the fee lost when a commitment expires unrevealed.
+	type CommitForfeit: Get<Perbill>;
+	/// Blocks in a rental period, the unit of rental prices and durations.
+	type RentalPeriod: Get<Self::BlockNumber>;
+	/// Weight `on_initialize` spends at most settling ended auctions. The rest waits for the
+	/// next block or `settle_next`.
+	type AuctionSettlementBudget: Get<Weight>;
+	/// Paid from the pallet account to the caller of `settle_next` for each auction it
+	/// settles, while the pallet account can afford it.
+	type SettlementReward: Get<BalanceOf<Self, I>>;
+	/// Part of each sale paid by the seller to the creator of the kitty.
+	type RoyaltyRate: Get<Perbill>;
+	/// Number of sales kept in the `KittySaleHistory` of each kitty.
+	type MaxSaleHistory: Get<u32>;
+	/// Breeding a kitty of a generation that is a multiple of this mints a soulbound badge to
+	/// the breeder. Zero disables badges.
+	type GenerationMilestone: Get<u32>;
+	/// Count failed buys in `ActivityCounters` as well.
+	type VerboseMetrics: Get<bool>;
+	/// Refuse to breed kitties locked through `LockableKitties`, not only to move them.
+	type LocksBlockBreeding: Get<bool>;
+	/// Pool priority of the unsigned `expire_listing` calls of the off-chain worker.
+	type UnsignedPriority: Get<TransactionPriority>;
+	/// Weights of the calls, `()` for the defaults in `default_weights.rs`.
+	type WeightInfo: WeightInfo;
+	/// Account holding funds the pallet owes to users, e.g. claimable refunds. Every instance
+	/// of the pallet needs its own.
+	type ModuleId: Get<ModuleId>;
 }
 
-type BalanceOf<T> = <<T as Trait>::Currency as Currency<<T as frame_system::Trait>::AccountId>>::Balance;
-type KittyIndexOf<T> = <T as orml_nft::Trait>::TokenId;
+pub type BalanceOf<T, I = DefaultInstance> = <<T as Config<I>>::Currency as Currency<<T as frame_system::Trait>::AccountId>>::Balance;
+pub type AssetIdOf<T, I = DefaultInstance> = <<T as Config<I>>::Assets as MultiCurrency<<T as frame_system::Trait>::AccountId>>::CurrencyId;
+type NegativeImbalanceOf<T, I = DefaultInstance> = <<T as Config<I>>::Currency as Currency<<T as frame_system::Trait>::AccountId>>::NegativeImbalance;
+/// Kitty id as used in storage, calls and events. It is the NFT token id configured for
+/// `orml_nft`, so code outside the pallet should name it through this alias rather than
+/// assume the runtime's concrete integer type.
+pub type KittyIndexOf<T> = <T as orml_nft::Trait>::TokenId;
+pub type TaxEventOf<T, I = DefaultInstance> =
+	TaxEvent<<T as frame_system::Trait>::AccountId, KittyIndexOf<T>, BalanceOf<T, I>, <T as frame_system::Trait>::BlockNumber>;
+pub type BreedingPoolOf<T> =
+	BreedingPool<<T as frame_system::Trait>::AccountId, KittyIndexOf<T>, <T as frame_system::Trait>::BlockNumber>;
+pub type TradeProposalOf<T, I = DefaultInstance> = TradeProposal<
+	<T as frame_system::Trait>::AccountId,
+	KittyIndexOf<T>,
+	BalanceOf<T, I>,
+	<T as frame_system::Trait>::BlockNumber,
+>;
+pub type BreedingProposalOf<T> = BreedingProposal<<T as frame_system::Trait>::AccountId, KittyIndexOf<T>>;
+pub type SwapProposalOf<T, I = DefaultInstance> = SwapProposal<<T as frame_system::Trait>::AccountId, KittyIndexOf<T>, BalanceOf<T, I>>;
+pub type TaxReportOf<T, I = DefaultInstance> =
+	TaxReport<<T as frame_system::Trait>::AccountId, KittyIndexOf<T>, BalanceOf<T, I>, <T as frame_system::Trait>::BlockNumber>;
 
 // The pallet's runtime storage items.
 // https://substrate.dev/docs/en/knowledgebase/runtime/storage
 decl_storage! {
-    trait Store for Module<T: Trait> as Kitties {
-        pub KittyPrices get(fn kitty_prices): map hasher(blake2_128_concat) KittyIndexOf<T> => Option<BalanceOf<T>>;
+    trait Store for Module<T: Config<I>, I: Instance = DefaultInstance> as Kitties {
+        /// Kitties for sale. Kitty ids are assigned by the pallet, so the cheaper hasher is safe here.
+        pub Listings get(fn listing): map hasher(twox_64_concat) KittyIndexOf<T> => Option<ListingOf<T, I>>;
+        /// Kitties with an entry in `Listings`, paged through by `for_sale`.
+        pub ForSaleKitties: map hasher(twox_64_concat) KittyIndexOf<T> => ();
+        /// Listings expiring in a block, removed by the sweep in `on_initialize` once expired.
+        /// Entries of listings changed or removed since are dropped by the sweep.
+        pub ListingExpiries: double_map hasher(twox_64_concat) T::BlockNumber, hasher(twox_64_concat) KittyIndexOf<T> => ();
+        /// First block whose expiring listings the sweep hasn't fully removed yet.
+        pub ListingSweepCursor get(fn listing_sweep_cursor): T::BlockNumber;
+        /// Listing prices without a seller, from before `Listings`. Only read by the migrations.
+        pub KittyPrices: map hasher(twox_64_concat) KittyIndexOf<T> => Option<BalanceOf<T, I>>;
 
 		pub ClassId get(fn class_id): T::ClassId;
+
+		/// Breeding statistics per kitty. Keyed by kitty id, so they follow the kitty on transfer.
+		pub SireStats get(fn sire_stats): map hasher(blake2_128_concat) KittyIndexOf<T> => BreedingStats;
+		/// Last partners of a kitty, at most `MAX_RECENT_PARTNERS`, oldest first.
+		pub RecentPartners get(fn recent_partners): map hasher(blake2_128_concat) KittyIndexOf<T> => Vec<KittyIndexOf<T>>;
+
+		/// Market maker quotes. kitty id / quoter => quote
+		pub Quotes get(fn quotes): double_map hasher(blake2_128_concat) KittyIndexOf<T>, hasher(blake2_128_concat) T::AccountId
+			=> Option<Quote<BalanceOf<T, I>, T::BlockNumber>>;
+		/// Nonce of the next posted quote.
+		pub NextQuoteNonce get(fn next_quote_nonce): u64;
+		/// Buy offers. kitty id / bidder => offer
+		pub Offers get(fn offer): double_map hasher(blake2_128_concat) KittyIndexOf<T>, hasher(blake2_128_concat) T::AccountId
+			=> Option<Offer<BalanceOf<T, I>, T::BlockNumber>>;
+		/// Nonce of the next offer made.
+		pub NextOfferNonce get(fn next_offer_nonce): u64;
+		/// Running auctions, see `Auction`.
+		pub Auctions get(fn auction):
+			map hasher(twox_64_concat) KittyIndexOf<T> => Option<Auction<T::AccountId, BalanceOf<T, I>, T::BlockNumber>>;
+		/// Kitties whose auction ends in a block.
+		pub AuctionsEnding get(fn auctions_ending): map hasher(twox_64_concat) T::BlockNumber => Vec<KittyIndexOf<T>>;
+		/// Ended auctions by position in the queue, settled first in first out.
+		pub SettlementQueue get(fn queued_settlement): map hasher(twox_64_concat) u32 => Option<KittyIndexOf<T>>;
+		/// Positions of the first queued auction and the next free one.
+		pub SettlementQueueRange get(fn settlement_queue_range): (u32, u32);
+		/// Declining-price listings, see `DutchAuction`.
+		pub DutchAuctions get(fn dutch_auction):
+			map hasher(twox_64_concat) KittyIndexOf<T> => Option<DutchAuction<T::AccountId, BalanceOf<T, I>, T::BlockNumber>>;
+		/// Gifts waiting to be claimed, see `PendingGift`.
+		pub PendingGifts get(fn pending_gift):
+			map hasher(twox_64_concat) KittyIndexOf<T> => Option<PendingGift<T::AccountId, T::BlockNumber>>;
+		/// Kitties offered for rent, see `RentalTerms`.
+		pub RentalOffers get(fn rental_terms): map hasher(twox_64_concat) KittyIndexOf<T> => Option<RentalTerms<BalanceOf<T, I>>>;
+		/// Leased kitties. Use `active_rental`, an ended rental may not be removed yet.
+		pub KittyRentals get(fn kitty_rental):
+			map hasher(twox_64_concat) KittyIndexOf<T> => Option<Rental<T::AccountId, T::BlockNumber>>;
+		/// Kitties held by a pallet account on behalf of their beneficial owner.
+		pub KittyCustodies get(fn kitty_custody): map hasher(twox_64_concat) KittyIndexOf<T> => Option<Custody<T::AccountId>>;
+		/// Kitties whose rental ends in a block.
+		pub RentalsEnding get(fn rentals_ending): map hasher(twox_64_concat) T::BlockNumber => Vec<KittyIndexOf<T>>;
+		/// Fees of streamed rentals, by kitty. See `rent_streamed`.
+		pub RentEscrows get(fn rent_escrow): map hasher(twox_64_concat) KittyIndexOf<T> => Option<RentEscrowOf<T, I>>;
+
+		/// Inclusive block ranges in which breeding is allowed, sorted and non-overlapping.
+		/// An empty schedule means breeding is always allowed.
+		pub BreedingSeasons get(fn breeding_seasons): Vec<(T::BlockNumber, T::BlockNumber)>;
+
+		/// Pending physical redemptions. The kitty is locked while its request is open.
+		pub Redemptions get(fn redemptions): map hasher(blake2_128_concat) KittyIndexOf<T>
+			=> Option<Redemption<T::AccountId, T::Hash, T::BlockNumber>>;
+		/// Kitties with an open redemption request, oldest first.
+		pub OpenRedemptions get(fn open_redemptions): Vec<KittyIndexOf<T>>;
+
+		/// Rebreed vouchers: breeding the pair again waives the `BreedingFee` and cooldowns once.
+		/// owner / (lower kitty id, higher kitty id) => last block the voucher can be used
+		pub RebreedVouchers get(fn rebreed_vouchers): double_map hasher(blake2_128_concat) T::AccountId,
+			hasher(blake2_128_concat) (KittyIndexOf<T>, KittyIndexOf<T>) => Option<T::BlockNumber>;
+
+		/// Kitty holder polls, kept with their final tally once closed.
+		pub Polls get(fn polls): map hasher(twox_64_concat) PollId => Option<Poll<T::Hash, T::BlockNumber>>;
+		pub NextPollId get(fn next_poll_id): PollId;
+		/// Polls not closed yet.
+		pub OpenPolls get(fn open_polls): Vec<PollId>;
+		/// Votes of open polls. poll / voter => (option, power)
+		pub PollVoters get(fn poll_voters): double_map hasher(twox_64_concat) PollId,
+			hasher(blake2_128_concat) T::AccountId => Option<(u8, u32)>;
+		/// Kitties that already voted in an open poll.
+		pub PollKitties get(fn poll_kitties): double_map hasher(twox_64_concat) PollId,
+			hasher(blake2_128_concat) KittyIndexOf<T> => Option<()>;
+
+		/// Number of living kitties per DNA byte position and value bucket.
+		pub GenePool get(fn gene_pool): map hasher(twox_64_concat) u8 => GeneBuckets;
+
+		/// Dead man's switches by owner.
+		pub DeadMansSwitches get(fn dead_mans_switch): map hasher(blake2_128_concat) T::AccountId
+			=> Option<DeadMansSwitch<T::AccountId, BalanceOf<T, I>, T::BlockNumber>>;
+		/// Accounts receiving the sale proceeds of a dormant owner until the owner is active again.
+		pub ProceedsBeneficiaries get(fn proceeds_beneficiary): map hasher(blake2_128_concat) T::AccountId
+			=> Option<T::AccountId>;
+		/// Bidding balances, held by the pallet account.
+		pub BiddingBalances get(fn bidding_balance): map hasher(blake2_128_concat) T::AccountId
+			=> BiddingBalance<BalanceOf<T, I>>;
+
+		/// Price each kitty last sold for.
+		pub LastSalePrices get(fn last_sale_price): map hasher(blake2_128_concat) KittyIndexOf<T>
+			=> Option<BalanceOf<T, I>>;
+
+		/// Funds reserved by each account for kitty features: commitments, quotes and trade
+		/// proposals. The pallet never unreserves more than this on an account's behalf.
+		pub KittyReserves get(fn kitty_reserved): map hasher(blake2_128_concat) T::AccountId => BalanceOf<T, I>;
+
+		/// Payer and amount of the `KittyDeposit` reserved for each kitty.
+		pub KittyDeposits get(fn kitty_deposit): map hasher(twox_64_concat) KittyIndexOf<T>
+			=> Option<(T::AccountId, BalanceOf<T, I>)>;
+
+		/// Number of kitties each account holds, capped by `MaxKittiesPerOwner`.
+		pub OwnedKittiesCount get(fn owned_kitties_count): map hasher(blake2_128_concat) T::AccountId => u32;
+
+		/// Pending two-phase creations by account.
+		pub CreateCommitments get(fn create_commitment): map hasher(blake2_128_concat) T::AccountId
+			=> Option<CreateCommitment<T::Hash, BalanceOf<T, I>, T::BlockNumber>>;
+
+		/// Current holder of the creator role of each kitty, receiving royalties. Initially
+		/// the minter; unset once renounced. The only record of who the creator is.
+		pub KittyCreators get(fn kitty_creator): map hasher(blake2_128_concat) KittyIndexOf<T> => Option<T::AccountId>;
+		/// Creator role offered to an account, see `transfer_creator_role`.
+		pub PendingCreators get(fn pending_creator): map hasher(blake2_128_concat) KittyIndexOf<T> => Option<T::AccountId>;
+
+		/// Generation of each bred kitty: one more than its older parent. Created kitties are
+		/// generation zero.
+		pub Generations get(fn generation): map hasher(blake2_128_concat) KittyIndexOf<T> => u32;
+		/// Hash over the DNA of a kitty and the ancestry hashes of its parents.
+		pub Ancestries get(fn ancestry): map hasher(blake2_128_concat) KittyIndexOf<T> => T::Hash;
+		/// Kitty having each genome. No two kitties share their DNA.
+		pub KittyDna get(fn kitty_with_dna): map hasher(blake2_128_concat) [u8; 16] => Option<KittyIndexOf<T>>;
+		/// Account allowed to move each kitty on behalf of its owner, see `approve`.
+		pub KittyApprovals get(fn kitty_approval): map hasher(twox_64_concat) KittyIndexOf<T> => Option<T::AccountId>;
+		/// Fee anyone can pay to breed with each kitty, see `breed_with_stud`.
+		pub SiringPrices get(fn siring_price): map hasher(twox_64_concat) KittyIndexOf<T> => Option<BalanceOf<T, I>>;
+		/// Account allowed to breed with each kitty once, see `approve_breeding`.
+		pub SiringApprovals get(fn siring_approval): map hasher(twox_64_concat) KittyIndexOf<T> => Option<T::AccountId>;
+		/// Parents of each bred kitty. Created kitties have none.
+		pub KittyParents get(fn kitty_parents):
+			map hasher(twox_64_concat) KittyIndexOf<T> => Option<(KittyIndexOf<T>, KittyIndexOf<T>)>;
+		/// Kitties that can't be moved, listed or bred, such as milestone badges.
+		pub SoulboundKitties get(fn soulbound): map hasher(blake2_128_concat) KittyIndexOf<T> => Option<()>;
+
+		/// Open trade proposals.
+		pub TradeProposals get(fn trade_proposal):
+			map hasher(twox_64_concat) TradeId => Option<TradeProposalOf<T, I>>;
+		pub NextTradeId get(fn next_trade_id): TradeId;
+		/// Kitties offered in an open trade proposal, and the proposal.
+		pub TradeLocks get(fn trade_lock): map hasher(twox_64_concat) KittyIndexOf<T> => Option<TradeId>;
+		/// Kitties locked by other pallets through `LockableKitties`, and the reason.
+		pub KittyLocks get(fn kitty_lock): map hasher(twox_64_concat) KittyIndexOf<T> => Option<LockReason>;
+
+		/// Open breeding proposals, see `BreedingProposal`.
+		pub BreedingProposals get(fn breeding_proposal):
+			map hasher(twox_64_concat) BreedingProposalId => Option<BreedingProposalOf<T>>;
+		pub NextBreedingProposalId get(fn next_breeding_proposal_id): BreedingProposalId;
+		/// Open kitty-for-kitty swaps, see `SwapProposal`.
+		pub SwapProposals get(fn swap_proposal): map hasher(twox_64_concat) SwapId => Option<SwapProposalOf<T, I>>;
+		pub NextSwapId get(fn next_swap_id): SwapId;
+
+		/// Breeding pools, see `BreedingPool`.
+		pub BreedingPools get(fn breeding_pool): map hasher(twox_64_concat) PoolId => Option<BreedingPoolOf<T>>;
+		pub NextPoolId get(fn next_pool_id): PoolId;
+
+		/// Kitties below this id were rewritten as `VersionedKitty::V2` by `upgrade_kitties`.
+		pub KittyUpgradeCursor get(fn kitty_upgrade_cursor): KittyIndexOf<T>;
+
+		/// Burned kitties under `BurnPolicy::Archive`. Ids are never minted again.
+		pub BurnedKitties get(fn burned_kitty):
+			map hasher(twox_64_concat) KittyIndexOf<T> => Option<ArchivedKitty<T::Hash, T::BlockNumber>>;
+
+		/// Block each kitty was minted in.
+		pub BirthBlocks get(fn birth_block): map hasher(blake2_128_concat) KittyIndexOf<T> => T::BlockNumber;
+
+		/// Number of successful calls per kind of activity, for node operators. Only reset by
+		/// `reset_activity_counters`.
+		pub ActivityCounters get(fn activity_counter): map hasher(twox_64_concat) ActivityKind => u64;
+
+		/// Source of all entropy when built with the `deterministic` feature, seeded in genesis.
+		pub EntropyCounter get(fn entropy_counter) config(deterministic_seed): u64;
+
+		/// Extrinsic index and next event sequence number in it. Cleared at the end of the block.
+		EventSeq: (Option<u32>, u32);
+		/// Set once every kitty id was taken. Mints fail from then on, mints on the side of
+		/// other calls are skipped.
+		pub MintingExhausted get(fn minting_exhausted): bool;
+		/// Set by `pause`: minting, breeding and trading fail until `unpause`.
+		pub Paused get(fn paused): bool;
+		/// Set while an outside hook called by the pallet runs. Minting, burning, moving kitties
+		/// and moving funds through the pallet fail meanwhile; reads stay allowed.
+		ExecutionLock: bool;
+
+		/// Version of the pallet storage layout, see `migrations`.
+		pub StorageVersion get(fn storage_version) build(|_| migrations::CURRENT_VERSION): u16;
+		/// Raw key the move of `KittyPrices` into `Listings` continues after, set while it is in
+		/// progress.
+		pub RehashCursor get(fn rehash_cursor): Option<Vec<u8>>;
+
+		/// Marketplace fee steps set by governance: (minimum volume of the previous era, fee
+		/// rate), by strictly increasing volume. Sales below the first step pay no fee.
+		pub FeeSchedule get(fn fee_schedule): Vec<(BalanceOf<T, I>, Permill)>;
+		/// Fee rate applied to sales of `FeeEra`.
+		pub CurrentFeeRate get(fn current_fee_rate): Permill;
+		/// Era the fee rate was computed for, moved forward by the first sale of an era.
+		pub FeeEra get(fn fee_era): Option<T::BlockNumber>;
+		/// Sales volume settled in `FeeEra` so far.
+		pub EraVolume get(fn era_volume): BalanceOf<T, I>;
+		/// Sales volume of the era before `FeeEra`.
+		pub LastEraVolume get(fn last_era_volume): BalanceOf<T, I>;
+
+		/// Funds moved by the pallet per kind in the block noted alongside, rolled over lazily
+		/// by the first movement of a block.
+		pub FlowTotals: map hasher(blake2_128_concat) FlowKind => (T::BlockNumber, BalanceOf<T, I>);
+
+		/// Funds held by the pallet account that can be claimed back by an account.
+		pub Refunds get(fn refunds): map hasher(blake2_128_concat) T::AccountId => BalanceOf<T, I>;
+
+		/// Promotional windows that haven't ended yet, by start block. Never overlapping.
+		pub PromoWindows get(fn promo_windows): Vec<PromoWindow<T::BlockNumber>>;
+		/// Rarity and price of the latest `SALE_PRICE_WINDOW` sales, oldest first.
+		pub RecentSales get(fn recent_sales): Vec<(RarityTier, BalanceOf<T, I>)>;
+		/// Latest `MaxSaleHistory` sales of each kitty, oldest first. block / seller / buyer / price
+		pub KittySaleHistory get(fn sale_history): map hasher(twox_64_concat) KittyIndexOf<T>
+			=> Vec<(T::BlockNumber, T::AccountId, T::AccountId, BalanceOf<T, I>)>;
+		/// Latest `MAX_TAX_HISTORY` acquisitions and disposals of each account.
+		pub TaxHistories get(fn tax_history):
+			map hasher(blake2_128_concat) T::AccountId => Vec<TaxEventOf<T, I>>;
+		/// Block of the newest event dropped from an account's `TaxHistories`.
+		pub TaxHistoryTruncated get(fn tax_history_truncated):
+			map hasher(blake2_128_concat) T::AccountId => Option<T::BlockNumber>;
+
+		/// Starter kitties minted in the block noted alongside, see `MintStarterKitty`.
+		StarterKittiesMinted: (T::BlockNumber, u32);
+		/// Kitties created in the current block, see `MaxCreatePerBlock`.
+		pub KittiesCreatedThisBlock get(fn kitties_created_this_block): u32;
+		/// Kitties bred in the current block when they have their own `MaxBreedPerBlock`.
+		pub KittiesBredThisBlock get(fn kitties_bred_this_block): u32;
+		/// Accounts that claimed their starter kitty with `claim_starter_kitty`.
+		pub StarterClaimed get(fn starter_claimed): map hasher(blake2_128_concat) T::AccountId => bool;
+
+		/// Free mints per window start and account.
+		pub PromoMints get(fn promo_mints):
+			double_map hasher(twox_64_concat) T::BlockNumber, hasher(blake2_128_concat) T::AccountId => u32;
     }
 	add_extra_genesis {
-			build(|_config| {
+			/// Founder kitties minted at genesis, in order. owner / DNA / price to list it at
+			config(genesis_kitties): Vec<(T::AccountId, [u8; 16], Option<BalanceOf<T, I>>)>;
+			build(|config: &GenesisConfig<T, I>| {
 				// create an NTF class
 				let class_id = NftModule::<T>::create_class(&Default::default(), Vec::new(), ()).expect("Cannot fail or invalid chain spec");
-				ClassId::<T>::put(class_id);
+				ClassId::<T, I>::put(class_id);
+
+				// genesis storage has no transactions, so no `do_mint` and no hooks
+				for (owner, dna, price) in config.genesis_kitties.iter() {
+					let kitty_id = Module::<T, I>::mint_kitty(owner, &Kitty(*dna), None).expect("Invalid genesis kitty");
+					if let Some(price) = price {
+						let listing = Listing { seller: owner.clone(), price: *price, expires_at: None, deposit: Zero::zero(), asset: None };
+						Listings::<T, I>::insert(kitty_id, listing);
+						ForSaleKitties::<T, I>::insert(kitty_id, ());
+					}
+				}
 			})
 	}
 }
 
 // Pallets use events to inform users when important changes are made.
 // https://substrate.dev/docs/en/knowledgebase/runtime/events
+// Every event ends with its sequence number within the extrinsic, so indexers can order
+// the events of multi-event extrinsics. See `Module::next_event_seq`.
 decl_event!(
-    pub enum Event<T>
+    pub enum Event<T, I = DefaultInstance>
     where
         AccountId = <T as frame_system::Trait>::AccountId,
 		KittyIndex = KittyIndexOf<T>,
-        Balance = BalanceOf<T>,
+        Balance = BalanceOf<T, I>,
+        BlockNumber = <T as frame_system::Trait>::BlockNumber,
+        Hash = <T as frame_system::Trait>::Hash,
+        AssetId = AssetIdOf<T, I>,
     {
-        /// Kitty created. owner / kitty id / Kitty
-        KittyCreated(AccountId, KittyIndex, Kitty),
-        /// Kitty breed. owner / Kitty / Kitty / Resulting kitty
-        KittyBreed(AccountId, Kitty, Kitty, KittyIndex),
-        /// Kitty transferred. old owner / new owner / kitty
-        KittyTransferred(AccountId, AccountId, KittyIndex),
-        /// Kitty price set. owner / kitty id / price
-        KittyPriceUpdated(AccountId, KittyIndex, Option<Balance>),
-        /// Kitty sold set. seller/ byer / kitty id / price
-        KittySold(AccountId, AccountId, KittyIndex, Balance),
+        /// Kitty created. owner / kitty id / Kitty / seq
+        KittyCreated(AccountId, KittyIndex, Kitty, u32),
+        /// Kitty breed. owner / Kitty / Kitty / Resulting kitty / generation of the kitten / seq
+        KittyBreed(AccountId, Kitty, Kitty, KittyIndex, u32, u32),
+        /// Kitty transferred. old owner / new owner / kitty / seq
+        KittyTransferred(AccountId, AccountId, KittyIndex, u32),
+        /// Siring fee of a stud set or removed. owner / kitty id / fee / seq
+        SiringPriceUpdated(AccountId, KittyIndex, Option<Balance>, u32),
+        /// Breeder paid to breed with a stud. breeder / stud owner / stud kitty id / fee / seq
+        StudFeePaid(AccountId, AccountId, KittyIndex, Balance, u32),
+        /// Partner allowed to breed with a kitty set or revoked. owner / kitty id / partner / seq
+        BreedingApproved(AccountId, KittyIndex, Option<AccountId>, u32),
+        /// Delegate of a kitty set or revoked. owner / kitty id / delegate / seq
+        KittyApproved(AccountId, KittyIndex, Option<AccountId>, u32),
+        /// The preceding `KittyTransferred` was done by the approved delegate. delegate /
+        /// kitty id / seq
+        TransferredByDelegate(AccountId, KittyIndex, u32),
+        /// Kitty destroyed by its owner. owner / kitty id / seq
+        KittyBurned(AccountId, KittyIndex, u32),
+        /// Deposit reserved for a new kitty. It stays with the payer when the kitty changes
+        /// hands, until the kitty is burned. payer / kitty id / amount / seq
+        KittyDepositReserved(AccountId, KittyIndex, Balance, u32),
+        /// Deposit of a burned kitty returned. payer / kitty id / amount / seq
+        KittyDepositReleased(AccountId, KittyIndex, Balance, u32),
+        /// Kitty price set. owner / kitty id / price / asset of the price, `None` for the native
+        /// currency / seq
+        KittyPriceUpdated(AccountId, KittyIndex, Option<Balance>, Option<AssetId>, u32),
+        /// Expired listing removed. seller / kitty id / seq
+        ListingExpiredEvent(AccountId, KittyIndex, u32),
+        /// Kitty sold. seller / buyer / kitty id / price / asset of the price, `None` for the
+        /// native currency / market fee and royalty paid by the seller out of the price /
+        /// freshness / seq
+        KittySold(AccountId, AccountId, KittyIndex, Balance, Option<AssetId>, Balance, Balance, KittyFreshness<BlockNumber>, u32),
+        /// Kitten gender preference paid for. owner / kitty id / preferred gender / satisfied / seq
+        GenderPreferenceApplied(AccountId, KittyIndex, KittyGender, bool, u32),
+        /// Quote posted or replaced. quoter / kitty id / bid / ask / expiry / nonce / seq
+        QuotePosted(AccountId, KittyIndex, Balance, Balance, BlockNumber, u64, u32),
+        /// Quote withdrawn. quoter / kitty id / seq
+        QuoteCancelled(AccountId, KittyIndex, u32),
+        /// Offer made or replaced. bidder / kitty id / amount / expiry / nonce / seq
+        OfferMade(AccountId, KittyIndex, Balance, BlockNumber, u64, u32),
+        /// Offer withdrawn or dropped after expiry, funds released. bidder / kitty id / seq
+        OfferCancelled(AccountId, KittyIndex, u32),
+        /// Auction started. seller / kitty id / reserve price / end block / seq
+        AuctionStarted(AccountId, KittyIndex, Balance, BlockNumber, u32),
+        /// New best bid, the previous one was released. bidder / kitty id / bid / seq
+        AuctionBid(AccountId, KittyIndex, Balance, u32),
+        /// Auction ended with a sale. seller / winner / kitty id / price / market fee and royalty
+        /// paid by the seller out of the price / seq
+        AuctionSettled(AccountId, AccountId, KittyIndex, Balance, Balance, Balance, u32),
+        /// Auction ended without a sale, the kitty stays with its seller. seller / kitty id / seq
+        AuctionUnsold(AccountId, KittyIndex, u32),
+        /// Auction over and queued for settlement. kitty id / seq
+        AuctionEnded(KittyIndex, u32),
+        /// Caller of `settle_next` rewarded. caller / auctions settled / reward / seq
+        SettlementRewardPaid(AccountId, u32, Balance, u32),
+        /// Dutch auction started. seller / kitty id / start price / end price / duration / seq
+        DutchAuctionStarted(AccountId, KittyIndex, Balance, Balance, BlockNumber, u32),
+        /// Dutch auction withdrawn by its seller. seller / kitty id / seq
+        DutchAuctionCancelled(AccountId, KittyIndex, u32),
+        /// Kitty sent as a gift to be claimed. sender / recipient / kitty id / expiry / seq
+        KittyGifted(AccountId, AccountId, KittyIndex, BlockNumber, u32),
+        /// Gift claimed by its recipient. recipient / kitty id / seq
+        GiftClaimed(AccountId, KittyIndex, u32),
+        /// Gift cancelled, kitty back with its sender. sender / kitty id / seq
+        GiftCancelled(AccountId, KittyIndex, u32),
+        /// Kitty offered for rent. owner / kitty id / price per period / max periods / seq
+        RentalListed(AccountId, KittyIndex, Balance, u32, u32),
+        /// Rental offer withdrawn. owner / kitty id / seq
+        RentalUnlisted(AccountId, KittyIndex, u32),
+        /// Kitty leased, paid up front. owner / renter / kitty id / periods / paid / ends at / seq
+        KittyRented(AccountId, AccountId, KittyIndex, u32, Balance, BlockNumber, u32),
+        /// Rental ended, the kitty is back with its owner. renter / kitty id / seq
+        RentalEnded(AccountId, KittyIndex, u32),
+        /// Kitty leased, its fee held in escrow and paid to the owner as the rental runs.
+        /// owner / renter / kitty id / periods / escrowed / ends at / seq
+        KittyRentedStreamed(AccountId, AccountId, KittyIndex, u32, Balance, BlockNumber, u32),
+        /// Owner of a streamed rental paid the fee earned so far. owner / kitty id / amount / seq
+        RentIncomeClaimed(AccountId, KittyIndex, Balance, u32),
+        /// Escrow of a streamed rental split up as the rental ended.
+        /// owner / renter / kitty id / paid to the owner / refunded to the renter / seq
+        RentEscrowSettled(AccountId, AccountId, KittyIndex, Balance, Balance, u32),
+        /// Owner sold to a quote. seller / quoter / kitty id / bid / seq
+        BidHit(AccountId, AccountId, KittyIndex, Balance, KittyFreshness<BlockNumber>, u32),
+        /// Pallet paused. seq
+        Paused(u32),
+        /// Pallet unpaused. seq
+        Unpaused(u32),
+        /// Breeding season schedule replaced. seasons / seq
+        BreedingSeasonsUpdated(Vec<(BlockNumber, BlockNumber)>, u32),
+        /// Physical redemption requested, kitty locked. owner / kitty id / shipping commitment / expires at / seq
+        RedemptionRequested(AccountId, KittyIndex, Hash, BlockNumber, u32),
+        /// Redemption fulfilled and kitty burned. owner / kitty id / seq
+        RedemptionConfirmed(AccountId, KittyIndex, u32),
+        /// Redemption rejected, kitty unlocked. owner / kitty id / seq
+        RedemptionRejected(AccountId, KittyIndex, u32),
+        /// Redemption not fulfilled in time, kitty unlocked. owner / kitty id / seq
+        RedemptionExpired(AccountId, KittyIndex, u32),
+        /// Kitten too similar to a parent, voucher granted. owner / kitty id / kitty id / expires at / seq
+        RebreedVoucherIssued(AccountId, KittyIndex, KittyIndex, BlockNumber, u32),
+        /// Rebreed voucher consumed. owner / kitty id / kitty id / seq
+        RebreedVoucherUsed(AccountId, KittyIndex, KittyIndex, u32),
+        /// Marketplace fee schedule replaced. schedule / seq
+        FeeScheduleUpdated(Vec<(Balance, Permill)>, u32),
+        /// Marketplace fee rate changed. era / rate / seq
+        FeeRateChanged(BlockNumber, Permill, u32),
+        /// Funds moved by the pallet. from / to (none for fees) / amount / kind / seq
+        FundsFlow(AccountId, Option<AccountId>, Balance, FlowKind, u32),
+        /// Refund credited to the ledger. account / amount / seq
+        RefundCredited(AccountId, Balance, u32),
+        /// Refund paid out. account / amount / seq
+        RefundClaimed(AccountId, Balance, u32),
+        /// Bred kitty reached a generation milestone, badge minted to the breeder.
+        /// breeder / kitty id / generation / badge kitty id / seq
+        MilestoneReached(AccountId, KittyIndex, u32, KittyIndex, u32),
+        /// Kitten carries a rare gene, bonus paid. owner / kitty id / bonus / seq
+        RareGeneBonusPaid(AccountId, KittyIndex, Balance, u32),
+        /// Two-phase creation committed. owner / commitment / first reveal block / last reveal block / seq
+        CreateCommitted(AccountId, Hash, BlockNumber, BlockNumber, u32),
+        /// Commitment expired unrevealed. owner / forfeited fee / seq
+        CreateCommitmentExpired(AccountId, Balance, u32),
+        /// Bidding balance funded. account / amount / seq
+        BiddingFundsDeposited(AccountId, Balance, u32),
+        /// Free bidding balance withdrawn. account / amount / seq
+        BiddingFundsWithdrawn(AccountId, Balance, u32),
+        /// Activity counters reset. seq
+        ActivityCountersReset(u32),
+        /// Creator role offered. creator / new creator / kitty id / seq
+        CreatorRoleOffered(AccountId, AccountId, KittyIndex, u32),
+        /// Creator role handed over. old creator / new creator / kitty id / seq
+        CreatorRoleTransferred(AccountId, AccountId, KittyIndex, u32),
+        /// Creator role given up, no more royalties. creator / kitty id / seq
+        CreatorRoleRenounced(AccountId, KittyIndex, u32),
+        /// Dead man's switch set up. owner / beneficiary / inactivity blocks / seq
+        DeadMansSwitchConfigured(AccountId, AccountId, BlockNumber, u32),
+        /// Dead man's switch removed by its owner. owner / seq
+        DeadMansSwitchDisabled(AccountId, u32),
+        /// Kitties of a dormant owner listed. owner / beneficiary / number of listed kitties / seq
+        DeadMansSwitchTriggered(AccountId, AccountId, u32, u32),
+        /// Poll created. poll id / description hash / end block / number of options / seq
+        PollCreated(PollId, Hash, BlockNumber, u8, u32),
+        /// Vote cast. voter / poll id / option / power / seq
+        Voted(AccountId, PollId, u8, u32, u32),
+        /// Poll closed. poll id / tally / seq
+        PollClosed(PollId, Vec<u32>, u32),
+        /// Promotional window scheduled. start / end / max per account / max total / seq
+        PromoWindowSet(BlockNumber, BlockNumber, u32, u32, u32),
+        /// Kitty created without the creation fee. owner / kitty id / seq
+        PromoMint(AccountId, KittyIndex, u32),
+        /// Creation fee paid. owner / kitty id / fee / seq
+        CreationFeePaid(AccountId, KittyIndex, Balance, u32),
+        /// Starter kitty minted to a new account. owner / kitty id / seq
+        StarterKittyMinted(AccountId, KittyIndex, u32),
+        /// Stored kitties rewritten in the latest layout. number rewritten / next id to check / seq
+        KittiesUpgraded(u32, KittyIndex, u32),
+        /// Bundle trade proposed. proposer / counterparty / trade id / seq
+        TradeProposed(AccountId, AccountId, TradeId, u32),
+        /// Bundle trade accepted and swapped. trade id / seq
+        TradeAccepted(TradeId, u32),
+        /// Bundle trade cancelled, expired or voided, locks released. trade id / seq
+        TradeClosed(TradeId, u32),
+        /// Breeding proposed. proposer / partner / proposal id / seq
+        BreedingProposed(AccountId, AccountId, BreedingProposalId, u32),
+        /// Breeding proposal accepted. proposal id / kitten id / seq
+        BreedingAccepted(BreedingProposalId, KittyIndex, u32),
+        /// Breeding proposal cancelled or voided. proposal id / seq
+        BreedingProposalCancelled(BreedingProposalId, u32),
+        /// Kitty swap proposed. proposer / counterparty / swap id / seq
+        SwapProposed(AccountId, AccountId, SwapId, u32),
+        /// Kitties swapped. swap id / proposer / its former kitty / counterparty / its former kitty / seq
+        KittiesSwapped(SwapId, AccountId, KittyIndex, AccountId, KittyIndex, u32),
+        /// Kitty swap cancelled or voided. swap id / seq
+        SwapCancelled(SwapId, u32),
+        /// Breeding pool created. operator / pool id / seq
+        PoolCreated(AccountId, PoolId, u32),
+        /// Kitty contributed to a breeding pool. contributor / pool id / kitty id / seq
+        PoolJoined(AccountId, PoolId, KittyIndex, u32),
+        /// Kitty withdrawn from a breeding pool. contributor / pool id / kitty id / seq
+        PoolLeft(AccountId, PoolId, KittyIndex, u32),
+        /// Genes of a bred kitten mutated instead of being inherited. owner / kitten id /
+        /// number of mutated genes / seq
+        KittyMutated(AccountId, KittyIndex, u32, u32),
+        /// Kitten bred in a breeding pool. pool id / kitten id / recipient / generation of the
+        /// kitten / seq
+        PoolKittyBred(PoolId, KittyIndex, AccountId, u32, u32),
+        /// Breeding pool dissolved, every kitty returned. pool id / seq
+        PoolDissolved(PoolId, u32),
+        /// The last kitty id was taken, no kitty can be minted anymore. seq
+        MintingExhausted(u32),
+        /// A kitty minted on the side of a call, such as a badge or a starter kitty, was
+        /// skipped because minting is exhausted or paused, the supply cap is reached, the account
+        /// holds as many kitties as it may or the mint failed, e.g. on a hook veto. account / seq
+        AutoMintSkipped(AccountId, u32),
     }
 );
 
 // Errors inform users that something went wrong.
 decl_error! {
-    pub enum Error for Module<T: Trait> {
+    pub enum Error for Module<T: Config<I>, I: Instance> {
         NoneValue,
         StorageOverflow,
         SameGenderBreed,
         KittenNotFound,
+        /// Another kitty has the DNA of the new kitty and every genome re-derived from it.
         WrongDNA,
         NotForSale,
+        /// The listing's seller no longer owns the kitty.
+        StaleListing,
+        /// The listing expired, or would expire in the past.
+        ListingExpired,
+        /// Only expired listings can be removed with `expire_listing`.
+        ListingNotExpired,
+        /// The seller can't cover the `ListingDeposit`.
+        ListingDepositUnavailable,
+        /// The kitty is listed in another asset than the one offered.
+        WrongPaymentAsset,
         PriceTooLow,
         BuyFromSelf,
+        NoRefund,
+        /// The destination already holds an ownership entry for this kitty.
+        DuplicateKittySlot,
+        QuoteNotFound,
+        QuoteExpired,
+        /// The bid is above the ask or the quoter owns the kitty.
+        InvalidQuote,
+        /// The quoter's reserved funds no longer cover the bid.
+        QuoteUnderfunded,
+        /// The quote or offer was replaced since the given nonce was observed.
+        OfferChanged,
+        /// The bidder owns the kitty or the expiry is in the past.
+        InvalidOffer,
+        OfferNotFound,
+        OfferExpired,
+        /// Only the bidder can cancel an offer before it expires.
+        OfferNotExpired,
+        /// The bidder's reserved funds no longer cover the offer.
+        OfferUnderfunded,
+        /// Auctions must end in a later block.
+        InvalidAuction,
+        AuctionNotFound,
+        /// Bids are no longer accepted.
+        AuctionEnded,
+        /// The bid is below the reserve price or not above the best bid.
+        BidTooLow,
+        /// As many auctions as possible already end in the block.
+        TooManyAuctionsEnding,
+        /// No ended auction is waiting to be settled.
+        NothingToSettle,
+        /// The reserved funds of the best bidder no longer cover the bid.
+        BidUnderfunded,
+        /// Dutch auctions need a duration and a price that doesn't rise.
+        InvalidDutchAuction,
+        /// Gifts go to another account and can't expire in the past.
+        InvalidGift,
+        GiftNotFound,
+        NotGiftRecipient,
+        GiftExpired,
+        /// Only the sender can cancel a gift before it expires.
+        GiftNotExpired,
+        /// Rentals last at least one period and at most the listed maximum, and can't be taken by
+        /// the owner.
+        InvalidRental,
+        NotForRent,
+        /// The kitty is leased to someone else.
+        KittyRented,
+        /// As many rentals as possible already end in the block.
+        TooManyRentalsEnding,
+        /// The kitty isn't on a streamed rental.
+        NoRentEscrow,
+        /// Only the owner and the renter of a streamed rental can act on it.
+        NotRentalParty,
+        OutOfSeason,
+        /// A season ends before it starts or overlaps another one.
+        InvalidSeasons,
+        TooManySeasons,
+        /// The kitty is locked, e.g. by a pending redemption.
+        KittyLocked,
+        /// The kitty isn't locked for the given reason.
+        NotLocked,
+        /// The kitty is soulbound.
+        KittySoulbound,
+        RedemptionNotFound,
+        RedemptionExpired,
+        TooManyRedemptions,
+        /// Fee steps must have strictly increasing volumes and be at most `MAX_FEE_STEPS`.
+        InvalidFeeSchedule,
+        /// The kitty changed beyond what the buyer accepted.
+        KittyStateChanged,
+        /// A poll needs at least two options and must end in the future.
+        InvalidPoll,
+        TooManyPolls,
+        PollNotFound,
+        PollEnded,
+        PollNotEnded,
+        InvalidPollOption,
+        AlreadyVoted,
+        /// All kitties of the voter already voted in the poll.
+        NoVotingPower,
+        /// The votes of the caller's kitties would take the poll past `MaxPollVotes`.
+        PollFull,
+        NoDeadMansSwitch,
+        /// The free bidding balance doesn't cover the amount.
+        InsufficientBiddingFunds,
+        /// The caller doesn't hold the creator role of the kitty.
+        NotCreator,
+        /// The caller wasn't offered the creator role of the kitty.
+        NoPendingCreator,
+        /// The account already has a pending creation commitment.
+        CommitmentExists,
+        NoCommitment,
+        RevealTooEarly,
+        CommitmentExpired,
+        CommitmentNotExpired,
+        /// The salt doesn't match the commitment.
+        CommitmentMismatch,
+        /// The owner was active within the switch's inactivity period.
+        OwnerNotDormant,
+        /// The window is empty or already over.
+        InvalidPromoWindow,
+        PromoWindowOverlaps,
+        TooManyPromoWindows,
+        /// The randomness could be known at commit time, retry once it changed.
+        RandomnessNotFresh,
+        /// Empty, one-sided with the proposer, too large or listing a kitty twice.
+        InvalidTrade,
+        TradeNotFound,
+        NotTradeCounterparty,
+        TradeExpired,
+        /// Only the proposer can close a trade before it expires.
+        TradeNotExpired,
+        /// The counterparty no longer holds a wanted kitty; the proposal was closed.
+        WantedKittyUnavailable,
+        BreedingProposalNotFound,
+        /// Only the partner of a breeding proposal can accept it.
+        NotBreedingPartner,
+        /// Only the proposer can cancel a breeding proposal.
+        NotBreedingProposer,
+        /// A kitty of the breeding proposal changed hands; the proposal was cancelled.
+        BreedingProposalStale,
+        /// The proposer can't swap a kitty with itself.
+        InvalidSwap,
+        SwapNotFound,
+        /// Only the counterparty of a swap can accept it.
+        NotSwapCounterparty,
+        /// Only the proposer or the counterparty can cancel a swap.
+        NotSwapParty,
+        /// A kitty of the swap changed hands; the swap was cancelled.
+        SwapStale,
+        /// Called back into by an outside hook while the pallet is settling.
+        ReentrancyDetected,
+        /// Every kitty id was taken, see `MintingExhausted`.
+        MintingExhausted,
+        PoolNotFound,
+        NotPoolOperator,
+        /// The pool holds `MaxPoolKitties` kitties.
+        PoolFull,
+        /// The kitty isn't in the pool, or wasn't contributed by the caller.
+        NotInPool,
+        /// The pooled kitty is still resting after breeding.
+        PoolKittyResting,
+        /// The account isn't verified by the `PersonhoodProvider`.
+        NotVerified,
+        /// The account already claimed its starter kitty.
+        StarterAlreadyClaimed,
+        /// The account already holds `MaxKittiesPerOwner` kitties.
+        TooManyKitties,
+        /// The caller can't pay the `CreationFee`.
+        CannotPayCreationFee,
+        /// `MaxCreatePerBlock` kitties were created in this block already.
+        CreateRateLimited,
+        /// `MaxBreedPerBlock` kitties were bred in this block already.
+        BreedRateLimited,
+        /// The caller can't pay the fee of a breed.
+        InsufficientBreedingFee,
+        /// A parent already had `MaxBreedsPerKitty` kittens.
+        KittyExhausted,
+        /// The kitty is younger than `MaturityPeriod`.
+        KittyTooYoung,
+        /// One kitty is a parent of the other, or they share a parent.
+        RelatedKitties,
+        /// `MaxKittySupply` kitties exist already.
+        SupplyCapReached,
+        /// The pallet is paused.
+        PalletPaused,
+        /// The caller is neither the owner nor the approved delegate of the kitty.
+        NotApproved,
+        /// The owner of the partner kitty didn't approve the caller to breed with it.
+        BreedingNotApproved,
     }
 }
 
 decl_module! {
-    pub struct Module<T: Trait> for enum Call where origin: T::Origin {
-        type Error = Error<T>;
+    pub struct Module<T: Config<I>, I: Instance = DefaultInstance> for enum Call where origin: T::Origin {
+        type Error = Error<T, I>;
 
         fn deposit_event() = default;
 
-        #[weight = 1000]
-        pub fn create(origin) {
-            let sender = ensure_signed(origin)?;
-            let dna = Self::random_value(&sender);
-            let kitty = Kitty(dna);
-			let kitty_id = NftModule::<T>::mint(&sender, Self::class_id(), Vec::new(), kitty.clone())?;
+        const TokenDecimals: u8 = T::TokenDecimals::get();
+        const TokenSymbol: &'static str = T::TokenSymbol::get();
 
-            Self::deposit_event(RawEvent::KittyCreated(sender, kitty_id, kitty));
+        fn on_runtime_upgrade() -> Weight {
+            // ahead of the migrations decoding kitties
+            migrations::tag_v1_kitties::<T, I>()
+                .saturating_add(migrations::start_rehash_kitty_prices::<T, I>())
+                .saturating_add(migrations::backfill_kitty_reserves::<T, I>())
+                .saturating_add(migrations::migrate_listings::<T, I>())
+                .saturating_add(migrations::count_owned_kitties::<T, I>())
+                .saturating_add(migrations::register_kitty_dna::<T, I>())
+                .saturating_add(migrations::add_listing_expiry::<T, I>())
+                .saturating_add(migrations::add_listing_deposit::<T, I>())
+                .saturating_add(migrations::add_listing_asset::<T, I>())
+                .saturating_add(migrations::index_listings::<T, I>())
+                .saturating_add(migrations::add_offer_nonce::<T, I>())
+                .saturating_add(migrations::mark_kitties_tagged::<T, I>())
         }
 
-        #[weight = 1000]
-        pub fn breed(origin, first_kitty_id: KittyIndexOf<T>, second_kitty_id: KittyIndexOf<T>) {
-            let sender = ensure_signed(origin)?;
-            let first_kitty = Self::kitties(&sender, first_kitty_id).ok_or_else(|| Error::<T>::KittenNotFound)?;
-            let second_kitty = Self::kitties(&sender, second_kitty_id).ok_or_else(|| Error::<T>::KittenNotFound)?;
+        fn on_initialize(now: T::BlockNumber) -> Weight {
+            KittiesCreatedThisBlock::<I>::kill();
+            KittiesBredThisBlock::<I>::kill();
+            // queueing the auctions ending in on_finalize
+            let ending = AuctionsEnding::<T, I>::decode_len(now).unwrap_or(0) as Weight;
+            let (_, weight) = Self::settle_queued(u32::max_value(), T::AuctionSettlementBudget::get());
+            migrations::rehash_kitty_prices::<T, I>(migrations::REHASH_CHUNK)
+                .saturating_add(Self::sweep_expired_listings(now, LISTING_SWEEP_LIMIT))
+                .saturating_add(Self::end_rentals(now))
+                .saturating_add(weight)
+                .saturating_add(T::DbWeight::get().writes(2))
+                .saturating_add(T::DbWeight::get().reads_writes(2 + ending, 2 + ending))
+        }
 
-            ensure!(first_kitty.gender() != second_kitty.gender(), Error::<T>::SameGenderBreed);
+        fn offchain_worker(now: T::BlockNumber) {
+            Self::submit_listing_expiries(now);
+        }
 
-            let mut new_kitty_dna = [0u8; 16];
-            let random_dna_selector = Self::random_value(&sender);
+        fn on_finalize(now: T::BlockNumber) {
+            Self::queue_ended_auctions(now);
+            EventSeq::<I>::kill();
+        }
 
-            for i in 0..new_kitty_dna.len() {
-                new_kitty_dna[i] = combine_dna(
-                    first_kitty.dna()[i],
-                    second_kitty.dna()[i],
-                    random_dna_selector[i]);
-            }
+        #[weight = T::WeightInfo::create()]
+        pub fn create(origin) {
+            let sender = ensure_signed(origin)?;
+            Self::note_activity(&sender);
+            Self::ensure_not_paused()?;
+            let dna = Self::unique_dna(Self::random_value(&sender))?;
+            let kitty = Kitty(dna);
+            let fee = Self::creation_price();
+            let (kitty_id, promo) = with_transaction_result(|| {
+                Self::take_mint_budget(false)?;
+                let promo = Self::take_promo_mint(&sender);
+                if !promo {
+                    Self::charge_fee_or(&sender, fee, Error::<T, I>::CannotPayCreationFee)?;
+                }
+                let kitty_id = Self::do_mint(&sender, kitty.clone(), None)?;
+                Self::take_kitty_deposit(&sender, kitty_id)?;
+                Ok((kitty_id, promo))
+            })?;
+
+            Self::count_activity(ActivityKind::Creates);
+            Self::deposit_event(RawEvent::KittyCreated(sender.clone(), kitty_id, kitty, Self::next_event_seq()));
+            if promo {
+                Self::deposit_event(RawEvent::PromoMint(sender, kitty_id, Self::next_event_seq()));
+            } else if !fee.is_zero() {
+                Self::deposit_event(RawEvent::CreationFeePaid(sender, kitty_id, fee, Self::next_event_seq()));
+            }
+        }
+
+        /// First phase of a creation whose DNA can't be predicted by the caller: commit to
+        /// `hash_of(&(caller, salt))` and reserve `CommitFee`.
+        #[weight = 1000]
+        pub fn commit_create(origin, commitment: T::Hash) {
+            let sender = ensure_signed(origin)?;
+            Self::note_activity(&sender);
+            Self::ensure_not_paused()?;
+
+            ensure!(!CreateCommitments::<T, I>::contains_key(&sender), Error::<T, I>::CommitmentExists);
+
+            let fee = T::CommitFee::get();
+            Self::reserve_funds(&sender, fee)?;
+            let committed_at = <frame_system::Module<T>>::block_number();
+            CreateCommitments::<T, I>::insert(&sender, CreateCommitment { commitment, committed_at, fee });
+
+            let (reveal_from, expires_at) = Self::reveal_period(committed_at);
+            Self::deposit_event(RawEvent::CreateCommitted(sender, commitment, reveal_from, expires_at, Self::next_event_seq()));
+        }
+
+        /// Second phase of `commit_create`: mint a kitty whose DNA is drawn from secure
+        /// randomness that wasn't known at the commitment.
+        #[weight = 1000]
+        pub fn reveal_create(origin, salt: [u8; 32]) {
+            let sender = ensure_signed(origin)?;
+            Self::note_activity(&sender);
+
+            let pending = Self::create_commitment(&sender).ok_or(Error::<T, I>::NoCommitment)?;
+            let now = <frame_system::Module<T>>::block_number();
+            let (reveal_from, expires_at) = Self::reveal_period(pending.committed_at);
+            ensure!(now >= reveal_from, Error::<T, I>::RevealTooEarly);
+            ensure!(now <= expires_at, Error::<T, I>::CommitmentExpired);
+            ensure!(T::Hashing::hash_of(&(&sender, &salt)) == pending.commitment, Error::<T, I>::CommitmentMismatch);
+
+            let (seed, known_since) = Self::secure_random(b"kitties/reveal");
+            ensure!(known_since > pending.committed_at, Error::<T, I>::RandomnessNotFresh);
+            let kitty = Kitty(Self::unique_dna(blake2_128(&(seed, &sender, &salt).encode()))?);
+
+            let kitty_id = with_transaction_result(|| {
+                Self::take_mint_budget(false)?;
+                CreateCommitments::<T, I>::remove(&sender);
+                Self::unreserve_funds(&sender, pending.fee);
+                Self::charge_fee(&sender, pending.fee)?;
+                let kitty_id = Self::do_mint(&sender, kitty.clone(), None)?;
+                Self::take_kitty_deposit(&sender, kitty_id)?;
+                Ok(kitty_id)
+            })?;
+
+            Self::count_activity(ActivityKind::Creates);
+            Self::deposit_event(RawEvent::KittyCreated(sender, kitty_id, kitty, Self::next_event_seq()));
+        }
+
+        /// Mint a kitty of exactly `dna` to `owner`, for genesis and promotional drops. No fee,
+        /// deposit or per-block limit applies; the supply cap and `MaxKittiesPerOwner` do.
+        #[weight = 1000]
+        pub fn force_create(origin, owner: T::AccountId, dna: [u8; 16]) {
+            T::ForceOrigin::ensure_origin(origin)?;
+
+            let kitty = Kitty(dna);
+            let kitty_id = Self::do_mint(&owner, kitty.clone(), None)?;
+
+            Self::count_activity(ActivityKind::Creates);
+            Self::deposit_event(RawEvent::KittyCreated(owner, kitty_id, kitty, Self::next_event_seq()));
+        }
+
+        /// Drop an expired commitment of `who`, forfeiting `CommitForfeit` of its fee and
+        /// returning the rest. Anyone can call it.
+        #[weight = 1000]
+        pub fn expire_commitment(origin, who: T::AccountId) {
+            ensure_signed(origin)?;
+
+            let pending = Self::create_commitment(&who).ok_or(Error::<T, I>::NoCommitment)?;
+            let (_, expires_at) = Self::reveal_period(pending.committed_at);
+            ensure!(<frame_system::Module<T>>::block_number() > expires_at, Error::<T, I>::CommitmentNotExpired);
+
+            CreateCommitments::<T, I>::remove(&who);
+            let forfeit = T::CommitForfeit::get() * pending.fee;
+            let (imbalance, _) = T::Currency::slash_reserved(&who, forfeit.min(Self::available_reserve(&who)));
+            let forfeited = imbalance.peek();
+            Self::handle_fee(imbalance);
+            if !forfeited.is_zero() {
+                Self::record_flow(&who, None, forfeited, FlowKind::Fee);
+            }
+            Self::forget_reserve(&who, forfeit);
+            Self::unreserve_funds(&who, pending.fee.saturating_sub(forfeit));
+
+            Self::deposit_event(RawEvent::CreateCommitmentExpired(who, forfeited, Self::next_event_seq()));
+        }
+
+        /// Breed two own kitties of different genders. Passing `preferred_gender` charges
+        /// `GenderPreferenceFee` and enforces that gender with `GenderPreferenceChance`.
+        #[weight = T::WeightInfo::breed()]
+        pub fn breed(
+            origin,
+            first_kitty_id: KittyIndexOf<T>,
+            second_kitty_id: KittyIndexOf<T>,
+            preferred_gender: Option<KittyGender>,
+        ) {
+            let sender = ensure_signed(origin)?;
+            Self::note_activity(&sender);
+            Self::ensure_not_paused()?;
+            ensure!(Self::in_season(<frame_system::Module<T>>::block_number()), Error::<T, I>::OutOfSeason);
+            let context = BreedContext::<T>::load::<I>(&sender, first_kitty_id, &sender, second_kitty_id)?;
+            Self::do_breed(&sender, &sender, context, preferred_gender)?;
+        }
+
+        /// Offer a kitty of yours as a stud anyone can breed with for `price`, or withdraw the
+        /// offer with `None`. The offer ends when the kitty changes hands.
+        #[weight = 1000]
+        pub fn set_siring_price(origin, kitty_id: KittyIndexOf<T>, price: Option<BalanceOf<T, I>>) {
+            let sender = ensure_signed(origin)?;
+            Self::note_activity(&sender);
+
+            ensure!(Self::is_acting_owner(&sender, kitty_id), Error::<T, I>::KittenNotFound);
+            Self::ensure_unlocked(kitty_id)?;
+            match price {
+                Some(price) => {
+                    Self::ensure_not_paused()?;
+                    Self::ensure_mature(kitty_id)?;
+                    SiringPrices::<T, I>::insert(kitty_id, price);
+                }
+                None => SiringPrices::<T, I>::remove(kitty_id),
+            }
+
+            Self::deposit_event(RawEvent::SiringPriceUpdated(sender, kitty_id, price, Self::next_event_seq()));
+        }
+
+        /// Breed an own kitty with the stud of `stud_owner`, paying its siring fee of at most
+        /// `max_fee` to the owner. The caller gets the kitten. A stud owner breeding with their
+        /// own stud pays no fee.
+        #[weight = 1000]
+        pub fn breed_with_stud(
+            origin,
+            my_kitty_id: KittyIndexOf<T>,
+            stud_owner: T::AccountId,
+            stud_kitty_id: KittyIndexOf<T>,
+            max_fee: BalanceOf<T, I>,
+        ) {
+            let sender = ensure_signed(origin)?;
+            Self::note_activity(&sender);
+            Self::ensure_not_paused()?;
+            ensure!(Self::in_season(<frame_system::Module<T>>::block_number()), Error::<T, I>::OutOfSeason);
+            let context = BreedContext::<T>::load::<I>(&sender, my_kitty_id, &stud_owner, stud_kitty_id)?;
+            if sender == stud_owner {
+                Self::do_breed(&sender, &sender, context, None)?;
+                return Ok(());
+            }
+            let fee = Self::siring_price(stud_kitty_id).ok_or(Error::<T, I>::NotForSale)?;
+            ensure!(max_fee >= fee, Error::<T, I>::PriceTooLow);
+
+            let payee = Self::proceeds_payee(&stud_owner);
+            with_transaction_result(|| {
+                Self::transfer_funds(&sender, &payee, fee, FlowKind::StudFee)?;
+                Self::do_breed(&sender, &sender, context, None)?;
+                Self::deposit_event(RawEvent::StudFeePaid(sender, stud_owner, stud_kitty_id, fee, Self::next_event_seq()));
+                Ok(())
+            })?;
+        }
+
+        /// Let `partner` breed one of their kitties with a kitty of yours once, with
+        /// `breed_with`, or revoke the approval with `None`.
+        #[weight = 1000]
+        pub fn approve_breeding(origin, kitty_id: KittyIndexOf<T>, partner: Option<T::AccountId>) {
+            let sender = ensure_signed(origin)?;
+            Self::note_activity(&sender);
+
+            ensure!(Self::is_acting_owner(&sender, kitty_id), Error::<T, I>::KittenNotFound);
+            match &partner {
+                Some(partner) => SiringApprovals::<T, I>::insert(kitty_id, partner),
+                None => SiringApprovals::<T, I>::remove(kitty_id),
+            }
+
+            Self::deposit_event(RawEvent::BreedingApproved(sender, kitty_id, partner, Self::next_event_seq()));
+        }
+
+        /// Breed an own kitty with a kitty of `partner_owner` whose breeding was approved for
+        /// the caller. The caller gets the kitten and pays for it; the approval is used up.
+        #[weight = 1000]
+        pub fn breed_with(
+            origin,
+            my_kitty_id: KittyIndexOf<T>,
+            partner_owner: T::AccountId,
+            partner_kitty_id: KittyIndexOf<T>,
+        ) {
+            let sender = ensure_signed(origin)?;
+            Self::note_activity(&sender);
+            Self::ensure_not_paused()?;
+            ensure!(Self::in_season(<frame_system::Module<T>>::block_number()), Error::<T, I>::OutOfSeason);
+            ensure!(Self::siring_approval(partner_kitty_id) == Some(sender.clone()), Error::<T, I>::BreedingNotApproved);
+            let context = BreedContext::<T>::load::<I>(&sender, my_kitty_id, &partner_owner, partner_kitty_id)?;
+            with_transaction_result(|| {
+                SiringApprovals::<T, I>::remove(partner_kitty_id);
+                Self::do_breed(&sender, &sender, context, None)
+            })?;
+        }
+
+        #[weight = T::WeightInfo::transfer()]
+        pub fn transfer(origin, kitty_id: KittyIndexOf<T>, new_owner_id: T::AccountId) -> DispatchResultWithPostInfo {
+            let sender = ensure_signed(origin)?;
+            // the class id and the owner entry
+            let checked = Self::note_activity(&sender).saturating_add(T::DbWeight::get().reads(2));
+
+			ensure!(
+				orml_nft::TokensByOwner::<T>::contains_key(&sender, (Self::class_id(), kitty_id)),
+				Error::<T, I>::KittenNotFound.with_weight(checked)
+			);
+			if sender == new_owner_id {
+				return Ok(Some(checked).into());
+			}
+			Self::give_kitty(sender, new_owner_id, kitty_id)?;
+			Ok(().into())
+        }
+
+        /// Let `delegate` move a kitty of yours with `transfer_from`, or revoke the approval
+        /// with `None`. A kitty has at most one delegate, cleared whenever the kitty moves.
+        #[weight = 1000]
+        pub fn approve(origin, kitty_id: KittyIndexOf<T>, delegate: Option<T::AccountId>) {
+            let sender = ensure_signed(origin)?;
+            Self::note_activity(&sender);
+
+			ensure!(orml_nft::TokensByOwner::<T>::contains_key(&sender, (Self::class_id(), kitty_id)), Error::<T, I>::KittenNotFound);
+			match &delegate {
+				Some(delegate) => KittyApprovals::<T, I>::insert(kitty_id, delegate),
+				None => KittyApprovals::<T, I>::remove(kitty_id),
+			}
+
+			Self::deposit_event(RawEvent::KittyApproved(sender, kitty_id, delegate, Self::next_event_seq()));
+        }
+
+        /// Move a kitty of `owner` to `new_owner`, as its owner or its approved delegate.
+        #[weight = 1000]
+        pub fn transfer_from(origin, owner: T::AccountId, new_owner: T::AccountId, kitty_id: KittyIndexOf<T>) {
+            let sender = ensure_signed(origin)?;
+            Self::note_activity(&sender);
+
+			ensure!(orml_nft::TokensByOwner::<T>::contains_key(&owner, (Self::class_id(), kitty_id)), Error::<T, I>::KittenNotFound);
+			ensure!(sender == owner || Self::kitty_approval(kitty_id) == Some(sender.clone()), Error::<T, I>::NotApproved);
+			let by_delegate = sender != owner && owner != new_owner;
+			Self::give_kitty(owner, new_owner, kitty_id)?;
+
+			if by_delegate {
+				Self::deposit_event(RawEvent::TransferredByDelegate(sender, kitty_id, Self::next_event_seq()));
+			}
+        }
+
+        /// Auction a kitty of yours to the best bid of at least `reserve_price` placed up to
+        /// `end_block`. The kitty is delisted and locked until the auction is settled, which
+        /// happens in a later block, see `settle_next`.
+        #[weight = 1000]
+        pub fn start_auction(origin, kitty_id: KittyIndexOf<T>, reserve_price: BalanceOf<T, I>, end_block: T::BlockNumber) {
+            let sender = ensure_signed(origin)?;
+            Self::note_activity(&sender);
+            Self::ensure_not_paused()?;
+
+            ensure!(Self::kitties(&sender, kitty_id).is_some(), Error::<T, I>::KittenNotFound);
+            Self::ensure_unlocked(kitty_id)?;
+            Self::ensure_mature(kitty_id)?;
+            ensure!(end_block > <frame_system::Module<T>>::block_number(), Error::<T, I>::InvalidAuction);
+            ensure!(Self::auctions_ending(end_block).len() < MAX_AUCTIONS_PER_BLOCK, Error::<T, I>::TooManyAuctionsEnding);
+
+            AuctionsEnding::<T, I>::append(end_block, kitty_id);
+            Auctions::<T, I>::insert(kitty_id, Auction { seller: sender.clone(), reserve_price, end_block, best_bid: None });
+            if Self::remove_listing(kitty_id).is_some() {
+                Self::deposit_event(RawEvent::KittyPriceUpdated(sender.clone(), kitty_id, None, None, Self::next_event_seq()));
+            }
+
+            Self::deposit_event(RawEvent::AuctionStarted(sender, kitty_id, reserve_price, end_block, Self::next_event_seq()));
+        }
+
+        /// Bid on an auctioned kitty. The bid is reserved and the previous best bid released.
+        #[weight = 1000]
+        pub fn bid(origin, kitty_id: KittyIndexOf<T>, amount: BalanceOf<T, I>) {
+            let sender = ensure_signed(origin)?;
+            Self::note_activity(&sender);
+            Self::ensure_not_paused()?;
+
+            let mut auction = Self::auction(kitty_id).ok_or(Error::<T, I>::AuctionNotFound)?;
+            ensure!(auction.seller != sender, Error::<T, I>::BuyFromSelf);
+            ensure!(auction.end_block >= <frame_system::Module<T>>::block_number(), Error::<T, I>::AuctionEnded);
+            ensure!(amount >= auction.reserve_price, Error::<T, I>::BidTooLow);
+            if let Some((_, best)) = &auction.best_bid {
+                ensure!(amount > *best, Error::<T, I>::BidTooLow);
+            }
+
+            with_transaction_result(|| {
+				if let Some((bidder, best)) = auction.best_bid.take() {
+					Self::refund_reserved(&bidder, best);
+				}
+				Self::reserve_funds(&sender, amount)?;
+				auction.best_bid = Some((sender.clone(), amount));
+				Auctions::<T, I>::insert(kitty_id, auction);
+				Ok(())
+			})?;
+
+            Self::deposit_event(RawEvent::AuctionBid(sender, kitty_id, amount, Self::next_event_seq()));
+        }
+
+        /// Settle up to `limit` ended auctions from the head of the `SettlementQueue`, for a
+        /// `SettlementReward` each. Anyone can call it when `on_initialize` falls behind.
+        #[weight = Module::<T, I>::auction_settlement_weight()
+            .saturating_mul(Weight::from(*limit))
+            .saturating_add(T::DbWeight::get().reads_writes(2, 2))]
+        pub fn settle_next(origin, limit: u32) -> DispatchResultWithPostInfo {
+            let sender = ensure_signed(origin)?;
+            Self::note_activity(&sender);
+
+            let (settled, weight) = Self::settle_queued(limit, Weight::max_value());
+            ensure!(settled > 0, Error::<T, I>::NothingToSettle);
+            Self::pay_settlement_reward(&sender, settled);
+            Ok(Some(weight.saturating_add(T::DbWeight::get().reads_writes(2, 2))).into())
+        }
+
+        /// List a kitty of yours at a price falling from `start_price` to `end_price` over
+        /// `duration` blocks, replacing its fixed price. It stays buyable at `end_price` after.
+        #[weight = 1000]
+        pub fn start_dutch_auction(
+            origin,
+            kitty_id: KittyIndexOf<T>,
+            start_price: BalanceOf<T, I>,
+            end_price: BalanceOf<T, I>,
+            duration: T::BlockNumber,
+        ) {
+            let sender = ensure_signed(origin)?;
+            Self::note_activity(&sender);
+            Self::ensure_not_paused()?;
+
+            ensure!(Self::kitties(&sender, kitty_id).is_some(), Error::<T, I>::KittenNotFound);
+            Self::ensure_unlocked(kitty_id)?;
+            Self::ensure_mature(kitty_id)?;
+            ensure!(!duration.is_zero() && end_price <= start_price, Error::<T, I>::InvalidDutchAuction);
+
+            DutchAuctions::<T, I>::insert(kitty_id, DutchAuction {
+                seller: sender.clone(),
+                start_price,
+                end_price,
+                start_block: <frame_system::Module<T>>::block_number(),
+                duration,
+            });
+            if Self::remove_listing(kitty_id).is_some() {
+                Self::deposit_event(RawEvent::KittyPriceUpdated(sender.clone(), kitty_id, None, None, Self::next_event_seq()));
+            }
+
+            Self::deposit_event(RawEvent::DutchAuctionStarted(sender, kitty_id, start_price, end_price, duration, Self::next_event_seq()));
+        }
+
+        /// Withdraw a Dutch auction of yours.
+        #[weight = 1000]
+        pub fn cancel_dutch_auction(origin, kitty_id: KittyIndexOf<T>) {
+            let sender = ensure_signed(origin)?;
+            Self::note_activity(&sender);
+
+            let auction = Self::dutch_auction(kitty_id).ok_or(Error::<T, I>::NotForSale)?;
+            ensure!(auction.seller == sender, Error::<T, I>::KittenNotFound);
+            DutchAuctions::<T, I>::remove(kitty_id);
+
+            Self::deposit_event(RawEvent::DutchAuctionCancelled(sender, kitty_id, Self::next_event_seq()));
+        }
+
+        /// Buy a kitty on Dutch auction at its current price, see `current_dutch_price`.
+        #[weight = 1000]
+        pub fn buy_dutch(origin, kitty_id: KittyIndexOf<T>, max_price: BalanceOf<T, I>) {
+            let sender = ensure_signed(origin)?;
+            Self::note_activity(&sender);
+            Self::ensure_not_paused()?;
+
+            let seller = Self::dutch_auction(kitty_id).ok_or(Error::<T, I>::NotForSale)?.seller;
+            ensure!(sender != seller, Error::<T, I>::BuyFromSelf);
+            let price = Self::current_dutch_price(kitty_id).ok_or(Error::<T, I>::NotForSale)?;
+            ensure!(max_price >= price, Error::<T, I>::PriceTooLow);
+
+            let freshness = Self::freshness(kitty_id);
+            let payee = Self::proceeds_payee(&seller);
+            with_transaction_result(|| {
+				Self::transfer_funds(&sender, &payee, price, FlowKind::Sale)?;
+				let fee = Self::settle_market_fee(&payee, price)?;
+				let royalty = Self::pay_royalty(&seller, &payee, kitty_id, price)?;
+				LastSalePrices::<T, I>::insert(kitty_id, price);
+				// ends the auction
+				Self::do_transfer(&seller, &sender, kitty_id)?;
+				Self::note_sale(&seller, &sender, kitty_id, price);
+				Self::count_activity(ActivityKind::Sales);
+
+				Self::deposit_event(RawEvent::KittySold(seller, sender, kitty_id, price, None, fee, royalty, freshness, Self::next_event_seq()));
+				Ok(())
+			})?;
+        }
+
+        /// Remove an expired listing. Submitted unsigned by the off-chain worker, see
+        /// `ValidateUnsigned`.
+        #[weight = 1000]
+        pub fn expire_listing(origin, kitty_id: KittyIndexOf<T>) {
+            ensure_none(origin)?;
+
+            let listing = Self::listing(kitty_id).ok_or(Error::<T, I>::NotForSale)?;
+            ensure!(Self::expired(listing.expires_at), Error::<T, I>::ListingNotExpired);
+            Self::remove_expired_listing(kitty_id, listing.seller);
+        }
+
+        /// Send a kitty to `recipient`, who has to claim it until `expiry`. Until then the kitty
+        /// is held by its gift account and can't be listed, bred or moved.
+        #[weight = 1000]
+        pub fn gift(origin, kitty_id: KittyIndexOf<T>, recipient: T::AccountId, expiry: T::BlockNumber) {
+            let sender = ensure_signed(origin)?;
+            Self::note_activity(&sender);
+
+            ensure!(Self::kitties(&sender, kitty_id).is_some(), Error::<T, I>::KittenNotFound);
+            ensure!(recipient != sender, Error::<T, I>::InvalidGift);
+            ensure!(expiry >= <frame_system::Module<T>>::block_number(), Error::<T, I>::InvalidGift);
+
+            let listed = Listings::<T, I>::contains_key(kitty_id);
+            Self::do_transfer(&sender, &Self::gift_account_id(kitty_id), kitty_id)?;
+            KittyCustodies::<T, I>::insert(kitty_id, Custody { kind: CustodyKind::Gift, owner: sender.clone() });
+            PendingGifts::<T, I>::insert(kitty_id, PendingGift { sender: sender.clone(), recipient: recipient.clone(), expiry });
+
+            if listed {
+                Self::deposit_event(RawEvent::KittyPriceUpdated(sender.clone(), kitty_id, None, None, Self::next_event_seq()));
+            }
+            Self::deposit_event(RawEvent::KittyGifted(sender, recipient, kitty_id, expiry, Self::next_event_seq()));
+        }
+
+        /// Take a kitty gifted to you, up to and including its expiry block.
+        #[weight = 1000]
+        pub fn claim_gift(origin, kitty_id: KittyIndexOf<T>) {
+            let sender = ensure_signed(origin)?;
+            Self::note_activity(&sender);
+
+            let gift = Self::pending_gift(kitty_id).ok_or(Error::<T, I>::GiftNotFound)?;
+            ensure!(gift.recipient == sender, Error::<T, I>::NotGiftRecipient);
+            ensure!(gift.expiry >= <frame_system::Module<T>>::block_number(), Error::<T, I>::GiftExpired);
+
+            Self::do_transfer(&Self::gift_account_id(kitty_id), &sender, kitty_id)?;
+            PendingGifts::<T, I>::remove(kitty_id);
+            Self::note_tax_event(&gift.sender, kitty_id, TaxEventKind::GiftSent { to: sender.clone() });
+            Self::note_tax_event(&sender, kitty_id, TaxEventKind::GiftReceived { from: gift.sender });
+            Self::count_activity(ActivityKind::Transfers);
+
+            Self::deposit_event(RawEvent::GiftClaimed(sender, kitty_id, Self::next_event_seq()));
+        }
+
+        /// Return a pending gift to its sender. The sender can do so any time, anyone else
+        /// once the gift expired.
+        #[weight = 1000]
+        pub fn cancel_gift(origin, kitty_id: KittyIndexOf<T>) {
+            let sender = ensure_signed(origin)?;
+            Self::note_activity(&sender);
+
+            let gift = Self::pending_gift(kitty_id).ok_or(Error::<T, I>::GiftNotFound)?;
+            ensure!(
+                gift.sender == sender || <frame_system::Module<T>>::block_number() > gift.expiry,
+                Error::<T, I>::GiftNotExpired
+            );
+
+            Self::do_transfer(&Self::gift_account_id(kitty_id), &gift.sender, kitty_id)?;
+            PendingGifts::<T, I>::remove(kitty_id);
+
+            Self::deposit_event(RawEvent::GiftCancelled(gift.sender, kitty_id, Self::next_event_seq()));
+        }
+
+        /// Offer a kitty of yours for rent at `price_per_period`, for up to `max_duration`
+        /// periods at a time. Replaces an earlier offer; the offer ends when the kitty changes
+        /// hands.
+        #[weight = 1000]
+        pub fn list_for_rent(origin, kitty_id: KittyIndexOf<T>, price_per_period: BalanceOf<T, I>, max_duration: u32) {
+            let sender = ensure_signed(origin)?;
+            Self::note_activity(&sender);
+            Self::ensure_not_paused()?;
+
+            ensure!(Self::kitties(&sender, kitty_id).is_some(), Error::<T, I>::KittenNotFound);
+            Self::ensure_unlocked(kitty_id)?;
+            ensure!(max_duration > 0, Error::<T, I>::InvalidRental);
+
+            RentalOffers::<T, I>::insert(kitty_id, RentalTerms { price_per_period, max_duration });
+
+            Self::deposit_event(RawEvent::RentalListed(sender, kitty_id, price_per_period, max_duration, Self::next_event_seq()));
+        }
+
+        /// Withdraw the rental offer of a kitty of yours. A running rental isn't affected.
+        #[weight = 1000]
+        pub fn unlist_for_rent(origin, kitty_id: KittyIndexOf<T>) {
+            let sender = ensure_signed(origin)?;
+            Self::note_activity(&sender);
+
+            ensure!(Self::kitties(&sender, kitty_id).is_some(), Error::<T, I>::KittenNotFound);
+            ensure!(RentalOffers::<T, I>::contains_key(kitty_id), Error::<T, I>::NotForRent);
+            RentalOffers::<T, I>::remove(kitty_id);
+
+            Self::deposit_event(RawEvent::RentalUnlisted(sender, kitty_id, Self::next_event_seq()));
+        }
+
+        /// Lease a kitty offered for rent for `duration` periods, paying its owner up front.
+        /// Until the rental ends the caller can breed with it and the owner can't move it.
+        #[weight = 1000]
+        pub fn rent(origin, kitty_id: KittyIndexOf<T>, duration: u32) {
+            let sender = ensure_signed(origin)?;
+            Self::note_activity(&sender);
+            Self::ensure_not_paused()?;
+
+            let (owner, paid, ends_at) = Self::start_rental(&sender, kitty_id, duration, false)?;
+
+            Self::deposit_event(RawEvent::KittyRented(owner, sender, kitty_id, duration, paid, ends_at, Self::next_event_seq()));
+        }
+
+        /// `rent`, but the fee is reserved as an escrow the owner earns block by block, see
+        /// `accrued_rent`. The owner takes the earned part with `claim_lease_income`, either side
+        /// can stop the rental early with `end_lease`.
+        #[weight = 1000]
+        pub fn rent_streamed(origin, kitty_id: KittyIndexOf<T>, duration: u32) {
+            let sender = ensure_signed(origin)?;
+            Self::note_activity(&sender);
+            Self::ensure_not_paused()?;
+
+            let (owner, deposit, ends_at) = Self::start_rental(&sender, kitty_id, duration, true)?;
+
+            Self::deposit_event(RawEvent::KittyRentedStreamed(
+                owner,
+                sender,
+                kitty_id,
+                duration,
+                deposit,
+                ends_at,
+                Self::next_event_seq(),
+            ));
+        }
+
+        /// Take the part of a streamed rental's fee earned so far, as the owner of the kitty.
+        #[weight = 1000]
+        pub fn claim_lease_income(origin, kitty_id: KittyIndexOf<T>) {
+            let sender = ensure_signed(origin)?;
+            Self::note_activity(&sender);
+
+            let mut escrow = Self::rent_escrow(kitty_id).ok_or(Error::<T, I>::NoRentEscrow)?;
+            ensure!(escrow.owner == sender, Error::<T, I>::NotRentalParty);
+            let now = <frame_system::Module<T>>::block_number();
+            let paid = Self::pay_accrued_rent(&mut escrow, now);
+            RentEscrows::<T, I>::insert(kitty_id, escrow);
+
+            Self::deposit_event(RawEvent::RentIncomeClaimed(sender, kitty_id, paid, Self::next_event_seq()));
+        }
+
+        /// Stop a streamed rental before it ends, as its owner or renter. The owner gets the fee
+        /// earned so far, the renter the rest.
+        #[weight = 1000]
+        pub fn end_lease(origin, kitty_id: KittyIndexOf<T>) {
+            let sender = ensure_signed(origin)?;
+            Self::note_activity(&sender);
+
+            let escrow = Self::rent_escrow(kitty_id).ok_or(Error::<T, I>::NoRentEscrow)?;
+            ensure!(escrow.owner == sender || escrow.renter == sender, Error::<T, I>::NotRentalParty);
+
+            KittyRentals::<T, I>::remove(kitty_id);
+            Self::settle_rent_escrow(kitty_id);
+            Self::deposit_event(RawEvent::RentalEnded(escrow.renter, kitty_id, Self::next_event_seq()));
+        }
+
+        /// Move a kitty of `from` to `to` without its owner, for recovering lost accounts and
+        /// settling disputes. The kitty is delisted.
+        #[weight = 1000]
+        pub fn force_transfer(origin, from: T::AccountId, to: T::AccountId, kitty_id: KittyIndexOf<T>) {
+            T::ForceOrigin::ensure_origin(origin)?;
+
+			ensure!(orml_nft::TokensByOwner::<T>::contains_key(&from, (Self::class_id(), kitty_id)), Error::<T, I>::KittenNotFound);
+			let listed = Listings::<T, I>::contains_key(kitty_id);
+			Self::do_transfer(&from, &to, kitty_id)?;
+
+			if from != to {
+				if listed {
+					Self::deposit_event(RawEvent::KittyPriceUpdated(from.clone(), kitty_id, None, None, Self::next_event_seq()));
+				}
+				Self::count_activity(ActivityKind::Transfers);
+				Self::deposit_event(RawEvent::KittyTransferred(from, to, kitty_id, Self::next_event_seq()));
+			}
+        }
+
+        /// Destroy one of your kitties, delisting it if it is for sale.
+        #[weight = 1000]
+        pub fn burn(origin, kitty_id: KittyIndexOf<T>) {
+            let sender = ensure_signed(origin)?;
+            Self::note_activity(&sender);
+
+			Self::burn_kitty(&sender, kitty_id)?;
+        }
+
+        #[weight = T::WeightInfo::set_price()]
+        pub fn set_price(
+            origin,
+            kitty_id: KittyIndexOf<T>,
+            new_price: Option<BalanceOf<T, I>>,
+            expires_at: Option<T::BlockNumber>,
+            asset: Option<AssetIdOf<T, I>>,
+        ) {
+             let sender = ensure_signed(origin)?;
+            Self::note_activity(&sender);
+
+			ensure!(orml_nft::TokensByOwner::<T>::contains_key(&sender, (Self::class_id(), kitty_id)), Error::<T, I>::KittenNotFound);
+			Self::ensure_unlocked(kitty_id)?;
+			if new_price.is_some() {
+				Self::ensure_not_paused()?;
+				Self::ensure_mature(kitty_id)?;
+			}
+			if let Some(expires_at) = expires_at {
+				ensure!(expires_at >= <frame_system::Module<T>>::block_number(), Error::<T, I>::ListingExpired);
+			}
+
+            match new_price {
+                Some(price) => Self::insert_listing(&sender, kitty_id, price, expires_at, asset)?,
+                None => {
+                    Self::remove_listing(kitty_id);
+                }
+            }
+            if let (Some(_), Some(expires_at)) = (new_price, expires_at) {
+                ListingExpiries::<T, I>::insert(expires_at, kitty_id, ());
+            }
+            // a fixed price replaces a Dutch auction
+            DutchAuctions::<T, I>::remove(kitty_id);
+
+            Self::deposit_event(RawEvent::KittyPriceUpdated(sender, kitty_id, new_price, new_price.and(asset), Self::next_event_seq()));
+        }
+
+        /// Buy a listed kitty from the account that listed it. With `max_breed_count` the purchase
+        /// fails if the kitty was bred more often than that, e.g. by the seller in the same block.
+        /// `max_price` is in `asset`, which must be the asset of the listing.
+        #[weight = T::WeightInfo::buy()]
+        pub fn buy(
+            origin,
+            kitty_id: KittyIndexOf<T>,
+            max_price: BalanceOf<T, I>,
+            max_breed_count: Option<u32>,
+            asset: Option<AssetIdOf<T, I>>,
+        ) -> DispatchResultWithPostInfo {
+             let sender = ensure_signed(origin)?;
+            // the pause flag
+            let checked = Self::note_activity(&sender).saturating_add(T::DbWeight::get().reads(1));
+            Self::ensure_not_paused().map_err(|error| error.with_weight(checked))?;
+
+            let mut result = Self::do_buy(sender, kitty_id, max_price, max_breed_count, asset);
+            if let Err(error) = &mut result {
+                let mut failed = checked;
+                if T::VerboseMetrics::get() {
+                    Self::count_activity(ActivityKind::FailedBuys);
+                    failed = failed.saturating_add(T::DbWeight::get().reads_writes(1, 1));
+                }
+                error.post_info.actual_weight = error.post_info.actual_weight.map(|weight| weight.saturating_add(failed));
+            }
+            result
+        }
+
+        /// Quote a bid and an ask on a kitty owned by someone else, reserving the bid.
+        /// Replaces an earlier quote of the caller on the same kitty.
+        #[weight = 1000]
+        pub fn post_quote(origin, kitty_id: KittyIndexOf<T>, bid: BalanceOf<T, I>, ask: BalanceOf<T, I>, expiry: T::BlockNumber) {
+            let sender = ensure_signed(origin)?;
+            Self::note_activity(&sender);
+            Self::ensure_not_paused()?;
+
+            let kitty = NftModule::<T>::tokens(Self::class_id(), kitty_id).ok_or(Error::<T, I>::KittenNotFound)?;
+            ensure!(kitty.owner != sender && bid <= ask, Error::<T, I>::InvalidQuote);
+            ensure!(expiry >= <frame_system::Module<T>>::block_number(), Error::<T, I>::QuoteExpired);
+
+            let nonce = with_transaction_result(|| {
+				if let Some(old) = Quotes::<T, I>::take(kitty_id, &sender) {
+					Self::release_bid(&sender, &old);
+				}
+				let from_wallet = Self::hold_bid(&sender, bid)?;
+				let nonce = NextQuoteNonce::<I>::mutate(|next| {
+					let nonce = *next;
+					*next = next.wrapping_add(1);
+					nonce
+				});
+				Quotes::<T, I>::insert(kitty_id, &sender, Quote { bid, ask, expiry, nonce, from_wallet });
+				Ok(nonce)
+			})?;
+
+            Self::deposit_event(RawEvent::QuotePosted(sender, kitty_id, bid, ask, expiry, nonce, Self::next_event_seq()));
+        }
+
+        #[weight = 1000]
+        pub fn cancel_quote(origin, kitty_id: KittyIndexOf<T>) {
+            let sender = ensure_signed(origin)?;
+            Self::note_activity(&sender);
+
+            let quote = Quotes::<T, I>::take(kitty_id, &sender).ok_or(Error::<T, I>::QuoteNotFound)?;
+            Self::release_bid(&sender, &quote);
+
+            Self::deposit_event(RawEvent::QuoteCancelled(sender, kitty_id, Self::next_event_seq()));
+        }
+
+        /// Offer to buy a kitty for `amount`, reserved until the offer is accepted or cancelled.
+        /// Replaces an earlier offer of the caller on the kitty.
+        #[weight = 1000]
+        pub fn make_offer(origin, kitty_id: KittyIndexOf<T>, amount: BalanceOf<T, I>, expiry: T::BlockNumber) {
+            let sender = ensure_signed(origin)?;
+            Self::note_activity(&sender);
+            Self::ensure_not_paused()?;
+
+            let kitty = NftModule::<T>::tokens(Self::class_id(), kitty_id).ok_or(Error::<T, I>::KittenNotFound)?;
+            ensure!(kitty.owner != sender, Error::<T, I>::InvalidOffer);
+            ensure!(expiry >= <frame_system::Module<T>>::block_number(), Error::<T, I>::InvalidOffer);
+
+            let nonce = with_transaction_result(|| {
+				if let Some(old) = Offers::<T, I>::take(kitty_id, &sender) {
+					Self::refund_reserved(&sender, old.amount);
+				}
+				Self::reserve_funds(&sender, amount)?;
+				let nonce = NextOfferNonce::<I>::mutate(|next| {
+					let nonce = *next;
+					*next = next.wrapping_add(1);
+					nonce
+				});
+				Offers::<T, I>::insert(kitty_id, &sender, Offer { amount, expiry, nonce });
+				Ok(nonce)
+			})?;
+
+            Self::deposit_event(RawEvent::OfferMade(sender, kitty_id, amount, expiry, nonce, Self::next_event_seq()));
+        }
+
+        /// Withdraw the offer of `bidder` on a kitty, releasing its funds. The bidder can do so
+        /// any time, anyone else once the offer expired.
+        #[weight = 1000]
+        pub fn cancel_offer(origin, kitty_id: KittyIndexOf<T>, bidder: T::AccountId) {
+            let sender = ensure_signed(origin)?;
+            Self::note_activity(&sender);
+
+            let offer = Self::offer(kitty_id, &bidder).ok_or(Error::<T, I>::OfferNotFound)?;
+            ensure!(
+                sender == bidder || <frame_system::Module<T>>::block_number() > offer.expiry,
+                Error::<T, I>::OfferNotExpired
+            );
+            Offers::<T, I>::remove(kitty_id, &bidder);
+            Self::refund_reserved(&bidder, offer.amount);
+
+            Self::deposit_event(RawEvent::OfferCancelled(bidder, kitty_id, Self::next_event_seq()));
+        }
+
+        /// Sell an own kitty to `bidder` at its offer. Offers made before the caller got the
+        /// kitty can be accepted as well. `nonce` must be the one of the offer the caller saw,
+        /// see `OfferMade`.
+        #[weight = 1000]
+        pub fn accept_offer(origin, kitty_id: KittyIndexOf<T>, bidder: T::AccountId, nonce: u64) {
+            let sender = ensure_signed(origin)?;
+            Self::note_activity(&sender);
+            Self::ensure_not_paused()?;
+
+            ensure!(Self::kitties(&sender, kitty_id).is_some(), Error::<T, I>::KittenNotFound);
+            let offer = Self::offer(kitty_id, &bidder).ok_or(Error::<T, I>::OfferNotFound)?;
+            ensure!(offer.nonce == nonce, Error::<T, I>::OfferChanged);
+            ensure!(offer.expiry >= <frame_system::Module<T>>::block_number(), Error::<T, I>::OfferExpired);
+            // reserves can be slashed by other pallets after the offer was made
+            ensure!(Self::available_reserve(&bidder) >= offer.amount, Error::<T, I>::OfferUnderfunded);
+
+            let payee = Self::proceeds_payee(&sender);
+            with_transaction_result(|| {
+				Offers::<T, I>::remove(kitty_id, &bidder);
+				let unpaid = T::Currency::repatriate_reserved(&bidder, &payee, offer.amount, BalanceStatus::Free)?;
+				ensure!(unpaid.is_zero(), Error::<T, I>::OfferUnderfunded);
+				Self::forget_reserve(&bidder, offer.amount);
+				Self::record_flow(&bidder, Some(&payee), offer.amount, FlowKind::Sale);
+				let fee = Self::settle_market_fee(&payee, offer.amount)?;
+				let royalty = Self::pay_royalty(&sender, &payee, kitty_id, offer.amount)?;
+				LastSalePrices::<T, I>::insert(kitty_id, offer.amount);
+
+				let freshness = Self::freshness(kitty_id);
+				// clears the listing
+				Self::do_transfer(&sender, &bidder, kitty_id)?;
+				Self::note_sale(&sender, &bidder, kitty_id, offer.amount);
+				Self::count_activity(ActivityKind::Sales);
+				Self::deposit_event(RawEvent::KittySold(sender, bidder, kitty_id, offer.amount, None, fee, royalty, freshness, Self::next_event_seq()));
+
+				Ok(())
+			})?;
+        }
+
+        /// Move funds to the pallet account to bid from without reserving for every quote.
+        #[weight = 1000]
+        pub fn deposit_bidding_funds(origin, amount: BalanceOf<T, I>) {
+            let sender = ensure_signed(origin)?;
+            Self::note_activity(&sender);
+
+            Self::transfer_funds(&sender, &Self::account_id(), amount, FlowKind::Bidding)?;
+            BiddingBalances::<T, I>::mutate(&sender, |balance| balance.free = balance.free.saturating_add(amount));
+
+            Self::deposit_event(RawEvent::BiddingFundsDeposited(sender, amount, Self::next_event_seq()));
+        }
+
+        /// Take back bidding funds not held by an open bid.
+        #[weight = 1000]
+        pub fn withdraw_bidding_funds(origin, amount: BalanceOf<T, I>) {
+            let sender = ensure_signed(origin)?;
+            Self::note_activity(&sender);
+
+            ensure!(Self::bidding_balance(&sender).free >= amount, Error::<T, I>::InsufficientBiddingFunds);
+            Self::transfer_funds(&Self::account_id(), &sender, amount, FlowKind::Bidding)?;
+            BiddingBalances::<T, I>::mutate(&sender, |balance| balance.free -= amount);
+
+            Self::deposit_event(RawEvent::BiddingFundsWithdrawn(sender, amount, Self::next_event_seq()));
+        }
+
+        /// Sell an own kitty to `quoter` at its bid. The quoter's ask then becomes the listing.
+        /// `nonce` must be the one of the quote the caller saw, see `QuotePosted`.
+        #[weight = 1000]
+        pub fn hit_bid(origin, kitty_id: KittyIndexOf<T>, quoter: T::AccountId, nonce: u64) {
+            let sender = ensure_signed(origin)?;
+            Self::note_activity(&sender);
+            Self::ensure_not_paused()?;
+
+            ensure!(Self::kitties(&sender, kitty_id).is_some(), Error::<T, I>::KittenNotFound);
+            let quote = Self::quotes(kitty_id, &quoter).ok_or(Error::<T, I>::QuoteNotFound)?;
+            ensure!(quote.nonce == nonce, Error::<T, I>::OfferChanged);
+            ensure!(quote.expiry >= <frame_system::Module<T>>::block_number(), Error::<T, I>::QuoteExpired);
+            // reserves can be slashed by other pallets after the quote was posted
+            ensure!(
+                quote.from_wallet || Self::available_reserve(&quoter) >= quote.bid,
+                Error::<T, I>::QuoteUnderfunded
+            );
+
+            let payee = Self::proceeds_payee(&sender);
+            with_transaction_result(|| {
+				Quotes::<T, I>::remove(kitty_id, &quoter);
+				if quote.from_wallet {
+					BiddingBalances::<T, I>::mutate(&quoter, |balance| {
+						balance.encumbered = balance.encumbered.saturating_sub(quote.bid)
+					});
+					Self::transfer_funds(&Self::account_id(), &payee, quote.bid, FlowKind::Sale)?;
+				} else {
+					let unpaid = T::Currency::repatriate_reserved(&quoter, &payee, quote.bid, BalanceStatus::Free)?;
+					ensure!(unpaid.is_zero(), Error::<T, I>::QuoteUnderfunded);
+					Self::forget_reserve(&quoter, quote.bid);
+					Self::record_flow(&quoter, Some(&payee), quote.bid, FlowKind::Sale);
+				}
+				Self::settle_market_fee(&payee, quote.bid)?;
+				Self::pay_royalty(&sender, &payee, kitty_id, quote.bid)?;
+				LastSalePrices::<T, I>::insert(kitty_id, quote.bid);
+
+				Self::do_transfer(&sender, &quoter, kitty_id)?;
+				Self::note_sale(&sender, &quoter, kitty_id, quote.bid);
+				Self::list_ask(&quoter, kitty_id, quote.ask);
+
+				let freshness = Self::freshness(kitty_id);
+				Self::count_activity(ActivityKind::Sales);
+				Self::deposit_event(RawEvent::BidHit(sender, quoter, kitty_id, quote.bid, freshness, Self::next_event_seq()));
+
+				Ok(())
+			})?;
+        }
+
+        /// Stop creating, breeding, listing and trading kitties until `unpause`. Transfers,
+        /// delisting and reveals of earlier commitments stay allowed.
+        #[weight = 1000]
+        pub fn pause(origin) {
+            T::AdminOrigin::ensure_origin(origin)?;
+
+            Paused::<I>::put(true);
+
+            Self::deposit_event(RawEvent::Paused(Self::next_event_seq()));
+        }
+
+        /// Lift a `pause`.
+        #[weight = 1000]
+        pub fn unpause(origin) {
+            T::AdminOrigin::ensure_origin(origin)?;
+
+            Paused::<I>::kill();
+
+            Self::deposit_event(RawEvent::Unpaused(Self::next_event_seq()));
+        }
+
+        /// Replace the breeding season schedule. Seasons are inclusive block ranges and must
+        /// not overlap; an empty schedule opens breeding permanently.
+        #[weight = 1000]
+        pub fn set_breeding_seasons(origin, seasons: Vec<(T::BlockNumber, T::BlockNumber)>) {
+            T::ForceOrigin::ensure_origin(origin)?;
+
+            ensure!(seasons.len() <= T::MaxSeasons::get() as usize, Error::<T, I>::TooManySeasons);
+
+            let mut seasons = seasons;
+            seasons.sort();
+            ensure!(seasons.iter().all(|(start, end)| start <= end), Error::<T, I>::InvalidSeasons);
+            ensure!(seasons.windows(2).all(|pair| pair[0].1 < pair[1].0), Error::<T, I>::InvalidSeasons);
+
+            BreedingSeasons::<T, I>::put(&seasons);
+
+            Self::deposit_event(RawEvent::BreedingSeasonsUpdated(seasons, Self::next_event_seq()));
+        }
+
+        /// Lock an own kitty and ask for it to be redeemed for a physical item. The kitty is
+        /// delisted and can't be moved until the request is confirmed, rejected or expires.
+        #[weight = 1000]
+        pub fn redeem(origin, kitty_id: KittyIndexOf<T>, shipping_commitment: T::Hash) {
+            let sender = ensure_signed(origin)?;
+            Self::note_activity(&sender);
+
+            ensure!(Self::kitties(&sender, kitty_id).is_some(), Error::<T, I>::KittenNotFound);
+            Self::ensure_unlocked(kitty_id)?;
+            ensure!(
+                (Self::open_redemptions().len() as u32) < T::MaxOpenRedemptions::get(),
+                Error::<T, I>::TooManyRedemptions
+            );
+
+            let expires_at = <frame_system::Module<T>>::block_number() + T::RedemptionTtl::get();
+            Self::remove_listing(kitty_id);
+            Redemptions::<T, I>::insert(kitty_id, Redemption {
+                owner: sender.clone(),
+                shipping_commitment,
+                expires_at,
+            });
+            OpenRedemptions::<T, I>::append(kitty_id);
+
+            Self::deposit_event(RawEvent::RedemptionRequested(
+                sender,
+                kitty_id,
+                shipping_commitment,
+                expires_at,
+                Self::next_event_seq(),
+            ));
+        }
+
+        /// The item was shipped: burn the kitty.
+        #[weight = 1000]
+        pub fn confirm_redemption(origin, kitty_id: KittyIndexOf<T>) {
+            T::FulfillmentOrigin::ensure_origin(origin)?;
+
+            let redemption = Self::redemptions(kitty_id).ok_or(Error::<T, I>::RedemptionNotFound)?;
+            ensure!(redemption.expires_at >= <frame_system::Module<T>>::block_number(), Error::<T, I>::RedemptionExpired);
+
+            Self::close_redemption(kitty_id);
+            Self::do_burn(&redemption.owner, kitty_id)?;
+
+            Self::deposit_event(RawEvent::RedemptionConfirmed(redemption.owner, kitty_id, Self::next_event_seq()));
+        }
+
+        #[weight = 1000]
+        pub fn reject_redemption(origin, kitty_id: KittyIndexOf<T>) {
+            T::FulfillmentOrigin::ensure_origin(origin)?;
+
+            let redemption = Self::redemptions(kitty_id).ok_or(Error::<T, I>::RedemptionNotFound)?;
+            Self::close_redemption(kitty_id);
+
+            Self::deposit_event(RawEvent::RedemptionRejected(redemption.owner, kitty_id, Self::next_event_seq()));
+        }
+
+        /// Replace the marketplace fee schedule. Takes effect for the current era right away.
+        #[weight = 1000]
+        pub fn set_fee_schedule(origin, schedule: Vec<(BalanceOf<T, I>, Permill)>) {
+            T::ForceOrigin::ensure_origin(origin)?;
+
+            ensure!(schedule.len() <= MAX_FEE_STEPS, Error::<T, I>::InvalidFeeSchedule);
+            ensure!(schedule.windows(2).all(|pair| pair[0].0 < pair[1].0), Error::<T, I>::InvalidFeeSchedule);
+
+            FeeSchedule::<T, I>::put(&schedule);
+            Self::deposit_event(RawEvent::FeeScheduleUpdated(schedule, Self::next_event_seq()));
+
+            Self::update_fee_rate(Self::last_era_volume());
+        }
+
+        /// Open a poll of kitty holders with `options` options, accepting votes up to
+        /// `end_block`.
+        #[weight = 1000]
+        pub fn create_poll(origin, description_hash: T::Hash, end_block: T::BlockNumber, options: u8) {
+            T::ForceOrigin::ensure_origin(origin)?;
+
+            ensure!(options >= 2, Error::<T, I>::InvalidPoll);
+            ensure!(end_block > <frame_system::Module<T>>::block_number(), Error::<T, I>::InvalidPoll);
+            ensure!((Self::open_polls().len() as u32) < T::MaxOpenPolls::get(), Error::<T, I>::TooManyPolls);
+
+            let poll_id = Self::next_poll_id();
+            NextPollId::<I>::put(poll_id.checked_add(1).ok_or(Error::<T, I>::StorageOverflow)?);
+            Polls::<T, I>::insert(poll_id, Poll {
+                description_hash,
+                end_block,
+                tally: vec![0; options as usize],
+                closed: false,
+            });
+            OpenPolls::<I>::append(poll_id);
+
+            Self::deposit_event(RawEvent::PollCreated(poll_id, description_hash, end_block, options, Self::next_event_seq()));
+        }
+
+        /// Vote for `option` with every own kitty that didn't vote in the poll yet.
+        #[weight = T::DbWeight::get().reads_writes(
+            2 * Weight::from(T::MaxKittiesPerOwner::get()) + 6,
+            Weight::from(T::MaxKittiesPerOwner::get()) + 4,
+        )]
+        pub fn vote(origin, poll_id: PollId, option: u8) {
+            let sender = ensure_signed(origin)?;
+            Self::note_activity(&sender);
+
+            let mut poll = Self::polls(poll_id).ok_or(Error::<T, I>::PollNotFound)?;
+            ensure!(
+                !poll.closed && poll.end_block >= <frame_system::Module<T>>::block_number(),
+                Error::<T, I>::PollEnded
+            );
+            ensure!((option as usize) < poll.tally.len(), Error::<T, I>::InvalidPollOption);
+            ensure!(!PollVoters::<T, I>::contains_key(poll_id, &sender), Error::<T, I>::AlreadyVoted);
+
+            let class_id = Self::class_id();
+            let kitties: Vec<KittyIndexOf<T>> = orml_nft::TokensByOwner::<T>::iter_prefix(&sender)
+                .filter_map(|((class, kitty_id), _)| if class == class_id { Some(kitty_id) } else { None })
+                .filter(|kitty_id| !PollKitties::<T, I>::contains_key(poll_id, kitty_id))
+                .collect();
+            ensure!(!kitties.is_empty(), Error::<T, I>::NoVotingPower);
+
+            let power = kitties.len() as u32;
+            let votes = poll.tally.iter().fold(power, |sum, count| sum.saturating_add(*count));
+            ensure!(votes <= T::MaxPollVotes::get(), Error::<T, I>::PollFull);
+            for kitty_id in kitties {
+                PollKitties::<T, I>::insert(poll_id, kitty_id, ());
+            }
+            poll.tally[option as usize] = poll.tally[option as usize].saturating_add(power);
+            Polls::<T, I>::insert(poll_id, poll);
+            PollVoters::<T, I>::insert(poll_id, &sender, (option, power));
+
+            Self::deposit_event(RawEvent::Voted(sender, poll_id, option, power, Self::next_event_seq()));
+        }
+
+        /// Finalize the tally of a poll past its end block. Anyone can close it.
+        #[weight = T::DbWeight::get().reads_writes(2, 2 * Weight::from(T::MaxPollVotes::get()) + 2)]
+        pub fn close_poll(origin, poll_id: PollId) {
+            ensure_signed(origin)?;
+
+            let mut poll = Self::polls(poll_id).ok_or(Error::<T, I>::PollNotFound)?;
+            ensure!(!poll.closed, Error::<T, I>::PollEnded);
+            ensure!(poll.end_block < <frame_system::Module<T>>::block_number(), Error::<T, I>::PollNotEnded);
+
+            poll.closed = true;
+            let tally = poll.tally.clone();
+            Polls::<T, I>::insert(poll_id, poll);
+            OpenPolls::<I>::mutate(|open| open.retain(|id| *id != poll_id));
+            PollVoters::<T, I>::remove_prefix(poll_id);
+            PollKitties::<T, I>::remove_prefix(poll_id);
+
+            Self::deposit_event(RawEvent::PollClosed(poll_id, tally, Self::next_event_seq()));
+        }
+
+        /// Offer the creator role of a kitty to `new_creator`, who takes it with
+        /// `accept_creator_role`. Replaces an earlier offer.
+        #[weight = 1000]
+        pub fn transfer_creator_role(origin, kitty_id: KittyIndexOf<T>, new_creator: T::AccountId) {
+            let sender = ensure_signed(origin)?;
+            Self::note_activity(&sender);
+
+            ensure!(Self::kitty_creator(kitty_id).as_ref() == Some(&sender), Error::<T, I>::NotCreator);
+            PendingCreators::<T, I>::insert(kitty_id, &new_creator);
+
+            Self::deposit_event(RawEvent::CreatorRoleOffered(sender, new_creator, kitty_id, Self::next_event_seq()));
+        }
+
+        #[weight = 1000]
+        pub fn accept_creator_role(origin, kitty_id: KittyIndexOf<T>) {
+            let sender = ensure_signed(origin)?;
+            Self::note_activity(&sender);
+
+            ensure!(Self::pending_creator(kitty_id).as_ref() == Some(&sender), Error::<T, I>::NoPendingCreator);
+            let old_creator = Self::kitty_creator(kitty_id).ok_or(Error::<T, I>::NotCreator)?;
+            PendingCreators::<T, I>::remove(kitty_id);
+            KittyCreators::<T, I>::insert(kitty_id, &sender);
+
+            Self::deposit_event(RawEvent::CreatorRoleTransferred(old_creator, sender, kitty_id, Self::next_event_seq()));
+        }
+
+        /// Give up the creator role of a kitty for good, ending its royalties.
+        #[weight = 1000]
+        pub fn renounce_creator_role(origin, kitty_id: KittyIndexOf<T>) {
+            let sender = ensure_signed(origin)?;
+            Self::note_activity(&sender);
+
+            ensure!(Self::kitty_creator(kitty_id).as_ref() == Some(&sender), Error::<T, I>::NotCreator);
+            KittyCreators::<T, I>::remove(kitty_id);
+            PendingCreators::<T, I>::remove(kitty_id);
+
+            Self::deposit_event(RawEvent::CreatorRoleRenounced(sender, kitty_id, Self::next_event_seq()));
+        }
+
+        /// List all own kitties for sale, with proceeds going to `beneficiary`, if the caller
+        /// doesn't use the pallet for `inactivity_blocks`. Replaces an earlier switch.
+        #[weight = 1000]
+        pub fn configure_dead_mans_switch(
+            origin,
+            inactivity_blocks: T::BlockNumber,
+            beneficiary: T::AccountId,
+            price_strategy: PriceStrategy<BalanceOf<T, I>>,
+        ) {
+            let sender = ensure_signed(origin)?;
+
+            DeadMansSwitches::<T, I>::insert(&sender, DeadMansSwitch {
+                inactivity_blocks,
+                beneficiary: beneficiary.clone(),
+                price_strategy,
+                last_active: <frame_system::Module<T>>::block_number(),
+            });
+            ProceedsBeneficiaries::<T, I>::remove(&sender);
+
+            Self::deposit_event(RawEvent::DeadMansSwitchConfigured(
+                sender,
+                beneficiary,
+                inactivity_blocks,
+                Self::next_event_seq(),
+            ));
+        }
+
+        #[weight = 1000]
+        pub fn disable_dead_mans_switch(origin) {
+            let sender = ensure_signed(origin)?;
+
+            ensure!(DeadMansSwitches::<T, I>::contains_key(&sender), Error::<T, I>::NoDeadMansSwitch);
+            DeadMansSwitches::<T, I>::remove(&sender);
+
+            Self::deposit_event(RawEvent::DeadMansSwitchDisabled(sender, Self::next_event_seq()));
+        }
+
+        /// List every unlocked kitty of an owner whose dead man's switch ran out. Anyone can
+        /// trigger it; the switch is used up.
+        #[weight = T::DbWeight::get().reads_writes(
+            12 * Weight::from(T::MaxKittiesPerOwner::get()) + 3,
+            5 * Weight::from(T::MaxKittiesPerOwner::get()) + 3,
+        )]
+        pub fn trigger_switch(origin, dormant_owner: T::AccountId) {
+            ensure_signed(origin)?;
+
+            let switch = Self::dead_mans_switch(&dormant_owner).ok_or(Error::<T, I>::NoDeadMansSwitch)?;
+            ensure!(
+                <frame_system::Module<T>>::block_number() >= switch.last_active.saturating_add(switch.inactivity_blocks),
+                Error::<T, I>::OwnerNotDormant
+            );
+
+            DeadMansSwitches::<T, I>::remove(&dormant_owner);
+            ProceedsBeneficiaries::<T, I>::insert(&dormant_owner, &switch.beneficiary);
+
+            let class_id = Self::class_id();
+            let mut listed = 0u32;
+            for ((class, kitty_id), _) in orml_nft::TokensByOwner::<T>::iter_prefix(&dormant_owner) {
+                if class != class_id || Self::ensure_unlocked(kitty_id).is_err() {
+                    continue;
+                }
+                if let Some(price) = Self::switch_price(&switch.price_strategy, kitty_id) {
+                    if Self::list_ask(&dormant_owner, kitty_id, price) {
+                        listed += 1;
+                    }
+                }
+            }
+
+            Self::deposit_event(RawEvent::DeadMansSwitchTriggered(
+                dormant_owner,
+                switch.beneficiary,
+                listed,
+                Self::next_event_seq(),
+            ));
+        }
+
+        /// Waive the creation fee in blocks `[start, end)` for up to `max_per_account` mints
+        /// per account and `max_total` mints overall. Must not overlap a window that hasn't
+        /// ended yet.
+        #[weight = 1000]
+        pub fn set_promo_window(origin, start: T::BlockNumber, end: T::BlockNumber, max_per_account: u32, max_total: u32) {
+            T::ForceOrigin::ensure_origin(origin)?;
+
+            let now = <frame_system::Module<T>>::block_number();
+            ensure!(start < end && now < end, Error::<T, I>::InvalidPromoWindow);
+            let (mut windows, ended): (Vec<_>, Vec<_>) =
+                Self::promo_windows().into_iter().partition(|window| now < window.end);
+            ensure!(!windows.iter().any(|window| window.start < end && start < window.end), Error::<T, I>::PromoWindowOverlaps);
+            ensure!(windows.len() < MAX_PROMO_WINDOWS, Error::<T, I>::TooManyPromoWindows);
+
+            for window in ended {
+                PromoMints::<T, I>::remove_prefix(window.start);
+            }
+            let position = windows.iter().position(|window| start < window.start).unwrap_or(windows.len());
+            windows.insert(position, PromoWindow { start, end, max_per_account, max_total, minted: 0 });
+            PromoWindows::<T, I>::put(windows);
+
+            Self::deposit_event(RawEvent::PromoWindowSet(start, end, max_per_account, max_total, Self::next_event_seq()));
+        }
+
+        #[weight = 1000]
+        pub fn reset_activity_counters(origin) {
+            T::ForceOrigin::ensure_origin(origin)?;
+
+            for kind in ActivityKind::ALL.iter() {
+                ActivityCounters::<I>::remove(kind);
+            }
+
+            Self::deposit_event(RawEvent::ActivityCountersReset(Self::next_event_seq()));
+        }
+
+        #[weight = 1000]
+        pub fn claim_refund(origin) {
+            let sender = ensure_signed(origin)?;
+            Self::note_activity(&sender);
+
+            let amount = Self::refunds(&sender);
+            ensure!(!amount.is_zero(), Error::<T, I>::NoRefund);
+
+            Self::transfer_funds(&Self::account_id(), &sender, amount, FlowKind::Refund)?;
+            Refunds::<T, I>::remove(&sender);
+
+            Self::deposit_event(RawEvent::RefundClaimed(sender, amount, Self::next_event_seq()));
+        }
+
+        /// Mint a free starter kitty of the most common genes. Once ever per account verified
+        /// by the `PersonhoodProvider`.
+        #[weight = 1000]
+        pub fn claim_starter_kitty(origin) {
+            let sender = ensure_signed(origin)?;
+            Self::note_activity(&sender);
+            Self::ensure_not_paused()?;
+
+            ensure!(T::PersonhoodProvider::contains(&sender), Error::<T, I>::NotVerified);
+            ensure!(!Self::starter_claimed(&sender), Error::<T, I>::StarterAlreadyClaimed);
+
+            let dna = Self::unique_dna(Self::common_dna(&sender))?;
+            let kitty_id = Self::do_mint(&sender, Kitty(dna), None)?;
+            StarterClaimed::<T, I>::insert(&sender, true);
+
+            Self::count_activity(ActivityKind::Creates);
+            Self::deposit_event(RawEvent::StarterKittyMinted(sender, kitty_id, Self::next_event_seq()));
+        }
+
+        /// Rewrite up to `limit` stored kitties from the old `V1` layout, continuing where the
+        /// previous call stopped. Anyone can call it; kitties are also rewritten when moved.
+        #[weight = 1000]
+        pub fn upgrade_kitties(origin, limit: u32) {
+            let sender = ensure_signed(origin)?;
+            Self::note_activity(&sender);
+
+            let end = NftModule::<T>::next_token_id(Self::class_id());
+            let mut kitty_id = Self::kitty_upgrade_cursor();
+            let mut checked = 0;
+            let mut upgraded = 0u32;
+            while kitty_id < end && checked < limit {
+                if Self::upgrade_kitty(kitty_id) {
+                    upgraded += 1;
+                }
+                kitty_id = kitty_id.saturating_add(One::one());
+                checked += 1;
+            }
+            KittyUpgradeCursor::<T, I>::put(kitty_id);
+
+            Self::deposit_event(RawEvent::KittiesUpgraded(upgraded, kitty_id, Self::next_event_seq()));
+        }
+
+        /// Offer `give_kitties` plus `give_tokens` to `counterparty` for its `want_kitties` plus
+        /// `want_tokens`. Locks the offered kitties and reserves the offered tokens for
+        /// `TradeTtl` blocks.
+        #[weight = T::DbWeight::get().reads_writes(
+            8 * Weight::from(T::MaxTradeKitties::get()) + 6,
+            Weight::from(T::MaxTradeKitties::get()) + 5,
+        )]
+        pub fn propose_trade(
+            origin,
+            counterparty: T::AccountId,
+            give_kitties: Vec<KittyIndexOf<T>>,
+            give_tokens: BalanceOf<T, I>,
+            want_kitties: Vec<KittyIndexOf<T>>,
+            want_tokens: BalanceOf<T, I>,
+        ) {
+            let sender = ensure_signed(origin)?;
+            Self::note_activity(&sender);
+
+            ensure!(counterparty != sender, Error::<T, I>::InvalidTrade);
+            let max_kitties = T::MaxTradeKitties::get() as usize;
+            ensure!(give_kitties.len() <= max_kitties && want_kitties.len() <= max_kitties, Error::<T, I>::InvalidTrade);
+            ensure!(!give_kitties.is_empty() || !want_kitties.is_empty(), Error::<T, I>::InvalidTrade);
+            let mut kitties: Vec<_> = give_kitties.iter().chain(want_kitties.iter()).collect();
+            kitties.sort();
+            kitties.dedup();
+            ensure!(kitties.len() == give_kitties.len() + want_kitties.len(), Error::<T, I>::InvalidTrade);
+
+            for kitty_id in give_kitties.iter() {
+                ensure!(Self::kitties(&sender, *kitty_id).is_some(), Error::<T, I>::KittenNotFound);
+                Self::ensure_unlocked(*kitty_id)?;
+            }
+            for kitty_id in want_kitties.iter() {
+                ensure!(Self::kitties(&counterparty, *kitty_id).is_some(), Error::<T, I>::WantedKittyUnavailable);
+            }
+
+            Self::reserve_funds(&sender, give_tokens)?;
+            let trade_id = NextTradeId::<I>::mutate(|next| {
+                let id = *next;
+                *next = next.wrapping_add(1);
+                id
+            });
+            for kitty_id in give_kitties.iter() {
+                TradeLocks::<T, I>::insert(kitty_id, trade_id);
+            }
+            let expires_at = <frame_system::Module<T>>::block_number().saturating_add(T::TradeTtl::get());
+            TradeProposals::<T, I>::insert(trade_id, TradeProposal {
+                proposer: sender.clone(),
+                counterparty: counterparty.clone(),
+                give_kitties,
+                give_tokens,
+                want_kitties,
+                want_tokens,
+                expires_at,
+            });
+
+            Self::deposit_event(RawEvent::TradeProposed(sender, counterparty, trade_id, Self::next_event_seq()));
+        }
+
+        /// Swap the packages of a trade proposed to the caller. If the caller no longer holds
+        /// a wanted kitty the proposal is closed instead, and stays closed although the call
+        /// fails.
+        #[weight = T::WeightInfo::transfer()
+            .saturating_mul(2 * Weight::from(T::MaxTradeKitties::get()))
+            .saturating_add(T::DbWeight::get().reads_writes(
+                11 * Weight::from(T::MaxTradeKitties::get()) + 8,
+                5 * Weight::from(T::MaxTradeKitties::get()) + 8,
+            ))]
+        pub fn accept_trade(origin, trade_id: TradeId) {
+            let sender = ensure_signed(origin)?;
+            Self::note_activity(&sender);
+
+            let trade = Self::trade_proposal(trade_id).ok_or(Error::<T, I>::TradeNotFound)?;
+            ensure!(trade.counterparty == sender, Error::<T, I>::NotTradeCounterparty);
+            ensure!(<frame_system::Module<T>>::block_number() <= trade.expires_at, Error::<T, I>::TradeExpired);
+
+            let available = trade.want_kitties.iter()
+                .all(|kitty_id| Self::kitties(&sender, *kitty_id).is_some() && Self::ensure_unlocked(*kitty_id).is_ok());
+            if !available {
+                Self::close_trade(trade_id, &trade);
+                return Err(Error::<T, I>::WantedKittyUnavailable.into());
+            }
+
+            with_transaction_result(|| {
+                TradeProposals::<T, I>::remove(trade_id);
+                for kitty_id in trade.give_kitties.iter() {
+                    TradeLocks::<T, I>::remove(kitty_id);
+                }
+                Self::unreserve_funds(&trade.proposer, trade.give_tokens);
+                if !trade.give_tokens.is_zero() {
+                    Self::transfer_funds(&trade.proposer, &sender, trade.give_tokens, FlowKind::Sale)?;
+                }
+                if !trade.want_tokens.is_zero() {
+                    Self::transfer_funds(&sender, &trade.proposer, trade.want_tokens, FlowKind::Sale)?;
+                }
+                for kitty_id in trade.give_kitties.iter() {
+                    Self::do_transfer(&trade.proposer, &sender, *kitty_id)?;
+                    Self::note_swap(&trade.proposer, &sender, *kitty_id);
+                }
+                for kitty_id in trade.want_kitties.iter() {
+                    Self::do_transfer(&sender, &trade.proposer, *kitty_id)?;
+                    Self::note_swap(&sender, &trade.proposer, *kitty_id);
+                }
+                Self::deposit_event(RawEvent::TradeAccepted(trade_id, Self::next_event_seq()));
+                Ok(())
+            })?;
+        }
+
+        /// Close a trade proposal, releasing its locks. The proposer can close it any time,
+        /// anyone else once it expired.
+        #[weight = T::DbWeight::get().reads_writes(7, Weight::from(T::MaxTradeKitties::get()) + 5)]
+        pub fn cancel_trade(origin, trade_id: TradeId) {
+            let sender = ensure_signed(origin)?;
+            Self::note_activity(&sender);
+
+            let trade = Self::trade_proposal(trade_id).ok_or(Error::<T, I>::TradeNotFound)?;
+            ensure!(
+                trade.proposer == sender || <frame_system::Module<T>>::block_number() > trade.expires_at,
+                Error::<T, I>::TradeNotExpired
+            );
+
+            Self::close_trade(trade_id, &trade);
+        }
+
+        /// Propose to breed a kitty of yours with `partner_kitty_id` of `partner`, the kitten
+        /// going to `child_to`. Nothing is locked; the proposal is void once either kitty
+        /// changes hands.
+        #[weight = 1000]
+        pub fn propose_breeding(
+            origin,
+            my_kitty_id: KittyIndexOf<T>,
+            partner: T::AccountId,
+            partner_kitty_id: KittyIndexOf<T>,
+            child_to: T::AccountId,
+        ) {
+            let sender = ensure_signed(origin)?;
+            Self::note_activity(&sender);
+            Self::ensure_not_paused()?;
+
+            ensure!(Self::kitties(&sender, my_kitty_id).is_some(), Error::<T, I>::KittenNotFound);
+            ensure!(Self::kitties(&partner, partner_kitty_id).is_some(), Error::<T, I>::KittenNotFound);
+
+            let proposal_id = NextBreedingProposalId::<I>::mutate(|next| {
+                let id = *next;
+                *next = next.wrapping_add(1);
+                id
+            });
+            BreedingProposals::<T, I>::insert(proposal_id, BreedingProposal {
+                proposer: sender.clone(),
+                kitty_id: my_kitty_id,
+                partner: partner.clone(),
+                partner_kitty_id,
+                child_to,
+            });
+
+            Self::deposit_event(RawEvent::BreedingProposed(sender, partner, proposal_id, Self::next_event_seq()));
+        }
+
+        /// Breed the kitties of a proposal made to the caller, who pays for the breed. If
+        /// either kitty changed hands the proposal is cancelled instead, and stays cancelled
+        /// although the call fails.
+        #[weight = 1000]
+        pub fn accept_breeding(origin, proposal_id: BreedingProposalId) {
+            let sender = ensure_signed(origin)?;
+            Self::note_activity(&sender);
+            Self::ensure_not_paused()?;
+            ensure!(Self::in_season(<frame_system::Module<T>>::block_number()), Error::<T, I>::OutOfSeason);
+
+            let proposal = Self::breeding_proposal(proposal_id).ok_or(Error::<T, I>::BreedingProposalNotFound)?;
+            ensure!(proposal.partner == sender, Error::<T, I>::NotBreedingPartner);
+            if Self::kitties(&proposal.proposer, proposal.kitty_id).is_none()
+                || Self::kitties(&proposal.partner, proposal.partner_kitty_id).is_none()
+            {
+                BreedingProposals::<T, I>::remove(proposal_id);
+                Self::deposit_event(RawEvent::BreedingProposalCancelled(proposal_id, Self::next_event_seq()));
+                return Err(Error::<T, I>::BreedingProposalStale.into());
+            }
+
+            let context = BreedContext::<T>::load::<I>(
+                &proposal.proposer,
+                proposal.kitty_id,
+                &proposal.partner,
+                proposal.partner_kitty_id,
+            )?;
+            with_transaction_result(|| {
+                BreedingProposals::<T, I>::remove(proposal_id);
+                let kitty_id = Self::do_breed(&sender, &proposal.child_to, context, None)?;
+                Self::deposit_event(RawEvent::BreedingAccepted(proposal_id, kitty_id, Self::next_event_seq()));
+                Ok(())
+            })?;
+        }
+
+        /// Withdraw a breeding proposal of yours.
+        #[weight = 1000]
+        pub fn cancel_breeding(origin, proposal_id: BreedingProposalId) {
+            let sender = ensure_signed(origin)?;
+            Self::note_activity(&sender);
+
+            let proposal = Self::breeding_proposal(proposal_id).ok_or(Error::<T, I>::BreedingProposalNotFound)?;
+            ensure!(proposal.proposer == sender, Error::<T, I>::NotBreedingProposer);
+            BreedingProposals::<T, I>::remove(proposal_id);
+
+            Self::deposit_event(RawEvent::BreedingProposalCancelled(proposal_id, Self::next_event_seq()));
+        }
+
+        /// Propose to swap a kitty of yours for `their_kitty_id` of `counterparty`, with
+        /// `give_tokens` paid on top by you and `want_tokens` by the counterparty. Nothing is
+        /// locked or reserved; the swap is void once either kitty changes hands.
+        #[weight = 1000]
+        pub fn propose_swap(
+            origin,
+            my_kitty_id: KittyIndexOf<T>,
+            counterparty: T::AccountId,
+            their_kitty_id: KittyIndexOf<T>,
+            give_tokens: BalanceOf<T, I>,
+            want_tokens: BalanceOf<T, I>,
+        ) {
+            let sender = ensure_signed(origin)?;
+            Self::note_activity(&sender);
+            Self::ensure_not_paused()?;
+
+            ensure!(counterparty != sender, Error::<T, I>::InvalidSwap);
+            ensure!(Self::kitties(&sender, my_kitty_id).is_some(), Error::<T, I>::KittenNotFound);
+            ensure!(Self::kitties(&counterparty, their_kitty_id).is_some(), Error::<T, I>::KittenNotFound);
+
+            let swap_id = NextSwapId::<I>::mutate(|next| {
+                let id = *next;
+                *next = next.wrapping_add(1);
+                id
+            });
+            SwapProposals::<T, I>::insert(swap_id, SwapProposal {
+                proposer: sender.clone(),
+                kitty_id: my_kitty_id,
+                counterparty: counterparty.clone(),
+                counterparty_kitty_id: their_kitty_id,
+                give_tokens,
+                want_tokens,
+            });
+
+            Self::deposit_event(RawEvent::SwapProposed(sender, counterparty, swap_id, Self::next_event_seq()));
+        }
+
+        /// Exchange the kitties and tokens of a swap proposed to the caller. If either kitty
+        /// changed hands the swap is cancelled instead, and stays cancelled although the call
+        /// fails.
+        #[weight = 1000]
+        pub fn accept_swap(origin, swap_id: SwapId) {
+            let sender = ensure_signed(origin)?;
+            Self::note_activity(&sender);
+            Self::ensure_not_paused()?;
+
+            let swap = Self::swap_proposal(swap_id).ok_or(Error::<T, I>::SwapNotFound)?;
+            ensure!(swap.counterparty == sender, Error::<T, I>::NotSwapCounterparty);
+            if Self::kitties(&swap.proposer, swap.kitty_id).is_none()
+                || Self::kitties(&swap.counterparty, swap.counterparty_kitty_id).is_none()
+            {
+                SwapProposals::<T, I>::remove(swap_id);
+                Self::deposit_event(RawEvent::SwapCancelled(swap_id, Self::next_event_seq()));
+                return Err(Error::<T, I>::SwapStale.into());
+            }
+
+            with_transaction_result(|| {
+                SwapProposals::<T, I>::remove(swap_id);
+                if !swap.give_tokens.is_zero() {
+                    Self::transfer_funds(&swap.proposer, &sender, swap.give_tokens, FlowKind::Sale)?;
+                }
+                if !swap.want_tokens.is_zero() {
+                    Self::transfer_funds(&sender, &swap.proposer, swap.want_tokens, FlowKind::Sale)?;
+                }
+                // clears the listings of both
+                Self::do_transfer(&swap.proposer, &sender, swap.kitty_id)?;
+                Self::note_swap(&swap.proposer, &sender, swap.kitty_id);
+                Self::do_transfer(&sender, &swap.proposer, swap.counterparty_kitty_id)?;
+                Self::note_swap(&sender, &swap.proposer, swap.counterparty_kitty_id);
+
+                Self::deposit_event(RawEvent::KittiesSwapped(
+                    swap_id,
+                    swap.proposer.clone(),
+                    swap.kitty_id,
+                    sender.clone(),
+                    swap.counterparty_kitty_id,
+                    Self::next_event_seq(),
+                ));
+                Ok(())
+            })?;
+        }
+
+        /// Withdraw a swap you proposed or decline one proposed to you.
+        #[weight = 1000]
+        pub fn cancel_swap(origin, swap_id: SwapId) {
+            let sender = ensure_signed(origin)?;
+            Self::note_activity(&sender);
+
+            let swap = Self::swap_proposal(swap_id).ok_or(Error::<T, I>::SwapNotFound)?;
+            ensure!(swap.proposer == sender || swap.counterparty == sender, Error::<T, I>::NotSwapParty);
+            SwapProposals::<T, I>::remove(swap_id);
+
+            Self::deposit_event(RawEvent::SwapCancelled(swap_id, Self::next_event_seq()));
+        }
+
+        /// Open a breeding pool operated by the caller.
+        #[weight = 1000]
+        pub fn create_pool(origin) {
+            let sender = ensure_signed(origin)?;
+            Self::note_activity(&sender);
+
+            let pool_id = NextPoolId::<I>::mutate(|next| {
+                let id = *next;
+                *next = next.wrapping_add(1);
+                id
+            });
+            BreedingPools::<T, I>::insert(pool_id, BreedingPool { operator: sender.clone(), contributions: Vec::new(), cursor: 0 });
+
+            Self::deposit_event(RawEvent::PoolCreated(sender, pool_id, Self::next_event_seq()));
+        }
+
+        /// Move an own kitty into a breeding pool, for a share of its offspring weighted by
+        /// the kitty's rarity.
+        #[weight = 1000]
+        pub fn join_pool(origin, pool_id: PoolId, kitty_id: KittyIndexOf<T>) {
+            let sender = ensure_signed(origin)?;
+            Self::note_activity(&sender);
+
+            let mut pool = Self::breeding_pool(pool_id).ok_or(Error::<T, I>::PoolNotFound)?;
+            ensure!(pool.contributions.len() < T::MaxPoolKitties::get() as usize, Error::<T, I>::PoolFull);
+            let kitty = Self::kitties(&sender, kitty_id).ok_or(Error::<T, I>::KittenNotFound)?;
+
+            Self::do_transfer(&sender, &Self::pool_account_id(pool_id), kitty_id)?;
+            KittyCustodies::<T, I>::insert(kitty_id, Custody { kind: CustodyKind::Pool, owner: sender.clone() });
+            pool.contributions.push(PoolContribution {
+                contributor: sender.clone(),
+                kitty_id,
+                weight: Self::scarce_genes(&kitty) as u32 + 1,
+                resting_until: Zero::zero(),
+            });
+            BreedingPools::<T, I>::insert(pool_id, pool);
+
+            Self::deposit_event(RawEvent::PoolJoined(sender, pool_id, kitty_id, Self::next_event_seq()));
+        }
+
+        /// Take a contributed kitty back out of a pool, unless it is resting after breeding.
+        #[weight = 1000]
+        pub fn leave_pool(origin, pool_id: PoolId, kitty_id: KittyIndexOf<T>) {
+            let sender = ensure_signed(origin)?;
+            Self::note_activity(&sender);
+
+            let mut pool = Self::breeding_pool(pool_id).ok_or(Error::<T, I>::PoolNotFound)?;
+            let position = pool.contributions.iter()
+                .position(|contribution| contribution.kitty_id == kitty_id && contribution.contributor == sender)
+                .ok_or(Error::<T, I>::NotInPool)?;
+            ensure!(
+                pool.contributions[position].resting_until <= <frame_system::Module<T>>::block_number(),
+                Error::<T, I>::PoolKittyResting
+            );
+
+            Self::do_transfer(&Self::pool_account_id(pool_id), &sender, kitty_id)?;
+            pool.contributions.remove(position);
+            BreedingPools::<T, I>::insert(pool_id, pool);
+
+            Self::deposit_event(RawEvent::PoolLeft(sender, pool_id, kitty_id, Self::next_event_seq()));
+        }
+
+        /// Breed two pooled kitties of different genders by the rules of `breed`. The operator
+        /// pays the breeding fee and the kitty deposit and gets any rebreed voucher, rare gene
+        /// bonus or badge, and the kitten goes to the contributor whose turn it is.
+        #[weight = 1000]
+        pub fn breed_in_pool(origin, pool_id: PoolId, first_kitty_id: KittyIndexOf<T>, second_kitty_id: KittyIndexOf<T>) {
+            let sender = ensure_signed(origin)?;
+            Self::note_activity(&sender);
+            Self::ensure_not_paused()?;
+            let now = <frame_system::Module<T>>::block_number();
+            ensure!(Self::in_season(now), Error::<T, I>::OutOfSeason);
+
+            let mut pool = Self::breeding_pool(pool_id).ok_or(Error::<T, I>::PoolNotFound)?;
+            ensure!(pool.operator == sender, Error::<T, I>::NotPoolOperator);
+            for kitty_id in [first_kitty_id, second_kitty_id].iter() {
+                let contribution = pool.contributions.iter()
+                    .find(|contribution| contribution.kitty_id == *kitty_id)
+                    .ok_or(Error::<T, I>::NotInPool)?;
+                ensure!(contribution.resting_until <= now, Error::<T, I>::PoolKittyResting);
+            }
+            let pool_account = Self::pool_account_id(pool_id);
+            let context = BreedContext::<T>::load::<I>(&pool_account, first_kitty_id, &pool_account, second_kitty_id)?;
+            let generation = context.child_generation();
+
+            let recipient = Self::pool_recipient(&pool).ok_or(Error::<T, I>::NotInPool)?;
+            let kitty_id = Self::do_breed(&sender, &recipient, context, None)?;
+
+            let resting_until = now.saturating_add(T::PoolBreedCooldown::get());
+            for contribution in pool.contributions.iter_mut() {
+                if contribution.kitty_id == first_kitty_id || contribution.kitty_id == second_kitty_id {
+                    contribution.resting_until = resting_until;
+                }
+            }
+            pool.cursor = pool.cursor.wrapping_add(1);
+            BreedingPools::<T, I>::insert(pool_id, pool);
+
+            Self::deposit_event(RawEvent::PoolKittyBred(pool_id, kitty_id, recipient, generation, Self::next_event_seq()));
+        }
+
+        /// Close a pool, returning every kitty to its contributor, resting or not.
+        #[weight = T::WeightInfo::transfer()
+            .saturating_mul(Weight::from(T::MaxPoolKitties::get()))
+            .saturating_add(T::DbWeight::get().reads_writes(4, 3))]
+        pub fn dissolve_pool(origin, pool_id: PoolId) {
+            let sender = ensure_signed(origin)?;
+            Self::note_activity(&sender);
+
+            let pool = Self::breeding_pool(pool_id).ok_or(Error::<T, I>::PoolNotFound)?;
+            ensure!(pool.operator == sender, Error::<T, I>::NotPoolOperator);
+
+            let pool_account = Self::pool_account_id(pool_id);
+            with_transaction_result(|| {
+                for contribution in pool.contributions.iter() {
+                    Self::do_transfer(&pool_account, &contribution.contributor, contribution.kitty_id)?;
+                }
+                Ok(())
+            })?;
+            BreedingPools::<T, I>::remove(pool_id);
+
+            Self::deposit_event(RawEvent::PoolDissolved(pool_id, Self::next_event_seq()));
+        }
+    }
+}
+
+fn combine_dna(dna1: u8, dna2: u8, selector: u8) -> u8 {
+	(!selector & dna1) | (selector & dna2)
+}
+
+/// Replace each gene of `dna` by a random value with probability `chance`, rolled from `seed`.
+/// Returns the number of mutated genes. The roll is drawn from 64 bits, so its bias towards
+/// mutating is below one in a billion of `chance`.
+fn mutate_dna(dna: &mut [u8; 16], seed: &[u8; 16], chance: Perbill) -> u32 {
+	let mut mutations = 0;
+	for (i, gene) in dna.iter_mut().enumerate() {
+		let roll = (seed, i as u8).using_encoded(blake2_128);
+		let mut draw = [0u8; 8];
+		draw.copy_from_slice(&roll[..8]);
+		if u64::from_le_bytes(draw) % 1_000_000_000 < u64::from(chance.deconstruct()) {
+			*gene = roll[8];
+			mutations += 1;
+		}
+	}
+	mutations
+}
+
+/// Number of differing bits between two genomes.
+fn dna_distance(dna1: &[u8; 16], dna2: &[u8; 16]) -> u32 {
+	dna1.iter().zip(dna2.iter()).map(|(a, b)| (a ^ b).count_ones()).sum()
+}
+
+/// Order-independent key of a breeding pair.
+fn breeding_pair<K: Ord>(first: K, second: K) -> (K, K) {
+	if first <= second {
+		(first, second)
+	} else {
+		(second, first)
+	}
+}
+
+impl<T: Config<I>, I: Instance> Module<T, I> {
+	fn kitties(owner: &T::AccountId, kitty_id: KittyIndexOf<T>) -> Option<Kitty> {
+		NftModule::<T>::tokens(Self::class_id(), kitty_id).and_then(|x| {
+			if x.owner == *owner {
+				Some(x.data.kitty())
+			} else {
+				None
+			}
+		})
+	}
+
+	/// Move a kitty between accounts. Every ownership change goes through here.
+	/// Clears the listing and turns a live quote of the new owner into its listing.
+	fn do_transfer(from: &T::AccountId, to: &T::AccountId, kitty_id: KittyIndexOf<T>) -> DispatchResult {
+		with_transaction_result(|| {
+			Self::move_kitty(from, to, kitty_id)?;
+			if from == to {
+				return Ok(());
+			}
+			Self::run_hook(|| T::KittyHooks::on_transferred(from, to, kitty_id))
+		})
+	}
+
+	/// `do_transfer` without telling `T::KittyHooks`.
+	fn move_kitty(from: &T::AccountId, to: &T::AccountId, kitty_id: KittyIndexOf<T>) -> DispatchResult {
+		Self::ensure_not_reentrant()?;
+		let token = (Self::class_id(), kitty_id);
+		if from == to {
+			return NftModule::<T>::transfer(from, to, token);
+		}
+		Self::ensure_unlocked(kitty_id)?;
+		// a stale entry would otherwise be silently overwritten and the kitty lost
+		ensure!(!orml_nft::TokensByOwner::<T>::contains_key(to, token), Error::<T, I>::DuplicateKittySlot);
+		Self::ensure_kitty_room(to)?;
+		NftModule::<T>::transfer(from, to, token)?;
+		Self::note_kitty_count(from, false);
+		Self::note_kitty_count(to, true);
+		Self::upgrade_kitty(kitty_id);
+		// moving a kitty ends its custody, custody features register again after the move
+		KittyCustodies::<T, I>::remove(kitty_id);
+
+		Self::remove_listing(kitty_id);
+		KittyApprovals::<T, I>::remove(kitty_id);
+		SiringApprovals::<T, I>::remove(kitty_id);
+		SiringPrices::<T, I>::remove(kitty_id);
+		RentalOffers::<T, I>::remove(kitty_id);
+		// only an ended rental not swept yet can be left here
+		KittyRentals::<T, I>::remove(kitty_id);
+		Self::settle_rent_escrow(kitty_id);
+		DutchAuctions::<T, I>::remove(kitty_id);
+		if let Some(offer) = Offers::<T, I>::take(kitty_id, to) {
+			Self::refund_reserved(to, offer.amount);
+		}
+		if let Some(quote) = Quotes::<T, I>::take(kitty_id, to) {
+			Self::release_bid(to, &quote);
+			if quote.expiry >= <frame_system::Module<T>>::block_number() {
+				Self::list_ask(to, kitty_id, quote.ask);
+			}
+		}
+		Ok(())
+	}
+
+	/// Breed the parents of `context` for `sender`, who pays for it. The kitten goes to
+	/// `recipient`.
+	fn do_breed(
+		sender: &T::AccountId,
+		recipient: &T::AccountId,
+		context: BreedContext<T>,
+		preferred_gender: Option<KittyGender>,
+	) -> Result<KittyIndexOf<T>, DispatchError> {
+		let first_kitty = context.first.kitty.clone();
+		let second_kitty = context.second.kitty.clone();
+
+		ensure!(first_kitty.gender() != second_kitty.gender(), Error::<T, I>::SameGenderBreed);
+
+		let (mut new_kitty_dna, mutations) = Self::breed_dna(sender, &first_kitty, &second_kitty);
+
+		if let Some(gender) = preferred_gender {
+			if Self::gender_roll(sender) {
+				new_kitty_dna[0] = match gender {
+					KittyGender::Male => new_kitty_dna[0] & !1,
+					KittyGender::Female => new_kitty_dna[0] | 1,
+				};
+			}
+		}
+
+		let new_kitty_dna = Self::unique_dna(new_kitty_dna)?;
+		let new_kitty = Kitty(new_kitty_dna);
+		// rarity is judged against the pool without the kitten
+		let rare = Self::has_rare_gene(&new_kitty_dna);
+		let kitty_id = with_transaction_result(|| {
+			let rebreed = Self::take_rebreed_voucher(sender, context.first.id, context.second.id);
+			Self::take_mint_budget(true)?;
+			let fee = Self::breed_fee(preferred_gender.is_some(), rebreed);
+			Self::charge_fee_or(sender, fee, Error::<T, I>::InsufficientBreedingFee)?;
+			let kitty_id = Self::do_mint(recipient, new_kitty.clone(), Some(&context))?;
+			Self::take_kitty_deposit(sender, kitty_id)?;
+			Ok(kitty_id)
+		})?;
+
+		Self::record_breeding(context.first.id, context.second.id, mutations > 0);
+		Self::record_breeding(context.second.id, context.first.id, mutations > 0);
+
+		if let Some(gender) = preferred_gender {
+			let satisfied = new_kitty.gender() == gender;
+			Self::deposit_event(RawEvent::GenderPreferenceApplied(
+				recipient.clone(),
+				kitty_id,
+				gender,
+				satisfied,
+				Self::next_event_seq(),
+			));
+		}
+		Self::count_activity(ActivityKind::Breeds);
+		Self::deposit_event(RawEvent::KittyBreed(
+			recipient.clone(),
+			first_kitty.clone(),
+			second_kitty.clone(),
+			kitty_id,
+			context.child_generation(),
+			Self::next_event_seq(),
+		));
+		if mutations > 0 {
+			Self::deposit_event(RawEvent::KittyMutated(recipient.clone(), kitty_id, mutations, Self::next_event_seq()));
+		}
+
+		let novelty = dna_distance(&new_kitty.dna(), &first_kitty.dna())
+			.min(dna_distance(&new_kitty.dna(), &second_kitty.dna()));
+		if novelty < T::MinNovelty::get() {
+			Self::issue_rebreed_voucher(sender, context.first.id, context.second.id);
+		}
+
+		if rare {
+			Self::pay_rare_gene_bonus(sender, kitty_id);
+		}
+
+		let generation = context.child_generation();
+		let milestone = T::GenerationMilestone::get();
+		if milestone > 0 && generation % milestone == 0 {
+			Self::mint_badge(sender, kitty_id, generation);
+		}
+		Ok(kitty_id)
+	}
+
+	/// Burn an unlocked kitty of `owner`, delisting it.
+	fn burn_kitty(owner: &T::AccountId, kitty_id: KittyIndexOf<T>) -> DispatchResult {
+		ensure!(orml_nft::TokensByOwner::<T>::contains_key(owner, (Self::class_id(), kitty_id)), Error::<T, I>::KittenNotFound);
+		Self::ensure_unlocked(kitty_id)?;
+		let listed = Listings::<T, I>::contains_key(kitty_id);
+		Self::do_burn(owner, kitty_id)?;
+
+		if listed {
+			Self::deposit_event(RawEvent::KittyPriceUpdated(owner.clone(), kitty_id, None, None, Self::next_event_seq()));
+		}
+		Self::deposit_event(RawEvent::KittyBurned(owner.clone(), kitty_id, Self::next_event_seq()));
+		Ok(())
+	}
+
+	/// Hand a kitty of `from` to `to` for free, delisting it.
+	fn give_kitty(from: T::AccountId, to: T::AccountId, kitty_id: KittyIndexOf<T>) -> DispatchResult {
+		let listed = Listings::<T, I>::contains_key(kitty_id);
+		Self::do_transfer(&from, &to, kitty_id)?;
+
+		if from != to {
+			if listed {
+				Self::deposit_event(RawEvent::KittyPriceUpdated(from.clone(), kitty_id, None, None, Self::next_event_seq()));
+			}
+			Self::note_tax_event(&from, kitty_id, TaxEventKind::GiftSent { to: to.clone() });
+			Self::note_tax_event(&to, kitty_id, TaxEventKind::GiftReceived { from: from.clone() });
+			Self::count_activity(ActivityKind::Transfers);
+			Self::deposit_event(RawEvent::KittyTransferred(from, to, kitty_id, Self::next_event_seq()));
+		}
+		Ok(())
+	}
+
+	/// Count a creation, or a breed if `breed`, against the budget of the block.
+	fn take_mint_budget(breed: bool) -> DispatchResult {
+		match T::MaxBreedPerBlock::get() {
+			Some(max) if breed => {
+				ensure!(Self::kitties_bred_this_block() < max, Error::<T, I>::BreedRateLimited);
+				KittiesBredThisBlock::<I>::mutate(|bred| *bred += 1);
+			}
+			_ => {
+				ensure!(Self::kitties_created_this_block() < T::MaxCreatePerBlock::get(), Error::<T, I>::CreateRateLimited);
+				KittiesCreatedThisBlock::<I>::mutate(|created| *created += 1);
+			}
+		}
+		Ok(())
+	}
+
+	/// Reserve the `KittyDeposit` of a kitty just minted for `payer`.
+	fn take_kitty_deposit(payer: &T::AccountId, kitty_id: KittyIndexOf<T>) -> DispatchResult {
+		let amount = T::KittyDeposit::get();
+		if amount.is_zero() {
+			return Ok(());
+		}
+		Self::reserve_funds(payer, amount)?;
+		KittyDeposits::<T, I>::insert(kitty_id, (payer, amount));
+		Self::deposit_event(RawEvent::KittyDepositReserved(payer.clone(), kitty_id, amount, Self::next_event_seq()));
+		Ok(())
+	}
+
+	/// Fail if no kitty can be minted to `who`.
+	fn ensure_can_mint(who: &T::AccountId) -> DispatchResult {
+		ensure!(!Self::minting_exhausted(), Error::<T, I>::MintingExhausted);
+		if let Some(cap) = T::MaxKittySupply::get() {
+			ensure!(Self::total_kitties() < cap, Error::<T, I>::SupplyCapReached);
+		}
+		Self::ensure_kitty_room(who)
+	}
+
+	/// Number of kitties in existence.
+	pub fn total_kitties() -> u32 {
+		NftModule::<T>::classes(Self::class_id())
+			.map(|class| class.total_issuance.unique_saturated_into())
+			.unwrap_or(0)
+	}
+
+	/// Fail if `who` can't take another kitty.
+	fn ensure_kitty_room(who: &T::AccountId) -> DispatchResult {
+		ensure!(Self::owned_kitties_count(who) < T::MaxKittiesPerOwner::get(), Error::<T, I>::TooManyKitties);
+		Ok(())
+	}
+
+	/// Count a kitty `who` gained or lost.
+	fn note_kitty_count(who: &T::AccountId, gained: bool) {
+		OwnedKittiesCount::<T, I>::mutate_exists(who, |count| {
+			let current = count.unwrap_or(0);
+			let updated = if gained { current.saturating_add(1) } else { current.saturating_sub(1) };
+			*count = Some(updated).filter(|updated| *updated > 0);
+		});
+	}
+
+	/// Number of kitties that can still be minted before the ids run out.
+	pub fn kitty_id_headroom() -> KittyIndexOf<T> {
+		KittyIndexOf::<T>::max_value().saturating_sub(NftModule::<T>::next_token_id(Self::class_id()))
+	}
+
+	/// Rewrite a `V1` kitty as `V2`. Returns whether it was rewritten.
+	fn upgrade_kitty(kitty_id: KittyIndexOf<T>) -> bool {
+		let class_id = Self::class_id();
+		match NftModule::<T>::tokens(class_id, kitty_id) {
+			Some(mut token) if token.data.is_v1() => {
+				token.data = VersionedKitty::V2 {
+					dna: token.data.kitty().dna(),
+					birth: Self::birth_block(kitty_id).unique_saturated_into(),
+					flags: 0,
+				};
+				orml_nft::Tokens::<T>::insert(class_id, kitty_id, token);
+				true
+			}
+			_ => false,
+		}
+	}
+
+	/// Mint a kitty of the pallet's class, bred from the parents of `parents` if given. Its DNA
+	/// must be unique, see `unique_dna`.
+	fn do_mint(
+		owner: &T::AccountId,
+		kitty: Kitty,
+		parents: Option<&BreedContext<T>>,
+	) -> Result<KittyIndexOf<T>, DispatchError> {
+		with_transaction_result(|| {
+			let kitty_id = Self::mint_kitty(owner, &kitty, parents)?;
+			Self::run_hook(|| T::KittyHooks::on_minted(owner, kitty_id, &kitty))?;
+			Ok(kitty_id)
+		})
+	}
+
+	/// `do_mint` without telling `T::KittyHooks`.
+	fn mint_kitty(
+		owner: &T::AccountId,
+		kitty: &Kitty,
+		parents: Option<&BreedContext<T>>,
+	) -> Result<KittyIndexOf<T>, DispatchError> {
+		Self::ensure_not_reentrant()?;
+		Self::ensure_can_mint(owner)?;
+		ensure!(!KittyDna::<T, I>::contains_key(kitty.dna()), Error::<T, I>::WrongDNA);
+		let class_id = parents.map(|context| context.class_id).unwrap_or_else(Self::class_id);
+		let now = <frame_system::Module<T>>::block_number();
+		let data = VersionedKitty::V2 { dna: kitty.dna(), birth: now.unique_saturated_into(), flags: 0 };
+		let kitty_id = NftModule::<T>::mint(owner, class_id, Vec::new(), data)?;
+		Self::note_kitty_count(owner, true);
+		if Self::kitty_id_headroom().is_zero() {
+			MintingExhausted::<I>::put(true);
+			Self::deposit_event(RawEvent::MintingExhausted(Self::next_event_seq()));
+		}
+		BirthBlocks::<T, I>::insert(kitty_id, now);
+		KittyDna::<T, I>::insert(kitty.dna(), kitty_id);
+		if let Some(context) = parents {
+			Generations::<T, I>::insert(kitty_id, context.child_generation());
+			KittyParents::<T, I>::insert(kitty_id, (context.first.id, context.second.id));
+		}
+		let parent_ancestries = parents.map(|context| (context.first.ancestry, context.second.ancestry));
+		Ancestries::<T, I>::insert(kitty_id, T::Hashing::hash_of(&(parent_ancestries, kitty.dna())));
+		KittyCreators::<T, I>::insert(kitty_id, owner);
+		Self::update_gene_pool(&kitty.dna(), true);
+		Self::note_tax_event(owner, kitty_id, TaxEventKind::Minted);
+		Ok(kitty_id)
+	}
+
+	/// `dna`, or if another kitty has it already a genome re-derived from it with the same
+	/// gender. Fails with `WrongDNA` if `MAX_DNA_RETRIES` re-derivations are taken as well.
+	fn unique_dna(dna: [u8; 16]) -> Result<[u8; 16], DispatchError> {
+		if !KittyDna::<T, I>::contains_key(dna) {
+			return Ok(dna);
+		}
+		// salted with the id to be minted, so equal genomes minted in a row don't re-derive
+		// through the same candidates
+		let next_id = NftModule::<T>::next_token_id(Self::class_id());
+		for nonce in 1..=MAX_DNA_RETRIES {
+			let mut candidate = (dna, next_id, nonce).using_encoded(blake2_128);
+			candidate[0] = (candidate[0] & !1) | (dna[0] & 1);
+			if !KittyDna::<T, I>::contains_key(candidate) {
+				return Ok(candidate);
+			}
+		}
+		Err(Error::<T, I>::WrongDNA.into())
+	}
+
+	/// Mint the soulbound badge commemorating `kitty_id` reaching a generation milestone. The
+	/// badge is skipped with `AutoMintSkipped` rather than failing the breed that earned it.
+	fn mint_badge(breeder: &T::AccountId, kitty_id: KittyIndexOf<T>, generation: u32) {
+		let dna = (Self::ancestry(kitty_id), b"kitties/badge").using_encoded(blake2_128);
+		let minted = Self::ensure_can_mint(breeder)
+			.and_then(|_| Self::unique_dna(dna))
+			.and_then(|dna| Self::do_mint(breeder, Kitty(dna), None));
+		let badge_id = match minted {
+			Ok(badge_id) => badge_id,
+			Err(_) => {
+				Self::deposit_event(RawEvent::AutoMintSkipped(breeder.clone(), Self::next_event_seq()));
+				return;
+			}
+		};
+		SoulboundKitties::<T, I>::insert(badge_id, ());
+
+		Self::deposit_event(RawEvent::MilestoneReached(
+			breeder.clone(),
+			kitty_id,
+			generation,
+			badge_id,
+			Self::next_event_seq(),
+		));
+	}
+
+	/// Count a kitty's genes in or out of `GenePool`, one counter per position.
+	fn update_gene_pool(dna: &[u8; 16], added: bool) {
+		for (position, gene) in dna.iter().enumerate() {
+			GenePool::<I>::mutate(position as u8, |buckets| {
+				let count = &mut buckets[(gene >> 4) as usize];
+				*count = if added { count.saturating_add(1) } else { count.saturating_sub(1) };
+			});
+		}
+	}
+
+	/// Whether some gene of `dna` falls into the least populated bucket of its position.
+	/// Positions where all buckets are equally populated have no rare bucket.
+	fn has_rare_gene(dna: &[u8; 16]) -> bool {
+		dna.iter().enumerate().any(|(position, gene)| {
+			let buckets = Self::gene_pool(position as u8);
+			let min = buckets.iter().min().copied().unwrap_or_default();
+			let max = buckets.iter().max().copied().unwrap_or_default();
+			min < max && buckets[(gene >> 4) as usize] == min
+		})
+	}
+
+	/// Pay the rare gene bonus if the pallet account can afford it.
+	fn pay_rare_gene_bonus(owner: &T::AccountId, kitty_id: KittyIndexOf<T>) {
+		let bonus = T::RareGeneBonus::get();
+		if bonus.is_zero() {
+			return;
+		}
+		if Self::transfer_funds(&Self::account_id(), owner, bonus, FlowKind::Bonus).is_ok() {
+			Self::deposit_event(RawEvent::RareGeneBonusPaid(owner.clone(), kitty_id, bonus, Self::next_event_seq()));
+		}
+	}
+
+	/// DNA whose genes all fall into the most populated bucket of their position, so it
+	/// carries no rarity.
+	fn common_dna(who: &T::AccountId) -> [u8; 16] {
+		let mut dna = Self::random_value_for(who, b"kitties/starter");
+		for (position, gene) in dna.iter_mut().enumerate() {
+			let buckets = Self::gene_pool(position as u8);
+			let common = (0..GENE_BUCKETS).rev().max_by_key(|bucket| buckets[*bucket]).unwrap_or_default();
+			*gene = (common as u8) << 4 | (*gene & 0x0f);
+		}
+		dna
+	}
+
+	/// Number of DNA positions where the gene of `kitty` is scarce in the current gene pool.
+	fn scarce_genes(kitty: &Kitty) -> usize {
+		kitty.dna().iter().enumerate()
+			.filter(|(position, gene)| Self::is_scarce_gene(&Self::gene_pool(*position as u8), **gene))
+			.count()
+	}
+
+	/// Whether `gene` falls into a bucket populated below the average of its position.
+	fn is_scarce_gene(buckets: &GeneBuckets, gene: u8) -> bool {
+		let total: u32 = buckets.iter().fold(0u32, |sum, count| sum.saturating_add(*count));
+		buckets[(gene >> 4) as usize].saturating_mul(GENE_BUCKETS as u32) < total
+	}
+
+	/// Up to `limit` kitties of `owner` with their DNA, for the runtime API. Which ones make the
+	/// cut for an owner of more is up to the storage order; they are returned by id.
+	pub fn kitties_of(owner: &T::AccountId, limit: u32) -> Vec<(KittyIndexOf<T>, [u8; 16])> {
+		let class_id = Self::class_id();
+		let mut kitties: Vec<_> = orml_nft::TokensByOwner::<T>::iter_prefix(owner)
+			.filter_map(|((class, kitty_id), _)| if class == class_id { Some(kitty_id) } else { None })
+			.take(limit as usize)
+			.filter_map(|kitty_id| Some((kitty_id, NftModule::<T>::tokens(class_id, kitty_id)?.data.kitty().dna())))
+			.collect();
+		kitties.sort_by_key(|(kitty_id, _)| *kitty_id);
+		kitties
+	}
+
+	/// Traits of a kitty, one per DNA position, for the runtime API.
+	pub fn kitty_traits(kitty_id: KittyIndexOf<T>) -> Option<Vec<KittyTrait>> {
+		let kitty = NftModule::<T>::tokens(Self::class_id(), kitty_id)?.data.kitty();
+		Some(kitty.dna().iter().enumerate().map(|(position, gene)| KittyTrait {
+			slot: position as u8,
+			value: gene >> 4,
+			tier: Self::is_scarce_gene(&Self::gene_pool(position as u8), *gene) as u8,
+		}).collect())
+	}
+
+	/// Chance of each rarity tier for a kitten of parents with DNA `first` and `second`,
+	/// against the current gene pool, rounded down. Every bit of the kitten comes from either
+	/// parent with even odds, unless the gene mutates with `MutationChance` into a uniformly
+	/// random byte. So the odds of a scarce gene are exact per position, up to the mutation
+	/// roll's bias, and the positions are independent.
+	pub fn offspring_rarity_distribution(first: [u8; 16], second: [u8; 16]) -> Vec<(RarityTier, Permill)> {
+		// 1 << 60 stands for certainty, leaving room to multiply two chances in a u128
+		const ONE: u128 = 1 << 60;
+		const BILLION: u128 = 1_000_000_000;
+		let mutation = u128::from(T::MutationChance::get().deconstruct());
+		// chance[n]: chance of n scarce genes among the positions seen so far
+		let mut chance = [0u128; 17];
+		chance[0] = ONE;
+		for (position, (first_gene, second_gene)) in first.iter().zip(second.iter()).enumerate() {
+			let buckets = Self::gene_pool(position as u8);
+			// out of the 256 equally likely selectors
+			let inherited = (0..=255u8)
+				.filter(|selector| Self::is_scarce_gene(&buckets, combine_dna(*first_gene, *second_gene, *selector)))
+				.count() as u128;
+			// out of the 256 equally likely mutated genes
+			let mutated = (0..=255u8).filter(|gene| Self::is_scarce_gene(&buckets, *gene)).count() as u128;
+			let scarce = (inherited * (BILLION - mutation) + mutated * mutation) * ONE / (256 * BILLION);
+			for count in (0..=position + 1).rev() {
+				let carried = if count > 0 { chance[count - 1] * scarce } else { 0 };
+				chance[count] = (chance[count] * (ONE - scarce) + carried) / ONE;
+			}
+		}
+
+		RarityTier::ALL.iter().map(|tier| {
+			let tier_chance: u128 = (0..=16)
+				.filter(|count| RarityTier::from_scarce_genes(*count) == *tier)
+				.map(|count| chance[count])
+				.sum();
+			(*tier, Permill::from_parts((tier_chance * 1_000_000 / ONE) as u32))
+		}).collect()
+	}
+
+	/// Gene counts of every DNA byte position, for breeders planning pairings.
+	pub fn gene_scarcity() -> Vec<GeneBuckets> {
+		(0..16u8).map(Self::gene_pool).collect()
+	}
+
+	/// Current freshness data of a kitty.
+	pub fn freshness(kitty_id: KittyIndexOf<T>) -> KittyFreshness<T::BlockNumber> {
+		KittyFreshness {
+			breed_count: Self::sire_stats(kitty_id).offspring,
+			born_at: Self::birth_block(kitty_id),
+		}
+	}
+
+	/// Destroy a kitty and everything stored about it, archiving it first if `BurnPolicy` says so.
+	fn do_burn(owner: &T::AccountId, kitty_id: KittyIndexOf<T>) -> DispatchResult {
+		with_transaction_result(|| {
+			Self::remove_kitty(owner, kitty_id)?;
+			Self::run_hook(|| T::KittyHooks::on_burned(owner, kitty_id))
+		})
+	}
+
+	/// `do_burn` without telling `T::KittyHooks`.
+	fn remove_kitty(owner: &T::AccountId, kitty_id: KittyIndexOf<T>) -> DispatchResult {
+		Self::ensure_not_reentrant()?;
+		let kitty = NftModule::<T>::tokens(Self::class_id(), kitty_id).ok_or(Error::<T, I>::KittenNotFound)?.data.kitty();
+		NftModule::<T>::burn(owner, (Self::class_id(), kitty_id))?;
+		Self::note_kitty_count(owner, false);
+		// kitties that shared their genome before `KittyDna` existed only have one entry
+		if Self::kitty_with_dna(kitty.dna()) == Some(kitty_id) {
+			KittyDna::<T, I>::remove(kitty.dna());
+		}
+		Self::update_gene_pool(&kitty.dna(), false);
+		if T::BurnPolicy::get() == BurnPolicy::Archive {
+			BurnedKitties::<T, I>::insert(kitty_id, ArchivedKitty {
+				kitty,
+				generation: Self::generation(kitty_id),
+				ancestry: Self::ancestry(kitty_id),
+				burned_at: <frame_system::Module<T>>::block_number(),
+			});
+		}
+
+		Self::remove_listing(kitty_id);
+		KittyApprovals::<T, I>::remove(kitty_id);
+		SiringApprovals::<T, I>::remove(kitty_id);
+		SiringPrices::<T, I>::remove(kitty_id);
+		RentalOffers::<T, I>::remove(kitty_id);
+		KittyRentals::<T, I>::remove(kitty_id);
+		Self::settle_rent_escrow(kitty_id);
+		KittyLocks::<T, I>::remove(kitty_id);
+		DutchAuctions::<T, I>::remove(kitty_id);
+		SireStats::<T, I>::remove(kitty_id);
+		RecentPartners::<T, I>::remove(kitty_id);
+		KittySaleHistory::<T, I>::remove(kitty_id);
+		BirthBlocks::<T, I>::remove(kitty_id);
+		Generations::<T, I>::remove(kitty_id);
+		Ancestries::<T, I>::remove(kitty_id);
+		KittyParents::<T, I>::remove(kitty_id);
+		SoulboundKitties::<T, I>::remove(kitty_id);
+		KittyCustodies::<T, I>::remove(kitty_id);
+		LastSalePrices::<T, I>::remove(kitty_id);
+		KittyCreators::<T, I>::remove(kitty_id);
+		PendingCreators::<T, I>::remove(kitty_id);
+		if let Some((payer, amount)) = KittyDeposits::<T, I>::take(kitty_id) {
+			Self::unreserve_funds(&payer, amount);
+			Self::deposit_event(RawEvent::KittyDepositReleased(payer, kitty_id, amount, Self::next_event_seq()));
+		}
+		for (quoter, quote) in Quotes::<T, I>::drain_prefix(kitty_id) {
+			Self::release_bid(&quoter, &quote);
+		}
+		for (bidder, offer) in Offers::<T, I>::drain_prefix(kitty_id) {
+			Self::refund_reserved(&bidder, offer.amount);
+		}
+		Self::note_tax_event(owner, kitty_id, TaxEventKind::Burned);
+		Ok(())
+	}
+
+	/// Append to the tax history of `who`, dropping its oldest event if full.
+	fn note_tax_event(who: &T::AccountId, kitty_id: KittyIndexOf<T>, kind: TaxEventKind<T::AccountId, BalanceOf<T, I>>) {
+		let block = <frame_system::Module<T>>::block_number();
+		TaxHistories::<T, I>::mutate(who, |history| {
+			if history.len() >= MAX_TAX_HISTORY {
+				let dropped = history.remove(0);
+				TaxHistoryTruncated::<T, I>::insert(who, dropped.block);
+			}
+			history.push(TaxEvent { block, kitty_id, kind });
+		});
+	}
+
+	/// DNA of a kitten of `first` and `second`, bred by `breeder`, and the number of its genes
+	/// that mutated.
+	fn breed_dna(breeder: &T::AccountId, first: &Kitty, second: &Kitty) -> ([u8; 16], u32) {
+		let random_dna_selector = if T::ImmediateBreedRandomness::get() {
+			Self::random_value(breeder)
+		} else {
+			Self::secure_random(&(b"kitties/breed", breeder).encode()).0
+		};
+
+		let mut dna = [0u8; 16];
+		for (i, gene) in dna.iter_mut().enumerate() {
+			*gene = combine_dna(first.dna()[i], second.dna()[i], random_dna_selector[i]);
+		}
+
+		// no draw without a mutation chance, so the other randomness consumers are unaffected
+		let chance = T::MutationChance::get();
+		if chance.is_zero() {
+			return (dna, 0);
+		}
+		let mutation_seed = if T::ImmediateBreedRandomness::get() {
+			Self::random_value_for(breeder, b"kitties/mutation")
+		} else {
+			Self::secure_random(&(b"kitties/mutation", breeder).encode()).0
+		};
+		let mutations = mutate_dna(&mut dna, &mutation_seed, chance);
+		(dna, mutations)
+	}
+
+	/// The account holding the kitties of breeding pool `pool_id`.
+	pub fn pool_account_id(pool_id: PoolId) -> T::AccountId {
+		T::ModuleId::get().into_sub_account((b"pool", pool_id))
+	}
+
+	/// Account holding a kitty while it is a pending gift.
+	pub fn gift_account_id(kitty_id: KittyIndexOf<T>) -> T::AccountId {
+		T::ModuleId::get().into_sub_account((b"gift", kitty_id))
+	}
+
+	/// Account a kitty belongs to: the account that put it in custody while a pallet account
+	/// holds it, its owner otherwise.
+	pub fn beneficial_owner(kitty_id: KittyIndexOf<T>) -> Option<T::AccountId> {
+		match Self::kitty_custody(kitty_id) {
+			Some(custody) => Some(custody.owner),
+			None => Self::owner_of(kitty_id),
+		}
+	}
+
+	/// Whether `who` is the beneficial owner of `kitty_id` in a custody whose `CustodyPolicy`
+	/// leaves it the owner actions that don't move the kitty.
+	fn acts_for_custody(who: &T::AccountId, kitty_id: KittyIndexOf<T>) -> bool {
+		Self::kitty_custody(kitty_id)
+			.map_or(false, |custody| custody.owner == *who && T::CustodyPolicy::get().allows(custody.kind))
+	}
+
+	/// Whether `who` can take the owner actions on `kitty_id` that don't move it, as its owner
+	/// or through `acts_for_custody`.
+	fn is_acting_owner(who: &T::AccountId, kitty_id: KittyIndexOf<T>) -> bool {
+		orml_nft::TokensByOwner::<T>::contains_key(who, (Self::class_id(), kitty_id)) || Self::acts_for_custody(who, kitty_id)
+	}
+
+	/// Contributor due the next kitten of `pool`: contributors take turns in order of joining,
+	/// each as many turns per cycle as the summed weight of its kitties, spread over the cycle.
+	fn pool_recipient(pool: &BreedingPoolOf<T>) -> Option<T::AccountId> {
+		let mut members: Vec<(&T::AccountId, u32)> = Vec::new();
+		for contribution in pool.contributions.iter() {
+			match members.iter_mut().find(|(who, _)| **who == contribution.contributor) {
+				Some((_, weight)) => *weight = weight.saturating_add(contribution.weight),
+				None => members.push((&contribution.contributor, contribution.weight)),
+			}
+		}
+		let total = members.iter().fold(0u32, |sum, (_, weight)| sum.saturating_add(*weight));
+		let max_weight = members.iter().map(|(_, weight)| *weight).max()?;
+		let mut slot = pool.cursor % total.max(1);
+		for round in 0..max_weight {
+			for (who, weight) in members.iter() {
+				if *weight > round {
+					if slot == 0 {
+						return Some((*who).clone());
+					}
+					slot -= 1;
+				}
+			}
+		}
+		None
+	}
+
+	fn note_swap(from: &T::AccountId, to: &T::AccountId, kitty_id: KittyIndexOf<T>) {
+		Self::note_tax_event(from, kitty_id, TaxEventKind::TradeSent { to: to.clone() });
+		Self::note_tax_event(to, kitty_id, TaxEventKind::TradeReceived { from: from.clone() });
+	}
+
+	/// Drop a trade proposal without swapping, releasing its locks and reservation.
+	fn close_trade(trade_id: TradeId, trade: &TradeProposalOf<T, I>) {
+		TradeProposals::<T, I>::remove(trade_id);
+		for kitty_id in trade.give_kitties.iter() {
+			TradeLocks::<T, I>::remove(kitty_id);
+		}
+		Self::refund_reserved(&trade.proposer, trade.give_tokens);
+		Self::deposit_event(RawEvent::TradeClosed(trade_id, Self::next_event_seq()));
+	}
+
+	fn note_sale(seller: &T::AccountId, buyer: &T::AccountId, kitty_id: KittyIndexOf<T>, price: BalanceOf<T, I>) {
+		Self::note_tax_event(seller, kitty_id, TaxEventKind::Sold { buyer: buyer.clone(), price });
+		Self::note_tax_event(buyer, kitty_id, TaxEventKind::Bought { seller: seller.clone(), price });
+
+		let max_history = T::MaxSaleHistory::get() as usize;
+		KittySaleHistory::<T, I>::mutate(kitty_id, |history| {
+			history.push((<frame_system::Module<T>>::block_number(), seller.clone(), buyer.clone(), price));
+			// also catches up after the limit was lowered
+			let excess = history.len().saturating_sub(max_history);
+			history.drain(..excess);
+		});
+
+		if let Some(token) = NftModule::<T>::tokens(Self::class_id(), kitty_id) {
+			let kitty = token.data.kitty();
+			let tier = RarityTier::from_scarce_genes(Self::scarce_genes(&kitty));
+			RecentSales::<T, I>::mutate(|sales| {
+				if sales.len() >= SALE_PRICE_WINDOW {
+					sales.remove(0);
+				}
+				sales.push((tier, price));
+			});
+		}
+	}
+
+	/// Acquisitions and disposals of `who` in blocks `from..=to` with their totals, for the
+	/// runtime API. Only the latest `MAX_TAX_HISTORY` events of an account are kept, the report
+	/// tells whether older ones in the range were lost.
+	pub fn account_tax_report(who: &T::AccountId, from: T::BlockNumber, to: T::BlockNumber) -> TaxReportOf<T, I> {
+		let mut report = TaxReport {
+			events: Vec::new(),
+			acquisitions: 0,
+			disposals: 0,
+			acquisition_cost: Zero::zero(),
+			disposal_proceeds: Zero::zero(),
+			truncated_until: Self::tax_history_truncated(who).filter(|block| *block >= from),
+		};
+		for event in Self::tax_history(who).into_iter().filter(|event| from <= event.block && event.block <= to) {
+			match &event.kind {
+				TaxEventKind::Minted | TaxEventKind::GiftReceived { .. } | TaxEventKind::TradeReceived { .. } => {
+					report.acquisitions = report.acquisitions.saturating_add(1);
+				}
+				TaxEventKind::Bought { price, .. } => {
+					report.acquisitions = report.acquisitions.saturating_add(1);
+					report.acquisition_cost = report.acquisition_cost.saturating_add(*price);
+				}
+				TaxEventKind::Sold { price, .. } => {
+					report.disposals = report.disposals.saturating_add(1);
+					report.disposal_proceeds = report.disposal_proceeds.saturating_add(*price);
+				}
+				TaxEventKind::GiftSent { .. } | TaxEventKind::TradeSent { .. } | TaxEventKind::Burned => {
+					report.disposals = report.disposals.saturating_add(1);
+				}
+			}
+			report.events.push(event);
+		}
+		report
+	}
+
+	/// Rental of a kitty that hasn't ended yet.
+	pub fn active_rental(kitty_id: KittyIndexOf<T>) -> Option<Rental<T::AccountId, T::BlockNumber>> {
+		Self::kitty_rental(kitty_id).filter(|rental| rental.ends_at > <frame_system::Module<T>>::block_number())
+	}
+
+	/// Lease `kitty_id` to `renter` for `duration` periods, paying its owner up front or, if
+	/// `streamed`, reserving the fee in a `RentEscrow`. Returns the owner, the fee and the end
+	/// of the rental.
+	fn start_rental(
+		renter: &T::AccountId,
+		kitty_id: KittyIndexOf<T>,
+		duration: u32,
+		streamed: bool,
+	) -> Result<(T::AccountId, BalanceOf<T, I>, T::BlockNumber), DispatchError> {
+		let terms = Self::rental_terms(kitty_id).ok_or(Error::<T, I>::NotForRent)?;
+		let owner = Self::owner_of(kitty_id).ok_or(Error::<T, I>::KittenNotFound)?;
+		ensure!(owner != *renter, Error::<T, I>::InvalidRental);
+		ensure!(duration > 0 && duration <= terms.max_duration, Error::<T, I>::InvalidRental);
+		Self::ensure_unlocked(kitty_id)?;
+
+		let now = <frame_system::Module<T>>::block_number();
+		let ends_at = now.saturating_add(T::RentalPeriod::get().saturating_mul(duration.into()));
+		ensure!(Self::rentals_ending(ends_at).len() < MAX_RENTALS_PER_BLOCK, Error::<T, I>::TooManyRentalsEnding);
+		let fee = terms.price_per_period.saturating_mul(duration.into());
+
+		if streamed {
+			Self::reserve_funds(renter, fee)?;
+		} else {
+			Self::transfer_funds(renter, &owner, fee, FlowKind::Rent)?;
+		}
+		// returned late, the sweep missed it
+		if let Some(rental) = KittyRentals::<T, I>::take(kitty_id) {
+			Self::settle_rent_escrow(kitty_id);
+			Self::deposit_event(RawEvent::RentalEnded(rental.renter, kitty_id, Self::next_event_seq()));
+		}
+		KittyRentals::<T, I>::insert(kitty_id, Rental { renter: renter.clone(), ends_at });
+		RentalsEnding::<T, I>::append(ends_at, kitty_id);
+		if streamed {
+			let escrow = RentEscrow {
+				owner: owner.clone(),
+				renter: renter.clone(),
+				deposit: fee,
+				claimed: Zero::zero(),
+				started_at: now,
+				ends_at,
+			};
+			RentEscrows::<T, I>::insert(kitty_id, escrow);
+		}
+		Ok((owner, fee, ends_at))
+	}
+
+	/// Part of a streamed rental's fee the owner has earned at `now`: the fee times the share of
+	/// the rental's blocks gone by, rounded down. The rounding leaves less than one unit per
+	/// claim with the renter until the rental ends, when the whole fee is earned, so the owner
+	/// and the renter are always paid out exactly the fee between them.
+	pub fn accrued_rent(escrow: &RentEscrowOf<T, I>, now: T::BlockNumber) -> BalanceOf<T, I> {
+		if now >= escrow.ends_at {
+			return escrow.deposit;
+		}
+		let elapsed: u128 = now.saturating_sub(escrow.started_at).unique_saturated_into();
+		let duration: u128 = escrow.ends_at.saturating_sub(escrow.started_at).unique_saturated_into();
+		let deposit: u128 = escrow.deposit.unique_saturated_into();
+		(deposit.saturating_mul(elapsed) / duration.max(1)).unique_saturated_into()
+	}
+
+	/// Pay the owner of a streamed rental what it earned by `now` and hasn't been paid yet.
+	/// Returns the amount paid.
+	fn pay_accrued_rent(escrow: &mut RentEscrowOf<T, I>, now: T::BlockNumber) -> BalanceOf<T, I> {
+		let due = Self::accrued_rent(escrow, now).saturating_sub(escrow.claimed);
+		if due.is_zero() {
+			return due;
+		}
+		// reserves can be slashed by other pallets, the owner gets what is left of them
+		let unpaid = T::Currency::repatriate_reserved(&escrow.renter, &escrow.owner, due, BalanceStatus::Free)
+			.unwrap_or(due);
+		Self::forget_reserve(&escrow.renter, due);
+		let paid = due.saturating_sub(unpaid);
+		Self::record_flow(&escrow.renter, Some(&escrow.owner), paid, FlowKind::Rent);
+		escrow.claimed = escrow.claimed.saturating_add(due);
+		paid
+	}
+
+	/// Split up the escrow of a streamed rental of `kitty_id` that ends now, if there is one:
+	/// the owner gets the fee earned, the renter the rest back.
+	fn settle_rent_escrow(kitty_id: KittyIndexOf<T>) {
+		let mut escrow = match RentEscrows::<T, I>::take(kitty_id) {
+			Some(escrow) => escrow,
+			None => return,
+		};
+		let now = <frame_system::Module<T>>::block_number();
+		let paid = Self::pay_accrued_rent(&mut escrow, now);
+		let refund = escrow.deposit.saturating_sub(escrow.claimed);
+		Self::refund_reserved(&escrow.renter, refund);
+		Self::deposit_event(RawEvent::RentEscrowSettled(
+			escrow.owner,
+			escrow.renter,
+			kitty_id,
+			paid,
+			refund,
+			Self::next_event_seq(),
+		));
+	}
+
+	/// Give back the kitties whose rental ends at `now`.
+	fn end_rentals(now: T::BlockNumber) -> Weight {
+		let ending = RentalsEnding::<T, I>::take(now);
+		let mut writes = 1;
+		for kitty_id in ending.iter() {
+			match Self::kitty_rental(kitty_id) {
+				Some(rental) if rental.ends_at == now => {
+					KittyRentals::<T, I>::remove(kitty_id);
+					Self::settle_rent_escrow(*kitty_id);
+					Self::deposit_event(RawEvent::RentalEnded(rental.renter, *kitty_id, Self::next_event_seq()));
+					writes += 1;
+				}
+				_ => {}
+			}
+		}
+		T::DbWeight::get().reads_writes(1 + ending.len() as Weight, writes)
+	}
+
+	/// Fail if the kitty can't be moved, listed or bred right now.
+	fn ensure_unlocked(kitty_id: KittyIndexOf<T>) -> DispatchResult {
+		ensure!(Self::active_rental(kitty_id).is_none(), Error::<T, I>::KittyRented);
+		ensure!(!KittyLocks::<T, I>::contains_key(kitty_id), Error::<T, I>::KittyLocked);
+		Self::ensure_unlocked_but_rented(kitty_id)
+	}
+
+	/// `ensure_unlocked`, except for a rental, which still lets its renter breed the kitty.
+	fn ensure_unlocked_but_rented(kitty_id: KittyIndexOf<T>) -> DispatchResult {
+		ensure!(!SoulboundKitties::<T, I>::contains_key(kitty_id), Error::<T, I>::KittySoulbound);
+		ensure!(Self::pending_redemption(kitty_id).is_none(), Error::<T, I>::KittyLocked);
+		ensure!(!TradeLocks::<T, I>::contains_key(kitty_id), Error::<T, I>::KittyLocked);
+		ensure!(!Auctions::<T, I>::contains_key(kitty_id), Error::<T, I>::KittyLocked);
+		Ok(())
+	}
+
+	/// Current price of a kitty on Dutch auction: falls linearly by block from the start
+	/// price, reaching the end price after the duration.
+	pub fn current_dutch_price(kitty_id: KittyIndexOf<T>) -> Option<BalanceOf<T, I>> {
+		let auction = Self::dutch_auction(kitty_id)?;
+		let elapsed = <frame_system::Module<T>>::block_number()
+			.saturating_sub(auction.start_block)
+			.min(auction.duration);
+		let elapsed: u128 = elapsed.unique_saturated_into();
+		let duration: u128 = auction.duration.unique_saturated_into();
+		let span: u128 = auction.start_price.saturating_sub(auction.end_price).unique_saturated_into();
+		let fallen: BalanceOf<T, I> = (span.saturating_mul(elapsed) / duration.max(1)).unique_saturated_into();
+		Some(auction.start_price.saturating_sub(fallen))
+	}
+
+	/// Stage of the auction of `kitty_id`, `None` if there is none or it was settled.
+	pub fn auction_status(kitty_id: KittyIndexOf<T>) -> Option<AuctionStatus> {
+		let auction = Self::auction(kitty_id)?;
+		if auction.end_block < <frame_system::Module<T>>::block_number() {
+			Some(AuctionStatus::AwaitingSettlement)
+		} else {
+			Some(AuctionStatus::Open)
+		}
+	}
+
+	/// Weight of taking an auction off the `SettlementQueue` and settling it: moving the kitty
+	/// and the bid, charging the fee and paying the royalty.
+	fn auction_settlement_weight() -> Weight {
+		T::DbWeight::get().reads_writes(13, 17)
+	}
+
+	/// Append the auctions ending at `now` to the `SettlementQueue`, in the order they started.
+	fn queue_ended_auctions(now: T::BlockNumber) {
+		let ending = AuctionsEnding::<T, I>::take(now);
+		if ending.is_empty() {
+			return;
+		}
+		let (head, mut tail) = Self::settlement_queue_range();
+		for kitty_id in ending {
+			SettlementQueue::<T, I>::insert(tail, kitty_id);
+			tail = tail.wrapping_add(1);
+			Self::deposit_event(RawEvent::AuctionEnded(kitty_id, Self::next_event_seq()));
+		}
+		SettlementQueueRange::<I>::put((head, tail));
+	}
+
+	/// Settle queued auctions in order, at most `limit` of them and as many as fit `budget`.
+	/// Returns the number settled and their weight.
+	fn settle_queued(limit: u32, budget: Weight) -> (u32, Weight) {
+		let (mut head, tail) = Self::settlement_queue_range();
+		let per_auction = Self::auction_settlement_weight();
+		let mut settled = 0;
+		let mut weight: Weight = 0;
+		while head != tail && settled < limit && weight.saturating_add(per_auction) <= budget {
+			if let Some(kitty_id) = SettlementQueue::<T, I>::take(head) {
+				if let Some(auction) = Auctions::<T, I>::take(kitty_id) {
+					Self::settle_auction(kitty_id, auction);
+				}
+			}
+			head = head.wrapping_add(1);
+			settled += 1;
+			weight = weight.saturating_add(per_auction);
+		}
+		if settled > 0 {
+			SettlementQueueRange::<I>::put((head, tail));
+		}
+		(settled, weight)
+	}
 
+	/// Reward the caller of `settle_next` if the pallet account can afford it.
+	fn pay_settlement_reward(caller: &T::AccountId, settled: u32) {
+		let reward = T::SettlementReward::get().saturating_mul(settled.into());
+		if reward.is_zero() {
+			return;
+		}
+		if Self::transfer_funds(&Self::account_id(), caller, reward, FlowKind::Bonus).is_ok() {
+			Self::deposit_event(RawEvent::SettlementRewardPaid(caller.clone(), settled, reward, Self::next_event_seq()));
+		}
+	}
 
-            let new_kitty = Kitty(new_kitty_dna);
-            let kitty_id = NftModule::<T>::mint(&sender, Self::class_id(), Vec::new(), new_kitty.clone())?;
+	/// Sell the kitty of an ended auction to its best bidder, already removed from `Auctions`.
+	/// If there was no bid, or the sale fails, the kitty stays with the seller and the bid is
+	/// released.
+	fn settle_auction(kitty_id: KittyIndexOf<T>, auction: Auction<T::AccountId, BalanceOf<T, I>, T::BlockNumber>) {
+		let seller = auction.seller;
+		let (winner, price) = match auction.best_bid {
+			Some(best_bid) => best_bid,
+			None => {
+				Self::deposit_event(RawEvent::AuctionUnsold(seller, kitty_id, Self::next_event_seq()));
+				return;
+			}
+		};
 
-            Self::deposit_event(RawEvent::KittyBreed(sender, first_kitty, second_kitty, kitty_id))
-        }
+		let payee = Self::proceeds_payee(&seller);
+		let sold = with_transaction_result(|| {
+			let unpaid = T::Currency::repatriate_reserved(&winner, &payee, price, BalanceStatus::Free)?;
+			ensure!(unpaid.is_zero(), Error::<T, I>::BidUnderfunded);
+			Self::forget_reserve(&winner, price);
+			Self::record_flow(&winner, Some(&payee), price, FlowKind::Sale);
+			let fee = Self::settle_market_fee(&payee, price)?;
+			let royalty = Self::pay_royalty(&seller, &payee, kitty_id, price)?;
+			LastSalePrices::<T, I>::insert(kitty_id, price);
+			Self::do_transfer(&seller, &winner, kitty_id)?;
+			Self::note_sale(&seller, &winner, kitty_id, price);
+			Self::count_activity(ActivityKind::Sales);
+			Ok((fee, royalty))
+		});
 
-        #[weight = 1000]
-        pub fn transfer(origin, kitty_id: KittyIndexOf<T>, new_owner_id: T::AccountId) {
-            let sender = ensure_signed(origin)?;
-			NftModule::<T>::transfer(&sender, &new_owner_id, (Self::class_id(), kitty_id))?;
+		if let Ok((fee, royalty)) = sold {
+			Self::deposit_event(RawEvent::AuctionSettled(seller, winner, kitty_id, price, fee, royalty, Self::next_event_seq()));
+		} else {
+			Self::refund_reserved(&winner, price);
+			Self::deposit_event(RawEvent::AuctionUnsold(seller, kitty_id, Self::next_event_seq()));
+		}
+	}
 
-			if sender != new_owner_id {
-				KittyPrices::<T>::remove(kitty_id);
-				Self::deposit_event(RawEvent::KittyTransferred(sender, new_owner_id, kitty_id));
+	/// The open redemption request of a kitty, lazily closing it if it expired.
+	fn pending_redemption(kitty_id: KittyIndexOf<T>) -> Option<Redemption<T::AccountId, T::Hash, T::BlockNumber>> {
+		let redemption = Self::redemptions(kitty_id)?;
+		if redemption.expires_at < <frame_system::Module<T>>::block_number() {
+			Self::close_redemption(kitty_id);
+			Self::deposit_event(RawEvent::RedemptionExpired(redemption.owner, kitty_id, Self::next_event_seq()));
+			return None;
+		}
+		Some(redemption)
+	}
+
+	fn close_redemption(kitty_id: KittyIndexOf<T>) {
+		Redemptions::<T, I>::remove(kitty_id);
+		OpenRedemptions::<T, I>::mutate(|open| open.retain(|id| *id != kitty_id));
+	}
+
+	/// List a kitty on behalf of its owner at `ask`, with no expiry, if the owner can cover
+	/// the `ListingDeposit`. Returns whether it was listed.
+	fn list_ask(owner: &T::AccountId, kitty_id: KittyIndexOf<T>, ask: BalanceOf<T, I>) -> bool {
+		if Self::insert_listing(owner, kitty_id, ask, None, None).is_err() {
+			return false;
+		}
+		Self::deposit_event(RawEvent::KittyPriceUpdated(owner.clone(), kitty_id, Some(ask), None, Self::next_event_seq()));
+		true
+	}
+
+	/// List a kitty of `seller`. The `ListingDeposit` is reserved first, so nothing changes if
+	/// `seller` can't cover it; relisting keeps the deposit of the earlier listing.
+	fn insert_listing(
+		seller: &T::AccountId,
+		kitty_id: KittyIndexOf<T>,
+		price: BalanceOf<T, I>,
+		expires_at: Option<T::BlockNumber>,
+		asset: Option<AssetIdOf<T, I>>,
+	) -> DispatchResult {
+		let deposit = match Self::listing(kitty_id) {
+			Some(old) if old.seller == *seller => old.deposit,
+			_ => {
+				let deposit = T::ListingDeposit::get();
+				if !deposit.is_zero() {
+					Self::reserve_funds(seller, deposit).map_err(|_| Error::<T, I>::ListingDepositUnavailable)?;
+				}
+				Self::remove_listing(kitty_id);
+				deposit
 			}
-        }
+		};
+		Listings::<T, I>::insert(kitty_id, Listing { seller: seller.clone(), price, expires_at, deposit, asset });
+		ForSaleKitties::<T, I>::insert(kitty_id, ());
+		Ok(())
+	}
 
-         #[weight = 1000]
-        pub fn set_price(origin, kitty_id: KittyIndexOf<T>, new_price: Option<BalanceOf<T>>) {
-             let sender = ensure_signed(origin)?;
+	/// Delist a kitty and give the deposit of the listing back to its seller.
+	fn remove_listing(kitty_id: KittyIndexOf<T>) -> Option<ListingOf<T, I>> {
+		let listing = Listings::<T, I>::take(kitty_id)?;
+		ForSaleKitties::<T, I>::remove(kitty_id);
+		if !listing.deposit.is_zero() {
+			Self::unreserve_funds(&listing.seller, listing.deposit);
+		}
+		Some(listing)
+	}
 
-			ensure!(orml_nft::TokensByOwner::<T>::contains_key(&sender, (Self::class_id(), kitty_id)), Error::<T>::KittenNotFound);
+	/// First and last block a commitment made at `committed_at` can be revealed in.
+	fn reveal_period(committed_at: T::BlockNumber) -> (T::BlockNumber, T::BlockNumber) {
+		let reveal_from = committed_at.saturating_add(T::RevealDelay::get());
+		(reveal_from, reveal_from.saturating_add(T::RevealWindow::get()))
+	}
 
-            KittyPrices::<T>::mutate_exists(kitty_id, |price| *price = new_price);
+	/// Reset the dead man's switch timer of `who` and stop redirecting its proceeds. Returns
+	/// the weight used.
+	fn note_activity(who: &T::AccountId) -> Weight {
+		let mut weight = T::DbWeight::get().reads(2);
+		if DeadMansSwitches::<T, I>::contains_key(who) {
+			DeadMansSwitches::<T, I>::mutate(who, |switch| if let Some(switch) = switch {
+				switch.last_active = <frame_system::Module<T>>::block_number();
+			});
+			weight = weight.saturating_add(T::DbWeight::get().reads_writes(1, 1));
+		}
+		if ProceedsBeneficiaries::<T, I>::contains_key(who) {
+			ProceedsBeneficiaries::<T, I>::remove(who);
+			weight = weight.saturating_add(T::DbWeight::get().writes(1));
+		}
+		weight
+	}
 
-            Self::deposit_event(RawEvent::KittyPriceUpdated(sender, kitty_id, new_price));
-        }
+	/// Account the sale proceeds of `seller` go to.
+	fn proceeds_payee(seller: &T::AccountId) -> T::AccountId {
+		Self::proceeds_beneficiary(seller).unwrap_or_else(|| seller.clone())
+	}
 
-        #[weight = 1000]
-        pub fn buy(origin, owner: T::AccountId, kitty_id: KittyIndexOf<T>, max_price: BalanceOf<T>) {
-             let sender = ensure_signed(origin)?;
+	/// Listing price of a kitty under a dead man's switch strategy.
+	fn switch_price(strategy: &PriceStrategy<BalanceOf<T, I>>, kitty_id: KittyIndexOf<T>) -> Option<BalanceOf<T, I>> {
+		match strategy {
+			PriceStrategy::LastSale => Self::last_sale_price(kitty_id),
+			PriceStrategy::Fixed(price) => Some(*price),
+			PriceStrategy::Rarity(base) => {
+				let kitty = NftModule::<T>::tokens(Self::class_id(), kitty_id)?.data.kitty();
+				let scarce_genes = Self::scarce_genes(&kitty) as u32;
+				Some(base.saturating_mul((scarce_genes + 1).into()))
+			}
+		}
+	}
 
-            ensure!(sender != owner, Error::<T>::BuyFromSelf);
+	/// Hold a bid of `quoter`: from its free bidding balance if that covers the bid, otherwise
+	/// reserved. Returns whether the bidding balance was used.
+	fn hold_bid(quoter: &T::AccountId, bid: BalanceOf<T, I>) -> Result<bool, DispatchError> {
+		let mut balance = Self::bidding_balance(quoter);
+		if balance.free >= bid {
+			balance.free -= bid;
+			balance.encumbered = balance.encumbered.saturating_add(bid);
+			BiddingBalances::<T, I>::insert(quoter, balance);
+			Ok(true)
+		} else {
+			Self::reserve_funds(quoter, bid)?;
+			Ok(false)
+		}
+	}
 
-            KittyPrices::<T>::try_mutate_exists(kitty_id, |price| -> DispatchResult {
-				let price = price.take().ok_or(Error::<T>::NotForSale)?;
+	/// Reserve `amount` of `who` for a kitty feature, noting it in `KittyReserves`.
+	fn reserve_funds(who: &T::AccountId, amount: BalanceOf<T, I>) -> DispatchResult {
+		T::Currency::reserve(who, amount)?;
+		KittyReserves::<T, I>::mutate(who, |reserved| *reserved = reserved.saturating_add(amount));
+		Ok(())
+	}
 
-				ensure!(max_price >= price, Error::<T>::PriceTooLow);
+	/// Unreserve `amount` reserved by `reserve_funds`. Never releases more than the
+	/// `KittyReserves` of `who` still backed by reserved funds, so other pallets' reserves are
+	/// left alone. Returns the amount released, less than `amount` after a slash.
+	fn unreserve_funds(who: &T::AccountId, amount: BalanceOf<T, I>) -> BalanceOf<T, I> {
+		let releasable = amount.min(Self::available_reserve(who));
+		let released = releasable.saturating_sub(T::Currency::unreserve(who, releasable));
+		Self::forget_reserve(who, amount);
+		released
+	}
 
-				with_transaction_result(|| {
-					NftModule::<T>::transfer(&owner, &sender, (Self::class_id(), kitty_id))?;
-					T::Currency::transfer(&sender, &owner, price, ExistenceRequirement::KeepAlive)?;
+	/// Give back `amount` reserved by `reserve_funds` through the `Refunds` ledger: it moves
+	/// to the pallet account and `who` takes it with `claim_refund`. While the pallet account
+	/// doesn't exist the reserve is released in place instead, as `unreserve_funds` does.
+	/// Returns the amount given back, less than `amount` after a slash.
+	fn refund_reserved(who: &T::AccountId, amount: BalanceOf<T, I>) -> BalanceOf<T, I> {
+		let releasable = amount.min(Self::available_reserve(who));
+		let pot = Self::account_id();
+		match T::Currency::repatriate_reserved(who, &pot, releasable, BalanceStatus::Free) {
+			Ok(unpaid) => {
+				let moved = releasable.saturating_sub(unpaid);
+				Self::forget_reserve(who, amount);
+				Self::record_flow(who, Some(&pot), moved, FlowKind::Refund);
+				Self::credit_refund(who, moved);
+				moved
+			}
+			Err(_) => Self::unreserve_funds(who, amount),
+		}
+	}
 
-					Self::deposit_event(RawEvent::KittySold(owner, sender, kitty_id, price));
+	/// Drop `amount` from the `KittyReserves` of `who` once it left the reserve otherwise.
+	fn forget_reserve(who: &T::AccountId, amount: BalanceOf<T, I>) {
+		let left = Self::kitty_reserved(who).saturating_sub(amount);
+		if left.is_zero() {
+			KittyReserves::<T, I>::remove(who);
+		} else {
+			KittyReserves::<T, I>::insert(who, left);
+		}
+	}
 
-					Ok(())
-				})
-			})?;
-        }
-    }
-}
+	/// Current owner of a kitty.
+	pub fn owner_of(kitty_id: KittyIndexOf<T>) -> Option<T::AccountId> {
+		NftModule::<T>::tokens(Self::class_id(), kitty_id).map(|token| token.owner)
+	}
 
-fn combine_dna(dna1: u8, dna2: u8, selector: u8) -> u8 {
-	(!selector & dna1) | (selector & dna2)
-}
+	/// Asking price of a listed kitty, in the asset of the listing. Expired listings not removed
+	/// yet have none.
+	pub fn kitty_prices(kitty_id: KittyIndexOf<T>) -> Option<BalanceOf<T, I>> {
+		Self::listing(kitty_id).filter(|listing| !Self::expired(listing.expires_at)).map(|listing| listing.price)
+	}
 
-impl<T: Trait> Module<T> {
-	fn kitties(owner: &T::AccountId, kitty_id: KittyIndexOf<T>) -> Option<Kitty> {
-		NftModule::<T>::tokens(Self::class_id(), kitty_id).and_then(|x| {
-			if x.owner == *owner {
-				Some(x.data)
-			} else {
-				None
+	/// Up to `limit` kitties for sale with their seller and price, in the asset of the listing.
+	/// Pages follow storage key order, not kitty ids: the next page starts after the last kitty
+	/// of the previous one, given as `start_after`. Expired listings are skipped.
+	pub fn for_sale(
+		limit: u32,
+		start_after: Option<KittyIndexOf<T>>,
+	) -> Vec<(KittyIndexOf<T>, T::AccountId, BalanceOf<T, I>)> {
+		let prefix = ForSaleKitties::<T, I>::final_prefix();
+		let mut key = start_after.map_or_else(|| prefix.to_vec(), ForSaleKitties::<T, I>::hashed_key_for);
+		let mut page = Vec::new();
+		while page.len() < limit as usize {
+			key = match sp_io::storage::next_key(&key).filter(|next| next.starts_with(&prefix)) {
+				Some(next) => next,
+				None => break,
+			};
+			let kitty_id = match KittyIndexOf::<T>::decode(&mut Twox64Concat::reverse(&key[prefix.len()..])) {
+				Ok(kitty_id) => kitty_id,
+				Err(_) => continue,
+			};
+			match Self::listing(kitty_id) {
+				Some(listing) if !Self::expired(listing.expires_at) => page.push((kitty_id, listing.seller, listing.price)),
+				_ => {}
+			}
+		}
+		page
+	}
+
+	/// Whether a listing with this expiry can no longer be bought.
+	fn expired(expires_at: Option<T::BlockNumber>) -> bool {
+		expires_at.map_or(false, |expires_at| <frame_system::Module<T>>::block_number() > expires_at)
+	}
+
+	fn remove_expired_listing(kitty_id: KittyIndexOf<T>, seller: T::AccountId) {
+		Self::remove_listing(kitty_id);
+		Self::deposit_event(RawEvent::ListingExpiredEvent(seller, kitty_id, Self::next_event_seq()));
+	}
+
+	/// Submit an unsigned `expire_listing` for listings expired by the block after `now`, up
+	/// to `OFFCHAIN_EXPIRE_LIMIT`. Runs off-chain, so it can read every listing.
+	fn submit_listing_expiries(now: T::BlockNumber) {
+		let expired = Listings::<T, I>::iter()
+			.filter(|(_, listing)| listing.expires_at.map_or(false, |expires_at| expires_at <= now))
+			.take(OFFCHAIN_EXPIRE_LIMIT);
+		for (kitty_id, _) in expired {
+			let call = Call::<T, I>::expire_listing(kitty_id);
+			if SubmitTransaction::<T, Call<T, I>>::submit_unsigned_transaction(call.into()).is_err() {
+				debug::warn!("failed to submit the expiry of the listing of kitty {:?}", kitty_id);
+			}
+		}
+	}
+
+	/// Remove listings that expired before `now`, continuing from `ListingSweepCursor`. Stops
+	/// after `limit` removals and checked blocks, to go on in the next block.
+	pub fn sweep_expired_listings(now: T::BlockNumber, limit: u32) -> Weight {
+		let mut block = Self::listing_sweep_cursor();
+		let (mut reads, mut writes, mut budget) = (1, 0, limit);
+		while block < now && budget > 0 {
+			budget -= 1;
+			let due: Vec<_> = ListingExpiries::<T, I>::iter_prefix(block)
+				.map(|(kitty_id, _)| kitty_id)
+				.take(budget as usize + 1)
+				.collect();
+			reads += due.len() as Weight;
+			for kitty_id in due.iter().take(budget as usize) {
+				ListingExpiries::<T, I>::remove(block, kitty_id);
+				writes += 1;
+				reads += 1;
+				match Self::listing(kitty_id) {
+					Some(listing) if listing.expires_at == Some(block) => {
+						Self::remove_expired_listing(*kitty_id, listing.seller);
+						writes += 1;
+					}
+					// changed or removed since
+					_ => {}
+				}
+			}
+			if due.len() > budget as usize {
+				break;
+			}
+			budget -= due.len() as u32;
+			block = block.saturating_add(One::one());
+		}
+		ListingSweepCursor::<T, I>::put(block);
+		T::DbWeight::get().reads_writes(reads, writes + 1)
+	}
+
+	/// Part of the `KittyReserves` of `who` that is still reserved. Less than recorded once
+	/// something slashed the account's reserve.
+	pub fn available_reserve(who: &T::AccountId) -> BalanceOf<T, I> {
+		Self::kitty_reserved(who).min(T::Currency::reserved_balance(who))
+	}
+
+	/// Give back the bid of a quote that is dropped without being hit.
+	fn release_bid(quoter: &T::AccountId, quote: &Quote<BalanceOf<T, I>, T::BlockNumber>) {
+		if quote.from_wallet {
+			BiddingBalances::<T, I>::mutate(quoter, |balance| {
+				balance.encumbered = balance.encumbered.saturating_sub(quote.bid);
+				balance.free = balance.free.saturating_add(quote.bid);
+			});
+		} else {
+			Self::refund_reserved(quoter, quote.bid);
+		}
+	}
+
+	/// Check the pallet storage invariants, for use in tests and try-runtime. It iterates every
+	/// storage map, so it is left out of runtimes built without the `try-runtime` feature.
+	#[cfg(any(test, feature = "try-runtime"))]
+	pub fn do_try_state() -> Result<(), &'static str> {
+		let class_id = Self::class_id();
+		let next_kitty_id = NftModule::<T>::next_token_id(class_id);
+		let mut counts = sp_std::collections::btree_map::BTreeMap::<T::AccountId, u32>::new();
+		for (owner, (class, kitty_id), _) in orml_nft::TokensByOwner::<T>::iter() {
+			if class != class_id {
+				continue;
+			}
+			*counts.entry(owner.clone()).or_default() += 1;
+			ensure!(kitty_id < next_kitty_id, "kitty id not below the next kitty id");
+			let token = NftModule::<T>::tokens(class_id, kitty_id).ok_or("owner entry for a missing kitty")?;
+			// ownership entries of one id under two accounts can't both match the token owner
+			ensure!(token.owner == owner, "kitty listed under an account that does not own it");
+			match token.data {
+				VersionedKitty::V1(_) => {
+					ensure!(kitty_id >= Self::kitty_upgrade_cursor(), "old kitty layout behind the upgrade cursor")
+				}
+				VersionedKitty::V2 { birth, .. } => {
+					let birth_block: u64 = Self::birth_block(kitty_id).unique_saturated_into();
+					ensure!(birth == birth_block, "kitty birth out of sync with BirthBlocks")
+				}
+			}
+		}
+		// with each owner entry matching its kitty, this leaves no kitty without one
+		ensure!(counts.values().sum::<u32>() == Self::total_kitties(), "kitty without an owner entry");
+		for (owner, count) in OwnedKittiesCount::<T, I>::iter() {
+			ensure!(counts.remove(&owner) == Some(count), "kitty count out of sync");
+		}
+		ensure!(counts.is_empty(), "kitty count missing");
+		for (kitty_id, _) in KittyDeposits::<T, I>::iter() {
+			ensure!(NftModule::<T>::tokens(class_id, kitty_id).is_some(), "deposit of a missing kitty");
+		}
+		for (dna, kitty_id) in KittyDna::<T, I>::iter() {
+			let token = NftModule::<T>::tokens(class_id, kitty_id).ok_or("genome of a missing kitty")?;
+			ensure!(token.data.kitty().dna() == dna, "genome registered for another kitty");
+		}
+		for (kitty_id, listing) in Listings::<T, I>::iter() {
+			let token = NftModule::<T>::tokens(class_id, kitty_id).ok_or("listing of a missing kitty")?;
+			ensure!(token.owner == listing.seller, "kitty listed by an account that does not own it");
+			ensure!(Self::kitty_reserved(&listing.seller) >= listing.deposit, "listing deposit not reserved");
+			ensure!(ForSaleKitties::<T, I>::contains_key(kitty_id), "listing missing from ForSaleKitties");
+		}
+		for (kitty_id, _) in ForSaleKitties::<T, I>::iter() {
+			ensure!(Listings::<T, I>::contains_key(kitty_id), "kitty for sale without a listing");
+		}
+		for (kitty_id, bidder, _) in Offers::<T, I>::iter() {
+			let token = NftModule::<T>::tokens(class_id, kitty_id).ok_or("offer on a missing kitty")?;
+			ensure!(token.owner != bidder, "offer by the owner");
+		}
+		let (head, tail) = Self::settlement_queue_range();
+		let mut queued = sp_std::collections::btree_set::BTreeSet::new();
+		for position in 0..tail.wrapping_sub(head) {
+			let kitty_id = Self::queued_settlement(head.wrapping_add(position)).ok_or("gap in the settlement queue")?;
+			ensure!(Self::auction(kitty_id).is_some(), "settlement queued for a missing auction");
+			queued.insert(kitty_id);
+		}
+		ensure!(SettlementQueue::<T, I>::iter().count() as u32 == tail.wrapping_sub(head), "settlement queued out of range");
+		for (kitty_id, auction) in Auctions::<T, I>::iter() {
+			let token = NftModule::<T>::tokens(class_id, kitty_id).ok_or("auction of a missing kitty")?;
+			ensure!(token.owner == auction.seller, "kitty auctioned by an account that does not own it");
+			ensure!(
+				Self::auctions_ending(auction.end_block).contains(&kitty_id) || queued.contains(&kitty_id),
+				"auction neither ending nor queued for settlement"
+			);
+		}
+		for (kitty_id, auction) in DutchAuctions::<T, I>::iter() {
+			let token = NftModule::<T>::tokens(class_id, kitty_id).ok_or("Dutch auction of a missing kitty")?;
+			ensure!(token.owner == auction.seller, "Dutch auction by an account that does not own it");
+		}
+		for (kitty_id, rental) in KittyRentals::<T, I>::iter() {
+			let token = NftModule::<T>::tokens(class_id, kitty_id).ok_or("rental of a missing kitty")?;
+			ensure!(token.owner != rental.renter, "kitty rented by its owner");
+			ensure!(Self::rentals_ending(rental.ends_at).contains(&kitty_id), "rental missing from its end block");
+		}
+		for (kitty_id, escrow) in RentEscrows::<T, I>::iter() {
+			let rental = Self::kitty_rental(kitty_id).ok_or("rent escrow without a rental")?;
+			ensure!(rental.renter == escrow.renter && rental.ends_at == escrow.ends_at, "rent escrow of another rental");
+			ensure!(escrow.claimed <= escrow.deposit, "rent escrow paid out more than its deposit");
+			ensure!(
+				Self::kitty_reserved(&escrow.renter) >= escrow.deposit.saturating_sub(escrow.claimed),
+				"rent escrow not reserved"
+			);
+		}
+		for (kitty_id, _) in KittyLocks::<T, I>::iter() {
+			ensure!(NftModule::<T>::tokens(class_id, kitty_id).is_some(), "lock of a missing kitty");
+		}
+		for (kitty_id, _) in PendingGifts::<T, I>::iter() {
+			let token = NftModule::<T>::tokens(class_id, kitty_id).ok_or("gift of a missing kitty")?;
+			ensure!(token.owner == Self::gift_account_id(kitty_id), "pending gift not held by its gift account");
+		}
+		for (kitty_id, custody) in KittyCustodies::<T, I>::iter() {
+			let token = NftModule::<T>::tokens(class_id, kitty_id).ok_or("custody of a missing kitty")?;
+			ensure!(token.owner != custody.owner, "kitty in custody held by its beneficial owner");
+			if custody.kind == CustodyKind::Gift {
+				ensure!(Self::pending_gift(kitty_id).is_some(), "gift custody without a pending gift");
+			}
+		}
+		ensure!(
+			!Self::minting_exhausted() || Self::kitty_id_headroom().is_zero(),
+			"minting flagged exhausted with kitty ids left"
+		);
+		Ok(())
+	}
+
+	/// Whether breeding is allowed at block `now`.
+	pub fn in_season(now: T::BlockNumber) -> bool {
+		let seasons = Self::breeding_seasons();
+		seasons.is_empty() || seasons.iter().any(|(start, end)| *start <= now && now <= *end)
+	}
+
+	/// Wrap a price for RPC output.
+	#[cfg(feature = "std")]
+	pub fn price_display(price: BalanceOf<T, I>) -> PriceDisplay {
+		PriceDisplay {
+			raw: price.saturated_into::<u128>().to_string(),
+			decimals: T::TokenDecimals::get(),
+			symbol: T::TokenSymbol::get().to_string(),
+		}
+	}
+
+	/// The account holding funds owed by the pallet.
+	pub fn account_id() -> T::AccountId {
+		T::ModuleId::get().into_account()
+	}
+
+	/// Record that `amount`, already held by the pallet account, is owed to `who`.
+	/// Settlements give funds back this way, see `refund_reserved`, so a single account that
+	/// cannot receive a transfer does not block them; `who` pulls the funds via `claim_refund`.
+	fn credit_refund(who: &T::AccountId, amount: BalanceOf<T, I>) {
+		if amount.is_zero() {
+			return;
+		}
+		Refunds::<T, I>::mutate(who, |total| *total = total.saturating_add(amount));
+		Self::deposit_event(RawEvent::RefundCredited(who.clone(), amount, Self::next_event_seq()));
+	}
+
+	/// Number of blocks since `kitty_id` was minted.
+	pub fn age_of(kitty_id: KittyIndexOf<T>) -> T::BlockNumber {
+		<frame_system::Module<T>>::block_number().saturating_sub(Self::birth_block(kitty_id))
+	}
+
+	/// Fail while the pallet is paused.
+	fn ensure_not_paused() -> DispatchResult {
+		ensure!(!Self::paused(), Error::<T, I>::PalletPaused);
+		Ok(())
+	}
+
+	/// Fail if `kitty_id` is younger than `MaturityPeriod`.
+	fn ensure_mature(kitty_id: KittyIndexOf<T>) -> DispatchResult {
+		ensure!(Self::age_of(kitty_id) >= T::MaturityPeriod::get(), Error::<T, I>::KittyTooYoung);
+		Ok(())
+	}
+
+	/// Number of kittens `kitty_id` can still parent. Counts follow the kitty, not its owner.
+	pub fn remaining_breeds(kitty_id: KittyIndexOf<T>) -> u32 {
+		T::MaxBreedsPerKitty::get().saturating_sub(Self::sire_stats(kitty_id).offspring)
+	}
+
+	/// Whether one kitty is a parent of the other, or they share a parent.
+	pub fn related(first: KittyIndexOf<T>, second: KittyIndexOf<T>) -> bool {
+		let first_parents = Self::kitty_parents(first);
+		let second_parents = Self::kitty_parents(second);
+		let is_parent = |kitty_id, parents: Option<(KittyIndexOf<T>, KittyIndexOf<T>)>| {
+			parents.map_or(false, |(sire, dam)| sire == kitty_id || dam == kitty_id)
+		};
+		let share_parent = first_parents
+			.map_or(false, |(sire, dam)| is_parent(sire, second_parents) || is_parent(dam, second_parents));
+		is_parent(first, second_parents) || is_parent(second, first_parents) || share_parent
+	}
+
+	/// Update breeding statistics of `kitty_id` after it got a kitten with `partner_id`,
+	/// `mutated` if genes of the kitten mutated.
+	fn record_breeding(kitty_id: KittyIndexOf<T>, partner_id: KittyIndexOf<T>, mutated: bool) {
+		let is_new_partner = RecentPartners::<T, I>::mutate(kitty_id, |recent| {
+			if recent.contains(&partner_id) {
+				return false;
+			}
+			if recent.len() >= MAX_RECENT_PARTNERS {
+				recent.remove(0);
+			}
+			recent.push(partner_id);
+			true
+		});
+
+		SireStats::<T, I>::mutate(kitty_id, |stats| {
+			stats.offspring = stats.offspring.saturating_add(1);
+			if is_new_partner {
+				stats.partners = stats.partners.saturating_add(1);
+			}
+			if mutated {
+				stats.mewtations = stats.mewtations.saturating_add(1);
+			}
+		});
+	}
+
+	/// Use up a free mint of `who` in the current promotional window. False if there is no
+	/// window or a quota is exhausted.
+	fn take_promo_mint(who: &T::AccountId) -> bool {
+		let now = <frame_system::Module<T>>::block_number();
+		PromoWindows::<T, I>::mutate(|windows| {
+			let window = match windows.iter_mut().find(|window| window.start <= now && now < window.end) {
+				Some(window) => window,
+				None => return false,
+			};
+			let used = Self::promo_mints(window.start, who);
+			if window.minted >= window.max_total || used >= window.max_per_account {
+				return false;
+			}
+			PromoMints::<T, I>::insert(window.start, who, used.saturating_add(1));
+			window.minted = window.minted.saturating_add(1);
+			true
+		})
+	}
+
+	/// Withdraw a pallet fee from `who` and hand it to `T::FeeHandler`.
+	fn charge_fee(who: &T::AccountId, fee: BalanceOf<T, I>) -> DispatchResult {
+		Self::ensure_not_reentrant()?;
+		if fee.is_zero() {
+			return Ok(());
+		}
+		let imbalance = T::Currency::withdraw(who, fee, WithdrawReason::Fee.into(), ExistenceRequirement::KeepAlive)?;
+		Self::handle_fee(imbalance);
+		Self::record_flow(who, None, fee, FlowKind::Fee);
+		Ok(())
+	}
+
+	/// `charge_fee`, failing with `error` instead of the currency's error if `who` can't pay.
+	fn charge_fee_or(who: &T::AccountId, fee: BalanceOf<T, I>, error: Error<T, I>) -> DispatchResult {
+		Self::ensure_not_reentrant()?;
+		Self::charge_fee(who, fee).map_err(|_| error.into())
+	}
+
+	/// Hand a fee to `T::FeeHandler` under the `ExecutionLock`, so the handler can't move
+	/// kitties or funds through the pallet mid-settlement.
+	fn handle_fee(imbalance: NegativeImbalanceOf<T, I>) {
+		ExecutionLock::<I>::put(true);
+		T::FeeHandler::on_unbalanced(imbalance);
+		ExecutionLock::<I>::kill();
+	}
+
+	/// Run a `T::KittyHooks` callback under the `ExecutionLock`, like `handle_fee`.
+	fn run_hook(hook: impl FnOnce() -> DispatchResult) -> DispatchResult {
+		ExecutionLock::<I>::put(true);
+		let result = hook();
+		ExecutionLock::<I>::kill();
+		result
+	}
+
+	/// Fail with `ReentrancyDetected` while an outside hook runs under the `ExecutionLock`.
+	fn ensure_not_reentrant() -> DispatchResult {
+		ensure!(!ExecutionLock::<I>::get(), Error::<T, I>::ReentrancyDetected);
+		Ok(())
+	}
+
+	/// Charge the marketplace fee on a sale to the seller and add the sale to the era volume.
+	/// Returns the fee charged.
+	fn settle_market_fee(seller: &T::AccountId, price: BalanceOf<T, I>) -> Result<BalanceOf<T, I>, DispatchError> {
+		let fee = Self::market_fee(price);
+		Self::charge_fee(seller, fee)?;
+		EraVolume::<T, I>::mutate(|volume| *volume = volume.saturating_add(price));
+		Ok(fee)
+	}
+
+	/// Sell the listed `kitty_id` to `sender`. An error of a check of the listing, which only
+	/// reads storage, carries the weight of the reads.
+	fn do_buy(
+		sender: T::AccountId,
+		kitty_id: KittyIndexOf<T>,
+		max_price: BalanceOf<T, I>,
+		max_breed_count: Option<u32>,
+		asset: Option<AssetIdOf<T, I>>,
+	) -> DispatchResultWithPostInfo {
+		let reads = |count: Weight| T::DbWeight::get().reads(count);
+		let Listing { seller: owner, price, expires_at, asset: listed_asset, .. } =
+			Self::listing(kitty_id).ok_or_else(|| Error::<T, I>::NotForSale.with_weight(reads(1)))?;
+		if Self::expired(expires_at) {
+			Self::remove_expired_listing(kitty_id, owner);
+			return Err(Error::<T, I>::ListingExpired.into());
+		}
+		// the listing, the class id and the token
+		ensure!(Self::owner_of(kitty_id).as_ref() == Some(&owner), Error::<T, I>::StaleListing.with_weight(reads(3)));
+		ensure!(sender != owner, Error::<T, I>::BuyFromSelf.with_weight(reads(3)));
+
+		ensure!(asset == listed_asset, Error::<T, I>::WrongPaymentAsset.with_weight(reads(3)));
+		ensure!(max_price >= price, Error::<T, I>::PriceTooLow.with_weight(reads(3)));
+
+		let freshness = Self::freshness(kitty_id);
+		if let Some(max_breed_count) = max_breed_count {
+			ensure!(freshness.breed_count <= max_breed_count, Error::<T, I>::KittyStateChanged.with_weight(reads(5)));
+		}
+
+		let payee = Self::proceeds_payee(&owner);
+		with_transaction_result(|| {
+			let (fee, royalty) = match asset {
+				None => {
+					Self::transfer_funds(&sender, &payee, price, FlowKind::Sale)?;
+					let fee = Self::settle_market_fee(&payee, price)?;
+					let royalty = Self::pay_royalty(&owner, &payee, kitty_id, price)?;
+					(fee, royalty)
+				}
+				Some(asset) => Self::settle_asset_sale(asset, &sender, &payee, &owner, kitty_id, price)?,
+			};
+			// clears the listing
+			Self::do_transfer(&owner, &sender, kitty_id)?;
+			// sale prices, volumes and tax reports are kept in `Currency`
+			if asset.is_none() {
+				LastSalePrices::<T, I>::insert(kitty_id, price);
+				Self::note_sale(&owner, &sender, kitty_id, price);
+			}
+			Self::count_activity(ActivityKind::Sales);
+
+			Self::deposit_event(RawEvent::KittySold(owner, sender, kitty_id, price, asset, fee, royalty, freshness, Self::next_event_seq()));
+
+			Ok(())
+		})?;
+		Ok(().into())
+	}
+
+	/// Pay for a sale in `asset`: `buyer` pays `payee`, who pays the market fee, which is
+	/// burned, and the royalty out of the price. Returns the fee and the royalty.
+	fn settle_asset_sale(
+		asset: AssetIdOf<T, I>,
+		buyer: &T::AccountId,
+		payee: &T::AccountId,
+		seller: &T::AccountId,
+		kitty_id: KittyIndexOf<T>,
+		price: BalanceOf<T, I>,
+	) -> Result<(BalanceOf<T, I>, BalanceOf<T, I>), DispatchError> {
+		Self::ensure_not_reentrant()?;
+		T::Assets::transfer(asset, buyer, payee, price)?;
+		let fee = Self::market_fee(price);
+		T::Assets::withdraw(asset, payee, fee)?;
+		let royalty = match Self::royalty_due(seller, kitty_id, price) {
+			Some((creator, royalty)) => {
+				T::Assets::transfer(asset, payee, &creator, royalty)?;
+				royalty
+			}
+			None => Zero::zero(),
+		};
+		Ok((fee, royalty))
+	}
+
+	fn count_activity(kind: ActivityKind) {
+		ActivityCounters::<I>::mutate(kind, |count| *count = count.saturating_add(1));
+	}
+
+	/// All activity counters, for the runtime API.
+	pub fn activity_counters() -> Vec<(ActivityKind, u64)> {
+		ActivityKind::ALL.iter().map(|kind| (*kind, Self::activity_counter(kind))).collect()
+	}
+
+	/// Pay the creator of a kitty its royalty on a sale, out of the proceeds received by
+	/// `payee`. Creators selling their own kitty pay nothing. Returns the royalty paid, rounded
+	/// down so it never exceeds `price`.
+	fn pay_royalty(
+		seller: &T::AccountId,
+		payee: &T::AccountId,
+		kitty_id: KittyIndexOf<T>,
+		price: BalanceOf<T, I>,
+	) -> Result<BalanceOf<T, I>, DispatchError> {
+		match Self::royalty_due(seller, kitty_id, price) {
+			Some((creator, royalty)) => {
+				Self::transfer_funds(payee, &creator, royalty, FlowKind::Royalty)?;
+				Ok(royalty)
+			}
+			None => Ok(Zero::zero()),
+		}
+	}
+
+	/// Creator and amount of the royalty owed by `seller` on a sale, if any.
+	fn royalty_due(
+		seller: &T::AccountId,
+		kitty_id: KittyIndexOf<T>,
+		price: BalanceOf<T, I>,
+	) -> Option<(T::AccountId, BalanceOf<T, I>)> {
+		let creator = Self::kitty_creator(kitty_id)?;
+		let royalty = T::RoyaltyRate::get().mul_floor(price);
+		if creator == *seller || royalty.is_zero() {
+			return None;
+		}
+		Some((creator, royalty))
+	}
+
+	/// Marketplace fee on a sale at `price` in the current era.
+	fn market_fee(price: BalanceOf<T, I>) -> BalanceOf<T, I> {
+		Self::fee_rate().mul_floor(price)
+	}
+
+	/// Fee of the next `create` outside a promo window, `CreationFee * (1 + minted / step)`
+	/// with `minted` the kitties minted so far, burned ones included.
+	pub fn creation_price() -> BalanceOf<T, I> {
+		let fee = T::CreationFee::get();
+		let step = T::CreationPriceStep::get();
+		if step == 0 {
+			return fee;
+		}
+		let minted: u32 = NftModule::<T>::next_token_id(Self::class_id()).unique_saturated_into();
+		fee.saturating_mul(BalanceOf::<T, I>::from((minted / step).saturating_add(1)))
+	}
+
+	/// Fee charged by `breed`, without the `BreedingFee` on a rebreed.
+	fn breed_fee(gender_preference: bool, rebreed: bool) -> BalanceOf<T, I> {
+		let fee = if rebreed { Zero::zero() } else { T::BreedingFee::get() };
+		if gender_preference {
+			fee.saturating_add(T::GenderPreferenceFee::get())
+		} else {
+			fee
+		}
+	}
+
+	/// Pallet-level charges of `call_kind` against the current state, for the runtime API.
+	/// `None` if a bought kitty isn't listed. Promotional mints aren't taken into account. Moving to a new fee era on the way only touches
+	/// the runtime API's throwaway state.
+	pub fn operation_cost(call_kind: KittyCallKind<KittyIndexOf<T>>) -> Option<CostBreakdown<BalanceOf<T, I>>> {
+		let cost = match call_kind {
+			KittyCallKind::Create => {
+				CostBreakdown { fee: Self::creation_price(), reserved: T::KittyDeposit::get(), ..Default::default() }
+			}
+			KittyCallKind::CommitCreate => CostBreakdown { reserved: T::CommitFee::get(), ..Default::default() },
+			KittyCallKind::Breed { gender_preference } => CostBreakdown {
+				fee: Self::breed_fee(gender_preference, false),
+				reserved: T::KittyDeposit::get(),
+				..Default::default()
+			},
+			KittyCallKind::Buy { kitty_id } => {
+				let Listing { seller, price, expires_at, asset, .. } = Self::listing(kitty_id)?;
+				// costs are in `Currency`
+				if Self::expired(expires_at) || asset.is_some() {
+					return None;
+				}
+				let royalty = Self::royalty_due(&seller, kitty_id, price).map(|(_, royalty)| royalty);
+				CostBreakdown {
+					price,
+					market_fee: Self::market_fee(price),
+					royalty: royalty.unwrap_or_else(Zero::zero),
+					..Default::default()
+				}
+			}
+		};
+		Some(cost)
+	}
+
+	/// Fee rate of the current era, moving to a new era first if needed.
+	fn fee_rate() -> Permill {
+		let era = <frame_system::Module<T>>::block_number() / T::EraLength::get();
+		let fee_era = Self::fee_era();
+		if fee_era != Some(era) {
+			// an era without sales never became `FeeEra`, its volume is zero
+			let last_volume = match fee_era {
+				Some(fee_era) if fee_era.saturating_add(One::one()) == era => Self::era_volume(),
+				_ => Zero::zero(),
+			};
+			FeeEra::<T, I>::put(era);
+			EraVolume::<T, I>::kill();
+			LastEraVolume::<T, I>::put(last_volume);
+			Self::update_fee_rate(last_volume);
+		}
+		Self::current_fee_rate()
+	}
+
+	/// Set the fee rate to the highest schedule step reached by `volume`.
+	fn update_fee_rate(volume: BalanceOf<T, I>) {
+		let rate = Self::fee_schedule()
+			.into_iter()
+			.rev()
+			.find(|(threshold, _)| *threshold <= volume)
+			.map(|(_, rate)| rate)
+			.unwrap_or_default();
+		if rate != Self::current_fee_rate() {
+			CurrentFeeRate::<I>::put(rate);
+			let era = Self::fee_era().unwrap_or_else(Zero::zero);
+			Self::deposit_event(RawEvent::FeeRateChanged(era, rate, Self::next_event_seq()));
+		}
+	}
+
+	/// Transfer funds on behalf of the pallet. Funds must not be moved with `T::Currency`
+	/// directly, so every movement shows up in `FlowTotals` and `FundsFlow`. Reserving and
+	/// unreserving keep funds on the same account and are not recorded.
+	fn transfer_funds(from: &T::AccountId, to: &T::AccountId, amount: BalanceOf<T, I>, kind: FlowKind) -> DispatchResult {
+		Self::ensure_not_reentrant()?;
+		let existence = if *from == Self::account_id() {
+			ExistenceRequirement::AllowDeath
+		} else {
+			ExistenceRequirement::KeepAlive
+		};
+		T::Currency::transfer(from, to, amount, existence)?;
+		Self::record_flow(from, Some(to), amount, kind);
+		Ok(())
+	}
+
+	fn issue_rebreed_voucher(owner: &T::AccountId, first: KittyIndexOf<T>, second: KittyIndexOf<T>) {
+		let expires_at = <frame_system::Module<T>>::block_number() + T::RebreedWindow::get();
+		let (first, second) = breeding_pair(first, second);
+		RebreedVouchers::<T, I>::insert(owner, (first, second), expires_at);
+		Self::deposit_event(RawEvent::RebreedVoucherIssued(owner.clone(), first, second, expires_at, Self::next_event_seq()));
+	}
+
+	/// Consume the owner's voucher for the pair, if there is a valid one. Expired ones are dropped.
+	fn take_rebreed_voucher(owner: &T::AccountId, first: KittyIndexOf<T>, second: KittyIndexOf<T>) -> bool {
+		let (first, second) = breeding_pair(first, second);
+		match RebreedVouchers::<T, I>::take(owner, (first, second)) {
+			Some(expires_at) if expires_at >= <frame_system::Module<T>>::block_number() => {
+				Self::deposit_event(RawEvent::RebreedVoucherUsed(owner.clone(), first, second, Self::next_event_seq()));
+				true
+			}
+			_ => false,
+		}
+	}
+
+	/// Sequence number of the next event of the current extrinsic, starting at 0.
+	fn next_event_seq() -> u32 {
+		let index = <frame_system::Module<T>>::extrinsic_index();
+		EventSeq::<I>::mutate(|(extrinsic, seq)| {
+			if *extrinsic != index {
+				*extrinsic = index;
+				*seq = 0;
 			}
+			let current = *seq;
+			*seq = seq.saturating_add(1);
+			current
 		})
 	}
 
+	/// Account for funds moved by the pallet.
+	fn record_flow(from: &T::AccountId, to: Option<&T::AccountId>, amount: BalanceOf<T, I>, kind: FlowKind) {
+		let now = <frame_system::Module<T>>::block_number();
+		FlowTotals::<T, I>::mutate(kind, |(block, total)| {
+			if *block != now {
+				*block = now;
+				*total = Zero::zero();
+			}
+			*total = total.saturating_add(amount);
+		});
+		Self::deposit_event(RawEvent::FundsFlow(from.clone(), to.cloned(), amount, kind, Self::next_event_seq()));
+	}
+
+	/// Funds of `kind` moved by the pallet in the current block.
+	pub fn flow_total(kind: FlowKind) -> BalanceOf<T, I> {
+		let (block, total) = FlowTotals::<T, I>::get(kind);
+		if block == <frame_system::Module<T>>::block_number() {
+			total
+		} else {
+			Zero::zero()
+		}
+	}
+
+	/// Whether a gender preference is enforced, drawn independently of the kitten DNA.
+	fn gender_roll(sender: &T::AccountId) -> bool {
+		let random = Self::random_value_for(sender, b"kitties/gender");
+		let roll = u32::from_le_bytes([random[0], random[1], random[2], random[3]]) % 1_000_000_000;
+		roll < T::GenderPreferenceChance::get().deconstruct()
+	}
+
+	/// Entropy of the randomness consumers, for `subject` or the general seed. Built with the
+	/// `deterministic` feature it is derived from `EntropyCounter` instead of `T::Randomness`,
+	/// so local testnets replay bit for bit.
+	#[cfg(not(feature = "deterministic"))]
+	fn entropy(subject: Option<&[u8]>) -> T::Hash {
+		match subject {
+			Some(subject) => T::Randomness::random(subject),
+			None => T::Randomness::random_seed(),
+		}
+	}
+
+	#[cfg(feature = "deterministic")]
+	fn entropy(subject: Option<&[u8]>) -> T::Hash {
+		let counter = EntropyCounter::<I>::mutate(|counter| {
+			*counter = counter.wrapping_add(1);
+			*counter
+		});
+		T::Hashing::hash_of(&(counter, subject))
+	}
+
 	fn random_value(sender: &T::AccountId) -> [u8; 16] {
 		let payload = (
-			T::Randomness::random_seed(),
+			Self::entropy(None),
+			&sender,
+			<frame_system::Module<T>>::extrinsic_index(),
+		);
+		payload.using_encoded(blake2_128)
+	}
+
+	/// Random value for `context` from `T::SecureRandomness`, and the first block it could be
+	/// known in. Built with the `deterministic` feature it is known since the current block.
+	#[cfg(not(feature = "deterministic"))]
+	fn secure_random(context: &[u8]) -> ([u8; 16], T::BlockNumber) {
+		let (random, known_since) = T::SecureRandomness::random(context);
+		let payload = (random, context, <frame_system::Module<T>>::extrinsic_index());
+		(payload.using_encoded(blake2_128), known_since)
+	}
+
+	#[cfg(feature = "deterministic")]
+	fn secure_random(context: &[u8]) -> ([u8; 16], T::BlockNumber) {
+		let payload = (Self::entropy(Some(context)), context, <frame_system::Module<T>>::extrinsic_index());
+		(payload.using_encoded(blake2_128), <frame_system::Module<T>>::block_number())
+	}
+
+	/// Like `random_value`, but drawn from a separate `subject` so the outcome is independent.
+	fn random_value_for(sender: &T::AccountId, subject: &[u8]) -> [u8; 16] {
+		let payload = (
+			Self::entropy(Some(subject)),
 			&sender,
 			<frame_system::Module<T>>::extrinsic_index(),
+			subject,
 		);
 		payload.using_encoded(blake2_128)
 	}
 }
+
+impl<T: Config<I>, I: Instance> KittyProvider<T::AccountId> for Module<T, I> {
+	type KittyId = KittyIndexOf<T>;
+
+	fn owner(kitty_id: KittyIndexOf<T>) -> Option<T::AccountId> {
+		Self::owner_of(kitty_id)
+	}
+
+	fn transfer(kitty_id: KittyIndexOf<T>, dest: &T::AccountId) -> DispatchResult {
+		let owner = Self::owner_of(kitty_id).ok_or(Error::<T, I>::KittenNotFound)?;
+		Self::give_kitty(owner, dest.clone(), kitty_id)
+	}
+
+	fn mint_into(dest: &T::AccountId, kitty: Kitty) -> Result<KittyIndexOf<T>, DispatchError> {
+		Self::ensure_not_paused()?;
+		let kitty_id = Self::do_mint(dest, kitty.clone(), None)?;
+		Self::count_activity(ActivityKind::Creates);
+		Self::deposit_event(RawEvent::KittyCreated(dest.clone(), kitty_id, kitty, Self::next_event_seq()));
+		Ok(kitty_id)
+	}
+
+	fn burn(kitty_id: KittyIndexOf<T>) -> DispatchResult {
+		let owner = Self::owner_of(kitty_id).ok_or(Error::<T, I>::KittenNotFound)?;
+		Self::burn_kitty(&owner, kitty_id)
+	}
+}
+
+impl<T: Config<I>, I: Instance> LockableKitties<T::AccountId> for Module<T, I> {
+	type KittyId = KittyIndexOf<T>;
+
+	fn owner_of(kitty_id: KittyIndexOf<T>) -> Option<T::AccountId> {
+		Module::<T, I>::owner_of(kitty_id)
+	}
+
+	fn lock(kitty_id: KittyIndexOf<T>, reason: LockReason) -> DispatchResult {
+		ensure!(NftModule::<T>::tokens(Self::class_id(), kitty_id).is_some(), Error::<T, I>::KittenNotFound);
+		ensure!(!KittyLocks::<T, I>::contains_key(kitty_id), Error::<T, I>::KittyLocked);
+		KittyLocks::<T, I>::insert(kitty_id, reason);
+		Ok(())
+	}
+
+	fn unlock(kitty_id: KittyIndexOf<T>, reason: LockReason) -> DispatchResult {
+		ensure!(Self::kitty_lock(kitty_id) == Some(reason), Error::<T, I>::NotLocked);
+		KittyLocks::<T, I>::remove(kitty_id);
+		Ok(())
+	}
+
+	fn is_locked(kitty_id: KittyIndexOf<T>) -> bool {
+		KittyLocks::<T, I>::contains_key(kitty_id)
+	}
+}
+
+impl<T: Config<I>, I: Instance> frame_support::unsigned::ValidateUnsigned for Module<T, I> {
+	type Call = Call<T, I>;
+
+	/// Only `expire_listing` of a listing that is expired by the next block is accepted, once
+	/// per kitty.
+	fn validate_unsigned(_source: TransactionSource, call: &Self::Call) -> TransactionValidity {
+		let kitty_id = match call {
+			Call::expire_listing(kitty_id) => kitty_id,
+			_ => return InvalidTransaction::Call.into(),
+		};
+		match Self::listing(kitty_id) {
+			Some(listing) if Self::expired(listing.expires_at) => {}
+			_ => return InvalidTransaction::Stale.into(),
+		}
+		ValidTransaction::with_tag_prefix("KittiesExpireListing")
+			.priority(T::UnsignedPriority::get())
+			.and_provides(kitty_id)
+			.longevity(EXPIRE_LISTING_LONGEVITY)
+			.propagate(true)
+			.build()
+	}
+}
@@ -0,0 +1,423 @@
+//! Storage migrations of the kitties pallet.
+
+use super::*;
+use frame_support::{
+	storage::{unhashed, StoragePrefixedMap},
+	Blake2_128Concat, IterableStorageDoubleMap, IterableStorageMap, StorageHasher, StorageValue, Twox64Concat,
+};
+use sp_io::hashing::twox_64;
+
+/// Storage layout written by this version of the pallet.
+/// 0: original layout.
+/// 1: `KittyPrices` keyed by `twox_64_concat`.
+/// 2: `KittyReserves` tracks the funds reserved by the pallet.
+/// 3: `Listings` with their seller replace `KittyPrices`.
+/// 4: `OwnedKittiesCount` counts the kitties of each account.
+/// 5: `KittyDna` registers the genome of each kitty.
+/// 6: `Listings` have an optional expiry.
+/// 7: `Listings` record the deposit reserved for them.
+/// 8: `Listings` record the asset of their price.
+/// 9: `ForSaleKitties` indexes `Listings`.
+/// 10: `Offers` carry a nonce.
+/// 11: kitty token data starts with its `VersionedKitty` tag.
+pub const CURRENT_VERSION: u16 = 11;
+
+/// Number of `KittyPrices` entries moved per block while the rehash is in progress.
+pub const REHASH_CHUNK: u32 = 500;
+
+/// Start moving `KittyPrices` from `blake2_128_concat` to `twox_64_concat` keys and move the
+/// first chunk. The rest moves in `on_initialize`; listings not moved yet read as unlisted.
+pub fn start_rehash_kitty_prices<T: Config<I>, I: Instance>() -> Weight {
+	let db = T::DbWeight::get();
+	if StorageVersion::<I>::get() >= 1 {
+		return db.reads(1);
+	}
+	StorageVersion::<I>::put(1);
+	RehashCursor::<I>::put(Vec::<u8>::new());
+	db.reads_writes(1, 2).saturating_add(rehash_kitty_prices::<T, I>(REHASH_CHUNK))
+}
+
+/// Move up to `limit` `KittyPrices` entries, under either hasher, into `Listings`, continuing
+/// from `RehashCursor`. Prices of kitties that no longer exist are dropped and counted in the
+/// log.
+pub fn rehash_kitty_prices<T: Config<I>, I: Instance>(limit: u32) -> Weight {
+	let db = T::DbWeight::get();
+	let cursor = match RehashCursor::<I>::get() {
+		Some(cursor) => cursor,
+		None => return db.reads(1),
+	};
+
+	let prefix = KittyPrices::<T, I>::final_prefix();
+	let mut key = if cursor.is_empty() { prefix.to_vec() } else { cursor };
+	let (mut reads, mut writes, mut moved, mut dropped) = (1, 0, 0, 0);
+	loop {
+		if moved >= limit {
+			RehashCursor::<I>::put(&key);
+			writes += 1;
+			break;
+		}
+		key = match sp_io::storage::next_key(&key) {
+			Some(next) if next.starts_with(&prefix) => next,
+			_ => {
+				RehashCursor::<I>::kill();
+				reads += 1;
+				writes += 1;
+				break;
+			}
+		};
+		reads += 1;
+		let suffix = &key[prefix.len()..];
+		if let Some(kitty_id) = old_kitty_id::<T>(suffix).or_else(|| new_kitty_id::<T>(suffix)) {
+			if let Some(price) = unhashed::take::<BalanceOf<T, I>>(&key) {
+				reads += 2;
+				writes += 1;
+				if list_for_owner::<T, I>(kitty_id, price) {
+					writes += 2;
+				} else {
+					dropped += 1;
+				}
+				moved += 1;
+			}
+		}
+	}
+	if dropped > 0 {
+		debug::info!("kitties: dropped {} listing prices of kitties that no longer exist", dropped);
+	}
+	db.reads_writes(reads, writes)
+}
+
+/// Fill `KittyReserves` with the funds reserved before it existed: commitment fees, bids
+/// not held in a bidding balance, and tokens offered in trade proposals.
+pub fn backfill_kitty_reserves<T: Config<I>, I: Instance>() -> Weight {
+	let db = T::DbWeight::get();
+	if StorageVersion::<I>::get() >= 2 {
+		return db.reads(1);
+	}
+	let (mut reads, mut writes) = (1, 1);
+	let mut note = |who: &T::AccountId, amount: BalanceOf<T, I>| {
+		KittyReserves::<T, I>::mutate(who, |reserved| *reserved = reserved.saturating_add(amount));
+		reads += 1;
+		writes += 1;
+	};
+	for (who, commitment) in CreateCommitments::<T, I>::iter() {
+		note(&who, commitment.fee);
+	}
+	for (_, quoter, quote) in Quotes::<T, I>::iter() {
+		if !quote.from_wallet {
+			note(&quoter, quote.bid);
+		}
+	}
+	for (_, trade) in TradeProposals::<T, I>::iter() {
+		note(&trade.proposer, trade.give_tokens);
+	}
+	StorageVersion::<I>::put(2);
+	db.reads_writes(reads, writes)
+}
+
+/// Move all `KittyPrices` entries into `Listings`, with the current owner as seller, in
+/// chunks: the first one now, the rest in `on_initialize` like the rehash. The sweep restarts
+/// from the first key, as a rehash still in progress may have skipped entries under the new
+/// hasher.
+pub fn migrate_listings<T: Config<I>, I: Instance>() -> Weight {
+	let db = T::DbWeight::get();
+	if StorageVersion::<I>::get() >= 3 {
+		return db.reads(1);
+	}
+	StorageVersion::<I>::put(3);
+	RehashCursor::<I>::put(Vec::<u8>::new());
+	db.reads_writes(1, 2).saturating_add(rehash_kitty_prices::<T, I>(REHASH_CHUNK))
+}
+
+/// Fill `OwnedKittiesCount` from the ownership entries of the pallet's class.
+pub fn count_owned_kitties<T: Config<I>, I: Instance>() -> Weight {
+	let db = T::DbWeight::get();
+	if StorageVersion::<I>::get() >= 4 {
+		return db.reads(1);
+	}
+	let class_id = Module::<T, I>::class_id();
+	let (mut reads, mut writes) = (2, 1);
+	for (owner, (class, _), _) in orml_nft::TokensByOwner::<T>::iter() {
+		reads += 1;
+		if class == class_id {
+			OwnedKittiesCount::<T, I>::mutate(&owner, |count| *count = count.saturating_add(1));
+			writes += 1;
+		}
+	}
+	StorageVersion::<I>::put(4);
+	db.reads_writes(reads, writes)
+}
+
+/// Fill `KittyDna` from the kitties of the pallet's class. Of kitties sharing a genome, the
+/// one with the lowest id is registered.
+pub fn register_kitty_dna<T: Config<I>, I: Instance>() -> Weight {
+	let db = T::DbWeight::get();
+	if StorageVersion::<I>::get() >= 5 {
+		return db.reads(1);
+	}
+	let (mut reads, mut writes) = (1, 1);
+	for (kitty_id, token) in orml_nft::Tokens::<T>::iter_prefix(Module::<T, I>::class_id()) {
+		let dna = token.data.kitty().dna();
+		reads += 2;
+		match KittyDna::<T, I>::get(dna) {
+			Some(registered) if registered < kitty_id => {}
+			_ => {
+				KittyDna::<T, I>::insert(dna, kitty_id);
+				writes += 1;
+			}
+		}
+	}
+	StorageVersion::<I>::put(5);
+	db.reads_writes(reads, writes)
+}
+
+/// Listing layout before version 6.
+#[derive(Decode)]
+struct ListingV5<AccountId, Balance> {
+	seller: AccountId,
+	price: Balance,
+}
+
+/// Rewrite `Listings` with no expiry, and start the expiry sweep at the current block.
+pub fn add_listing_expiry<T: Config<I>, I: Instance>() -> Weight {
+	let db = T::DbWeight::get();
+	if StorageVersion::<I>::get() >= 6 {
+		return db.reads(1);
+	}
+	let prefix = Listings::<T, I>::final_prefix();
+	let mut key = prefix.to_vec();
+	let (mut reads, mut writes) = (1, 2);
+	while let Some(next) = sp_io::storage::next_key(&key).filter(|next| next.starts_with(&prefix)) {
+		key = next;
+		reads += 1;
+		if let Some(old) = unhashed::get::<ListingV5<T::AccountId, BalanceOf<T, I>>>(&key) {
+			let listing: ListingOf<T, I> =
+				Listing { seller: old.seller, price: old.price, expires_at: None, deposit: Zero::zero(), asset: None };
+			unhashed::put(&key, &listing);
+			writes += 1;
+		}
+	}
+	ListingSweepCursor::<T, I>::put(<frame_system::Module<T>>::block_number());
+	StorageVersion::<I>::put(6);
+	db.reads_writes(reads, writes)
+}
+
+/// Listing layout before version 7.
+#[derive(Decode)]
+struct ListingV6<AccountId, Balance, BlockNumber> {
+	seller: AccountId,
+	price: Balance,
+	expires_at: Option<BlockNumber>,
+}
+
+/// Rewrite `Listings` with no deposit. Listings made before the `ListingDeposit` stay free and
+/// release nothing when removed.
+pub fn add_listing_deposit<T: Config<I>, I: Instance>() -> Weight {
+	let db = T::DbWeight::get();
+	if StorageVersion::<I>::get() >= 7 {
+		return db.reads(1);
+	}
+	let prefix = Listings::<T, I>::final_prefix();
+	let mut key = prefix.to_vec();
+	let (mut reads, mut writes) = (1, 1);
+	while let Some(next) = sp_io::storage::next_key(&key).filter(|next| next.starts_with(&prefix)) {
+		key = next;
+		reads += 1;
+		if let Some(old) = unhashed::get::<ListingV6<T::AccountId, BalanceOf<T, I>, T::BlockNumber>>(&key) {
+			let listing: ListingOf<T, I> =
+				Listing {
+					seller: old.seller,
+					price: old.price,
+					expires_at: old.expires_at,
+					deposit: Zero::zero(),
+					asset: None,
+				};
+			unhashed::put(&key, &listing);
+			writes += 1;
+		}
+	}
+	StorageVersion::<I>::put(7);
+	db.reads_writes(reads, writes)
+}
+
+/// Listing layout before version 8.
+#[derive(Decode)]
+struct ListingV7<AccountId, Balance, BlockNumber> {
+	seller: AccountId,
+	price: Balance,
+	expires_at: Option<BlockNumber>,
+	deposit: Balance,
+}
+
+/// Rewrite `Listings` as priced in `Currency`.
+pub fn add_listing_asset<T: Config<I>, I: Instance>() -> Weight {
+	let db = T::DbWeight::get();
+	if StorageVersion::<I>::get() >= 8 {
+		return db.reads(1);
+	}
+	let prefix = Listings::<T, I>::final_prefix();
+	let mut key = prefix.to_vec();
+	let (mut reads, mut writes) = (1, 1);
+	while let Some(next) = sp_io::storage::next_key(&key).filter(|next| next.starts_with(&prefix)) {
+		key = next;
+		reads += 1;
+		if let Some(old) = unhashed::get::<ListingV7<T::AccountId, BalanceOf<T, I>, T::BlockNumber>>(&key) {
+			let listing: ListingOf<T, I> = Listing {
+				seller: old.seller,
+				price: old.price,
+				expires_at: old.expires_at,
+				deposit: old.deposit,
+				asset: None,
+			};
+			unhashed::put(&key, &listing);
+			writes += 1;
+		}
+	}
+	StorageVersion::<I>::put(8);
+	db.reads_writes(reads, writes)
+}
+
+/// Fill `ForSaleKitties` with the kitties listed before it existed.
+pub fn index_listings<T: Config<I>, I: Instance>() -> Weight {
+	let db = T::DbWeight::get();
+	if StorageVersion::<I>::get() >= 9 {
+		return db.reads(1);
+	}
+	let (mut reads, mut writes) = (1, 1);
+	for (kitty_id, _) in Listings::<T, I>::iter() {
+		ForSaleKitties::<T, I>::insert(kitty_id, ());
+		reads += 1;
+		writes += 1;
+	}
+	StorageVersion::<I>::put(9);
+	db.reads_writes(reads, writes)
+}
+
+/// Offer layout before version 10.
+#[derive(Decode)]
+struct OfferV9<Balance, BlockNumber> {
+	amount: Balance,
+	expiry: BlockNumber,
+}
+
+/// Rewrite `Offers` with a nonce each, drawn from `NextOfferNonce`.
+pub fn add_offer_nonce<T: Config<I>, I: Instance>() -> Weight {
+	let db = T::DbWeight::get();
+	if StorageVersion::<I>::get() >= 10 {
+		return db.reads(1);
+	}
+	let prefix = Offers::<T, I>::final_prefix();
+	let mut key = prefix.to_vec();
+	let mut nonce = NextOfferNonce::<I>::get();
+	let (mut reads, mut writes) = (2, 2);
+	while let Some(next) = sp_io::storage::next_key(&key).filter(|next| next.starts_with(&prefix)) {
+		key = next;
+		reads += 1;
+		if let Some(old) = unhashed::get::<OfferV9<BalanceOf<T, I>, T::BlockNumber>>(&key) {
+			unhashed::put(&key, &Offer { amount: old.amount, expiry: old.expiry, nonce });
+			nonce = nonce.wrapping_add(1);
+			writes += 1;
+		}
+	}
+	NextOfferNonce::<I>::put(nonce);
+	StorageVersion::<I>::put(10);
+	db.reads_writes(reads, writes)
+}
+
+/// Give the kitties stored as bare DNA the `V1` tag. Runs ahead of the other migrations, as
+/// they decode kitties; `mark_kitties_tagged` records version 11 once they are done too.
+pub fn tag_v1_kitties<T: Config<I>, I: Instance>() -> Weight {
+	let db = T::DbWeight::get();
+	if StorageVersion::<I>::get() >= 11 {
+		return db.reads(1);
+	}
+	let mut prefix = orml_nft::Tokens::<T>::final_prefix().to_vec();
+	prefix.extend(Twox64Concat::hash(&Module::<T, I>::class_id().encode()));
+	let mut key = prefix.clone();
+	let (mut reads, mut writes) = (2, 0);
+	while let Some(next) = sp_io::storage::next_key(&key).filter(|next| next.starts_with(&prefix)) {
+		key = next;
+		reads += 1;
+		let value = match unhashed::get_raw(&key) {
+			Some(value) => value,
+			None => continue,
+		};
+		// metadata and owner come first, bare DNA is all that follows them
+		let input = &mut &value[..];
+		let head = <(Vec<u8>, T::AccountId)>::decode(input);
+		if let (Ok((metadata, owner)), Ok(dna)) = (head, <[u8; 16]>::decode(input)) {
+			if input.is_empty() {
+				unhashed::put(&key, &(metadata, owner, VersionedKitty::V1(dna)));
+				writes += 1;
+			}
+		}
+	}
+	db.reads_writes(reads, writes)
+}
+
+/// Record version 11 after `tag_v1_kitties` and the migrations to earlier versions ran.
+pub fn mark_kitties_tagged<T: Config<I>, I: Instance>() -> Weight {
+	let db = T::DbWeight::get();
+	if StorageVersion::<I>::get() >= 11 {
+		return db.reads(1);
+	}
+	StorageVersion::<I>::put(11);
+	db.reads_writes(1, 1)
+}
+
+/// List `kitty_id` at `price` on behalf of its owner. Prices of kitties that no longer exist
+/// are dropped, returning false.
+fn list_for_owner<T: Config<I>, I: Instance>(kitty_id: KittyIndexOf<T>, price: BalanceOf<T, I>) -> bool {
+	match orml_nft::Module::<T>::tokens(Module::<T, I>::class_id(), kitty_id) {
+		Some(token) => {
+			let listing = Listing { seller: token.owner, price, expires_at: None, deposit: Zero::zero(), asset: None };
+			Listings::<T, I>::insert(kitty_id, listing);
+			ForSaleKitties::<T, I>::insert(kitty_id, ());
+			true
+		}
+		None => false,
+	}
+}
+
+/// Kitty id of a `KittyPrices` key suffix written with `twox_64_concat`.
+fn new_kitty_id<T: orml_nft::Trait>(suffix: &[u8]) -> Option<KittyIndexOf<T>> {
+	if suffix.len() < 8 {
+		return None;
+	}
+	let (hash, mut encoded) = suffix.split_at(8);
+	if twox_64(encoded)[..] != *hash {
+		return None;
+	}
+	let kitty_id = KittyIndexOf::<T>::decode(&mut encoded).ok()?;
+	if !encoded.is_empty() {
+		return None;
+	}
+	Some(kitty_id)
+}
+
+/// Kitty id of a `KittyPrices` key suffix written with `blake2_128_concat`.
+fn old_kitty_id<T: orml_nft::Trait>(suffix: &[u8]) -> Option<KittyIndexOf<T>> {
+	if suffix.len() < 16 {
+		return None;
+	}
+	let (hash, mut encoded) = suffix.split_at(16);
+	if blake2_128(encoded)[..] != *hash {
+		return None;
+	}
+	let kitty_id = KittyIndexOf::<T>::decode(&mut encoded).ok()?;
+	if !encoded.is_empty() {
+		return None;
+	}
+	Some(kitty_id)
+}
+
+/// Listing price of `kitty_id` still stored under the old `blake2_128_concat` key.
+pub fn old_kitty_price<T: Config<I>, I: Instance>(kitty_id: KittyIndexOf<T>) -> Option<BalanceOf<T, I>> {
+	unhashed::get(&old_kitty_price_key::<T, I>(kitty_id))
+}
+
+/// Raw storage key of `kitty_id` in `KittyPrices` before the rehash.
+pub fn old_kitty_price_key<T: Config<I>, I: Instance>(kitty_id: KittyIndexOf<T>) -> Vec<u8> {
+	let mut key = KittyPrices::<T, I>::final_prefix().to_vec();
+	key.extend(Blake2_128Concat::hash(&kitty_id.encode()));
+	key
+}
@@ -1,14 +1,17 @@
 use super::*;
 
 use frame_support::{
-    assert_noop, assert_ok, impl_outer_event, impl_outer_origin, parameter_types, weights::Weight,
+    assert_noop, assert_ok, impl_outer_event, impl_outer_origin, parameter_types,
+    traits::{OnFinalize, OnInitialize},
+    weights::{RuntimeDbWeight, Weight}, IterableStorageMap, StorageValue,
 };
+use sp_runtime::DispatchError;
 use sp_core::H256;
 use std::cell::RefCell;
 use sp_runtime::{
     testing::Header,
-    traits::{BlakeTwo256, IdentityLookup},
-    Perbill,
+    traits::{BlakeTwo256, Hash as HashT, IdentityLookup},
+    Perbill, Permill,
 };
 
 impl_outer_origin! {
@@ -23,7 +26,10 @@ mod kitties {
 impl_outer_event! {
     pub enum Event for Test {
         frame_system<T>,
+        pallet_balances<T>,
+        orml_tokens<T>,
         kitties<T>,
+        kitties Instance1<T>,
     }
 }
 
@@ -32,8 +38,306 @@ pub struct Test;
 parameter_types! {
     pub const BlockHashCount: u64 = 250;
     pub const MaximumBlockWeight: Weight = 1024;
+    pub const DbWeight: RuntimeDbWeight = RuntimeDbWeight { read: 1, write: 1_000 };
     pub const MaximumBlockLength: u32 = 2 * 1024;
     pub const AvailableBlockRatio: Perbill = Perbill::one();
+    pub const ExistentialDeposit: u64 = 1;
+    pub const MaxLocks: u32 = 50;
+    pub const GenderPreferenceFee: u64 = 10;
+    pub const GenderPreferenceChance: Perbill = Perbill::from_percent(50);
+    pub const TokenDecimals: u8 = 12;
+    pub const TokenSymbol: &'static str = "UNIT";
+    pub const MaxSeasons: u32 = 3;
+    pub const RedemptionTtl: u64 = 10;
+    pub const MaxOpenRedemptions: u32 = 2;
+    pub const MinNovelty: u32 = 24;
+    pub const RebreedWindow: u64 = 5;
+    pub const EraLength: u64 = 10;
+    pub const MaxOpenPolls: u32 = 2;
+    pub const MaxPollVotes: u32 = 6;
+    pub const RareGeneBonus: u64 = 50;
+    pub const RevealDelay: u64 = 2;
+    pub const RevealWindow: u64 = 5;
+    pub const CommitFee: u64 = 20;
+    pub const MaxStarterKittiesPerBlock: u32 = 2;
+    pub const TradeTtl: u64 = 10;
+    pub const MaxTradeKitties: u32 = 8;
+    pub const PoolBreedCooldown: u64 = 5;
+    pub const MaxPoolKitties: u32 = 16;
+    pub const CommitForfeit: Perbill = Perbill::from_percent(50);
+    pub const RentalPeriod: u64 = 10;
+    // room for two auction settlements
+    pub const AuctionSettlementBudget: Weight = 40_000;
+    pub const SettlementReward: u64 = 5;
+    pub const RoyaltyRate: Perbill = Perbill::from_percent(10);
+    pub const MaxSaleHistory: u32 = 3;
+    pub const GenerationMilestone: u32 = 2;
+    pub const UnsignedPriority: u64 = 1 << 20;
+    pub const KittiesModuleId: ModuleId = ModuleId(*b"py/kitty");
+    pub const PuppiesModuleId: ModuleId = ModuleId(*b"py/puppy");
+}
+
+thread_local! {
+    static VERBOSE_METRICS: RefCell<bool> = RefCell::new(false);
+    static CREATION_FEE: RefCell<u64> = RefCell::new(0);
+    static CREATION_PRICE_STEP: RefCell<u32> = RefCell::new(0);
+    static BREEDING_FEE: RefCell<u64> = RefCell::new(0);
+    static MAX_BREEDS_PER_KITTY: RefCell<u32> = RefCell::new(100);
+    static MATURITY_PERIOD: RefCell<u64> = RefCell::new(0);
+    static MUTATION_CHANCE: RefCell<Perbill> = RefCell::new(Perbill::zero());
+    static IMMEDIATE_BREED_RANDOMNESS: RefCell<bool> = RefCell::new(true);
+    static BURN_POLICY: RefCell<BurnPolicy> = RefCell::new(BurnPolicy::Delete);
+    static CUSTODY_POLICY: RefCell<CustodyPolicy> = RefCell::new(CustodyPolicy::default());
+    static REENTER_ON_FEE: RefCell<bool> = RefCell::new(false);
+    static FEES_RECEIVED: RefCell<Vec<u64>> = RefCell::new(Vec::new());
+    static FEE_BENEFICIARY: RefCell<Option<u64>> = RefCell::new(None);
+    static REENTRY_RESULT: RefCell<Option<DispatchResultWithPostInfo>> = RefCell::new(None);
+    static VERIFIED_PERSONS: RefCell<Vec<u64>> = RefCell::new(Vec::new());
+    static MAX_KITTIES_PER_OWNER: RefCell<u32> = RefCell::new(100);
+    static MAX_CREATE_PER_BLOCK: RefCell<u32> = RefCell::new(100);
+    static MAX_BREED_PER_BLOCK: RefCell<Option<u32>> = RefCell::new(None);
+    static MAX_KITTY_SUPPLY: RefCell<Option<u32>> = RefCell::new(None);
+    static KITTY_DEPOSIT: RefCell<u64> = RefCell::new(0);
+    static LISTING_DEPOSIT: RefCell<u64> = RefCell::new(0);
+    static LOCKS_BLOCK_BREEDING: RefCell<bool> = RefCell::new(true);
+    static HOOK_CALLS: RefCell<Vec<HookCall>> = RefCell::new(Vec::new());
+    static REJECT_TRANSFERS_TO: RefCell<Option<u64>> = RefCell::new(None);
+    static REJECT_MINT_OF: RefCell<Option<u32>> = RefCell::new(None);
+}
+
+/// Verifies the accounts in `VERIFIED_PERSONS`.
+pub struct PersonhoodProvider;
+impl Contains<u64> for PersonhoodProvider {
+    fn sorted_members() -> Vec<u64> {
+        let mut members = VERIFIED_PERSONS.with(|v| v.borrow().clone());
+        members.sort();
+        members
+    }
+}
+
+/// Account allowed to act as `ForceOrigin` besides root.
+const GOVERNOR: u64 = 200;
+
+pub struct Governors;
+impl Contains<u64> for Governors {
+    fn sorted_members() -> Vec<u64> {
+        vec![GOVERNOR]
+    }
+}
+
+fn set_verified(who: u64, verified: bool) {
+    VERIFIED_PERSONS.with(|v| {
+        let mut members = v.borrow_mut();
+        members.retain(|member| *member != who);
+        if verified {
+            members.push(who);
+        }
+    });
+}
+
+/// Records the fees it gets and burns them like `()`, or deposits them into
+/// `FEE_BENEFICIARY` if set. Can be told to call back into the pallet.
+pub struct MockFeeHandler;
+impl OnUnbalanced<NegativeImbalanceOf<Test>> for MockFeeHandler {
+    fn on_nonzero_unbalanced(fee: NegativeImbalanceOf<Test>) {
+        FEES_RECEIVED.with(|v| v.borrow_mut().push(fee.peek()));
+        match FEE_BENEFICIARY.with(|v| *v.borrow()) {
+            Some(beneficiary) => Balances::resolve_creating(&beneficiary, fee),
+            None => drop(fee),
+        }
+        if REENTER_ON_FEE.with(|v| *v.borrow()) {
+            let result = KittiesModule::transfer(Origin::signed(100), 1, 102);
+            REENTRY_RESULT.with(|v| *v.borrow_mut() = Some(result));
+        }
+    }
+}
+
+pub struct VerboseMetrics;
+impl Get<bool> for VerboseMetrics {
+    fn get() -> bool {
+        VERBOSE_METRICS.with(|v| *v.borrow())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum HookCall {
+    Minted(u64, u32),
+    Transferred(u64, u64, u32),
+    Burned(u64, u32),
+}
+
+/// Records its calls in `HOOK_CALLS`, vetoes transfers to `REJECT_TRANSFERS_TO` and the mint
+/// of `REJECT_MINT_OF`.
+pub struct MockKittyHooks;
+impl KittyLifecycleHooks<u64, u32> for MockKittyHooks {
+    fn on_minted(owner: &u64, kitty_id: u32, _kitty: &Kitty) -> DispatchResult {
+        if REJECT_MINT_OF.with(|v| *v.borrow()) == Some(kitty_id) {
+            return Err(DispatchError::Other("mint rejected"));
+        }
+        HOOK_CALLS.with(|v| v.borrow_mut().push(HookCall::Minted(*owner, kitty_id)));
+        Ok(())
+    }
+
+    fn on_transferred(from: &u64, to: &u64, kitty_id: u32) -> DispatchResult {
+        if REJECT_TRANSFERS_TO.with(|v| *v.borrow()) == Some(*to) {
+            return Err(DispatchError::Other("transfer rejected"));
+        }
+        HOOK_CALLS.with(|v| v.borrow_mut().push(HookCall::Transferred(*from, *to, kitty_id)));
+        Ok(())
+    }
+
+    fn on_burned(owner: &u64, kitty_id: u32) -> DispatchResult {
+        HOOK_CALLS.with(|v| v.borrow_mut().push(HookCall::Burned(*owner, kitty_id)));
+        Ok(())
+    }
+}
+
+fn hook_calls() -> Vec<HookCall> {
+    HOOK_CALLS.with(|v| v.borrow().clone())
+}
+
+pub const BRIDGE_ACCOUNT: u64 = 999;
+
+/// Stand-in for a bridge pallet that only knows kitties as a `KittyProvider`. Kitties sent
+/// abroad wait with `BRIDGE_ACCOUNT` until brought back, or are burned if retired there.
+pub struct Bridge<P>(std::marker::PhantomData<P>);
+impl<P: KittyProvider<u64, KittyId = u32>> Bridge<P> {
+    fn bring_in(dest: &u64, kitty: Kitty) -> Result<u32, DispatchError> {
+        P::mint_into(dest, kitty)
+    }
+
+    fn send_out(who: &u64, kitty_id: u32) -> DispatchResult {
+        if P::owner(kitty_id) != Some(*who) {
+            return Err(DispatchError::Other("not the owner"));
+        }
+        P::transfer(kitty_id, &BRIDGE_ACCOUNT)
+    }
+
+    fn bring_back(kitty_id: u32, dest: &u64) -> DispatchResult {
+        if P::owner(kitty_id) != Some(BRIDGE_ACCOUNT) {
+            return Err(DispatchError::Other("not abroad"));
+        }
+        P::transfer(kitty_id, dest)
+    }
+
+    fn retire(kitty_id: u32) -> DispatchResult {
+        if P::owner(kitty_id) != Some(BRIDGE_ACCOUNT) {
+            return Err(DispatchError::Other("not abroad"));
+        }
+        P::burn(kitty_id)
+    }
+}
+
+pub struct LocksBlockBreeding;
+impl Get<bool> for LocksBlockBreeding {
+    fn get() -> bool {
+        LOCKS_BLOCK_BREEDING.with(|v| *v.borrow())
+    }
+}
+
+pub struct ImmediateBreedRandomness;
+impl Get<bool> for ImmediateBreedRandomness {
+    fn get() -> bool {
+        IMMEDIATE_BREED_RANDOMNESS.with(|v| *v.borrow())
+    }
+}
+
+pub struct KittyBurnPolicy;
+impl Get<BurnPolicy> for KittyBurnPolicy {
+    fn get() -> BurnPolicy {
+        BURN_POLICY.with(|v| *v.borrow())
+    }
+}
+
+pub struct KittyCustodyPolicy;
+impl Get<CustodyPolicy> for KittyCustodyPolicy {
+    fn get() -> CustodyPolicy {
+        CUSTODY_POLICY.with(|v| *v.borrow())
+    }
+}
+
+pub struct MaxCreatePerBlock;
+impl Get<u32> for MaxCreatePerBlock {
+    fn get() -> u32 {
+        MAX_CREATE_PER_BLOCK.with(|v| *v.borrow())
+    }
+}
+
+pub struct MaxBreedPerBlock;
+impl Get<Option<u32>> for MaxBreedPerBlock {
+    fn get() -> Option<u32> {
+        MAX_BREED_PER_BLOCK.with(|v| *v.borrow())
+    }
+}
+
+pub struct MaxKittiesPerOwner;
+impl Get<u32> for MaxKittiesPerOwner {
+    fn get() -> u32 {
+        MAX_KITTIES_PER_OWNER.with(|v| *v.borrow())
+    }
+}
+
+pub struct MaxKittySupply;
+impl Get<Option<u32>> for MaxKittySupply {
+    fn get() -> Option<u32> {
+        MAX_KITTY_SUPPLY.with(|v| *v.borrow())
+    }
+}
+
+pub struct KittyDeposit;
+impl Get<u64> for KittyDeposit {
+    fn get() -> u64 {
+        KITTY_DEPOSIT.with(|v| *v.borrow())
+    }
+}
+
+pub struct ListingDeposit;
+impl Get<u64> for ListingDeposit {
+    fn get() -> u64 {
+        LISTING_DEPOSIT.with(|v| *v.borrow())
+    }
+}
+
+pub struct CreationFee;
+impl Get<u64> for CreationFee {
+    fn get() -> u64 {
+        CREATION_FEE.with(|v| *v.borrow())
+    }
+}
+
+pub struct MaturityPeriod;
+impl Get<u64> for MaturityPeriod {
+    fn get() -> u64 {
+        MATURITY_PERIOD.with(|v| *v.borrow())
+    }
+}
+
+pub struct MaxBreedsPerKitty;
+impl Get<u32> for MaxBreedsPerKitty {
+    fn get() -> u32 {
+        MAX_BREEDS_PER_KITTY.with(|v| *v.borrow())
+    }
+}
+
+pub struct MutationChance;
+impl Get<Perbill> for MutationChance {
+    fn get() -> Perbill {
+        MUTATION_CHANCE.with(|v| *v.borrow())
+    }
+}
+
+pub struct BreedingFee;
+impl Get<u64> for BreedingFee {
+    fn get() -> u64 {
+        BREEDING_FEE.with(|v| *v.borrow())
+    }
+}
+
+pub struct CreationPriceStep;
+impl Get<u32> for CreationPriceStep {
+    fn get() -> u32 {
+        CREATION_PRICE_STEP.with(|v| *v.borrow())
+    }
 }
 
 impl frame_system::Trait for Test {
@@ -50,7 +354,7 @@ impl frame_system::Trait for Test {
     type Event = Event;
     type BlockHashCount = BlockHashCount;
     type MaximumBlockWeight = MaximumBlockWeight;
-    type DbWeight = ();
+    type DbWeight = DbWeight;
     type BlockExecutionWeight = ();
     type ExtrinsicBaseWeight = ();
     type MaximumExtrinsicWeight = MaximumBlockWeight;
@@ -58,22 +362,198 @@ impl frame_system::Trait for Test {
     type AvailableBlockRatio = AvailableBlockRatio;
     type Version = ();
     type PalletInfo = ();
-    type AccountData = ();
+    type AccountData = pallet_balances::AccountData<u64>;
     type OnNewAccount = ();
     type OnKilledAccount = ();
     type SystemWeightInfo = ();
 }
 
-impl Trait for Test {
+impl pallet_balances::Trait for Test {
+    type MaxLocks = MaxLocks;
+    type Balance = u64;
+    type Event = Event;
+    type DustRemoval = ();
+    type ExistentialDeposit = ExistentialDeposit;
+    type AccountStore = System;
+    type WeightInfo = ();
+}
+
+/// Asset kitties are listed in besides the native currency.
+const USD: u32 = 1;
+
+impl orml_tokens::Trait for Test {
+    type Event = Event;
+    type Balance = u64;
+    type Amount = i64;
+    type CurrencyId = u32;
+    type OnReceived = ();
+    type WeightInfo = ();
+}
+
+impl orml_nft::Trait for Test {
+    type ClassId = u32;
+    type TokenId = u32;
+    type ClassData = ();
+    type TokenData = VersionedKitty;
+}
+
+impl Config for Test {
+    type Event = Event;
+    type Randomness = MockRandom;
+    type SecureRandomness = MockRandom;
+    type ImmediateBreedRandomness = ImmediateBreedRandomness;
+    type MaxStarterKittiesPerBlock = MaxStarterKittiesPerBlock;
+    type MaxCreatePerBlock = MaxCreatePerBlock;
+    type MaxBreedPerBlock = MaxBreedPerBlock;
+    type MaxKittiesPerOwner = MaxKittiesPerOwner;
+    type MaxKittySupply = MaxKittySupply;
+    type PersonhoodProvider = PersonhoodProvider;
+    type BurnPolicy = KittyBurnPolicy;
+    type CustodyPolicy = KittyCustodyPolicy;
+    type TradeTtl = TradeTtl;
+    type MaxTradeKitties = MaxTradeKitties;
+    type PoolBreedCooldown = PoolBreedCooldown;
+    type MaxPoolKitties = MaxPoolKitties;
+    type PriceOracle = SaleMedianOracle<Test>;
+    type Currency = Balances;
+    type Assets = Tokens;
+    type FeeHandler = MockFeeHandler;
+    type KittyHooks = MockKittyHooks;
+    type MaturityPeriod = MaturityPeriod;
+    type MaxBreedsPerKitty = MaxBreedsPerKitty;
+    type BreedingFee = BreedingFee;
+    type GenderPreferenceFee = GenderPreferenceFee;
+    type MutationChance = MutationChance;
+    type GenderPreferenceChance = GenderPreferenceChance;
+    type TokenDecimals = TokenDecimals;
+    type TokenSymbol = TokenSymbol;
+    type ForceOrigin = frame_system::EnsureOneOf<
+        u64,
+        frame_system::EnsureRoot<u64>,
+        frame_system::EnsureSignedBy<Governors, u64>,
+    >;
+    type MaxSeasons = MaxSeasons;
+    type AdminOrigin = frame_system::EnsureRoot<u64>;
+    type FulfillmentOrigin = frame_system::EnsureRoot<u64>;
+    type RedemptionTtl = RedemptionTtl;
+    type MaxOpenRedemptions = MaxOpenRedemptions;
+    type MinNovelty = MinNovelty;
+    type RebreedWindow = RebreedWindow;
+    type EraLength = EraLength;
+    type MaxOpenPolls = MaxOpenPolls;
+    type MaxPollVotes = MaxPollVotes;
+    type RareGeneBonus = RareGeneBonus;
+    type RevealDelay = RevealDelay;
+    type RevealWindow = RevealWindow;
+    type CreationFee = CreationFee;
+    type CreationPriceStep = CreationPriceStep;
+    type CommitFee = CommitFee;
+    type KittyDeposit = KittyDeposit;
+    type ListingDeposit = ListingDeposit;
+    type ModuleId = KittiesModuleId;
+    type CommitForfeit = CommitForfeit;
+    type RentalPeriod = RentalPeriod;
+    type AuctionSettlementBudget = AuctionSettlementBudget;
+    type SettlementReward = SettlementReward;
+    type RoyaltyRate = RoyaltyRate;
+    type MaxSaleHistory = MaxSaleHistory;
+    type VerboseMetrics = VerboseMetrics;
+    type LocksBlockBreeding = LocksBlockBreeding;
+    type GenerationMilestone = GenerationMilestone;
+    type UnsignedPriority = UnsignedPriority;
+    type WeightInfo = ();
+}
+
+// A second instance, as a runtime holding both kitties and puppies would have.
+impl Config<Instance1> for Test {
     type Event = Event;
-    type Randomness = pallet_randomness_collective_flip::Module<Test>;
+    type Randomness = MockRandom;
+    type SecureRandomness = MockRandom;
+    type ImmediateBreedRandomness = ImmediateBreedRandomness;
+    type MaxStarterKittiesPerBlock = MaxStarterKittiesPerBlock;
+    type MaxCreatePerBlock = MaxCreatePerBlock;
+    type MaxBreedPerBlock = MaxBreedPerBlock;
+    type MaxKittiesPerOwner = MaxKittiesPerOwner;
+    type MaxKittySupply = MaxKittySupply;
+    type PersonhoodProvider = PersonhoodProvider;
+    type BurnPolicy = KittyBurnPolicy;
+    type CustodyPolicy = KittyCustodyPolicy;
+    type TradeTtl = TradeTtl;
+    type MaxTradeKitties = MaxTradeKitties;
+    type PoolBreedCooldown = PoolBreedCooldown;
+    type MaxPoolKitties = MaxPoolKitties;
+    type PriceOracle = SaleMedianOracle<Test, Instance1>;
+    type Currency = Balances;
+    type Assets = Tokens;
+    type FeeHandler = ();
+    type KittyHooks = ();
+    type MaturityPeriod = MaturityPeriod;
+    type MaxBreedsPerKitty = MaxBreedsPerKitty;
+    type BreedingFee = BreedingFee;
+    type GenderPreferenceFee = GenderPreferenceFee;
+    type MutationChance = MutationChance;
+    type GenderPreferenceChance = GenderPreferenceChance;
+    type TokenDecimals = TokenDecimals;
+    type TokenSymbol = TokenSymbol;
+    type ForceOrigin = frame_system::EnsureOneOf<
+        u64,
+        frame_system::EnsureRoot<u64>,
+        frame_system::EnsureSignedBy<Governors, u64>,
+    >;
+    type MaxSeasons = MaxSeasons;
+    type AdminOrigin = frame_system::EnsureRoot<u64>;
+    type FulfillmentOrigin = frame_system::EnsureRoot<u64>;
+    type RedemptionTtl = RedemptionTtl;
+    type MaxOpenRedemptions = MaxOpenRedemptions;
+    type MinNovelty = MinNovelty;
+    type RebreedWindow = RebreedWindow;
+    type EraLength = EraLength;
+    type MaxOpenPolls = MaxOpenPolls;
+    type MaxPollVotes = MaxPollVotes;
+    type RareGeneBonus = RareGeneBonus;
+    type RevealDelay = RevealDelay;
+    type RevealWindow = RevealWindow;
+    type CreationFee = CreationFee;
+    type CreationPriceStep = CreationPriceStep;
+    type CommitFee = CommitFee;
+    type KittyDeposit = KittyDeposit;
+    type ListingDeposit = ListingDeposit;
+    type ModuleId = PuppiesModuleId;
+    type CommitForfeit = CommitForfeit;
+    type RentalPeriod = RentalPeriod;
+    type AuctionSettlementBudget = AuctionSettlementBudget;
+    type SettlementReward = SettlementReward;
+    type RoyaltyRate = RoyaltyRate;
+    type MaxSaleHistory = MaxSaleHistory;
+    type VerboseMetrics = VerboseMetrics;
+    type LocksBlockBreeding = LocksBlockBreeding;
+    type GenerationMilestone = GenerationMilestone;
+    type UnsignedPriority = UnsignedPriority;
+    type WeightInfo = ();
+}
+
+type Extrinsic = sp_runtime::testing::TestXt<Call<Test>, ()>;
+
+impl frame_system::offchain::SendTransactionTypes<Call<Test>> for Test {
+    type OverarchingCall = Call<Test>;
+    type Extrinsic = Extrinsic;
+}
+
+impl frame_system::offchain::SendTransactionTypes<Call<Test, Instance1>> for Test {
+    type OverarchingCall = Call<Test, Instance1>;
+    type Extrinsic = sp_runtime::testing::TestXt<Call<Test, Instance1>, ()>;
 }
 
 type KittiesModule = Module<Test>;
+type PuppiesModule = Module<Test, Instance1>;
 type System = frame_system::Module<Test>;
+type Balances = pallet_balances::Module<Test>;
+type Tokens = orml_tokens::Module<Test>;
+type Nft = orml_nft::Module<Test>;
 
 thread_local! {
     static RANDOM_PAYLOAD: RefCell<H256> = RefCell::new(Default::default());
+    static SECURE_RANDOM_PAYLOAD: RefCell<(H256, u64)> = RefCell::new(Default::default());
 }
 
 pub struct MockRandom;
@@ -84,18 +564,41 @@ impl Randomness<H256> for MockRandom {
 	}
 }
 
+impl KnownSinceRandomness<H256, u64> for MockRandom {
+	fn random(_subject: &[u8]) -> (H256, u64) {
+		SECURE_RANDOM_PAYLOAD.with(|v| *v.borrow())
+	}
+}
+
 fn set_random(val: H256) {
 	RANDOM_PAYLOAD.with(|v| *v.borrow_mut() = val)
 }
 
+fn set_secure_random(val: H256, known_since: u64) {
+	SECURE_RANDOM_PAYLOAD.with(|v| *v.borrow_mut() = (val, known_since))
+}
+
 // Build genesis storage according to the mock runtime.
 pub fn new_test_ext() -> sp_io::TestExternalities {
-    let mut t: sp_io::TestExternalities = frame_system::GenesisConfig::default()
+    let mut t = frame_system::GenesisConfig::default()
         .build_storage::<Test>()
-        .unwrap()
-        .into();
-    t.execute_with(|| System::set_block_number(1));
-    t
+        .unwrap();
+    pallet_balances::GenesisConfig::<Test> {
+        balances: vec![(100, 10_000), (101, 10_000), (102, 10_000)],
+    }
+    .assimilate_storage(&mut t)
+    .unwrap();
+    orml_tokens::GenesisConfig::<Test> {
+        endowed_accounts: vec![(100, USD, 1_000), (101, USD, 1_000), (102, USD, 1_000)],
+    }
+    .assimilate_storage(&mut t)
+    .unwrap();
+    GenesisConfig::<Test>::default().assimilate_storage(&mut t).unwrap();
+    GenesisConfig::<Test, Instance1>::default().assimilate_storage(&mut t).unwrap();
+
+    let mut ext: sp_io::TestExternalities = t.into();
+    ext.execute_with(|| System::set_block_number(1));
+    ext
 }
 
 fn last_event() -> Event {
@@ -111,12 +614,12 @@ fn can_create() {
             59, 250, 138, 82, 209, 39, 141, 109, 163, 238, 183, 145, 235, 168, 18, 122,
         ]);
 
-        assert_eq!(KittiesModule::kitties(100, 0), Some(kitty.clone()));
-        assert_eq!(KittiesModule::next_kitty_id(), 1);
+        assert_eq!(KittiesModule::kitties(&100, 0), Some(kitty.clone()));
+        assert_eq!(Nft::next_token_id(KittiesModule::class_id()), 1);
 
         assert_eq!(
             last_event(),
-            Event::kitties(RawEvent::KittyCreated(100, 0, kitty))
+            Event::kitties(RawEvent::KittyCreated(100, 0, kitty, 0))
         );
     });
 }
@@ -133,38 +636,38 @@ fn can_breed() {
         assert_ok!(KittiesModule::create(Origin::signed(100)));
 		set_random(H256::from([2; 32]));
 
-		let kitty_one = KittiesModule::kitties(100, 0).unwrap();
+		let kitty_one = KittiesModule::kitties(&100, 0).unwrap();
 
         System::set_extrinsic_index(1);
 
         assert_ok!(KittiesModule::create(Origin::signed(100)));
-		let kitty_two = KittiesModule::kitties(100, 1).unwrap();
+		let kitty_two = KittiesModule::kitties(&100, 1).unwrap();
 
         assert_noop!(
-            KittiesModule::breed(Origin::signed(100), 0, 11),
+            KittiesModule::breed(Origin::signed(100), 0, 11, None),
             Error::<Test>::KittenNotFound
         );
         assert_noop!(
-            KittiesModule::breed(Origin::signed(100), 0, 0),
+            KittiesModule::breed(Origin::signed(100), 0, 0, None),
             Error::<Test>::SameGenderBreed
         );
         assert_noop!(
-            KittiesModule::breed(Origin::signed(101), 0, 1),
+            KittiesModule::breed(Origin::signed(101), 0, 1, None),
             Error::<Test>::KittenNotFound
         );
 
-        assert_ok!(KittiesModule::breed(Origin::signed(100), 0, 1));
+        assert_ok!(KittiesModule::breed(Origin::signed(100), 0, 1, None));
 
         let kitty = Kitty([
-			59, 254, 219, 122, 245, 239, 191, 125, 255, 239, 247, 247, 251, 239, 247, 254
+			187, 254, 239, 222, 215, 167, 173, 111, 247, 254, 255, 189, 239, 186, 115, 123
         ]);
 
-        assert_eq!(KittiesModule::kitties(100, 2), Some(kitty.clone()));
-        assert_eq!(KittiesModule::next_kitty_id(), 3);
+        assert_eq!(KittiesModule::kitties(&100, 2), Some(kitty.clone()));
+        assert_eq!(Nft::next_token_id(KittiesModule::class_id()), 3);
 
         assert_eq!(
             last_event(),
-            Event::kitties(RawEvent::KittyBreed(100, kitty_one, kitty_two, kitty))
+            Event::kitties(RawEvent::KittyBreed(100, kitty_one, kitty_two, 2, 1, 1))
         );
     });
 }
@@ -174,3 +677,4262 @@ fn combine_dna_works() {
     assert_eq!(combine_dna(0b11111111, 0b00000000, 0b00001111), 0b11110000);
     assert_eq!(combine_dna(0b10101010, 0b11110000, 0b11001100), 0b11100010);
 }
+
+#[test]
+fn mutate_dna_works() {
+    let seed = [7u8; 16];
+    let mut dna = [1u8; 16];
+    assert_eq!(mutate_dna(&mut dna, &seed, Perbill::zero()), 0);
+    assert_eq!(dna, [1u8; 16]);
+
+    assert_eq!(mutate_dna(&mut dna, &seed, Perbill::one()), 16);
+    for (i, gene) in dna.iter().enumerate() {
+        assert_eq!(*gene, (seed, i as u8).using_encoded(blake2_128)[4]);
+    }
+
+    // the same seed mutates the same genes
+    let mut first = [1u8; 16];
+    let mut second = [1u8; 16];
+    let mutations = mutate_dna(&mut first, &seed, Perbill::from_percent(50));
+    assert_eq!(mutate_dna(&mut second, &seed, Perbill::from_percent(50)), mutations);
+    assert_eq!(first, second);
+    assert!(mutations > 0 && mutations < 16);
+}
+
+#[test]
+fn breeding_updates_sire_stats() {
+    new_test_ext().execute_with(|| {
+        // female, male, female
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        System::set_extrinsic_index(1);
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        System::set_extrinsic_index(2);
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+
+        assert_eq!(KittiesModule::sire_stats(0), BreedingStats::default());
+
+        assert_ok!(KittiesModule::breed(Origin::signed(100), 0, 1, None));
+        assert_ok!(KittiesModule::breed(Origin::signed(100), 0, 1, None));
+        assert_ok!(KittiesModule::breed(Origin::signed(100), 1, 2, None));
+
+        assert_eq!(KittiesModule::sire_stats(0), BreedingStats { offspring: 2, partners: 1, mewtations: 0 });
+        assert_eq!(KittiesModule::sire_stats(1), BreedingStats { offspring: 3, partners: 2, mewtations: 0 });
+        assert_eq!(KittiesModule::sire_stats(2), BreedingStats { offspring: 1, partners: 1, mewtations: 0 });
+        assert_eq!(KittiesModule::recent_partners(1), vec![0, 2]);
+
+        // stats are keyed by kitty and follow it to the new owner
+        assert_ok!(KittiesModule::transfer(Origin::signed(100), 1, 101));
+        assert_eq!(KittiesModule::sire_stats(1).offspring, 3);
+    });
+}
+
+#[test]
+fn refunds_can_be_claimed_once() {
+    new_test_ext().execute_with(|| {
+        let pot = KittiesModule::account_id();
+        let _ = Balances::deposit_creating(&pot, 1_000);
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::start_auction(Origin::signed(100), 0, 100, 3));
+
+        assert_noop!(KittiesModule::claim_refund(Origin::signed(101)), Error::<Test>::NoRefund);
+
+        // outbid twice, both bids are owed back through the pallet account
+        assert_ok!(KittiesModule::bid(Origin::signed(101), 0, 300));
+        assert_ok!(KittiesModule::bid(Origin::signed(102), 0, 400));
+        assert!(System::events().iter().any(|r| matches!(
+            r.event,
+            Event::kitties(RawEvent::RefundCredited(101, 300, _))
+        )));
+        assert_ok!(KittiesModule::bid(Origin::signed(101), 0, 500));
+        assert_eq!(KittiesModule::refunds(101), 300);
+        assert_eq!(KittiesModule::refunds(102), 400);
+        assert_eq!(Balances::reserved_balance(101), 500);
+        assert_eq!(Balances::reserved_balance(102), 0);
+        assert_eq!(Balances::free_balance(pot), 1_700);
+        assert_eq!(KittiesModule::kitty_reserved(102), 0);
+        assert_ok!(KittiesModule::do_try_state());
+
+        // settling doesn't wait for the refunds to be claimed
+        KittiesModule::on_finalize(3);
+        System::set_block_number(4);
+        KittiesModule::on_initialize(4);
+        assert!(KittiesModule::kitties(&101, 0).is_some());
+        assert_eq!(Balances::free_balance(100), 10_500);
+
+        assert_ok!(KittiesModule::claim_refund(Origin::signed(101)));
+        assert!(matches!(last_event(), Event::kitties(RawEvent::RefundClaimed(101, 300, _))));
+        assert_eq!(Balances::free_balance(101), 9_500);
+        assert_eq!(KittiesModule::refunds(101), 0);
+        assert_noop!(KittiesModule::claim_refund(Origin::signed(101)), Error::<Test>::NoRefund);
+
+        assert_ok!(KittiesModule::claim_refund(Origin::signed(102)));
+        assert_eq!(Balances::free_balance(102), 10_000);
+        assert_eq!(Balances::free_balance(pot), 1_000);
+        assert_noop!(KittiesModule::claim_refund(Origin::signed(102)), Error::<Test>::NoRefund);
+        assert_ok!(KittiesModule::do_try_state());
+    });
+}
+
+#[test]
+fn cancelled_offers_are_refunded_through_the_ledger() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+
+        // without a pallet account the offer is released in place
+        assert_ok!(KittiesModule::make_offer(Origin::signed(101), 0, 200, 10));
+        assert_ok!(KittiesModule::cancel_offer(Origin::signed(101), 0, 101));
+        assert_eq!(Balances::free_balance(101), 10_000);
+        assert_eq!(KittiesModule::refunds(101), 0);
+
+        let _ = Balances::deposit_creating(&KittiesModule::account_id(), 1_000);
+        assert_ok!(KittiesModule::make_offer(Origin::signed(101), 0, 200, 10));
+        assert_ok!(KittiesModule::make_offer(Origin::signed(101), 0, 250, 10));
+        assert_eq!(KittiesModule::refunds(101), 200);
+        assert_ok!(KittiesModule::cancel_offer(Origin::signed(101), 0, 101));
+        assert_eq!(KittiesModule::refunds(101), 450);
+        assert_eq!(Balances::free_balance(101), 9_550);
+        assert_eq!(Balances::reserved_balance(101), 0);
+
+        assert_ok!(KittiesModule::claim_refund(Origin::signed(101)));
+        assert_eq!(Balances::free_balance(101), 10_000);
+        assert_noop!(KittiesModule::claim_refund(Origin::signed(101)), Error::<Test>::NoRefund);
+        assert_ok!(KittiesModule::do_try_state());
+    });
+}
+
+#[test]
+fn breed_with_gender_preference() {
+    new_test_ext().execute_with(|| {
+        // female, male
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        System::set_extrinsic_index(1);
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        let issuance = Balances::total_issuance();
+
+        // the kitten would be male, the roll succeeds and makes it female
+        System::set_extrinsic_index(3);
+        assert_ok!(KittiesModule::breed(Origin::signed(100), 0, 1, Some(KittyGender::Female)));
+        assert_eq!(KittiesModule::kitties(&100, 2).unwrap().gender(), KittyGender::Female);
+        assert!(System::events().iter().any(|r| r.event
+            == Event::kitties(RawEvent::GenderPreferenceApplied(100, 2, KittyGender::Female, true, 1))));
+        assert_eq!(Balances::free_balance(100), 9_990);
+
+        // the kitten would be male and the roll fails, the fee is still charged
+        System::set_extrinsic_index(2);
+        assert_ok!(KittiesModule::breed(Origin::signed(100), 0, 1, Some(KittyGender::Female)));
+        assert_eq!(KittiesModule::kitties(&100, 3).unwrap().gender(), KittyGender::Male);
+        assert!(System::events().iter().any(|r| r.event
+            == Event::kitties(RawEvent::GenderPreferenceApplied(100, 3, KittyGender::Female, false, 1))));
+        assert_eq!(Balances::free_balance(100), 9_980);
+        assert_eq!(Balances::total_issuance(), issuance - 20);
+
+        // no preference, no fee
+        assert_ok!(KittiesModule::breed(Origin::signed(100), 0, 1, None));
+        assert_eq!(Balances::free_balance(100), 9_980);
+    });
+}
+
+#[test]
+fn transfer_refuses_to_overwrite_ownership_entry() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::do_try_state());
+
+        // plant a stale entry, as a buggy migration could
+        orml_nft::TokensByOwner::<Test>::insert(101, (KittiesModule::class_id(), 0), ());
+        assert!(KittiesModule::do_try_state().is_err());
+
+        assert_noop!(
+            KittiesModule::transfer(Origin::signed(100), 0, 101),
+            Error::<Test>::DuplicateKittySlot
+        );
+        assert_eq!(Nft::tokens(KittiesModule::class_id(), 0).unwrap().owner, 100);
+
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 0, Some(10), None, None));
+        assert_noop!(
+            KittiesModule::buy(Origin::signed(101), 0, 10, None, None),
+            Error::<Test>::DuplicateKittySlot
+        );
+    });
+}
+
+#[test]
+fn try_state_catches_kitties_missing_from_the_owner_index() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::create(Origin::signed(101)));
+        assert_ok!(KittiesModule::do_try_state());
+        let class_id = KittiesModule::class_id();
+
+        orml_nft::TokensByOwner::<Test>::remove(101, (class_id, 1));
+        assert_eq!(KittiesModule::do_try_state(), Err("kitty without an owner entry"));
+        orml_nft::TokensByOwner::<Test>::insert(101, (class_id, 1), ());
+        assert_ok!(KittiesModule::do_try_state());
+
+        // planted past the id the next kitty gets
+        let token = Nft::tokens(class_id, 1).unwrap();
+        orml_nft::Tokens::<Test>::insert(class_id, 5, token);
+        orml_nft::TokensByOwner::<Test>::insert(101, (class_id, 5), ());
+        assert_eq!(KittiesModule::do_try_state(), Err("kitty id not below the next kitty id"));
+    });
+}
+
+#[test]
+fn hit_bid_sells_at_the_bid() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_noop!(
+            KittiesModule::post_quote(Origin::signed(100), 0, 100, 200, 10),
+            Error::<Test>::InvalidQuote
+        );
+        assert_ok!(KittiesModule::post_quote(Origin::signed(101), 0, 100, 200, 10));
+        assert_eq!(Balances::reserved_balance(101), 100);
+
+        assert_noop!(
+            KittiesModule::hit_bid(Origin::signed(102), 0, 101, 0),
+            Error::<Test>::KittenNotFound
+        );
+        assert_ok!(KittiesModule::hit_bid(Origin::signed(100), 0, 101, 0));
+
+        assert_eq!(KittiesModule::kitties(&101, 0).is_some(), true);
+        assert_eq!(Balances::free_balance(100), 10_100);
+        assert_eq!(Balances::free_balance(101), 9_900);
+        assert_eq!(Balances::reserved_balance(101), 0);
+        assert_eq!(KittiesModule::quotes(0, 101), None);
+        // the ask is now live
+        assert_eq!(KittiesModule::kitty_prices(0), Some(200));
+        assert_eq!(last_event(), Event::kitties(RawEvent::BidHit(100, 101, 0, 100, KittyFreshness { breed_count: 0, born_at: 1 }, 4)));
+    });
+}
+
+#[test]
+fn hit_bid_fails_when_expired_or_underfunded() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::post_quote(Origin::signed(101), 0, 100, 200, 5));
+        assert_ok!(KittiesModule::post_quote(Origin::signed(102), 0, 100, 200, 50));
+
+        System::set_block_number(6);
+        assert_noop!(
+            KittiesModule::hit_bid(Origin::signed(100), 0, 101, 0),
+            Error::<Test>::QuoteExpired
+        );
+
+        let _ = Balances::slash_reserved(&102, 50);
+        assert_noop!(
+            KittiesModule::hit_bid(Origin::signed(100), 0, 102, 1),
+            Error::<Test>::QuoteUnderfunded
+        );
+
+        // the quoter gets back whatever is left
+        assert_ok!(KittiesModule::cancel_quote(Origin::signed(102), 0));
+        assert_eq!(Balances::reserved_balance(102), 0);
+        assert_eq!(Balances::free_balance(102), 9_950);
+    });
+}
+
+#[test]
+fn quote_ask_is_listed_when_quoter_acquires_kitty() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 0, Some(150), None, None));
+        assert_ok!(KittiesModule::post_quote(Origin::signed(101), 0, 100, 300, 10));
+
+        assert_ok!(KittiesModule::buy(Origin::signed(101), 0, 150, None, None));
+
+        assert_eq!(Balances::reserved_balance(101), 0);
+        assert_eq!(Balances::free_balance(101), 9_850);
+        assert_eq!(KittiesModule::quotes(0, 101), None);
+        assert_eq!(KittiesModule::kitty_prices(0), Some(300));
+    });
+}
+
+#[test]
+fn price_display_serializes_raw_as_string() {
+    assert_eq!(
+        serde_json::to_string(&KittiesModule::price_display(1_500_000_000_000)).unwrap(),
+        r#"{"raw":"1500000000000","decimals":12,"symbol":"UNIT"}"#
+    );
+    assert_eq!(KittiesModule::price_display(u64::max_value()).raw, "18446744073709551615");
+}
+
+#[test]
+fn breeding_only_in_season() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        System::set_extrinsic_index(1);
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+
+        // an empty schedule means always open
+        assert!(KittiesModule::in_season(1));
+        assert_ok!(KittiesModule::breed(Origin::signed(100), 0, 1, None));
+
+        assert_noop!(
+            KittiesModule::set_breeding_seasons(Origin::signed(100), vec![(5, 10)]),
+            DispatchError::BadOrigin
+        );
+        assert_ok!(KittiesModule::set_breeding_seasons(Origin::root(), vec![(20, 30), (5, 10)]));
+        assert_eq!(KittiesModule::breeding_seasons(), vec![(5, 10), (20, 30)]);
+
+        System::set_block_number(4);
+        assert_noop!(KittiesModule::breed(Origin::signed(100), 0, 1, None), Error::<Test>::OutOfSeason);
+        System::set_block_number(5);
+        assert_ok!(KittiesModule::breed(Origin::signed(100), 0, 1, None));
+        System::set_block_number(10);
+        assert_ok!(KittiesModule::breed(Origin::signed(100), 0, 1, None));
+        System::set_block_number(11);
+        assert_noop!(KittiesModule::breed(Origin::signed(100), 0, 1, None), Error::<Test>::OutOfSeason);
+
+        assert_ok!(KittiesModule::set_breeding_seasons(Origin::root(), vec![]));
+        assert_ok!(KittiesModule::breed(Origin::signed(100), 0, 1, None));
+    });
+}
+
+#[test]
+fn breeding_seasons_are_validated() {
+    new_test_ext().execute_with(|| {
+        assert_noop!(
+            KittiesModule::set_breeding_seasons(Origin::root(), vec![(1, 5), (5, 8)]),
+            Error::<Test>::InvalidSeasons
+        );
+        assert_noop!(
+            KittiesModule::set_breeding_seasons(Origin::root(), vec![(5, 1)]),
+            Error::<Test>::InvalidSeasons
+        );
+        assert_noop!(
+            KittiesModule::set_breeding_seasons(Origin::root(), vec![(1, 1), (2, 2), (3, 3), (4, 4)]),
+            Error::<Test>::TooManySeasons
+        );
+    });
+}
+
+#[test]
+fn pending_redemption_locks_kitty() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 0, Some(10), None, None));
+
+        assert_noop!(
+            KittiesModule::redeem(Origin::signed(101), 0, H256::repeat_byte(1)),
+            Error::<Test>::KittenNotFound
+        );
+        assert_ok!(KittiesModule::redeem(Origin::signed(100), 0, H256::repeat_byte(1)));
+        assert_eq!(
+            last_event(),
+            Event::kitties(RawEvent::RedemptionRequested(100, 0, H256::repeat_byte(1), 11, 2))
+        );
+        assert_eq!(KittiesModule::kitty_prices(0), None);
+        assert_eq!(KittiesModule::open_redemptions(), vec![0]);
+
+        assert_noop!(KittiesModule::transfer(Origin::signed(100), 0, 101), Error::<Test>::KittyLocked);
+        assert_noop!(KittiesModule::set_price(Origin::signed(100), 0, Some(10), None, None), Error::<Test>::KittyLocked);
+        assert_noop!(KittiesModule::buy(Origin::signed(101), 0, 10, None, None), Error::<Test>::NotForSale.with_weight(DbWeight::get().reads(4)));
+
+        assert_ok!(KittiesModule::reject_redemption(Origin::root(), 0));
+        assert_eq!(KittiesModule::open_redemptions(), Vec::<u32>::new());
+        assert_ok!(KittiesModule::transfer(Origin::signed(100), 0, 101));
+    });
+}
+
+#[test]
+fn expired_redemption_unlocks_kitty() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::redeem(Origin::signed(100), 0, H256::repeat_byte(1)));
+
+        System::set_block_number(11);
+        assert_noop!(KittiesModule::transfer(Origin::signed(100), 0, 101), Error::<Test>::KittyLocked);
+
+        System::set_block_number(12);
+        assert_noop!(
+            KittiesModule::confirm_redemption(Origin::root(), 0),
+            Error::<Test>::RedemptionExpired
+        );
+        assert_ok!(KittiesModule::transfer(Origin::signed(100), 0, 101));
+        assert_eq!(KittiesModule::redemptions(0), None);
+        assert_eq!(KittiesModule::open_redemptions(), Vec::<u32>::new());
+        assert!(System::events().iter().any(|r| r.event
+            == Event::kitties(RawEvent::RedemptionExpired(100, 0, 2))));
+    });
+}
+
+#[test]
+fn confirmed_redemption_burns_kitty() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        System::set_extrinsic_index(1);
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::breed(Origin::signed(100), 0, 1, None));
+        assert_ok!(KittiesModule::post_quote(Origin::signed(101), 0, 100, 200, 50));
+
+        assert_ok!(KittiesModule::redeem(Origin::signed(100), 0, H256::repeat_byte(1)));
+        assert_noop!(
+            KittiesModule::confirm_redemption(Origin::signed(100), 0),
+            DispatchError::BadOrigin
+        );
+        assert_ok!(KittiesModule::confirm_redemption(Origin::root(), 0));
+
+        assert_eq!(KittiesModule::kitties(&100, 0), None);
+        assert_eq!(Nft::tokens(KittiesModule::class_id(), 0), None);
+        assert!(!orml_nft::TokensByOwner::<Test>::contains_key(100, (KittiesModule::class_id(), 0)));
+        assert_eq!(KittiesModule::sire_stats(0), BreedingStats::default());
+        assert_eq!(KittiesModule::recent_partners(0), Vec::<u32>::new());
+        assert_eq!(KittiesModule::quotes(0, 101), None);
+        assert_eq!(Balances::reserved_balance(101), 0);
+        assert_eq!(KittiesModule::redemptions(0), None);
+        assert_eq!(KittiesModule::open_redemptions(), Vec::<u32>::new());
+        assert_eq!(last_event(), Event::kitties(RawEvent::RedemptionConfirmed(100, 0, 4)));
+        assert_ok!(KittiesModule::do_try_state());
+    });
+}
+
+#[test]
+fn retried_hit_bid_does_not_match_replaced_quote() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::post_quote(Origin::signed(101), 0, 100, 200, 10));
+        assert_eq!(KittiesModule::quotes(0, 101).unwrap().nonce, 0);
+
+        // the quoter lowers the bid before the owner's retried call lands
+        assert_ok!(KittiesModule::post_quote(Origin::signed(101), 0, 50, 200, 10));
+        assert_eq!(
+            last_event(),
+            Event::kitties(RawEvent::QuotePosted(101, 0, 50, 200, 10, 1, 2))
+        );
+        assert_eq!(Balances::reserved_balance(101), 50);
+
+        assert_noop!(
+            KittiesModule::hit_bid(Origin::signed(100), 0, 101, 0),
+            Error::<Test>::OfferChanged
+        );
+        assert_ok!(KittiesModule::hit_bid(Origin::signed(100), 0, 101, 1));
+        assert_eq!(Balances::free_balance(100), 10_050);
+    });
+}
+
+#[test]
+fn recorded_flows_match_balance_changes() {
+    new_test_ext().execute_with(|| {
+        let pot = KittiesModule::account_id();
+        let _ = Balances::deposit_creating(&pot, 1_000);
+        let accounts = [100, 101, 102, pot];
+        let before: Vec<u64> = accounts.iter().map(|a| Balances::total_balance(a)).collect();
+
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        System::set_extrinsic_index(1);
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::breed(Origin::signed(100), 0, 1, Some(KittyGender::Male)));
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 0, Some(300), None, None));
+        assert_ok!(KittiesModule::buy(Origin::signed(101), 0, 300, None, None));
+        assert_ok!(KittiesModule::post_quote(Origin::signed(102), 1, 70, 90, 10));
+        assert_ok!(KittiesModule::hit_bid(Origin::signed(100), 1, 102, 0));
+        assert_ok!(KittiesModule::make_offer(Origin::signed(102), 0, 40, 10));
+        assert_ok!(KittiesModule::cancel_offer(Origin::signed(102), 0, 102));
+        assert_ok!(KittiesModule::claim_refund(Origin::signed(102)));
+
+        let mut deltas = vec![0i128; accounts.len()];
+        for record in System::events() {
+            if let Event::kitties(RawEvent::FundsFlow(from, to, amount, _, _)) = record.event {
+                let position = |who: u64| accounts.iter().position(|a| *a == who).unwrap();
+                deltas[position(from)] -= amount as i128;
+                if let Some(to) = to {
+                    deltas[position(to)] += amount as i128;
+                }
+            }
+        }
+        for (i, account) in accounts.iter().enumerate() {
+            let actual = Balances::total_balance(account) as i128 - before[i] as i128;
+            assert_eq!(actual, deltas[i], "account {}", account);
+        }
+
+        assert_eq!(KittiesModule::flow_total(FlowKind::Sale), 370);
+        assert_eq!(KittiesModule::flow_total(FlowKind::Fee), 10);
+        // into the pallet account on the cancel, out again on the claim
+        assert_eq!(KittiesModule::flow_total(FlowKind::Refund), 80);
+
+        System::set_block_number(2);
+        assert_eq!(KittiesModule::flow_total(FlowKind::Sale), 0);
+    });
+}
+
+#[test]
+fn events_are_numbered_within_extrinsic() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        System::set_extrinsic_index(1);
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 0, Some(300), None, None));
+        assert_ok!(KittiesModule::post_quote(Origin::signed(101), 0, 100, 200, 10));
+
+        // breeding with a fee
+        System::reset_events();
+        System::set_extrinsic_index(2);
+        assert_ok!(KittiesModule::breed(Origin::signed(100), 1, 0, Some(KittyGender::Male)));
+        let seqs: Vec<u32> = System::events().into_iter().filter_map(|r| match r.event {
+            Event::kitties(RawEvent::FundsFlow(.., seq))
+            | Event::kitties(RawEvent::GenderPreferenceApplied(.., seq))
+            | Event::kitties(RawEvent::KittyBreed(.., seq)) => Some(seq),
+            _ => None,
+        }).collect();
+        assert_eq!(seqs, vec![0, 1, 2]);
+
+        // sale paying the seller, moving the kitty and activating the buyer's ask
+        System::reset_events();
+        System::set_extrinsic_index(3);
+        assert_ok!(KittiesModule::buy(Origin::signed(101), 0, 300, None, None));
+
+        let events: Vec<Event> = System::events().into_iter().map(|r| r.event).collect();
+        assert_eq!(events, vec![
+            Event::kitties(RawEvent::FundsFlow(101, Some(100), 300, FlowKind::Sale, 0)),
+            Event::kitties(RawEvent::KittyPriceUpdated(101, 0, Some(200), None, 1)),
+            Event::kitties(RawEvent::KittySold(100, 101, 0, 300, None, 0, 0, KittyFreshness { breed_count: 1, born_at: 1 }, 2)),
+        ]);
+    });
+}
+
+#[test]
+fn low_novelty_kitten_grants_rebreed_voucher() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        System::set_extrinsic_index(1);
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+
+        // differs from the second parent by 23 bits only
+        System::set_extrinsic_index(9);
+        assert_ok!(KittiesModule::breed(Origin::signed(100), 0, 1, None));
+        assert_eq!(last_event(), Event::kitties(RawEvent::RebreedVoucherIssued(100, 0, 1, 6, 1)));
+        assert_eq!(KittiesModule::rebreed_vouchers(100, (0, 1)), Some(6));
+
+        // 32 bits apart, no new voucher; the pair matches in either order
+        System::set_extrinsic_index(7);
+        assert_ok!(KittiesModule::breed(Origin::signed(100), 1, 0, None));
+        assert!(System::events().iter().any(|r| r.event
+            == Event::kitties(RawEvent::RebreedVoucherUsed(100, 0, 1, 0))));
+        assert_eq!(KittiesModule::rebreed_vouchers(100, (0, 1)), None);
+        assert_eq!(last_event(), Event::kitties(RawEvent::KittyBreed(
+            100,
+            KittiesModule::kitties(&100, 1).unwrap(),
+            KittiesModule::kitties(&100, 0).unwrap(),
+            3,
+            1,
+            1,
+        )));
+    });
+}
+
+#[test]
+fn rebreed_voucher_expires() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        System::set_extrinsic_index(1);
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        System::set_extrinsic_index(9);
+        assert_ok!(KittiesModule::breed(Origin::signed(100), 0, 1, None));
+
+        System::set_block_number(7);
+        System::set_extrinsic_index(7);
+        assert_ok!(KittiesModule::breed(Origin::signed(100), 0, 1, None));
+        assert!(!System::events().iter().any(|r| matches!(r.event,
+            Event::kitties(RawEvent::RebreedVoucherUsed(..)))));
+        assert_eq!(KittiesModule::rebreed_vouchers(100, (0, 1)), None);
+    });
+}
+
+#[test]
+fn market_fee_follows_previous_era_volume() {
+    new_test_ext().execute_with(|| {
+        assert_noop!(
+            KittiesModule::set_fee_schedule(
+                Origin::root(),
+                vec![(1000, Permill::from_percent(5)), (0, Permill::from_percent(10))]
+            ),
+            Error::<Test>::InvalidFeeSchedule
+        );
+        assert_ok!(KittiesModule::set_fee_schedule(
+            Origin::root(),
+            vec![(0, Permill::from_percent(10)), (1000, Permill::from_percent(5))]
+        ));
+        assert_eq!(KittiesModule::current_fee_rate(), Permill::from_percent(10));
+
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+
+        // era 0, nothing sold before
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 0, Some(1000), None, None));
+        assert_ok!(KittiesModule::buy(Origin::signed(101), 0, 1000, None, None));
+        assert_eq!(Balances::free_balance(100), 10_900);
+        assert_eq!(KittiesModule::era_volume(), 1000);
+
+        // era 1, previous volume exactly at the second step; 100 gets a royalty of 100
+        System::set_block_number(10);
+        assert_ok!(KittiesModule::set_price(Origin::signed(101), 0, Some(1000), None, None));
+        assert_ok!(KittiesModule::buy(Origin::signed(102), 0, 1000, None, None));
+        assert_eq!(Balances::free_balance(101), 9_850);
+        assert_eq!(KittiesModule::current_fee_rate(), Permill::from_percent(5));
+        assert!(System::events().iter().any(|record| matches!(record.event,
+            Event::kitties(RawEvent::FeeRateChanged(1, rate, _)) if rate == Permill::from_percent(5))));
+
+        // era 4, nothing sold in era 3
+        System::set_block_number(40);
+        assert_ok!(KittiesModule::set_price(Origin::signed(102), 0, Some(1000), None, None));
+        assert_ok!(KittiesModule::buy(Origin::signed(100), 0, 1000, None, None));
+        assert_eq!(Balances::free_balance(102), 9_800);
+        assert_eq!(KittiesModule::current_fee_rate(), Permill::from_percent(10));
+    });
+}
+
+#[test]
+fn buyer_can_refuse_kitty_bred_before_sale() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        System::set_extrinsic_index(1);
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        System::set_block_number(3);
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 0, Some(300), None, None));
+
+        // the seller breeds the listed kitty in the block the buyer is about to buy it
+        assert_ok!(KittiesModule::breed(Origin::signed(100), 0, 1, None));
+        assert_noop!(
+            KittiesModule::buy(Origin::signed(101), 0, 300, Some(0), None),
+            Error::<Test>::KittyStateChanged.with_weight(DbWeight::get().reads(8))
+        );
+
+        assert_ok!(KittiesModule::buy(Origin::signed(101), 0, 300, Some(1), None));
+        assert_eq!(
+            last_event(),
+            Event::kitties(RawEvent::KittySold(100, 101, 0, 300, None, 0, 0, KittyFreshness { breed_count: 1, born_at: 1 }, 4))
+        );
+        assert_eq!(KittiesModule::freshness(2), KittyFreshness { breed_count: 0, born_at: 3 });
+    });
+}
+
+#[test]
+fn polls_count_each_kitty_once() {
+    new_test_ext().execute_with(|| {
+        for _ in 0..3 {
+            assert_ok!(KittiesModule::create(Origin::signed(100)));
+        }
+        for _ in 0..2 {
+            assert_ok!(KittiesModule::create(Origin::signed(102)));
+        }
+
+        assert_noop!(
+            KittiesModule::create_poll(Origin::signed(100), H256::zero(), 10, 2),
+            DispatchError::BadOrigin
+        );
+        assert_noop!(KittiesModule::create_poll(Origin::root(), H256::zero(), 10, 1), Error::<Test>::InvalidPoll);
+        assert_ok!(KittiesModule::create_poll(Origin::root(), H256::repeat_byte(1), 10, 2));
+        assert_ok!(KittiesModule::create_poll(Origin::root(), H256::repeat_byte(2), 10, 2));
+        assert_noop!(KittiesModule::create_poll(Origin::root(), H256::zero(), 10, 2), Error::<Test>::TooManyPolls);
+
+        assert_ok!(KittiesModule::vote(Origin::signed(100), 0, 0));
+        assert_noop!(KittiesModule::vote(Origin::signed(100), 0, 1), Error::<Test>::AlreadyVoted);
+        assert_noop!(KittiesModule::vote(Origin::signed(102), 0, 2), Error::<Test>::InvalidPollOption);
+
+        // kitties that voted can't vote again from another account
+        for kitty_id in 0..3 {
+            assert_ok!(KittiesModule::transfer(Origin::signed(100), kitty_id, 101));
+        }
+        assert_noop!(KittiesModule::vote(Origin::signed(101), 0, 1), Error::<Test>::NoVotingPower);
+        assert_ok!(KittiesModule::create(Origin::signed(101)));
+        assert_ok!(KittiesModule::vote(Origin::signed(101), 0, 1));
+        assert_eq!(KittiesModule::poll_voters(0, 101), Some((1, 1)));
+
+        assert_ok!(KittiesModule::vote(Origin::signed(102), 0, 1));
+        // other polls are independent
+        assert_ok!(KittiesModule::vote(Origin::signed(101), 1, 0));
+
+        assert_noop!(KittiesModule::close_poll(Origin::signed(100), 0), Error::<Test>::PollNotEnded);
+        System::set_block_number(11);
+        assert_noop!(KittiesModule::vote(Origin::signed(100), 1, 0), Error::<Test>::PollEnded);
+
+        assert_ok!(KittiesModule::close_poll(Origin::signed(100), 0));
+        assert_eq!(last_event(), Event::kitties(RawEvent::PollClosed(0, vec![3, 3], 15)));
+        assert_eq!(KittiesModule::polls(0).unwrap().closed, true);
+        assert_eq!(KittiesModule::open_polls(), vec![1]);
+        assert_eq!(KittiesModule::poll_kitties(0, 0), None);
+        assert_noop!(KittiesModule::close_poll(Origin::signed(100), 0), Error::<Test>::PollEnded);
+    });
+}
+
+#[test]
+fn polls_count_a_bounded_number_of_votes() {
+    new_test_ext().execute_with(|| {
+        for _ in 0..4 {
+            assert_ok!(KittiesModule::create(Origin::signed(100)));
+        }
+        for _ in 0..3 {
+            assert_ok!(KittiesModule::create(Origin::signed(101)));
+        }
+        assert_ok!(KittiesModule::create_poll(Origin::root(), H256::repeat_byte(1), 10, 2));
+
+        assert_ok!(KittiesModule::vote(Origin::signed(100), 0, 0));
+        assert_noop!(KittiesModule::vote(Origin::signed(101), 0, 1), Error::<Test>::PollFull);
+        assert_ok!(KittiesModule::transfer(Origin::signed(101), 4, 102));
+        assert_ok!(KittiesModule::vote(Origin::signed(101), 0, 1));
+        assert_eq!(KittiesModule::polls(0).unwrap().tally, vec![4, 2]);
+    });
+}
+
+#[test]
+fn gene_pool_follows_mint_and_burn() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        let dna = KittiesModule::kitties(&100, 0).unwrap().dna();
+
+        let scarcity = KittiesModule::gene_scarcity();
+        assert_eq!(scarcity.len(), 16);
+        for (position, buckets) in scarcity.iter().enumerate() {
+            assert_eq!(buckets.iter().sum::<u32>(), 1);
+            assert_eq!(buckets[(dna[position] >> 4) as usize], 1);
+        }
+
+        assert_ok!(KittiesModule::redeem(Origin::signed(100), 0, H256::zero()));
+        assert_ok!(KittiesModule::confirm_redemption(Origin::root(), 0));
+        assert!(KittiesModule::gene_scarcity().iter().all(|buckets| *buckets == [0; GENE_BUCKETS]));
+    });
+}
+
+#[test]
+fn rare_gene_bonus_only_for_bottom_bucket() {
+    // kitten bred below, see `can_breed`
+    let kitten = [187, 254, 239, 222, 215, 167, 173, 111, 247, 254, 255, 189, 239, 186, 115, 123u8];
+    let breed = |pool: &dyn Fn(usize) -> GeneBuckets| {
+        set_random(H256::zero());
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        set_random(H256::from([2; 32]));
+        System::set_extrinsic_index(1);
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        for position in 0..16 {
+            GenePool::<DefaultInstance>::insert(position as u8, pool(position));
+        }
+        Balances::make_free_balance_be(&KittiesModule::account_id(), 1_000);
+
+        assert_ok!(KittiesModule::breed(Origin::signed(100), 0, 1, None));
+        assert_eq!(KittiesModule::kitties(&100, 2).unwrap().dna(), kitten);
+    };
+
+    new_test_ext().execute_with(|| {
+        // every gene of the kitten sits in a more populated bucket
+        breed(&|position| {
+            let mut buckets = [1; GENE_BUCKETS];
+            buckets[(kitten[position] >> 4) as usize] = 2;
+            buckets
+        });
+        assert_eq!(Balances::free_balance(KittiesModule::account_id()), 1_000);
+        assert_eq!(KittiesModule::gene_pool(0)[(kitten[0] >> 4) as usize], 3);
+    });
+
+    new_test_ext().execute_with(|| {
+        // a single gene in the least populated bucket of its position
+        breed(&|position| {
+            let mut buckets = [1; GENE_BUCKETS];
+            if position == 3 {
+                buckets[(kitten[3] >> 4) as usize] = 0;
+            }
+            buckets
+        });
+        assert_eq!(Balances::free_balance(KittiesModule::account_id()), 950);
+        assert_eq!(Balances::free_balance(100), 10_050);
+        assert!(matches!(last_event(), Event::kitties(RawEvent::RareGeneBonusPaid(100, 2, 50, _))));
+    });
+}
+
+#[test]
+fn dead_mans_switch_lists_kitties_of_dormant_owner() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_noop!(KittiesModule::trigger_switch(Origin::signed(102), 100), Error::<Test>::NoDeadMansSwitch);
+
+        assert_ok!(KittiesModule::configure_dead_mans_switch(
+            Origin::signed(100),
+            10,
+            101,
+            PriceStrategy::Fixed(500)
+        ));
+
+        // activity resets the timer
+        System::set_block_number(5);
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 0, None, None, None));
+        System::set_block_number(14);
+        assert_noop!(KittiesModule::trigger_switch(Origin::signed(102), 100), Error::<Test>::OwnerNotDormant);
+
+        System::set_block_number(15);
+        assert_ok!(KittiesModule::trigger_switch(Origin::signed(102), 100));
+        assert!(matches!(last_event(), Event::kitties(RawEvent::DeadMansSwitchTriggered(100, 101, 2, _))));
+        assert_eq!(KittiesModule::kitty_prices(0), Some(500));
+        assert_eq!(KittiesModule::kitty_prices(1), Some(500));
+        assert_eq!(KittiesModule::dead_mans_switch(100), None);
+
+        // the beneficiary is paid
+        assert_ok!(KittiesModule::buy(Origin::signed(102), 0, 500, None, None));
+        assert_eq!(Balances::free_balance(101), 10_500);
+        assert_eq!(Balances::free_balance(100), 10_000);
+
+        // until the owner is back
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 1, Some(400), None, None));
+        assert_ok!(KittiesModule::buy(Origin::signed(102), 1, 400, None, None));
+        assert_eq!(Balances::free_balance(100), 10_400);
+    });
+}
+
+#[test]
+fn dead_mans_switch_relists_at_last_sale_price() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::create(Origin::signed(101)));
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 0, Some(300), None, None));
+        assert_ok!(KittiesModule::buy(Origin::signed(101), 0, 300, None, None));
+
+        assert_ok!(KittiesModule::configure_dead_mans_switch(Origin::signed(101), 10, 102, PriceStrategy::LastSale));
+        assert_ok!(KittiesModule::disable_dead_mans_switch(Origin::signed(101)));
+        assert_noop!(KittiesModule::disable_dead_mans_switch(Origin::signed(101)), Error::<Test>::NoDeadMansSwitch);
+        assert_ok!(KittiesModule::configure_dead_mans_switch(Origin::signed(101), 10, 102, PriceStrategy::LastSale));
+
+        System::set_block_number(11);
+        assert_ok!(KittiesModule::trigger_switch(Origin::signed(100), 101));
+        assert_eq!(KittiesModule::kitty_prices(0), Some(300));
+        // never sold
+        assert_eq!(KittiesModule::kitty_prices(1), None);
+    });
+}
+
+#[test]
+fn commit_reveal_create() {
+    new_test_ext().execute_with(|| {
+        let salt = [1; 32];
+        let commitment = BlakeTwo256::hash_of(&(100u64, salt));
+        assert_ok!(KittiesModule::commit_create(Origin::signed(100), commitment));
+        assert_noop!(KittiesModule::commit_create(Origin::signed(100), commitment), Error::<Test>::CommitmentExists);
+        assert_eq!(Balances::reserved_balance(100), 20);
+
+        System::set_block_number(2);
+        assert_noop!(KittiesModule::reveal_create(Origin::signed(100), salt), Error::<Test>::RevealTooEarly);
+
+        // known when the commitment was made
+        set_secure_random(H256::repeat_byte(7), 1);
+        System::set_block_number(3);
+        assert_noop!(KittiesModule::reveal_create(Origin::signed(100), [2; 32]), Error::<Test>::CommitmentMismatch);
+        assert_noop!(KittiesModule::reveal_create(Origin::signed(101), salt), Error::<Test>::NoCommitment);
+        assert_noop!(KittiesModule::reveal_create(Origin::signed(100), salt), Error::<Test>::RandomnessNotFresh);
+
+        set_secure_random(H256::repeat_byte(7), 2);
+        assert_ok!(KittiesModule::reveal_create(Origin::signed(100), salt));
+
+        assert_eq!(
+            KittiesModule::kitties(&100, 0),
+            Some(Kitty([119, 135, 175, 117, 152, 61, 174, 206, 126, 214, 16, 219, 136, 43, 246, 142]))
+        );
+        assert_eq!(Balances::reserved_balance(100), 0);
+        assert_eq!(Balances::free_balance(100), 9_980);
+        assert_eq!(KittiesModule::create_commitment(100), None);
+    });
+}
+
+#[test]
+fn unrevealed_commitment_expires() {
+    new_test_ext().execute_with(|| {
+        let salt = [1; 32];
+        assert_ok!(KittiesModule::commit_create(Origin::signed(100), BlakeTwo256::hash_of(&(100u64, salt))));
+
+        System::set_block_number(8);
+        assert_noop!(KittiesModule::expire_commitment(Origin::signed(102), 100), Error::<Test>::CommitmentNotExpired);
+
+        System::set_block_number(9);
+        assert_noop!(KittiesModule::reveal_create(Origin::signed(100), salt), Error::<Test>::CommitmentExpired);
+        assert_ok!(KittiesModule::expire_commitment(Origin::signed(102), 100));
+        assert!(matches!(last_event(), Event::kitties(RawEvent::CreateCommitmentExpired(100, 10, _))));
+        assert_eq!(Balances::reserved_balance(100), 0);
+        assert_eq!(Balances::free_balance(100), 9_990);
+        assert_eq!(KittiesModule::create_commitment(100), None);
+    });
+}
+
+#[test]
+fn royalties_follow_creator_role() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_eq!(KittiesModule::kitty_creator(0), Some(100));
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 0, Some(1000), None, None));
+        assert_ok!(KittiesModule::buy(Origin::signed(101), 0, 1000, None, None));
+
+        assert_noop!(KittiesModule::transfer_creator_role(Origin::signed(101), 0, 102), Error::<Test>::NotCreator);
+        assert_ok!(KittiesModule::transfer_creator_role(Origin::signed(100), 0, 102));
+        assert_noop!(KittiesModule::accept_creator_role(Origin::signed(101), 0), Error::<Test>::NoPendingCreator);
+        // the offer alone changes nothing
+        assert_eq!(KittiesModule::kitty_creator(0), Some(100));
+        assert_ok!(KittiesModule::accept_creator_role(Origin::signed(102), 0));
+        assert_eq!(KittiesModule::kitty_creator(0), Some(102));
+        assert_eq!(KittiesModule::pending_creator(0), None);
+
+        assert_ok!(KittiesModule::set_price(Origin::signed(101), 0, Some(1000), None, None));
+        assert_ok!(KittiesModule::buy(Origin::signed(100), 0, 1000, None, None));
+        assert_eq!(Balances::free_balance(102), 10_100);
+        assert_eq!(Balances::free_balance(101), 9_900);
+
+        assert_noop!(KittiesModule::renounce_creator_role(Origin::signed(100), 0), Error::<Test>::NotCreator);
+        assert_ok!(KittiesModule::renounce_creator_role(Origin::signed(102), 0));
+        assert_eq!(KittiesModule::kitty_creator(0), None);
+
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 0, Some(1000), None, None));
+        assert_ok!(KittiesModule::buy(Origin::signed(101), 0, 1000, None, None));
+        assert_eq!(Balances::free_balance(102), 10_100);
+        assert_eq!(Balances::free_balance(100), 11_000);
+    });
+}
+
+#[cfg(feature = "deterministic")]
+#[test]
+fn deterministic_mode_replays_identically() {
+    let run = |random: u8| {
+        let mut t = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+        pallet_balances::GenesisConfig::<Test> {
+            balances: vec![(100, 10_000), (101, 10_000)],
+        }
+        .assimilate_storage(&mut t)
+        .unwrap();
+        GenesisConfig::<Test> { deterministic_seed: 42, ..Default::default() }.assimilate_storage(&mut t).unwrap();
+        let mut ext = sp_io::TestExternalities::new(t);
+        ext.execute_with(|| {
+            System::set_block_number(1);
+            // ignored in deterministic mode
+            set_random(H256::repeat_byte(random));
+
+            assert_ok!(KittiesModule::create(Origin::signed(100)));
+            System::set_extrinsic_index(1);
+            assert_ok!(KittiesModule::create(Origin::signed(100)));
+            // whether the genders allow breeding depends on the seed only
+            let _ = KittiesModule::breed(Origin::signed(100), 0, 1, None);
+            assert_ok!(KittiesModule::set_price(Origin::signed(100), 0, Some(100), None, None));
+            assert_ok!(KittiesModule::buy(Origin::signed(101), 0, 100, None, None));
+            assert!(KittiesModule::entropy_counter() > 42);
+
+            sp_io::storage::root()
+        })
+    };
+
+    assert_eq!(run(1), run(2));
+}
+
+#[test]
+fn activity_is_counted() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        System::set_extrinsic_index(1);
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::breed(Origin::signed(100), 0, 1, None));
+        assert_ok!(KittiesModule::transfer(Origin::signed(100), 2, 102));
+        // not a transfer
+        assert_ok!(KittiesModule::transfer(Origin::signed(102), 2, 102));
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 0, Some(100), None, None));
+        assert_ok!(KittiesModule::buy(Origin::signed(101), 0, 100, None, None));
+        assert_noop!(KittiesModule::buy(Origin::signed(101), 0, 100, None, None), Error::<Test>::NotForSale.with_weight(DbWeight::get().reads(4)));
+
+        assert_eq!(KittiesModule::activity_counters(), vec![
+            (ActivityKind::Creates, 2),
+            (ActivityKind::Breeds, 1),
+            (ActivityKind::Transfers, 1),
+            (ActivityKind::Sales, 1),
+            (ActivityKind::FailedBuys, 0),
+        ]);
+
+        VERBOSE_METRICS.with(|v| *v.borrow_mut() = true);
+        assert!(KittiesModule::buy(Origin::signed(102), 0, 100, None, None).is_err());
+        assert_eq!(KittiesModule::activity_counter(ActivityKind::FailedBuys), 1);
+
+        assert_noop!(KittiesModule::reset_activity_counters(Origin::signed(100)), DispatchError::BadOrigin);
+        assert_ok!(KittiesModule::reset_activity_counters(Origin::root()));
+        assert!(KittiesModule::activity_counters().iter().all(|(_, count)| *count == 0));
+    });
+}
+
+#[test]
+fn quotes_draw_from_bidding_balance() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+
+        assert_ok!(KittiesModule::deposit_bidding_funds(Origin::signed(101), 1000));
+        assert_eq!(Balances::free_balance(101), 9_000);
+
+        assert_ok!(KittiesModule::post_quote(Origin::signed(101), 0, 300, 500, 10));
+        assert_ok!(KittiesModule::post_quote(Origin::signed(101), 1, 400, 500, 10));
+        assert_eq!(KittiesModule::bidding_balance(101), BiddingBalance { free: 300, encumbered: 700 });
+        assert_eq!(Balances::reserved_balance(101), 0);
+        // not covered by the bidding balance, reserved instead
+        assert_ok!(KittiesModule::post_quote(Origin::signed(101), 2, 500, 500, 10));
+        assert_eq!(Balances::reserved_balance(101), 500);
+        assert_eq!(KittiesModule::bidding_balance(101).free, 300);
+
+        assert_noop!(
+            KittiesModule::withdraw_bidding_funds(Origin::signed(101), 400),
+            Error::<Test>::InsufficientBiddingFunds
+        );
+
+        // a dropped bid returns to the bidding balance
+        assert_ok!(KittiesModule::cancel_quote(Origin::signed(101), 1));
+        assert_eq!(KittiesModule::bidding_balance(101), BiddingBalance { free: 700, encumbered: 300 });
+
+        assert_ok!(KittiesModule::hit_bid(Origin::signed(100), 0, 101, 0));
+        assert_eq!(Balances::free_balance(100), 10_300);
+        assert_eq!(KittiesModule::bidding_balance(101), BiddingBalance { free: 700, encumbered: 0 });
+
+        assert_ok!(KittiesModule::withdraw_bidding_funds(Origin::signed(101), 700));
+        assert_eq!(Balances::free_balance(101), 9_200);
+        assert_eq!(KittiesModule::bidding_balance(101), BiddingBalance::default());
+    });
+}
+
+#[test]
+fn milestone_generation_mints_soulbound_badge() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        System::set_extrinsic_index(1);
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+
+        assert_ok!(KittiesModule::breed(Origin::signed(100), 0, 1, None));
+        assert_eq!(KittiesModule::generation(2), 1);
+        assert!(!System::events().iter().any(|r| matches!(r.event, Event::kitties(RawEvent::MilestoneReached(..)))));
+
+        // an unrelated partner of the other gender: extrinsic 2 creates a female, 8 a male
+        let kitten_is_male = KittiesModule::kitties(&100, 2).unwrap().gender() == KittyGender::Male;
+        System::set_extrinsic_index(if kitten_is_male { 2 } else { 8 });
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::breed(Origin::signed(100), 2, 3, None));
+        assert_eq!(KittiesModule::generation(4), 2);
+        assert!(matches!(last_event(), Event::kitties(RawEvent::MilestoneReached(100, 4, 2, 5, _))));
+
+        let badge = KittiesModule::kitties(&100, 5).unwrap();
+        let expected = (KittiesModule::ancestry(4), b"kitties/badge").using_encoded(blake2_128);
+        assert_eq!(badge.dna(), expected);
+        assert_eq!(KittiesModule::soulbound(5), Some(()));
+        assert_eq!(KittiesModule::generation(5), 0);
+
+        assert_noop!(KittiesModule::transfer(Origin::signed(100), 5, 101), Error::<Test>::KittySoulbound);
+        assert_noop!(KittiesModule::set_price(Origin::signed(100), 5, Some(10), None, None), Error::<Test>::KittySoulbound);
+        assert_noop!(KittiesModule::breed(Origin::signed(100), 5, 0, None), Error::<Test>::KittySoulbound);
+    });
+}
+
+#[test]
+fn vetoed_badge_is_skipped() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        System::set_extrinsic_index(1);
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::breed(Origin::signed(100), 0, 1, None));
+        let kitten_is_male = KittiesModule::kitties(&100, 2).unwrap().gender() == KittyGender::Male;
+        System::set_extrinsic_index(if kitten_is_male { 2 } else { 8 });
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+
+        // the hook refuses the badge, the kitten is bred all the same
+        REJECT_MINT_OF.with(|v| *v.borrow_mut() = Some(5));
+        assert_ok!(KittiesModule::breed(Origin::signed(100), 2, 3, None));
+        assert_eq!(KittiesModule::generation(4), 2);
+        assert!(matches!(last_event(), Event::kitties(RawEvent::AutoMintSkipped(100, _))));
+        assert!(System::events().iter().any(|r| matches!(r.event, Event::kitties(RawEvent::KittyBreed(100, _, _, 4, 2, _)))));
+        assert!(!System::events().iter().any(|r| matches!(r.event, Event::kitties(RawEvent::MilestoneReached(..)))));
+        assert_eq!(KittiesModule::kitties(&100, 5), None);
+        assert_eq!(KittiesModule::soulbound(5), None);
+        assert_eq!(KittiesModule::owned_kitties_count(100), 5);
+    });
+}
+
+#[test]
+fn operation_cost_matches_balance_changes() {
+    new_test_ext().execute_with(|| {
+        let cost = KittiesModule::operation_cost(KittyCallKind::Create).unwrap();
+        assert_eq!(cost, CostBreakdown::default());
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_eq!(Balances::free_balance(100), 10_000);
+
+        let cost = KittiesModule::operation_cost(KittyCallKind::CommitCreate).unwrap();
+        assert_ok!(KittiesModule::commit_create(Origin::signed(101), H256::repeat_byte(1)));
+        assert_eq!(Balances::reserved_balance(101), cost.reserved);
+        assert_eq!(Balances::free_balance(101), 10_000 - cost.reserved);
+
+        // female, male
+        System::set_extrinsic_index(1);
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        let cost = KittiesModule::operation_cost(KittyCallKind::Breed { gender_preference: true }).unwrap();
+        assert_eq!(cost.fee, 10);
+        assert_ok!(KittiesModule::breed(Origin::signed(100), 0, 1, Some(KittyGender::Female)));
+        assert_eq!(Balances::free_balance(100), 10_000 - cost.fee);
+        let cost = KittiesModule::operation_cost(KittyCallKind::Breed { gender_preference: false }).unwrap();
+        assert_eq!(cost.fee, 0);
+
+        // 100 created kitty 0 and is owed a royalty when 102 sells it
+        assert_ok!(KittiesModule::transfer(Origin::signed(100), 0, 102));
+        assert_ok!(KittiesModule::set_fee_schedule(Origin::root(), vec![(0, Permill::from_percent(10))]));
+        assert_eq!(KittiesModule::operation_cost(KittyCallKind::Buy { kitty_id: 0 }), None);
+        assert_ok!(KittiesModule::set_price(Origin::signed(102), 0, Some(1000), None, None));
+        let cost = KittiesModule::operation_cost(KittyCallKind::Buy { kitty_id: 0 }).unwrap();
+        assert_eq!(cost, CostBreakdown { price: 1000, market_fee: 100, royalty: 100, ..Default::default() });
+
+        let (creator, seller) = (Balances::free_balance(100), Balances::free_balance(102));
+        assert_ok!(KittiesModule::buy(Origin::signed(101), 0, 1000, None, None));
+        assert_eq!(Balances::free_balance(101), 10_000 - 20 - cost.price);
+        assert_eq!(Balances::free_balance(102), seller + cost.price - cost.market_fee - cost.royalty);
+        assert_eq!(Balances::free_balance(100), creator + cost.royalty);
+    });
+}
+
+#[test]
+fn promo_window_waives_creation_fee_within_quotas() {
+    new_test_ext().execute_with(|| {
+        CREATION_FEE.with(|v| *v.borrow_mut() = 10);
+
+        assert_noop!(KittiesModule::set_promo_window(Origin::signed(100), 2, 5, 1, 2), DispatchError::BadOrigin);
+        assert_noop!(KittiesModule::set_promo_window(Origin::root(), 5, 5, 1, 2), Error::<Test>::InvalidPromoWindow);
+        assert_ok!(KittiesModule::set_promo_window(Origin::root(), 2, 5, 1, 2));
+        assert_noop!(KittiesModule::set_promo_window(Origin::root(), 4, 8, 1, 2), Error::<Test>::PromoWindowOverlaps);
+        assert_noop!(KittiesModule::set_promo_window(Origin::root(), 1, 3, 1, 2), Error::<Test>::PromoWindowOverlaps);
+        assert_ok!(KittiesModule::set_promo_window(Origin::root(), 5, 8, 1, 1));
+
+        // before the window
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_eq!(Balances::free_balance(100), 9_990);
+
+        System::set_block_number(2);
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_eq!(Balances::free_balance(100), 9_990);
+        assert!(matches!(last_event(), Event::kitties(RawEvent::PromoMint(100, 1, _))));
+        // second mint of the same account
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_eq!(Balances::free_balance(100), 9_980);
+        assert_ok!(KittiesModule::create(Origin::signed(101)));
+        assert_eq!(Balances::free_balance(101), 10_000);
+        // total quota used up
+        assert_ok!(KittiesModule::create(Origin::signed(102)));
+        assert_eq!(Balances::free_balance(102), 9_990);
+        assert_eq!(KittiesModule::promo_windows()[0].minted, 2);
+
+        // the next window has its own quotas
+        System::set_block_number(5);
+        assert_ok!(KittiesModule::create(Origin::signed(102)));
+        assert_eq!(Balances::free_balance(102), 9_990);
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_eq!(Balances::free_balance(100), 9_970);
+
+        // scheduling after the first window ended forgets it
+        assert_ok!(KittiesModule::set_promo_window(Origin::root(), 8, 9, 1, 1));
+        assert_eq!(KittiesModule::promo_windows().len(), 2);
+        assert_eq!(KittiesModule::promo_mints(2, 100), 0);
+        assert_eq!(KittiesModule::promo_mints(5, 102), 1);
+
+        System::set_block_number(9);
+        assert_ok!(KittiesModule::create(Origin::signed(101)));
+        assert_eq!(Balances::free_balance(101), 9_990);
+    });
+}
+
+#[test]
+fn breed_randomness_source_is_configurable() {
+    let breed = |immediate: bool| {
+        new_test_ext().execute_with(|| {
+            IMMEDIATE_BREED_RANDOMNESS.with(|v| *v.borrow_mut() = immediate);
+            assert_ok!(KittiesModule::create(Origin::signed(100)));
+            System::set_extrinsic_index(1);
+            assert_ok!(KittiesModule::create(Origin::signed(100)));
+
+            set_random(H256::repeat_byte(1));
+            set_secure_random(H256::repeat_byte(2), 0);
+            assert_ok!(KittiesModule::breed(Origin::signed(100), 0, 1, None));
+            set_random(H256::repeat_byte(3));
+            assert_ok!(KittiesModule::breed(Origin::signed(100), 0, 1, None));
+            // the secure source didn't change, the second kitten only differs by the extrinsic
+            System::set_extrinsic_index(2);
+            assert_ok!(KittiesModule::breed(Origin::signed(100), 0, 1, None));
+            (2..5).map(|id| KittiesModule::kitties(&100, id).unwrap()).collect::<Vec<_>>()
+        })
+    };
+
+    let immediate = breed(true);
+    assert_ne!(immediate[0], immediate[1]);
+    let secure = breed(false);
+    assert_eq!(secure[0], secure[1]);
+    assert_ne!(secure[1], secure[2]);
+}
+
+#[test]
+fn tax_report_lists_acquisitions_and_disposals() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        System::set_extrinsic_index(1);
+        assert_ok!(KittiesModule::create(Origin::signed(101)));
+
+        System::set_block_number(2);
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 0, Some(500), None, None));
+        assert_ok!(KittiesModule::buy(Origin::signed(101), 0, 500, None, None));
+        System::set_block_number(3);
+        assert_ok!(KittiesModule::transfer(Origin::signed(101), 0, 102));
+        System::set_block_number(4);
+        assert_ok!(KittiesModule::set_price(Origin::signed(102), 0, Some(300), None, None));
+        assert_ok!(KittiesModule::buy(Origin::signed(100), 0, 300, None, None));
+        System::set_block_number(5);
+        assert_ok!(KittiesModule::do_burn(&100, 0));
+
+        let event = |block, kitty_id, kind| TaxEvent { block, kitty_id, kind };
+        assert_eq!(KittiesModule::account_tax_report(&100, 1, 5), TaxReport {
+            events: vec![
+                event(1, 0, TaxEventKind::Minted),
+                event(2, 0, TaxEventKind::Sold { buyer: 101, price: 500 }),
+                event(4, 0, TaxEventKind::Bought { seller: 102, price: 300 }),
+                event(5, 0, TaxEventKind::Burned),
+            ],
+            acquisitions: 2,
+            disposals: 2,
+            acquisition_cost: 300,
+            disposal_proceeds: 500,
+            truncated_until: None,
+        });
+        assert_eq!(KittiesModule::account_tax_report(&101, 1, 5), TaxReport {
+            events: vec![
+                event(1, 1, TaxEventKind::Minted),
+                event(2, 0, TaxEventKind::Bought { seller: 100, price: 500 }),
+                event(3, 0, TaxEventKind::GiftSent { to: 102 }),
+            ],
+            acquisitions: 2,
+            disposals: 1,
+            acquisition_cost: 500,
+            disposal_proceeds: 0,
+            truncated_until: None,
+        });
+
+        let report = KittiesModule::account_tax_report(&100, 2, 4);
+        assert_eq!(report.events.len(), 2);
+        assert_eq!((report.acquisition_cost, report.disposal_proceeds), (300, 500));
+    });
+}
+
+#[test]
+fn tax_report_flags_truncated_history() {
+    new_test_ext().execute_with(|| {
+        for i in 0..=MAX_TAX_HISTORY as u64 {
+            System::set_block_number(10 + i);
+            assert_ok!(KittiesModule::create(Origin::signed(102)));
+        }
+        assert_eq!(KittiesModule::tax_history(102).len(), MAX_TAX_HISTORY);
+        assert_eq!(KittiesModule::tax_history_truncated(102), Some(10));
+
+        let report = KittiesModule::account_tax_report(&102, 0, 1_000);
+        assert_eq!(report.truncated_until, Some(10));
+        assert_eq!(report.acquisitions, MAX_TAX_HISTORY as u32);
+        assert_eq!(report.events[0].kitty_id, 1);
+
+        // the lost event is before the range
+        assert_eq!(KittiesModule::account_tax_report(&102, 11, 1_000).truncated_until, None);
+    });
+}
+
+#[test]
+fn new_accounts_get_a_capped_number_of_starter_kitties() {
+    new_test_ext().execute_with(|| {
+        set_random(H256::repeat_byte(0xff));
+        // makes bucket 0 the most common one of every position
+        assert_ok!(KittiesModule::do_mint(&100, Kitty([0x05; 16]), None));
+
+        MintStarterKitty::<Test>::on_new_account(&200);
+        let starter = KittiesModule::kitties(&200, 1).unwrap();
+        assert!(starter.dna().iter().all(|gene| gene >> 4 == 0));
+        assert_eq!(KittiesModule::generation(1), 0);
+        assert_eq!(KittiesModule::soulbound(1), Some(()));
+        assert!(matches!(last_event(), Event::kitties(RawEvent::StarterKittyMinted(200, 1, _))));
+
+        MintStarterKitty::<Test>::on_new_account(&201);
+        MintStarterKitty::<Test>::on_new_account(&202);
+        assert!(KittiesModule::kitties(&201, 2).is_some());
+        // cap reached, nothing minted to 202
+        assert_eq!(Nft::next_token_id(KittiesModule::class_id()), 3);
+
+        System::set_block_number(2);
+        MintStarterKitty::<Test>::on_new_account(&202);
+        assert!(KittiesModule::kitties(&202, 3).is_some());
+    });
+}
+
+#[test]
+fn kitty_prices_rehash_in_chunks() {
+    new_test_ext().execute_with(|| {
+        assert_eq!(KittiesModule::storage_version(), migrations::CURRENT_VERSION);
+        for _ in 0..5 {
+            assert_ok!(KittiesModule::create(Origin::signed(100)));
+        }
+        // listings as the previous version stored them
+        StorageVersion::<DefaultInstance>::put(0);
+        for kitty_id in 0..5u32 {
+            frame_support::storage::unhashed::put(&migrations::old_kitty_price_key::<Test, DefaultInstance>(kitty_id), &(u64::from(kitty_id) * 10));
+        }
+        assert_eq!(KittiesModule::kitty_prices(3), None);
+        assert_eq!(migrations::old_kitty_price::<Test, DefaultInstance>(3), Some(30));
+
+        RehashCursor::<DefaultInstance>::put(Vec::<u8>::new());
+        migrations::rehash_kitty_prices::<Test, DefaultInstance>(2);
+        assert!(KittiesModule::rehash_cursor().is_some());
+        let moved = (0..5u32).filter(|kitty_id| KittiesModule::kitty_prices(*kitty_id).is_some()).count();
+        assert_eq!(moved, 2);
+
+        migrations::rehash_kitty_prices::<Test, DefaultInstance>(2);
+        migrations::rehash_kitty_prices::<Test, DefaultInstance>(2);
+        assert_eq!(KittiesModule::rehash_cursor(), None);
+
+        for kitty_id in 0..5u32 {
+            assert_eq!(
+                KittiesModule::listing(kitty_id),
+                Some(Listing { seller: 100, price: u64::from(kitty_id) * 10, expires_at: None, deposit: 0, asset: None })
+            );
+            assert_eq!(migrations::old_kitty_price::<Test, DefaultInstance>(kitty_id), None);
+        }
+        assert_eq!(Listings::<Test>::iter().count(), 5);
+        assert_eq!(KittyPrices::<Test>::iter().count(), 0);
+    });
+}
+
+#[test]
+fn runtime_upgrade_starts_kitty_prices_rehash() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        StorageVersion::<DefaultInstance>::put(0);
+        frame_support::storage::unhashed::put(&migrations::old_kitty_price_key::<Test, DefaultInstance>(0), &70u64);
+        // the kitty is gone, so is its listing
+        frame_support::storage::unhashed::put(&migrations::old_kitty_price_key::<Test, DefaultInstance>(7), &70u64);
+        migrations::start_rehash_kitty_prices::<Test, DefaultInstance>();
+        assert_eq!(KittiesModule::storage_version(), 1);
+        // nothing to do the second time
+        migrations::start_rehash_kitty_prices::<Test, DefaultInstance>();
+        assert_eq!(KittiesModule::rehash_cursor(), None);
+        assert_eq!(KittiesModule::kitty_prices(0), Some(70));
+        assert_eq!(KittiesModule::kitty_prices(7), None);
+        assert_eq!(migrations::old_kitty_price::<Test, DefaultInstance>(7), None);
+    });
+}
+
+#[test]
+fn kitty_prices_migrate_to_listings() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::create(Origin::signed(101)));
+        StorageVersion::<DefaultInstance>::put(2);
+        // prices of live and of gone kitties, under both hashers
+        frame_support::storage::unhashed::put(&KittyPrices::<Test>::hashed_key_for(0), &50u64);
+        frame_support::storage::unhashed::put(&KittyPrices::<Test>::hashed_key_for(9), &70u64);
+        frame_support::storage::unhashed::put(&migrations::old_kitty_price_key::<Test, DefaultInstance>(1), &80u64);
+        frame_support::storage::unhashed::put(&migrations::old_kitty_price_key::<Test, DefaultInstance>(8), &90u64);
+
+        let weight = migrations::migrate_listings::<Test, DefaultInstance>();
+        assert_eq!(KittiesModule::storage_version(), 3);
+        assert_eq!(KittiesModule::listing(0), Some(Listing { seller: 100, price: 50, expires_at: None, deposit: 0, asset: None }));
+        assert_eq!(KittiesModule::listing(1), Some(Listing { seller: 101, price: 80, expires_at: None, deposit: 0, asset: None }));
+        assert_eq!(KittiesModule::listing(8), None);
+        assert_eq!(KittiesModule::listing(9), None);
+        assert_eq!(KittyPrices::<Test>::iter_values().count(), 0);
+        assert_eq!(KittiesModule::rehash_cursor(), None);
+        // version and cursor, the cursor again, then each key with its price and kitty, taking the
+        // price and listing the two live kitties, and the end of the map
+        assert_eq!(weight, DbWeight::get().reads_writes(1 + 1 + 4 * 3 + 1, 2 + 4 + 2 * 2 + 1));
+
+        // nothing to do the second time
+        KittyPrices::<Test>::insert(0, 90);
+        assert_eq!(migrations::migrate_listings::<Test, DefaultInstance>(), DbWeight::get().reads(1));
+        assert_eq!(KittiesModule::kitty_prices(0), Some(50));
+    });
+}
+
+#[test]
+fn kitty_prices_migrate_in_chunks() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        StorageVersion::<DefaultInstance>::put(2);
+        KittyPrices::<Test>::insert(0, 50);
+        for kitty_id in 1..=migrations::REHASH_CHUNK + 10 {
+            KittyPrices::<Test>::insert(kitty_id, 70);
+        }
+
+        migrations::migrate_listings::<Test, DefaultInstance>();
+        assert!(KittiesModule::rehash_cursor().is_some());
+        assert_eq!(KittyPrices::<Test>::iter_values().count(), 11);
+
+        KittiesModule::on_initialize(2);
+        assert_eq!(KittiesModule::rehash_cursor(), None);
+        assert_eq!(KittyPrices::<Test>::iter_values().count(), 0);
+        assert_eq!(KittiesModule::kitty_prices(0), Some(50));
+        assert_eq!(Listings::<Test>::iter().count(), 1);
+        assert_ok!(KittiesModule::do_try_state());
+    });
+}
+
+#[test]
+fn buy_rejects_stale_listing() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 0, Some(500), None, None));
+        assert_eq!(KittiesModule::listing(0), Some(Listing { seller: 100, price: 500, expires_at: None, deposit: 0, asset: None }));
+
+        // moved behind the pallet's back, the listing stays behind
+        assert_ok!(Nft::transfer(&100, &101, (KittiesModule::class_id(), 0)));
+        assert_noop!(KittiesModule::buy(Origin::signed(102), 0, 500, None, None), Error::<Test>::StaleListing.with_weight(DbWeight::get().reads(6)));
+        assert_noop!(KittiesModule::buy(Origin::signed(101), 0, 500, None, None), Error::<Test>::StaleListing.with_weight(DbWeight::get().reads(6)));
+        assert!(KittiesModule::do_try_state().is_err());
+    });
+}
+
+#[test]
+fn buys_and_transfers_failing_early_are_refunded() {
+    new_test_ext().execute_with(|| {
+        use frame_support::weights::{extract_actual_weight, GetDispatchInfo};
+        let reads = |count| DbWeight::get().reads(count);
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+
+        assert_eq!(
+            KittiesModule::transfer(Origin::signed(101), 0, 102),
+            Err(Error::<Test>::KittenNotFound.with_weight(reads(4)))
+        );
+        // to the owner itself, a no-op
+        assert_eq!(KittiesModule::transfer(Origin::signed(100), 0, 100), Ok(Some(reads(4)).into()));
+        assert_eq!(KittiesModule::transfer(Origin::signed(100), 0, 101), Ok(().into()));
+
+        assert_ok!(KittiesModule::set_price(Origin::signed(101), 0, Some(100), None, None));
+        assert_eq!(
+            KittiesModule::buy(Origin::signed(101), 0, 100, None, None),
+            Err(Error::<Test>::BuyFromSelf.with_weight(reads(6)))
+        );
+        assert_eq!(
+            KittiesModule::buy(Origin::signed(102), 0, 99, None, None),
+            Err(Error::<Test>::PriceTooLow.with_weight(reads(6)))
+        );
+        // counting the failed buy is charged on top
+        VERBOSE_METRICS.with(|v| *v.borrow_mut() = true);
+        assert_eq!(
+            KittiesModule::buy(Origin::signed(102), 0, 99, None, None),
+            Err(Error::<Test>::PriceTooLow.with_weight(reads(6) + DbWeight::get().reads_writes(1, 1)))
+        );
+        assert_eq!(KittiesModule::buy(Origin::signed(102), 0, 100, None, None), Ok(().into()));
+
+        // the refund comes off the weight charged up front
+        let info = Call::<Test>::transfer(0, 102).get_dispatch_info();
+        assert_eq!(info.weight, <() as WeightInfo>::transfer());
+        let result = KittiesModule::transfer(Origin::signed(101), 0, 102);
+        assert_eq!(extract_actual_weight(&result, &info), reads(4));
+    });
+}
+
+#[test]
+fn offspring_rarity_distribution_is_exact() {
+    new_test_ext().execute_with(|| {
+        let tiers = |distribution: Vec<(RarityTier, Permill)>| -> Vec<u32> {
+            assert_eq!(distribution.iter().map(|(tier, _)| *tier).collect::<Vec<_>>(), RarityTier::ALL.to_vec());
+            distribution.iter().map(|(_, chance)| chance.deconstruct()).collect()
+        };
+
+        // nothing is scarce in an empty pool
+        assert_eq!(tiers(KittiesModule::offspring_rarity_distribution([0; 16], [0xff; 16])), vec![1_000_000, 0, 0, 0]);
+
+        // every bucket but 0 is scarce from now on
+        assert_ok!(KittiesModule::do_mint(&100, Kitty([0; 16]), None));
+        let mut second = [0; 16];
+        second[0] = 0x10;
+        assert_eq!(tiers(KittiesModule::offspring_rarity_distribution([0; 16], second)), vec![500_000, 500_000, 0, 0]);
+
+        // three positions with a scarce gene at even odds: 1/8, 6/8, 1/8
+        second[5] = 0x10;
+        second[9] = 0x10;
+        assert_eq!(
+            tiers(KittiesModule::offspring_rarity_distribution([0; 16], second)),
+            vec![125_000, 750_000, 125_000, 0]
+        );
+
+        // the low bits of a gene never move it to another bucket
+        assert_eq!(tiers(KittiesModule::offspring_rarity_distribution([0; 16], [0x0f; 16])), vec![1_000_000, 0, 0, 0]);
+        assert_eq!(tiers(KittiesModule::offspring_rarity_distribution([0xf0; 16], [0xf0; 16])), vec![0, 0, 0, 1_000_000]);
+    });
+}
+
+#[test]
+fn offspring_rarity_distribution_counts_mutations() {
+    new_test_ext().execute_with(|| {
+        let tiers = || -> Vec<u32> {
+            KittiesModule::offspring_rarity_distribution([0; 16], [0; 16]).iter().map(|(_, chance)| chance.deconstruct()).collect()
+        };
+        // buckets 0 to 7 hold a kitty each, so every gene of buckets 8 to 15 is scarce
+        for bucket in 0..8u8 {
+            assert_ok!(KittiesModule::do_mint(&100, Kitty([bucket << 4; 16]), None));
+        }
+        assert_eq!(tiers(), vec![1_000_000, 0, 0, 0]);
+
+        // each gene mutates into a scarce one at odds of 1/2: 16 fair coins
+        MUTATION_CHANCE.with(|v| *v.borrow_mut() = Perbill::one());
+        assert_eq!(tiers(), vec![15, 2_075, 102_966, 894_943]);
+
+        // at odds of 1/4
+        MUTATION_CHANCE.with(|v| *v.borrow_mut() = Perbill::from_percent(50));
+        assert_eq!(tiers(), vec![10_022, 187_088, 613_234, 189_654]);
+    });
+}
+
+#[test]
+fn burn_policy_deletes_or_archives() {
+    let burn = |policy: BurnPolicy| {
+        new_test_ext().execute_with(|| {
+            BURN_POLICY.with(|v| *v.borrow_mut() = policy);
+            assert_ok!(KittiesModule::create(Origin::signed(100)));
+            System::set_extrinsic_index(1);
+            assert_ok!(KittiesModule::create(Origin::signed(100)));
+            assert_ok!(KittiesModule::breed(Origin::signed(100), 0, 1, None));
+            let kitty = KittiesModule::kitties(&100, 2).unwrap();
+            let ancestry = KittiesModule::ancestry(2);
+
+            System::set_block_number(4);
+            assert_ok!(KittiesModule::do_burn(&100, 2));
+            assert_eq!(Nft::tokens(KittiesModule::class_id(), 2), None);
+            assert_eq!(Nft::classes(KittiesModule::class_id()).unwrap().total_issuance, 2);
+            assert_eq!(KittiesModule::gene_scarcity().iter().map(|buckets| buckets.iter().sum::<u32>()).max(), Some(2));
+            assert!(!Generations::<Test>::contains_key(2));
+            assert!(!Ancestries::<Test>::contains_key(2));
+
+            // the archived id isn't handed out again
+            assert_ok!(KittiesModule::create(Origin::signed(100)));
+            assert!(KittiesModule::kitties(&100, 3).is_some());
+
+            (KittiesModule::burned_kitty(2), kitty, ancestry)
+        })
+    };
+
+    let (archived, _, _) = burn(BurnPolicy::Delete);
+    assert_eq!(archived, None);
+
+    let (archived, kitty, ancestry) = burn(BurnPolicy::Archive);
+    assert_eq!(archived, Some(ArchivedKitty { kitty, generation: 1, ancestry, burned_at: 4 }));
+}
+
+#[test]
+fn bundle_trade_swaps_every_item_once() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::create(Origin::signed(101)));
+
+        assert_noop!(
+            KittiesModule::propose_trade(Origin::signed(100), 101, vec![0, 0], 50, vec![2], 20),
+            Error::<Test>::InvalidTrade
+        );
+        assert_noop!(
+            KittiesModule::propose_trade(Origin::signed(100), 101, vec![0; 9], 50, vec![2], 20),
+            Error::<Test>::InvalidTrade
+        );
+        assert_noop!(
+            KittiesModule::propose_trade(Origin::signed(100), 101, vec![2], 50, vec![0], 20),
+            Error::<Test>::KittenNotFound
+        );
+        assert_ok!(KittiesModule::propose_trade(Origin::signed(100), 101, vec![0, 1], 50, vec![2], 20));
+        assert_eq!(Balances::reserved_balance(100), 50);
+        assert_eq!(KittiesModule::trade_lock(0), Some(0));
+        assert_noop!(KittiesModule::transfer(Origin::signed(100), 0, 102), Error::<Test>::KittyLocked);
+
+        assert_noop!(KittiesModule::accept_trade(Origin::signed(102), 0), Error::<Test>::NotTradeCounterparty);
+        assert_ok!(KittiesModule::accept_trade(Origin::signed(101), 0));
+        assert!(matches!(last_event(), Event::kitties(RawEvent::TradeAccepted(0, _))));
+
+        assert!(KittiesModule::kitties(&101, 0).is_some());
+        assert!(KittiesModule::kitties(&101, 1).is_some());
+        assert!(KittiesModule::kitties(&100, 2).is_some());
+        assert_eq!(orml_nft::TokensByOwner::<Test>::iter_prefix(100).count(), 1);
+        assert_eq!(orml_nft::TokensByOwner::<Test>::iter_prefix(101).count(), 2);
+        assert_eq!(Balances::free_balance(100), 9_970);
+        assert_eq!(Balances::free_balance(101), 10_030);
+        assert_eq!(Balances::reserved_balance(100), 0);
+        assert_eq!(KittiesModule::trade_proposal(0), None);
+        assert_eq!(KittiesModule::trade_lock(0), None);
+        assert_ok!(KittiesModule::do_try_state());
+    });
+}
+
+#[test]
+fn bundle_trade_is_voided_once_a_wanted_kitty_is_gone() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::create(Origin::signed(101)));
+        assert_ok!(KittiesModule::propose_trade(Origin::signed(100), 101, vec![0], 50, vec![1], 0));
+
+        assert_ok!(KittiesModule::set_price(Origin::signed(101), 1, Some(100), None, None));
+        assert_ok!(KittiesModule::buy(Origin::signed(102), 1, 100, None, None));
+
+        assert_eq!(
+            KittiesModule::accept_trade(Origin::signed(101), 0),
+            Err(Error::<Test>::WantedKittyUnavailable.into())
+        );
+        assert!(System::events().iter().any(|r| matches!(r.event, Event::kitties(RawEvent::TradeClosed(0, _)))));
+        assert_eq!(KittiesModule::trade_proposal(0), None);
+        assert_eq!(Balances::reserved_balance(100), 0);
+        assert_ok!(KittiesModule::transfer(Origin::signed(100), 0, 102));
+    });
+}
+
+#[test]
+fn bundle_trade_can_be_cancelled() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::create(Origin::signed(101)));
+        assert_ok!(KittiesModule::propose_trade(Origin::signed(100), 101, vec![0], 50, vec![1], 0));
+        assert_ok!(KittiesModule::propose_trade(Origin::signed(101), 100, vec![1], 0, vec![], 30));
+
+        assert_ok!(KittiesModule::cancel_trade(Origin::signed(100), 0));
+        assert_eq!(Balances::reserved_balance(100), 0);
+        assert_eq!(KittiesModule::trade_lock(0), None);
+
+        assert_noop!(KittiesModule::cancel_trade(Origin::signed(102), 1), Error::<Test>::TradeNotExpired);
+        System::set_block_number(12);
+        assert_noop!(KittiesModule::accept_trade(Origin::signed(100), 1), Error::<Test>::TradeExpired);
+        assert_ok!(KittiesModule::cancel_trade(Origin::signed(102), 1));
+        assert_eq!(KittiesModule::trade_lock(1), None);
+    });
+}
+
+#[test]
+fn fee_handler_cannot_reenter() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::set_fee_schedule(Origin::root(), vec![(0, Permill::from_percent(10))]));
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 0, Some(1000), None, None));
+
+        REENTER_ON_FEE.with(|v| *v.borrow_mut() = true);
+        assert_ok!(KittiesModule::buy(Origin::signed(101), 0, 1000, None, None));
+        assert_eq!(REENTRY_RESULT.with(|v| v.borrow().clone()), Some(Err(Error::<Test>::ReentrancyDetected.into())));
+
+        // the sale settled, the nested transfer didn't happen
+        assert!(KittiesModule::kitties(&101, 0).is_some());
+        assert!(KittiesModule::kitties(&100, 1).is_some());
+        assert_eq!(Balances::free_balance(100), 10_900);
+        assert!(!ExecutionLock::<DefaultInstance>::get());
+
+        // the lock is gone afterwards
+        assert_ok!(KittiesModule::transfer(Origin::signed(100), 1, 102));
+    });
+}
+
+/// Store kitty `kitty_id` in the old layout, as a chain from before `V2` would have it.
+fn downgrade_kitty(kitty_id: u32) {
+    let class_id = KittiesModule::class_id();
+    let mut token = Nft::tokens(class_id, kitty_id).unwrap();
+    token.data = VersionedKitty::V1(token.data.kitty().dna());
+    orml_nft::Tokens::<Test>::insert(class_id, kitty_id, token);
+}
+
+#[test]
+fn old_kitties_are_upgraded_when_moved() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(3);
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        let class_id = KittiesModule::class_id();
+        let dna = KittiesModule::kitties(&100, 0).unwrap().dna();
+        assert_eq!(Nft::tokens(class_id, 0).unwrap().data, VersionedKitty::V2 { dna, birth: 3, flags: 0 });
+
+        downgrade_kitty(0);
+        assert_eq!(Nft::tokens(class_id, 0).unwrap().data, VersionedKitty::V1(dna));
+        assert_eq!(KittiesModule::kitties(&100, 0).map(|kitty| kitty.dna()), Some(dna));
+        assert_ok!(KittiesModule::do_try_state());
+
+        assert_ok!(KittiesModule::transfer(Origin::signed(100), 0, 101));
+        assert_eq!(Nft::tokens(class_id, 0).unwrap().data, VersionedKitty::V2 { dna, birth: 3, flags: 0 });
+        assert_ok!(KittiesModule::do_try_state());
+    });
+}
+
+#[test]
+fn upgrade_kitties_walks_every_kitty() {
+    new_test_ext().execute_with(|| {
+        for _ in 0..3 {
+            assert_ok!(KittiesModule::create(Origin::signed(100)));
+        }
+        downgrade_kitty(0);
+        downgrade_kitty(2);
+
+        assert_ok!(KittiesModule::upgrade_kitties(Origin::signed(101), 2));
+        assert!(matches!(last_event(), Event::kitties(RawEvent::KittiesUpgraded(1, 2, _))));
+        assert!(!Nft::tokens(KittiesModule::class_id(), 0).unwrap().data.is_v1());
+        assert!(Nft::tokens(KittiesModule::class_id(), 2).unwrap().data.is_v1());
+
+        assert_ok!(KittiesModule::upgrade_kitties(Origin::signed(101), 2));
+        assert!(matches!(last_event(), Event::kitties(RawEvent::KittiesUpgraded(1, 3, _))));
+        assert!(!Nft::tokens(KittiesModule::class_id(), 2).unwrap().data.is_v1());
+        assert_eq!(KittiesModule::kitty_upgrade_cursor(), 3);
+        assert_ok!(KittiesModule::do_try_state());
+    });
+}
+
+#[test]
+fn verified_accounts_claim_one_starter_kitty_ever() {
+    new_test_ext().execute_with(|| {
+        assert_noop!(KittiesModule::claim_starter_kitty(Origin::signed(100)), Error::<Test>::NotVerified);
+
+        set_verified(100, true);
+        assert_ok!(KittiesModule::claim_starter_kitty(Origin::signed(100)));
+        assert!(matches!(last_event(), Event::kitties(RawEvent::StarterKittyMinted(100, 0, _))));
+        assert!(KittiesModule::starter_claimed(100));
+        assert_eq!(Balances::free_balance(100), 10_000);
+        let kitty = KittiesModule::kitties(&100, 0).unwrap();
+        assert!(kitty.dna().iter().enumerate().all(|(position, gene)| {
+            !KittiesModule::is_scarce_gene(&KittiesModule::gene_pool(position as u8), *gene)
+        }));
+
+        // giving the kitty away doesn't open a new claim
+        assert_ok!(KittiesModule::transfer(Origin::signed(100), 0, 101));
+        assert_noop!(KittiesModule::claim_starter_kitty(Origin::signed(100)), Error::<Test>::StarterAlreadyClaimed);
+
+        // neither does losing and regaining verification
+        set_verified(100, false);
+        assert_noop!(KittiesModule::claim_starter_kitty(Origin::signed(100)), Error::<Test>::NotVerified);
+        set_verified(100, true);
+        assert_noop!(KittiesModule::claim_starter_kitty(Origin::signed(100)), Error::<Test>::StarterAlreadyClaimed);
+
+        set_verified(101, true);
+        assert_ok!(KittiesModule::claim_starter_kitty(Origin::signed(101)));
+        assert_eq!(orml_nft::TokensByOwner::<Test>::iter_prefix(101).count(), 2);
+    });
+}
+
+#[test]
+fn median_of_sale_prices() {
+    assert_eq!(median::<u64>(vec![]), None);
+    assert_eq!(median(vec![7u64]), Some(7));
+    assert_eq!(median(vec![9u64, 1, 5]), Some(5));
+    assert_eq!(median(vec![8u64, 2, 4, 6]), Some(5));
+    // the middle two are averaged, rounding down
+    assert_eq!(median(vec![1u64, 2]), Some(1));
+    assert_eq!(median(vec![u64::max_value(), u64::max_value() - 1]), Some(u64::max_value() - 1));
+}
+
+#[test]
+fn sale_median_oracle_prices_from_recent_sales() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        let kitty = KittiesModule::kitties(&100, 0).unwrap();
+        assert_eq!(<Test as Config>::PriceOracle::floor_price(), None);
+        assert_eq!(<Test as Config>::PriceOracle::estimate(&kitty), None);
+
+        for (round, price) in [300u64, 100, 200].iter().enumerate() {
+            let (owner, buyer) = if round % 2 == 0 { (100, 101) } else { (101, 100) };
+            assert_ok!(KittiesModule::set_price(Origin::signed(owner), 0, Some(*price), None, None));
+            assert_ok!(KittiesModule::buy(Origin::signed(buyer), 0, *price, None, None));
+        }
+        assert_eq!(KittiesModule::recent_sales().len(), 3);
+        assert_eq!(<Test as Config>::PriceOracle::floor_price(), Some(200));
+        // every sale was of this kitty, so its tier has the same median
+        assert_eq!(<Test as Config>::PriceOracle::estimate(&kitty), Some(200));
+    });
+}
+
+#[test]
+fn recent_sales_are_bounded() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        for round in 0..SALE_PRICE_WINDOW as u64 + 2 {
+            let (owner, buyer) = if round % 2 == 0 { (100, 101) } else { (101, 100) };
+            assert_ok!(KittiesModule::set_price(Origin::signed(owner), 0, Some(round + 1), None, None));
+            assert_ok!(KittiesModule::buy(Origin::signed(buyer), 0, round + 1, None, None));
+        }
+        let sales = KittiesModule::recent_sales();
+        assert_eq!(sales.len(), SALE_PRICE_WINDOW);
+        assert_eq!(sales[0].1, 3);
+        assert_eq!(sales[SALE_PRICE_WINDOW - 1].1, SALE_PRICE_WINDOW as u64 + 2);
+    });
+}
+
+#[test]
+fn kitty_traits_are_numbers_only() {
+    new_test_ext().execute_with(|| {
+        assert_eq!(KittiesModule::kitty_traits(0), None);
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        let dna = KittiesModule::kitties(&100, 0).unwrap().dna();
+
+        let traits = KittiesModule::kitty_traits(0).unwrap();
+        assert_eq!(traits.len(), 16);
+        for (position, kitty_trait) in traits.iter().enumerate() {
+            assert_eq!(kitty_trait.slot, position as u8);
+            assert_eq!(kitty_trait.value, dna[position] >> 4);
+            assert!(kitty_trait.tier <= 1);
+        }
+        // a length prefix and three bytes per trait, no room for text
+        assert_eq!(traits.encode().len(), 1 + 16 * 3);
+    });
+}
+
+#[test]
+fn kitties_of_an_owner_are_bounded() {
+    new_test_ext().execute_with(|| {
+        assert_eq!(KittiesModule::kitties_of(&100, 10), vec![]);
+        for _ in 0..3 {
+            assert_ok!(KittiesModule::create(Origin::signed(100)));
+        }
+        assert_ok!(KittiesModule::create(Origin::signed(101)));
+
+        let dna = |kitty_id| KittiesModule::kitties(&100, kitty_id).unwrap().dna();
+        assert_eq!(KittiesModule::kitties_of(&100, 10), vec![(0, dna(0)), (1, dna(1)), (2, dna(2))]);
+        assert_eq!(KittiesModule::kitties_of(&100, 2).len(), 2);
+        assert_eq!(KittiesModule::kitties_of(&100, 0), vec![]);
+        assert_eq!(KittiesModule::kitties_of(&101, 10).iter().map(|(kitty_id, _)| *kitty_id).collect::<Vec<_>>(), vec![3]);
+
+        assert_ok!(KittiesModule::burn(Origin::signed(100), 1));
+        assert_eq!(KittiesModule::kitties_of(&100, 10), vec![(0, dna(0)), (2, dna(2))]);
+        assert_eq!(KittiesModule::owner_of(1), None);
+        assert_eq!(KittiesModule::owner_of(3), Some(101));
+    });
+}
+
+fn genesis_ext(genesis_kitties: Vec<(u64, [u8; 16], Option<u64>)>) -> sp_io::TestExternalities {
+    let mut t = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+    pallet_balances::GenesisConfig::<Test> {
+        balances: vec![(100, 10_000), (101, 10_000), (102, 10_000)],
+    }
+    .assimilate_storage(&mut t)
+    .unwrap();
+    GenesisConfig::<Test> { genesis_kitties, ..Default::default() }.assimilate_storage(&mut t).unwrap();
+    let mut ext: sp_io::TestExternalities = t.into();
+    ext.execute_with(|| System::set_block_number(1));
+    ext
+}
+
+#[test]
+fn founder_kitties_are_minted_at_genesis() {
+    genesis_ext(vec![(100, [2; 16], Some(300)), (101, [3; 16], None), (100, [4; 16], None)]).execute_with(|| {
+        assert_eq!(KittiesModule::kitties(&100, 0), Some(Kitty([2; 16])));
+        assert_eq!(KittiesModule::kitties(&101, 1), Some(Kitty([3; 16])));
+        assert_eq!(KittiesModule::kitties(&100, 2), Some(Kitty([4; 16])));
+        assert_eq!(KittiesModule::owned_kitties_count(100), 2);
+        assert_eq!(KittiesModule::kitty_with_dna([3; 16]), Some(1));
+        assert_eq!(KittiesModule::listing(0), Some(Listing { seller: 100, price: 300, expires_at: None, deposit: 0, asset: None }));
+        assert_eq!(KittiesModule::for_sale(10, None), vec![(0, 100, 300)]);
+        assert_eq!(KittiesModule::kitty_prices(1), None);
+        assert_eq!(Nft::next_token_id(KittiesModule::class_id()), 3);
+
+        // later kitties follow the founders
+        assert_ok!(KittiesModule::create(Origin::signed(102)));
+        assert_eq!(KittiesModule::owner_of(3), Some(102));
+        assert_eq!(KittiesModule::total_kitties(), 4);
+        assert_ok!(KittiesModule::buy(Origin::signed(102), 0, 300, None, None));
+        assert_eq!(KittiesModule::owner_of(0), Some(102));
+        assert_ok!(KittiesModule::do_try_state());
+    });
+}
+
+#[test]
+#[should_panic(expected = "Invalid genesis kitty")]
+fn founder_kitties_need_unique_dna() {
+    genesis_ext(vec![(100, [2; 16], None), (101, [2; 16], None)]);
+}
+
+#[test]
+fn fees_are_burned_by_a_burning_handler() {
+    new_test_ext().execute_with(|| {
+        CREATION_FEE.with(|v| *v.borrow_mut() = 100);
+        assert_ok!(KittiesModule::set_fee_schedule(Origin::root(), vec![(0, Permill::from_percent(10))]));
+        let issuance = Balances::total_issuance();
+
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 0, Some(1000), None, None));
+        assert_ok!(KittiesModule::buy(Origin::signed(101), 0, 1000, None, None));
+
+        assert_eq!(FEES_RECEIVED.with(|v| v.borrow().clone()), vec![100, 100]);
+        assert_eq!(Balances::total_issuance(), issuance - 200);
+        assert_eq!(Balances::free_balance(100), 10_000 - 100 + 1000 - 100);
+    });
+}
+
+#[test]
+fn fees_are_kept_by_a_depositing_handler() {
+    new_test_ext().execute_with(|| {
+        CREATION_FEE.with(|v| *v.borrow_mut() = 100);
+        FEE_BENEFICIARY.with(|v| *v.borrow_mut() = Some(200));
+        assert_ok!(KittiesModule::set_fee_schedule(Origin::root(), vec![(0, Permill::from_percent(10))]));
+        let issuance = Balances::total_issuance();
+
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 0, Some(1000), None, None));
+        assert_ok!(KittiesModule::buy(Origin::signed(101), 0, 1000, None, None));
+
+        assert_eq!(FEES_RECEIVED.with(|v| v.borrow().clone()), vec![100, 100]);
+        assert_eq!(Balances::total_issuance(), issuance);
+        assert_eq!(Balances::free_balance(200), 200);
+    });
+}
+
+/// Pool 0 operated by 102, holding female kitty 0 of 100 and male kitty 1 of 101.
+fn setup_breeding_pool() {
+    assert_ok!(KittiesModule::create(Origin::signed(100)));
+    System::set_extrinsic_index(1);
+    assert_ok!(KittiesModule::create(Origin::signed(100)));
+    assert_ok!(KittiesModule::transfer(Origin::signed(100), 1, 101));
+
+    assert_ok!(KittiesModule::create_pool(Origin::signed(102)));
+    assert_ok!(KittiesModule::join_pool(Origin::signed(100), 0, 0));
+    assert_ok!(KittiesModule::join_pool(Origin::signed(101), 0, 1));
+    assert!(KittiesModule::kitties(&KittiesModule::pool_account_id(0), 0).is_some());
+    assert!(KittiesModule::kitties(&KittiesModule::pool_account_id(0), 1).is_some());
+}
+
+#[test]
+fn breeding_pool_shares_offspring_by_weight() {
+    new_test_ext().execute_with(|| {
+        setup_breeding_pool();
+        let pool = KittiesModule::breeding_pool(0).unwrap();
+        let weights: Vec<(u64, u32)> = pool.contributions.iter().map(|c| (c.contributor, c.weight)).collect();
+        let total: u32 = weights.iter().map(|(_, weight)| weight).sum();
+
+        for _ in 0..2 * total {
+            System::set_block_number(System::block_number() + 5);
+            assert_ok!(KittiesModule::breed_in_pool(Origin::signed(102), 0, 0, 1));
+        }
+        for (who, weight) in weights {
+            assert_eq!(orml_nft::TokensByOwner::<Test>::iter_prefix(who).count() as u32, 2 * weight);
+        }
+        assert_eq!(KittiesModule::breeding_pool(0).unwrap().cursor, 2 * total);
+
+        // heavier contributors get their turns spread over the cycle
+        let contribution = |contributor, weight| PoolContribution { contributor, kitty_id: 0, weight, resting_until: 0 };
+        let mut pool = BreedingPool { operator: 102, contributions: vec![contribution(100, 2), contribution(101, 1), contribution(100, 1)], cursor: 0 };
+        let mut recipients = Vec::new();
+        for cursor in 0..8 {
+            pool.cursor = cursor;
+            recipients.push(KittiesModule::pool_recipient(&pool).unwrap());
+        }
+        assert_eq!(recipients, vec![100, 101, 100, 100, 100, 101, 100, 100]);
+    });
+}
+
+#[test]
+fn pooled_kitties_rest_after_breeding() {
+    new_test_ext().execute_with(|| {
+        setup_breeding_pool();
+        assert_noop!(KittiesModule::breed_in_pool(Origin::signed(100), 0, 0, 1), Error::<Test>::NotPoolOperator);
+        assert_ok!(KittiesModule::breed_in_pool(Origin::signed(102), 0, 0, 1));
+
+        assert_noop!(KittiesModule::leave_pool(Origin::signed(100), 0, 0), Error::<Test>::PoolKittyResting);
+        assert_noop!(KittiesModule::breed_in_pool(Origin::signed(102), 0, 0, 1), Error::<Test>::PoolKittyResting);
+        assert_noop!(KittiesModule::leave_pool(Origin::signed(101), 0, 0), Error::<Test>::NotInPool);
+
+        System::set_block_number(6);
+        assert_ok!(KittiesModule::leave_pool(Origin::signed(100), 0, 0));
+        assert!(KittiesModule::kitties(&100, 0).is_some());
+        assert_eq!(KittiesModule::breeding_pool(0).unwrap().contributions.len(), 1);
+        assert_noop!(KittiesModule::breed_in_pool(Origin::signed(102), 0, 0, 1), Error::<Test>::NotInPool);
+    });
+}
+
+#[test]
+fn pooled_breeding_follows_the_rules_of_breed() {
+    new_test_ext().execute_with(|| {
+        setup_breeding_pool();
+        assert_ok!(KittiesModule::breed_in_pool(Origin::signed(102), 0, 0, 1));
+        assert!(matches!(last_event(), Event::kitties(RawEvent::PoolKittyBred(0, 2, 100, 1, _))));
+        assert!(System::events().iter().any(|r| matches!(r.event, Event::kitties(RawEvent::KittyBreed(100, _, _, 2, 1, _)))));
+        assert_eq!(KittiesModule::sire_stats(0).offspring, 1);
+        assert_eq!(KittiesModule::activity_counter(ActivityKind::Breeds), 1);
+    });
+}
+
+#[test]
+fn owners_breed_kitties_held_in_custody() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        System::set_extrinsic_index(1);
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::create_pool(Origin::signed(102)));
+        assert_ok!(KittiesModule::join_pool(Origin::signed(100), 0, 0));
+        assert_eq!(KittiesModule::owner_of(0), Some(KittiesModule::pool_account_id(0)));
+        assert_eq!(KittiesModule::beneficial_owner(0), Some(100));
+        assert_eq!(KittiesModule::beneficial_owner(1), Some(100));
+
+        // not while the policy keeps pooled kitties to the pool
+        assert_noop!(KittiesModule::breed(Origin::signed(100), 0, 1, None), Error::<Test>::KittenNotFound);
+
+        CUSTODY_POLICY.with(|v| *v.borrow_mut() = CustodyPolicy { pool: true, gift: false });
+        assert_ok!(KittiesModule::breed(Origin::signed(100), 0, 1, None));
+        assert!(KittiesModule::kitties(&100, 2).is_some());
+        assert_ok!(KittiesModule::set_siring_price(Origin::signed(100), 0, Some(10)));
+        assert_noop!(KittiesModule::breed(Origin::signed(101), 0, 1, None), Error::<Test>::KittenNotFound);
+        // the pool still holds it, so it can't be moved
+        assert_noop!(
+            KittiesModule::transfer(Origin::signed(100), 0, 101),
+            Error::<Test>::KittenNotFound.with_weight(DbWeight::get().reads(4))
+        );
+        assert_ok!(KittiesModule::do_try_state());
+
+        // custody ends with the kitty leaving the pool
+        assert_ok!(KittiesModule::leave_pool(Origin::signed(100), 0, 0));
+        assert_eq!(KittiesModule::kitty_custody(0), None);
+        assert_eq!(KittiesModule::beneficial_owner(0), Some(100));
+
+        // a pending gift stays with its sender, who the policy leaves no owner actions
+        assert_ok!(KittiesModule::gift(Origin::signed(100), 1, 101, 10));
+        assert_eq!(KittiesModule::beneficial_owner(1), Some(100));
+        assert_noop!(KittiesModule::approve_breeding(Origin::signed(100), 1, Some(101)), Error::<Test>::KittenNotFound);
+        assert_ok!(KittiesModule::do_try_state());
+        assert_ok!(KittiesModule::claim_gift(Origin::signed(101), 1));
+        assert_eq!(KittiesModule::kitty_custody(1), None);
+        assert_eq!(KittiesModule::beneficial_owner(1), Some(101));
+    });
+}
+
+#[test]
+fn dissolving_a_pool_returns_every_kitty() {
+    new_test_ext().execute_with(|| {
+        setup_breeding_pool();
+        assert_ok!(KittiesModule::breed_in_pool(Origin::signed(102), 0, 0, 1));
+
+        assert_noop!(KittiesModule::dissolve_pool(Origin::signed(100), 0), Error::<Test>::NotPoolOperator);
+        assert_ok!(KittiesModule::dissolve_pool(Origin::signed(102), 0));
+        assert!(matches!(last_event(), Event::kitties(RawEvent::PoolDissolved(0, _))));
+
+        assert!(KittiesModule::kitties(&100, 0).is_some());
+        assert!(KittiesModule::kitties(&101, 1).is_some());
+        assert_eq!(orml_nft::TokensByOwner::<Test>::iter_prefix(KittiesModule::pool_account_id(0)).count(), 0);
+        assert_eq!(KittiesModule::breeding_pool(0), None);
+        assert_noop!(KittiesModule::join_pool(Origin::signed(100), 0, 0), Error::<Test>::PoolNotFound);
+        assert_ok!(KittiesModule::do_try_state());
+    });
+}
+
+#[test]
+fn kitty_releases_leave_foreign_reserves_alone() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(101)));
+        assert_ok!(KittiesModule::propose_trade(Origin::signed(100), 101, vec![], 50, vec![0], 0));
+        // another pallet reserves on the same account
+        assert_ok!(Balances::reserve(&100, 500));
+        assert_eq!(KittiesModule::kitty_reserved(100), 50);
+
+        assert_ok!(KittiesModule::cancel_trade(Origin::signed(100), 0));
+        assert_eq!(Balances::reserved_balance(100), 500);
+        assert_eq!(KittiesModule::kitty_reserved(100), 0);
+
+        // releasing again finds nothing of the pallet's to release
+        assert_eq!(KittiesModule::unreserve_funds(&100, 50), 0);
+        assert_eq!(Balances::reserved_balance(100), 500);
+    });
+}
+
+#[test]
+fn kitty_releases_survive_slashed_reserves() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(101)));
+        assert_ok!(KittiesModule::propose_trade(Origin::signed(100), 101, vec![], 50, vec![0], 0));
+        let (_, unslashed) = Balances::slash_reserved(&100, 30);
+        assert_eq!(unslashed, 0);
+        assert_eq!(KittiesModule::available_reserve(&100), 20);
+
+        assert_ok!(KittiesModule::cancel_trade(Origin::signed(100), 0));
+        assert_eq!(Balances::reserved_balance(100), 0);
+        assert_eq!(Balances::free_balance(100), 9_970);
+        assert_eq!(KittiesModule::kitty_reserved(100), 0);
+    });
+}
+
+#[test]
+fn kitty_reserves_are_backfilled_on_upgrade() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(101)));
+        assert_ok!(KittiesModule::propose_trade(Origin::signed(100), 101, vec![], 50, vec![0], 0));
+        assert_ok!(KittiesModule::commit_create(Origin::signed(100), H256::zero()));
+        // as before the pallet kept track of its reserves
+        KittyReserves::<Test>::remove(100);
+        StorageVersion::<DefaultInstance>::put(1);
+
+        migrations::backfill_kitty_reserves::<Test, DefaultInstance>();
+        assert_eq!(KittiesModule::kitty_reserved(100), 70);
+        assert_eq!(KittiesModule::storage_version(), 2);
+        migrations::backfill_kitty_reserves::<Test, DefaultInstance>();
+        assert_eq!(KittiesModule::kitty_reserved(100), 70);
+    });
+}
+
+#[test]
+fn exhausted_minting_skips_side_mints() {
+    new_test_ext().execute_with(|| {
+        let class_id = KittiesModule::class_id();
+        assert_eq!(KittiesModule::kitty_id_headroom(), u32::max_value());
+        let first = u32::max_value() - 4;
+        orml_nft::NextTokenId::<Test>::insert(class_id, first);
+        assert_eq!(KittiesModule::kitty_id_headroom(), 4);
+
+        // female, male
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        System::set_extrinsic_index(1);
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::breed(Origin::signed(100), first, first + 1, None));
+        assert!(!KittiesModule::minting_exhausted());
+
+        // the kitten takes the last id, its milestone badge is skipped
+        let kitten = first + 2;
+        let partner = if KittiesModule::kitties(&100, kitten).unwrap().gender() == KittyGender::Male { first } else { first + 1 };
+        assert_ok!(KittiesModule::breed(Origin::signed(100), kitten, partner, None));
+        assert_eq!(KittiesModule::generation(first + 3), 2);
+        assert!(KittiesModule::minting_exhausted());
+        assert_eq!(KittiesModule::kitty_id_headroom(), 0);
+        assert!(System::events().iter().any(|r| matches!(r.event, Event::kitties(RawEvent::MintingExhausted(_)))));
+        assert!(matches!(last_event(), Event::kitties(RawEvent::AutoMintSkipped(100, _))));
+        assert!(!System::events().iter().any(|r| matches!(r.event, Event::kitties(RawEvent::MilestoneReached(..)))));
+
+        // starter kitties are skipped too
+        MintStarterKitty::<Test>::on_new_account(&200);
+        assert!(matches!(last_event(), Event::kitties(RawEvent::AutoMintSkipped(200, _))));
+        assert_eq!(orml_nft::TokensByOwner::<Test>::iter_prefix(200).count(), 0);
+
+        // mints asked for fail
+        assert_noop!(KittiesModule::create(Origin::signed(100)), Error::<Test>::MintingExhausted);
+        set_verified(101, true);
+        assert_noop!(KittiesModule::claim_starter_kitty(Origin::signed(101)), Error::<Test>::MintingExhausted);
+        assert_ok!(KittiesModule::transfer(Origin::signed(100), first, 101));
+        assert_ok!(KittiesModule::do_try_state());
+    });
+}
+
+mod narrow;
+mod scenario;
+
+#[test]
+fn buy_without_funds_moves_nothing() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 0, Some(20_000), None, None));
+
+        assert_noop!(
+            KittiesModule::buy(Origin::signed(101), 0, 20_000, None, None),
+            pallet_balances::Error::<Test, pallet_balances::DefaultInstance>::InsufficientBalance
+        );
+        assert!(KittiesModule::kitties(&100, 0).is_some());
+        assert_eq!(KittiesModule::kitty_prices(0), Some(20_000));
+        assert_eq!(Balances::free_balance(101), 10_000);
+    });
+}
+
+#[test]
+fn transfer_delists_kitty() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 0, Some(50), None, None));
+
+        assert_ok!(KittiesModule::transfer(Origin::signed(100), 0, 101));
+        assert_eq!(KittiesModule::kitty_prices(0), None);
+        assert!(System::events().iter().any(|r| matches!(
+            r.event,
+            Event::kitties(RawEvent::KittyPriceUpdated(100, 0, None, None, _))
+        )));
+        assert_noop!(KittiesModule::buy(Origin::signed(102), 0, 50, None, None), Error::<Test>::NotForSale.with_weight(DbWeight::get().reads(4)));
+    });
+}
+
+#[test]
+fn buy_resolves_seller_after_transfer() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_eq!(KittiesModule::owner_of(0), Some(100));
+        assert_eq!(KittiesModule::owner_of(1), None);
+
+        assert_ok!(KittiesModule::transfer(Origin::signed(100), 0, 101));
+        assert_eq!(KittiesModule::owner_of(0), Some(101));
+        assert_ok!(KittiesModule::set_price(Origin::signed(101), 0, Some(300), None, None));
+
+        assert_ok!(KittiesModule::buy(Origin::signed(102), 0, 300, None, None));
+        assert_eq!(KittiesModule::owner_of(0), Some(102));
+        // the creator's royalty comes out of the proceeds
+        assert_eq!(Balances::free_balance(101), 10_000 + 300 - 30);
+        assert!(matches!(last_event(), Event::kitties(RawEvent::KittySold(101, 102, 0, 300, None, 0, 30, _, _))));
+    });
+}
+
+#[test]
+fn create_stops_at_kitty_cap() {
+    new_test_ext().execute_with(|| {
+        MAX_KITTIES_PER_OWNER.with(|v| *v.borrow_mut() = 2);
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_eq!(KittiesModule::owned_kitties_count(100), 2);
+
+        assert_noop!(KittiesModule::create(Origin::signed(100)), Error::<Test>::TooManyKitties);
+        assert_ok!(KittiesModule::create(Origin::signed(101)));
+        assert_ok!(KittiesModule::do_try_state());
+    });
+}
+
+#[test]
+fn breed_stops_at_kitty_cap() {
+    new_test_ext().execute_with(|| {
+        MAX_KITTIES_PER_OWNER.with(|v| *v.borrow_mut() = 3);
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        System::set_extrinsic_index(1);
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::breed(Origin::signed(100), 0, 1, None));
+        assert_eq!(KittiesModule::owned_kitties_count(100), 3);
+
+        assert_eq!(
+            KittiesModule::breed(Origin::signed(100), 0, 1, None),
+            Err(Error::<Test>::TooManyKitties.into())
+        );
+        assert_eq!(KittiesModule::owned_kitties_count(100), 3);
+        assert!(KittiesModule::kitties(&100, 3).is_none());
+        assert_ok!(KittiesModule::do_try_state());
+    });
+}
+
+#[test]
+fn transfer_stops_at_recipient_kitty_cap() {
+    new_test_ext().execute_with(|| {
+        MAX_KITTIES_PER_OWNER.with(|v| *v.borrow_mut() = 1);
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::create(Origin::signed(101)));
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 0, Some(50), None, None));
+
+        assert_noop!(KittiesModule::transfer(Origin::signed(100), 0, 101), Error::<Test>::TooManyKitties);
+        assert_noop!(KittiesModule::buy(Origin::signed(101), 0, 50, None, None), Error::<Test>::TooManyKitties);
+
+        // room again once the recipient parts with its own kitty
+        assert_ok!(KittiesModule::transfer(Origin::signed(101), 1, 102));
+        assert_ok!(KittiesModule::transfer(Origin::signed(100), 0, 101));
+        assert_eq!(KittiesModule::owned_kitties_count(100), 0);
+        assert_eq!(KittiesModule::owned_kitties_count(101), 1);
+        assert!(!OwnedKittiesCount::<Test>::contains_key(100));
+        assert_ok!(KittiesModule::do_try_state());
+    });
+}
+
+#[test]
+fn owned_kitties_are_counted_on_upgrade() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::create(Origin::signed(101)));
+        OwnedKittiesCount::<Test>::remove(100);
+        OwnedKittiesCount::<Test>::remove(101);
+        StorageVersion::<DefaultInstance>::put(3);
+
+        migrations::count_owned_kitties::<Test, DefaultInstance>();
+        assert_eq!(KittiesModule::storage_version(), 4);
+        assert_eq!(KittiesModule::owned_kitties_count(100), 2);
+        assert_eq!(KittiesModule::owned_kitties_count(101), 1);
+        // nothing to do the second time
+        migrations::count_owned_kitties::<Test, DefaultInstance>();
+        assert_eq!(KittiesModule::owned_kitties_count(100), 2);
+    });
+}
+
+#[test]
+fn supply_cap_stops_create_and_breed() {
+    new_test_ext().execute_with(|| {
+        MAX_KITTY_SUPPLY.with(|v| *v.borrow_mut() = Some(3));
+        assert_eq!(KittiesModule::total_kitties(), 0);
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        System::set_extrinsic_index(1);
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        // exactly at the cap
+        assert_ok!(KittiesModule::breed(Origin::signed(100), 0, 1, None));
+        assert_eq!(KittiesModule::total_kitties(), 3);
+
+        assert_eq!(
+            KittiesModule::breed(Origin::signed(100), 0, 1, None),
+            Err(Error::<Test>::SupplyCapReached.into())
+        );
+        assert_noop!(KittiesModule::create(Origin::signed(101)), Error::<Test>::SupplyCapReached);
+        assert_eq!(KittiesModule::total_kitties(), 3);
+
+        MAX_KITTY_SUPPLY.with(|v| *v.borrow_mut() = None);
+        assert_ok!(KittiesModule::create(Origin::signed(101)));
+        assert_eq!(KittiesModule::total_kitties(), 4);
+    });
+}
+
+#[test]
+fn owner_can_burn_kitty() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 0, Some(50), None, None));
+
+        assert_noop!(KittiesModule::burn(Origin::signed(101), 0), Error::<Test>::KittenNotFound);
+        assert_noop!(KittiesModule::burn(Origin::signed(100), 1), Error::<Test>::KittenNotFound);
+
+        System::reset_events();
+        System::set_extrinsic_index(1);
+        assert_ok!(KittiesModule::burn(Origin::signed(100), 0));
+        let events: Vec<Event> = System::events().into_iter().map(|r| r.event).collect();
+        assert_eq!(events, vec![
+            Event::kitties(RawEvent::KittyPriceUpdated(100, 0, None, None, 0)),
+            Event::kitties(RawEvent::KittyBurned(100, 0, 1)),
+        ]);
+
+        assert!(KittiesModule::kitties(&100, 0).is_none());
+        assert_eq!(KittiesModule::listing(0), None);
+        assert_eq!(KittiesModule::kitty_creator(0), None);
+        assert_eq!(KittiesModule::owned_kitties_count(100), 0);
+        assert_eq!(KittiesModule::total_kitties(), 0);
+        assert_ok!(KittiesModule::do_try_state());
+
+        assert_noop!(KittiesModule::burn(Origin::signed(100), 0), Error::<Test>::KittenNotFound);
+    });
+}
+
+#[test]
+fn kitty_deposit_needs_free_funds() {
+    new_test_ext().execute_with(|| {
+        KITTY_DEPOSIT.with(|v| *v.borrow_mut() = 20_000);
+        assert_noop!(
+            KittiesModule::create(Origin::signed(100)),
+            pallet_balances::Error::<Test, pallet_balances::DefaultInstance>::InsufficientBalance
+        );
+        assert_eq!(KittiesModule::total_kitties(), 0);
+        assert_eq!(Balances::reserved_balance(100), 0);
+    });
+}
+
+#[test]
+fn kitty_deposit_stays_with_payer_until_burn() {
+    new_test_ext().execute_with(|| {
+        KITTY_DEPOSIT.with(|v| *v.borrow_mut() = 100);
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        System::set_extrinsic_index(1);
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::breed(Origin::signed(100), 0, 1, None));
+        assert_eq!(Balances::reserved_balance(100), 300);
+        assert_eq!(KittiesModule::kitty_deposit(2), Some((100, 100)));
+        assert_eq!(
+            KittiesModule::operation_cost(KittyCallKind::Create),
+            Some(CostBreakdown { reserved: 100, ..Default::default() })
+        );
+
+        assert_ok!(KittiesModule::transfer(Origin::signed(100), 2, 101));
+        assert_eq!(Balances::reserved_balance(100), 300);
+        assert_eq!(Balances::reserved_balance(101), 0);
+        assert_eq!(KittiesModule::kitty_deposit(2), Some((100, 100)));
+
+        System::set_extrinsic_index(2);
+        assert_ok!(KittiesModule::burn(Origin::signed(101), 2));
+        assert!(System::events().iter().any(|r| r.event
+            == Event::kitties(RawEvent::KittyDepositReleased(100, 2, 100, 0))));
+        assert_eq!(Balances::reserved_balance(100), 200);
+        assert_eq!(KittiesModule::kitty_deposit(2), None);
+        assert_ok!(KittiesModule::do_try_state());
+    });
+}
+
+#[test]
+fn create_fails_cleanly_without_creation_fee() {
+    new_test_ext().execute_with(|| {
+        CREATION_FEE.with(|v| *v.borrow_mut() = 10_000);
+        // the fee would reap the account
+        assert_noop!(KittiesModule::create(Origin::signed(100)), Error::<Test>::CannotPayCreationFee);
+        assert_eq!(KittiesModule::total_kitties(), 0);
+        assert_eq!(FEES_RECEIVED.with(|v| v.borrow().len()), 0);
+
+        CREATION_FEE.with(|v| *v.borrow_mut() = 9_999);
+        let issuance = Balances::total_issuance();
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_eq!(Balances::free_balance(100), 1);
+        assert_eq!(Balances::total_issuance(), issuance - 9_999);
+        assert_eq!(FEES_RECEIVED.with(|v| v.borrow().clone()), vec![9_999]);
+    });
+}
+
+#[test]
+fn creation_price_follows_bonding_curve() {
+    new_test_ext().execute_with(|| {
+        CREATION_FEE.with(|v| *v.borrow_mut() = 10);
+        assert_eq!(KittiesModule::creation_price(), 10);
+        CREATION_PRICE_STEP.with(|v| *v.borrow_mut() = 2);
+
+        let mut paid = Vec::new();
+        for index in 0..5u32 {
+            System::set_extrinsic_index(index);
+            let price = KittiesModule::creation_price();
+            let before = Balances::free_balance(100);
+            assert_ok!(KittiesModule::create(Origin::signed(100)));
+            assert_eq!(Balances::free_balance(100), before - price);
+            assert!(matches!(
+                last_event(),
+                Event::kitties(RawEvent::CreationFeePaid(100, kitty_id, fee, _)) if kitty_id == index && fee == price
+            ));
+            paid.push(price);
+        }
+        assert_eq!(paid, vec![10, 10, 20, 20, 30]);
+
+        // burned kitties still count
+        assert_ok!(KittiesModule::burn(Origin::signed(100), 4));
+        assert_eq!(KittiesModule::creation_price(), 30);
+        assert_eq!(KittiesModule::operation_cost(KittyCallKind::Create).unwrap().fee, 30);
+    });
+}
+
+fn next_block() {
+    let now = System::block_number();
+    KittiesModule::on_finalize(now);
+    System::set_block_number(now + 1);
+    KittiesModule::on_initialize(now + 1);
+}
+
+#[test]
+fn creates_are_rate_limited_per_block() {
+    new_test_ext().execute_with(|| {
+        MAX_CREATE_PER_BLOCK.with(|v| *v.borrow_mut() = 2);
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        System::set_extrinsic_index(1);
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_eq!(KittiesModule::kitties_created_this_block(), 2);
+        assert_noop!(KittiesModule::create(Origin::signed(101)), Error::<Test>::CreateRateLimited);
+        // breeds share the budget
+        assert_eq!(
+            KittiesModule::breed(Origin::signed(100), 0, 1, None),
+            Err(Error::<Test>::CreateRateLimited.into())
+        );
+
+        next_block();
+        assert_eq!(KittiesModule::kitties_created_this_block(), 0);
+        assert_ok!(KittiesModule::breed(Origin::signed(100), 0, 1, None));
+        assert_ok!(KittiesModule::create(Origin::signed(101)));
+        assert_noop!(KittiesModule::create(Origin::signed(101)), Error::<Test>::CreateRateLimited);
+    });
+}
+
+#[test]
+fn breeds_can_have_their_own_budget() {
+    new_test_ext().execute_with(|| {
+        MAX_CREATE_PER_BLOCK.with(|v| *v.borrow_mut() = 2);
+        MAX_BREED_PER_BLOCK.with(|v| *v.borrow_mut() = Some(1));
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        System::set_extrinsic_index(1);
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::breed(Origin::signed(100), 0, 1, None));
+        assert_eq!(KittiesModule::kitties_created_this_block(), 2);
+        assert_eq!(KittiesModule::kitties_bred_this_block(), 1);
+        assert_eq!(
+            KittiesModule::breed(Origin::signed(100), 0, 1, None),
+            Err(Error::<Test>::BreedRateLimited.into())
+        );
+
+        next_block();
+        assert_ok!(KittiesModule::breed(Origin::signed(100), 0, 1, None));
+        assert_eq!(KittiesModule::kitties_bred_this_block(), 1);
+    });
+}
+
+#[test]
+fn breeding_fee_is_charged() {
+    new_test_ext().execute_with(|| {
+        BREEDING_FEE.with(|v| *v.borrow_mut() = 100);
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        System::set_extrinsic_index(1);
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_eq!(
+            KittiesModule::operation_cost(KittyCallKind::Breed { gender_preference: true }).unwrap().fee,
+            110
+        );
+
+        assert_ok!(KittiesModule::breed(Origin::signed(100), 0, 1, Some(KittyGender::Male)));
+        assert_eq!(Balances::free_balance(100), 10_000 - 110);
+        assert_eq!(FEES_RECEIVED.with(|v| v.borrow().clone()), vec![110]);
+    });
+}
+
+#[test]
+fn breed_fails_cleanly_without_breeding_fee() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        System::set_extrinsic_index(1);
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        BREEDING_FEE.with(|v| *v.borrow_mut() = 10_000);
+        let next_id = Nft::next_token_id(KittiesModule::class_id());
+
+        assert_eq!(
+            KittiesModule::breed(Origin::signed(100), 0, 1, None),
+            Err(Error::<Test>::InsufficientBreedingFee.into())
+        );
+        assert_eq!(Nft::next_token_id(KittiesModule::class_id()), next_id);
+        assert!(KittiesModule::kitties(&100, 2).is_none());
+        assert_eq!(KittiesModule::owned_kitties_count(100), 2);
+        assert_eq!(Balances::free_balance(100), 10_000);
+    });
+}
+
+#[test]
+fn rebreed_voucher_waives_breeding_fee() {
+    new_test_ext().execute_with(|| {
+        BREEDING_FEE.with(|v| *v.borrow_mut() = 100);
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        System::set_extrinsic_index(1);
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        // a low novelty kitten, see `low_novelty_kitten_grants_rebreed_voucher`
+        System::set_extrinsic_index(9);
+        assert_ok!(KittiesModule::breed(Origin::signed(100), 0, 1, None));
+        assert_eq!(Balances::free_balance(100), 9_900);
+
+        System::set_extrinsic_index(7);
+        assert_ok!(KittiesModule::breed(Origin::signed(100), 0, 1, None));
+        assert_eq!(Balances::free_balance(100), 9_900);
+        assert_eq!(KittiesModule::rebreed_vouchers(100, (0, 1)), None);
+    });
+}
+
+#[test]
+fn kitties_can_parent_a_limited_number_of_kittens() {
+    new_test_ext().execute_with(|| {
+        MAX_BREEDS_PER_KITTY.with(|v| *v.borrow_mut() = 2);
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        System::set_extrinsic_index(1);
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_eq!(KittiesModule::remaining_breeds(0), 2);
+
+        assert_ok!(KittiesModule::breed(Origin::signed(100), 0, 1, None));
+        System::set_extrinsic_index(2);
+        assert_ok!(KittiesModule::breed(Origin::signed(100), 0, 1, None));
+        assert_eq!(KittiesModule::remaining_breeds(0), 0);
+        assert_eq!(KittiesModule::remaining_breeds(1), 0);
+        assert_noop!(KittiesModule::breed(Origin::signed(100), 0, 1, None), Error::<Test>::KittyExhausted);
+
+        // the count stays with the kitty when it changes hands
+        assert_ok!(KittiesModule::transfer(Origin::signed(100), 0, 101));
+        System::set_extrinsic_index(3);
+        assert_ok!(KittiesModule::create(Origin::signed(101)));
+        assert_eq!(KittiesModule::remaining_breeds(4), 2);
+        assert_noop!(KittiesModule::breed(Origin::signed(101), 4, 0, None), Error::<Test>::KittyExhausted);
+
+        // kittens of the exhausted kitties start afresh
+        assert_eq!(KittiesModule::remaining_breeds(2), 2);
+    });
+}
+
+#[test]
+fn kitties_breed_and_sell_once_mature() {
+    new_test_ext().execute_with(|| {
+        MATURITY_PERIOD.with(|v| *v.borrow_mut() = 5);
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        System::set_extrinsic_index(1);
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_eq!(KittiesModule::age_of(0), 0);
+
+        assert_noop!(KittiesModule::breed(Origin::signed(100), 0, 1, None), Error::<Test>::KittyTooYoung);
+        assert_noop!(KittiesModule::set_price(Origin::signed(100), 0, Some(50), None, None), Error::<Test>::KittyTooYoung);
+        // delisting is always allowed
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 0, None, None, None));
+
+        System::set_block_number(6);
+        assert_eq!(KittiesModule::age_of(0), 5);
+        assert_ok!(KittiesModule::breed(Origin::signed(100), 0, 1, None));
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 0, Some(50), None, None));
+
+        // the kitten has to grow up as well
+        assert_eq!(KittiesModule::age_of(2), 0);
+        assert_noop!(KittiesModule::breed(Origin::signed(100), 2, 0, None), Error::<Test>::KittyTooYoung);
+        assert_noop!(KittiesModule::set_price(Origin::signed(100), 2, Some(50), None, None), Error::<Test>::KittyTooYoung);
+        System::set_block_number(11);
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 2, Some(50), None, None));
+    });
+}
+
+#[test]
+fn related_kitties_cant_breed() {
+    new_test_ext().execute_with(|| {
+        // female, male
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        System::set_extrinsic_index(1);
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_eq!(KittiesModule::kitty_parents(0), None);
+
+        assert_ok!(KittiesModule::breed(Origin::signed(100), 0, 1, None));
+        System::set_extrinsic_index(3);
+        assert_ok!(KittiesModule::breed(Origin::signed(100), 0, 1, Some(KittyGender::Female)));
+        assert_eq!(KittiesModule::kitty_parents(2), Some((0, 1)));
+        assert_eq!(KittiesModule::kitty_parents(3), Some((0, 1)));
+
+        // parent and child
+        assert_noop!(KittiesModule::breed(Origin::signed(100), 3, 1, None), Error::<Test>::RelatedKitties);
+        assert_noop!(KittiesModule::breed(Origin::signed(100), 0, 2, None), Error::<Test>::RelatedKitties);
+        // siblings
+        assert_noop!(KittiesModule::breed(Origin::signed(100), 2, 3, None), Error::<Test>::RelatedKitties);
+        assert!(KittiesModule::related(2, 3));
+
+        // unrelated: a created male and the female kitten
+        System::set_extrinsic_index(8);
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert!(!KittiesModule::related(3, 4));
+        assert_ok!(KittiesModule::breed(Origin::signed(100), 3, 4, None));
+        assert_eq!(KittiesModule::kitty_parents(5), Some((3, 4)));
+    });
+}
+
+#[test]
+fn breed_events_carry_the_generation() {
+    new_test_ext().execute_with(|| {
+        // female, male
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        System::set_extrinsic_index(1);
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_eq!(KittiesModule::generation(0), 0);
+
+        assert_ok!(KittiesModule::breed(Origin::signed(100), 0, 1, None));
+        assert_eq!(KittiesModule::generation(2), 1);
+        assert!(System::events().iter().any(|r| matches!(
+            r.event,
+            Event::kitties(RawEvent::KittyBreed(100, _, _, 2, 1, _))
+        )));
+
+        // an unrelated partner of the other gender: extrinsic 2 creates a female, 8 a male
+        let kitten_is_male = KittiesModule::kitties(&100, 2).unwrap().gender() == KittyGender::Male;
+        System::set_extrinsic_index(if kitten_is_male { 2 } else { 8 });
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_eq!(KittiesModule::generation(3), 0);
+        assert_ok!(KittiesModule::breed(Origin::signed(100), 3, 2, None));
+        assert_eq!(KittiesModule::generation(4), 2);
+        assert!(System::events().iter().any(|r| matches!(
+            r.event,
+            Event::kitties(RawEvent::KittyBreed(100, _, _, 4, 2, _))
+        )));
+    });
+}
+
+#[test]
+fn bred_genes_can_mutate() {
+    new_test_ext().execute_with(|| {
+        MUTATION_CHANCE.with(|v| *v.borrow_mut() = Perbill::one());
+        // female, male
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        System::set_extrinsic_index(1);
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+
+        assert_ok!(KittiesModule::breed(Origin::signed(100), 0, 1, None));
+        // every gene comes from the mutation roll rather than a parent
+        let seed = KittiesModule::random_value_for(&100, b"kitties/mutation");
+        let mut expected = [0u8; 16];
+        assert_eq!(mutate_dna(&mut expected, &seed, Perbill::one()), 16);
+        assert_eq!(KittiesModule::kitties(&100, 2).unwrap().dna(), expected);
+        assert!(System::events().iter().any(|r| matches!(
+            r.event,
+            Event::kitties(RawEvent::KittyMutated(100, 2, 16, _))
+        )));
+        assert_eq!(KittiesModule::sire_stats(0).mewtations, 1);
+        assert_eq!(KittiesModule::sire_stats(1).mewtations, 1);
+    });
+}
+
+#[test]
+fn colliding_dna_is_rederived() {
+    new_test_ext().execute_with(|| {
+        // the same account in the same extrinsic draws the same genome twice
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        let first = KittiesModule::kitties(&100, 0).unwrap();
+        let second = KittiesModule::kitties(&100, 1).unwrap();
+        assert_ne!(first.dna(), second.dna());
+        assert_eq!(second.gender(), first.gender());
+        let mut expected = (first.dna(), 1u32, 1u8).using_encoded(blake2_128);
+        expected[0] = (expected[0] & !1) | (first.dna()[0] & 1);
+        assert_eq!(second.dna(), expected);
+        assert_eq!(KittiesModule::kitty_with_dna(first.dna()), Some(0));
+        assert_eq!(KittiesModule::kitty_with_dna(second.dna()), Some(1));
+
+        // every re-derivation taken as well
+        for nonce in 1..=MAX_DNA_RETRIES {
+            let mut candidate = (first.dna(), 2u32, nonce).using_encoded(blake2_128);
+            candidate[0] = (candidate[0] & !1) | (first.dna()[0] & 1);
+            KittyDna::<Test>::insert(candidate, 0);
+        }
+        assert_noop!(KittiesModule::create(Origin::signed(100)), Error::<Test>::WrongDNA);
+    });
+}
+
+#[test]
+fn burned_dna_can_be_minted_again() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        let dna = KittiesModule::kitties(&100, 0).unwrap().dna();
+        assert_ok!(KittiesModule::burn(Origin::signed(100), 0));
+        assert_eq!(KittiesModule::kitty_with_dna(dna), None);
+
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_eq!(KittiesModule::kitties(&100, 1).unwrap().dna(), dna);
+        assert_eq!(KittiesModule::kitty_with_dna(dna), Some(1));
+    });
+}
+
+#[test]
+fn kitty_dna_is_registered_on_upgrade() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        System::set_extrinsic_index(1);
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        let dna = KittiesModule::kitties(&100, 1).unwrap().dna();
+        KittyDna::<Test>::remove(KittiesModule::kitties(&100, 0).unwrap().dna());
+        KittyDna::<Test>::remove(dna);
+        StorageVersion::<DefaultInstance>::put(4);
+
+        migrations::register_kitty_dna::<Test, DefaultInstance>();
+        assert_eq!(KittiesModule::storage_version(), 5);
+        assert_eq!(KittiesModule::kitty_with_dna(dna), Some(1));
+        assert_eq!(KittyDna::<Test>::iter().count(), 2);
+        assert_ok!(KittiesModule::do_try_state());
+    });
+}
+
+#[test]
+fn force_create_mints_exact_dna() {
+    new_test_ext().execute_with(|| {
+        let dna = [42u8; 16];
+        assert_noop!(KittiesModule::force_create(Origin::signed(100), 101, dna), DispatchError::BadOrigin);
+
+        assert_ok!(KittiesModule::force_create(Origin::root(), 101, dna));
+        assert_eq!(KittiesModule::kitties(&101, 0), Some(Kitty(dna)));
+        assert_eq!(Balances::free_balance(101), 10_000);
+        assert_eq!(last_event(), Event::kitties(RawEvent::KittyCreated(101, 0, Kitty(dna), 0)));
+
+        // the genome is taken now, and the owner limit holds
+        assert_noop!(KittiesModule::force_create(Origin::root(), 102, dna), Error::<Test>::WrongDNA);
+        MAX_KITTIES_PER_OWNER.with(|v| *v.borrow_mut() = 1);
+        assert_noop!(KittiesModule::force_create(Origin::root(), 101, [43u8; 16]), Error::<Test>::TooManyKitties);
+    });
+}
+
+#[test]
+fn force_transfer_moves_kitties_without_the_owner() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 0, Some(50), None, None));
+        assert_noop!(KittiesModule::force_transfer(Origin::signed(101), 100, 101, 0), DispatchError::BadOrigin);
+        assert_noop!(
+            KittiesModule::force_transfer(Origin::root(), 102, 101, 0),
+            Error::<Test>::KittenNotFound
+        );
+
+        System::set_extrinsic_index(1);
+        assert_ok!(KittiesModule::force_transfer(Origin::root(), 100, 101, 0));
+        assert_eq!(KittiesModule::owner_of(0), Some(101));
+        assert_eq!(KittiesModule::listing(0), None);
+        assert!(System::events().iter().any(|r| r.event
+            == Event::kitties(RawEvent::KittyPriceUpdated(100, 0, None, None, 0))));
+        assert_eq!(last_event(), Event::kitties(RawEvent::KittyTransferred(100, 101, 0, 1)));
+
+        // a governance account may do the same
+        assert_ok!(KittiesModule::force_transfer(Origin::signed(GOVERNOR), 101, 102, 0));
+        assert_eq!(KittiesModule::owner_of(0), Some(102));
+    });
+}
+
+#[test]
+fn pause_stops_minting_and_trading() {
+    new_test_ext().execute_with(|| {
+        // female, male
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        System::set_extrinsic_index(1);
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 0, Some(50), None, None));
+
+        assert_noop!(KittiesModule::pause(Origin::signed(100)), DispatchError::BadOrigin);
+        assert_ok!(KittiesModule::pause(Origin::root()));
+        assert!(KittiesModule::paused());
+        assert!(matches!(last_event(), Event::kitties(RawEvent::Paused(_))));
+
+        assert_noop!(KittiesModule::create(Origin::signed(100)), Error::<Test>::PalletPaused);
+        assert_noop!(KittiesModule::breed(Origin::signed(100), 0, 1, None), Error::<Test>::PalletPaused);
+        assert_noop!(KittiesModule::set_price(Origin::signed(100), 1, Some(50), None, None), Error::<Test>::PalletPaused);
+        assert_noop!(KittiesModule::buy(Origin::signed(101), 0, 50, None, None), Error::<Test>::PalletPaused.with_weight(DbWeight::get().reads(3)));
+        // assets can still be moved and delisted
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 0, None, None, None));
+        assert_ok!(KittiesModule::transfer(Origin::signed(100), 1, 101));
+
+        assert_noop!(KittiesModule::unpause(Origin::signed(100)), DispatchError::BadOrigin);
+        assert_ok!(KittiesModule::unpause(Origin::root()));
+        assert!(!KittiesModule::paused());
+        assert!(matches!(last_event(), Event::kitties(RawEvent::Unpaused(_))));
+
+        System::set_extrinsic_index(2);
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 0, Some(50), None, None));
+        assert_ok!(KittiesModule::buy(Origin::signed(101), 0, 50, None, None));
+        assert_ok!(KittiesModule::breed(Origin::signed(101), 0, 1, None));
+    });
+}
+
+#[test]
+fn delegates_can_move_approved_kitties() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_noop!(KittiesModule::approve(Origin::signed(101), 0, Some(101)), Error::<Test>::KittenNotFound);
+
+        // strangers can't, the owner can
+        assert_noop!(KittiesModule::transfer_from(Origin::signed(101), 100, 101, 0), Error::<Test>::NotApproved);
+        assert_ok!(KittiesModule::transfer_from(Origin::signed(100), 100, 102, 1));
+        assert_eq!(KittiesModule::owner_of(1), Some(102));
+
+        assert_ok!(KittiesModule::approve(Origin::signed(100), 0, Some(101)));
+        assert_eq!(KittiesModule::kitty_approval(0), Some(101));
+        assert!(matches!(last_event(), Event::kitties(RawEvent::KittyApproved(100, 0, Some(101), _))));
+        assert_noop!(KittiesModule::transfer_from(Origin::signed(102), 100, 102, 0), Error::<Test>::NotApproved);
+
+        System::set_extrinsic_index(1);
+        assert_ok!(KittiesModule::transfer_from(Origin::signed(101), 100, 102, 0));
+        assert_eq!(KittiesModule::owner_of(0), Some(102));
+        assert!(System::events().iter().any(|r| r.event
+            == Event::kitties(RawEvent::KittyTransferred(100, 102, 0, 0))));
+        assert_eq!(last_event(), Event::kitties(RawEvent::TransferredByDelegate(101, 0, 1)));
+        // the approval went with the move
+        assert_eq!(KittiesModule::kitty_approval(0), None);
+        assert_noop!(KittiesModule::transfer_from(Origin::signed(101), 102, 101, 0), Error::<Test>::NotApproved);
+    });
+}
+
+#[test]
+fn approvals_are_cleared_by_sales_and_revocation() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::approve(Origin::signed(100), 0, Some(102)));
+        assert_ok!(KittiesModule::approve(Origin::signed(100), 0, None));
+        assert_eq!(KittiesModule::kitty_approval(0), None);
+        assert_noop!(KittiesModule::transfer_from(Origin::signed(102), 100, 102, 0), Error::<Test>::NotApproved);
+
+        assert_ok!(KittiesModule::approve(Origin::signed(100), 0, Some(102)));
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 0, Some(50), None, None));
+        assert_ok!(KittiesModule::buy(Origin::signed(101), 0, 50, None, None));
+        assert_eq!(KittiesModule::kitty_approval(0), None);
+        assert_noop!(KittiesModule::transfer_from(Origin::signed(102), 101, 102, 0), Error::<Test>::NotApproved);
+    });
+}
+
+#[test]
+fn approved_partners_breed_once() {
+    new_test_ext().execute_with(|| {
+        // a female of 100 and a male of 101
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::create(Origin::signed(101)));
+        assert_noop!(
+            KittiesModule::breed_with(Origin::signed(101), 1, 100, 0),
+            Error::<Test>::BreedingNotApproved
+        );
+        assert_noop!(KittiesModule::approve_breeding(Origin::signed(101), 0, Some(101)), Error::<Test>::KittenNotFound);
+
+        assert_ok!(KittiesModule::approve_breeding(Origin::signed(100), 0, Some(101)));
+        assert_eq!(KittiesModule::siring_approval(0), Some(101));
+        assert_noop!(
+            KittiesModule::breed_with(Origin::signed(102), 1, 100, 0),
+            Error::<Test>::BreedingNotApproved
+        );
+
+        // the gender check still applies, and a failed breed keeps the approval
+        System::set_extrinsic_index(5);
+        assert_ok!(KittiesModule::create(Origin::signed(101)));
+        assert_noop!(KittiesModule::breed_with(Origin::signed(101), 2, 100, 0), Error::<Test>::SameGenderBreed);
+
+        System::set_extrinsic_index(1);
+        assert_ok!(KittiesModule::breed_with(Origin::signed(101), 1, 100, 0));
+        assert_eq!(KittiesModule::owner_of(3), Some(101));
+        assert_eq!(KittiesModule::owner_of(0), Some(100));
+        assert_eq!(KittiesModule::kitty_parents(3), Some((1, 0)));
+        assert_eq!(KittiesModule::siring_approval(0), None);
+        assert_noop!(
+            KittiesModule::breed_with(Origin::signed(101), 1, 100, 0),
+            Error::<Test>::BreedingNotApproved
+        );
+    });
+}
+
+#[test]
+fn breeding_approvals_can_be_revoked() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::create(Origin::signed(101)));
+        assert_ok!(KittiesModule::approve_breeding(Origin::signed(100), 0, Some(101)));
+        assert_ok!(KittiesModule::approve_breeding(Origin::signed(100), 0, None));
+        assert!(matches!(last_event(), Event::kitties(RawEvent::BreedingApproved(100, 0, None, _))));
+        assert_noop!(
+            KittiesModule::breed_with(Origin::signed(101), 1, 100, 0),
+            Error::<Test>::BreedingNotApproved
+        );
+
+        // and end when the kitty changes hands
+        assert_ok!(KittiesModule::approve_breeding(Origin::signed(100), 0, Some(101)));
+        assert_ok!(KittiesModule::transfer(Origin::signed(100), 0, 102));
+        assert_eq!(KittiesModule::siring_approval(0), None);
+    });
+}
+
+#[test]
+fn studs_sire_for_a_fee() {
+    new_test_ext().execute_with(|| {
+        // a female of 100 and a male stud of 101
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::create(Origin::signed(101)));
+        assert_noop!(
+            KittiesModule::breed_with_stud(Origin::signed(100), 0, 101, 1, 100),
+            Error::<Test>::NotForSale
+        );
+        assert_noop!(KittiesModule::set_siring_price(Origin::signed(100), 1, Some(100)), Error::<Test>::KittenNotFound);
+        assert_ok!(KittiesModule::set_siring_price(Origin::signed(101), 1, Some(100)));
+        assert_eq!(KittiesModule::siring_price(1), Some(100));
+
+        assert_noop!(
+            KittiesModule::breed_with_stud(Origin::signed(100), 0, 101, 1, 99),
+            Error::<Test>::PriceTooLow
+        );
+        System::set_extrinsic_index(1);
+        assert_ok!(KittiesModule::breed_with_stud(Origin::signed(100), 0, 101, 1, 100));
+        assert_eq!(KittiesModule::owner_of(2), Some(100));
+        assert_eq!(Balances::free_balance(101), 10_100);
+        assert!(System::events().iter().any(|r| matches!(
+            r.event,
+            Event::kitties(RawEvent::StudFeePaid(100, 101, 1, 100, _))
+        )));
+        // the stud stays on offer
+        assert_eq!(KittiesModule::siring_price(1), Some(100));
+
+        // delisted before the next breed
+        assert_ok!(KittiesModule::set_siring_price(Origin::signed(101), 1, None));
+        assert_noop!(
+            KittiesModule::breed_with_stud(Origin::signed(100), 0, 101, 1, 100),
+            Error::<Test>::NotForSale
+        );
+    });
+}
+
+#[test]
+fn stud_owners_breed_their_stud_for_free() {
+    new_test_ext().execute_with(|| {
+        // a male stud and a female of 101
+        assert_ok!(KittiesModule::create(Origin::signed(101)));
+        System::set_extrinsic_index(5);
+        assert_ok!(KittiesModule::create(Origin::signed(101)));
+        assert_ok!(KittiesModule::set_siring_price(Origin::signed(101), 0, Some(100)));
+
+        System::set_extrinsic_index(6);
+        assert_ok!(KittiesModule::breed_with_stud(Origin::signed(101), 1, 101, 0, 0));
+        assert_eq!(KittiesModule::owner_of(2), Some(101));
+        assert!(!System::events().iter().any(|r| matches!(r.event, Event::kitties(RawEvent::StudFeePaid(..)))));
+        assert_eq!(KittiesModule::flow_total(FlowKind::StudFee), 0);
+    });
+}
+
+#[test]
+fn renters_breed_with_rented_kitties() {
+    new_test_ext().execute_with(|| {
+        // a female of 100 and a male of 101
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::create(Origin::signed(101)));
+        assert_noop!(KittiesModule::rent(Origin::signed(100), 1, 2), Error::<Test>::NotForRent);
+        assert_noop!(KittiesModule::list_for_rent(Origin::signed(100), 1, 10, 3), Error::<Test>::KittenNotFound);
+        assert_noop!(KittiesModule::list_for_rent(Origin::signed(101), 1, 10, 0), Error::<Test>::InvalidRental);
+        assert_ok!(KittiesModule::list_for_rent(Origin::signed(101), 1, 10, 3));
+        assert_eq!(KittiesModule::rental_terms(1), Some(RentalTerms { price_per_period: 10, max_duration: 3 }));
+
+        assert_noop!(KittiesModule::rent(Origin::signed(101), 1, 1), Error::<Test>::InvalidRental);
+        assert_noop!(KittiesModule::rent(Origin::signed(100), 1, 0), Error::<Test>::InvalidRental);
+        assert_noop!(KittiesModule::rent(Origin::signed(100), 1, 4), Error::<Test>::InvalidRental);
+        assert_noop!(KittiesModule::breed(Origin::signed(100), 0, 1, None), Error::<Test>::KittenNotFound);
+
+        let (renter, owner) = (Balances::free_balance(100), Balances::free_balance(101));
+        assert_ok!(KittiesModule::rent(Origin::signed(100), 1, 2));
+        // two periods of 10 blocks, paid up front
+        assert_eq!(KittiesModule::active_rental(1), Some(Rental { renter: 100, ends_at: 21 }));
+        assert_eq!(Balances::free_balance(100), renter - 20);
+        assert_eq!(Balances::free_balance(101), owner + 20);
+        assert!(matches!(last_event(), Event::kitties(RawEvent::KittyRented(101, 100, 1, 2, 20, 21, _))));
+        assert_eq!(KittiesModule::owner_of(1), Some(101));
+        assert_noop!(KittiesModule::rent(Origin::signed(102), 1, 1), Error::<Test>::KittyRented);
+
+        System::set_extrinsic_index(1);
+        assert_ok!(KittiesModule::breed(Origin::signed(100), 0, 1, None));
+        assert_eq!(KittiesModule::owner_of(2), Some(100));
+
+        // neither the owner nor the renter can move it meanwhile
+        assert_noop!(KittiesModule::transfer(Origin::signed(101), 1, 102), Error::<Test>::KittyRented);
+        assert_noop!(KittiesModule::set_price(Origin::signed(101), 1, Some(50), None, None), Error::<Test>::KittyRented);
+        assert_noop!(KittiesModule::burn(Origin::signed(101), 1), Error::<Test>::KittyRented);
+        assert_noop!(KittiesModule::transfer(Origin::signed(100), 1, 102), Error::<Test>::KittyRented);
+        assert_noop!(KittiesModule::set_price(Origin::signed(100), 1, Some(50), None, None), Error::<Test>::KittenNotFound);
+        assert_ok!(KittiesModule::do_try_state());
+    });
+}
+
+#[test]
+fn rentals_end_on_their_own() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::create(Origin::signed(101)));
+        assert_ok!(KittiesModule::list_for_rent(Origin::signed(101), 1, 10, 3));
+        assert_ok!(KittiesModule::rent(Origin::signed(100), 1, 2));
+
+        // missed by the sweep, the rental is over all the same and the next renter replaces it
+        System::set_block_number(21);
+        assert_eq!(KittiesModule::active_rental(1), None);
+        assert_ok!(KittiesModule::rent(Origin::signed(102), 1, 1));
+        assert!(System::events().iter().any(|r| matches!(
+            r.event,
+            Event::kitties(RawEvent::RentalEnded(100, 1, _))
+        )));
+        assert_eq!(KittiesModule::kitty_rental(1), Some(Rental { renter: 102, ends_at: 31 }));
+        assert_ok!(KittiesModule::do_try_state());
+
+        System::set_block_number(31);
+        KittiesModule::on_initialize(31);
+        assert_eq!(KittiesModule::kitty_rental(1), None);
+        assert!(matches!(last_event(), Event::kitties(RawEvent::RentalEnded(102, 1, _))));
+        assert_noop!(KittiesModule::breed(Origin::signed(100), 0, 1, None), Error::<Test>::KittenNotFound);
+
+        // back with its owner, who can move it again
+        assert_ok!(KittiesModule::unlist_for_rent(Origin::signed(101), 1));
+        assert_noop!(KittiesModule::unlist_for_rent(Origin::signed(101), 1), Error::<Test>::NotForRent);
+        assert_ok!(KittiesModule::transfer(Origin::signed(101), 1, 100));
+        assert_eq!(KittiesModule::owner_of(1), Some(100));
+        assert_ok!(KittiesModule::do_try_state());
+    });
+}
+
+#[test]
+fn streamed_rent_is_earned_as_the_rental_runs() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::create(Origin::signed(101)));
+        assert_ok!(KittiesModule::list_for_rent(Origin::signed(101), 1, 7, 3));
+
+        let (renter, owner) = (Balances::free_balance(100), Balances::free_balance(101));
+        assert_ok!(KittiesModule::rent_streamed(Origin::signed(100), 1, 3));
+        // three periods of 10 blocks, 21 held back until they are earned
+        assert_eq!(KittiesModule::active_rental(1), Some(Rental { renter: 100, ends_at: 31 }));
+        assert!(matches!(last_event(), Event::kitties(RawEvent::KittyRentedStreamed(101, 100, 1, 3, 21, 31, _))));
+        assert_eq!(Balances::free_balance(100), renter - 21);
+        assert_eq!(Balances::reserved_balance(100), 21);
+        assert_noop!(KittiesModule::claim_lease_income(Origin::signed(100), 1), Error::<Test>::NotRentalParty);
+        assert_noop!(KittiesModule::claim_lease_income(Origin::signed(101), 0), Error::<Test>::NoRentEscrow);
+
+        // nothing earned yet
+        assert_ok!(KittiesModule::claim_lease_income(Origin::signed(101), 1));
+        assert!(matches!(last_event(), Event::kitties(RawEvent::RentIncomeClaimed(101, 1, 0, _))));
+        assert_eq!(Balances::free_balance(101), owner);
+
+        System::set_block_number(11);
+        assert_ok!(KittiesModule::claim_lease_income(Origin::signed(101), 1));
+        assert_eq!(Balances::free_balance(101), owner + 7);
+        // 10.5 earned, the half stays with the renter for now
+        System::set_block_number(16);
+        assert_ok!(KittiesModule::claim_lease_income(Origin::signed(101), 1));
+        assert!(matches!(last_event(), Event::kitties(RawEvent::RentIncomeClaimed(101, 1, 3, _))));
+        assert_eq!(Balances::free_balance(101), owner + 10);
+        assert_eq!(Balances::reserved_balance(100), 11);
+        assert_ok!(KittiesModule::do_try_state());
+
+        // the rest is paid out as the rental ends
+        System::set_block_number(31);
+        KittiesModule::on_initialize(31);
+        assert!(System::events().iter().any(|r| matches!(
+            r.event,
+            Event::kitties(RawEvent::RentEscrowSettled(101, 100, 1, 11, 0, _))
+        )));
+        assert!(matches!(last_event(), Event::kitties(RawEvent::RentalEnded(100, 1, _))));
+        assert_eq!(KittiesModule::rent_escrow(1), None);
+        assert_eq!(Balances::free_balance(101), owner + 21);
+        assert_eq!(Balances::free_balance(100), renter - 21);
+        assert_eq!(Balances::reserved_balance(100), 0);
+        assert_eq!(KittiesModule::kitty_reserved(100), 0);
+        assert_noop!(KittiesModule::claim_lease_income(Origin::signed(101), 1), Error::<Test>::NoRentEscrow);
+        assert_ok!(KittiesModule::do_try_state());
+    });
+}
+
+#[test]
+fn streamed_rentals_end_early_with_the_unearned_rent_refunded() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::create(Origin::signed(101)));
+        assert_ok!(KittiesModule::list_for_rent(Origin::signed(101), 1, 7, 3));
+
+        let (renter, owner) = (Balances::free_balance(100), Balances::free_balance(101));
+        assert_ok!(KittiesModule::rent_streamed(Origin::signed(100), 1, 3));
+        assert_noop!(KittiesModule::end_lease(Origin::signed(102), 1), Error::<Test>::NotRentalParty);
+
+        // 2.8 earned
+        System::set_block_number(5);
+        assert_ok!(KittiesModule::claim_lease_income(Origin::signed(101), 1));
+        assert_eq!(Balances::free_balance(101), owner + 2);
+
+        // 7.7 earned, the renter stops it and gets the other 14 back
+        System::set_block_number(12);
+        assert_ok!(KittiesModule::end_lease(Origin::signed(100), 1));
+        assert!(System::events().iter().any(|r| matches!(
+            r.event,
+            Event::kitties(RawEvent::RentEscrowSettled(101, 100, 1, 5, 14, _))
+        )));
+        assert!(matches!(last_event(), Event::kitties(RawEvent::RentalEnded(100, 1, _))));
+        assert_eq!(KittiesModule::active_rental(1), None);
+        assert_eq!(KittiesModule::rent_escrow(1), None);
+        assert_eq!(Balances::free_balance(101), owner + 7);
+        assert_eq!(Balances::free_balance(100), renter - 7);
+        assert_eq!(Balances::reserved_balance(100), 0);
+        assert_noop!(KittiesModule::end_lease(Origin::signed(101), 1), Error::<Test>::NoRentEscrow);
+        assert_noop!(KittiesModule::breed(Origin::signed(100), 0, 1, None), Error::<Test>::KittenNotFound);
+
+        // the owner has it back, the sweep at the old end leaves the next rental alone
+        assert_ok!(KittiesModule::transfer(Origin::signed(101), 1, 102));
+        assert_ok!(KittiesModule::list_for_rent(Origin::signed(102), 1, 7, 3));
+        assert_ok!(KittiesModule::rent_streamed(Origin::signed(100), 1, 3));
+        System::set_block_number(31);
+        KittiesModule::on_initialize(31);
+        assert_eq!(KittiesModule::active_rental(1), Some(Rental { renter: 100, ends_at: 42 }));
+        assert!(KittiesModule::rent_escrow(1).is_some());
+        assert_ok!(KittiesModule::do_try_state());
+    });
+}
+
+#[test]
+fn locked_kitties_stay_put() {
+    new_test_ext().execute_with(|| {
+        // a female of 100 and a male of 101, the female listed
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::create(Origin::signed(101)));
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 0, Some(100), None, None));
+        assert_noop!(<KittiesModule as LockableKitties<u64>>::lock(5, *b"staking "), Error::<Test>::KittenNotFound);
+        assert_ok!(<KittiesModule as LockableKitties<u64>>::lock(0, *b"staking "));
+        assert!(<KittiesModule as LockableKitties<u64>>::is_locked(0));
+        assert_eq!(<KittiesModule as LockableKitties<u64>>::owner_of(0), Some(100));
+        // locks don't stack
+        assert_noop!(<KittiesModule as LockableKitties<u64>>::lock(0, *b"staking "), Error::<Test>::KittyLocked);
+        assert_noop!(<KittiesModule as LockableKitties<u64>>::lock(0, *b"lending "), Error::<Test>::KittyLocked);
+
+        assert_noop!(KittiesModule::transfer(Origin::signed(100), 0, 102), Error::<Test>::KittyLocked);
+        assert_noop!(KittiesModule::set_price(Origin::signed(100), 0, Some(50), None, None), Error::<Test>::KittyLocked);
+        assert_noop!(KittiesModule::buy(Origin::signed(102), 0, 100, None, None), Error::<Test>::KittyLocked);
+        System::set_extrinsic_index(1);
+        assert_ok!(KittiesModule::set_siring_price(Origin::signed(101), 1, Some(10)));
+        assert_noop!(
+            KittiesModule::breed_with_stud(Origin::signed(100), 0, 101, 1, 10),
+            Error::<Test>::KittyLocked
+        );
+        assert_ok!(KittiesModule::do_try_state());
+
+        // only the reason it was locked for releases it
+        assert_noop!(<KittiesModule as LockableKitties<u64>>::unlock(0, *b"lending "), Error::<Test>::NotLocked);
+        assert_ok!(<KittiesModule as LockableKitties<u64>>::unlock(0, *b"staking "));
+        assert!(!<KittiesModule as LockableKitties<u64>>::is_locked(0));
+        assert_noop!(<KittiesModule as LockableKitties<u64>>::unlock(0, *b"staking "), Error::<Test>::NotLocked);
+        assert_ok!(KittiesModule::buy(Origin::signed(102), 0, 100, None, None));
+        assert_eq!(KittiesModule::owner_of(0), Some(102));
+    });
+}
+
+#[test]
+fn locked_kitties_can_breed_if_configured() {
+    new_test_ext().execute_with(|| {
+        LOCKS_BLOCK_BREEDING.with(|v| *v.borrow_mut() = false);
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::create(Origin::signed(101)));
+        assert_ok!(<KittiesModule as LockableKitties<u64>>::lock(0, *b"staking "));
+        assert_ok!(KittiesModule::set_siring_price(Origin::signed(101), 1, Some(10)));
+
+        System::set_extrinsic_index(1);
+        assert_ok!(KittiesModule::breed_with_stud(Origin::signed(100), 0, 101, 1, 10));
+        assert_eq!(KittiesModule::owner_of(2), Some(100));
+        assert_noop!(KittiesModule::transfer(Origin::signed(100), 0, 102), Error::<Test>::KittyLocked);
+    });
+}
+
+#[test]
+fn hooks_hear_of_every_mint_transfer_and_burn() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::create(Origin::signed(101)));
+        assert_ok!(KittiesModule::set_siring_price(Origin::signed(101), 1, Some(10)));
+        System::set_extrinsic_index(1);
+        assert_ok!(KittiesModule::breed_with_stud(Origin::signed(100), 0, 101, 1, 10));
+        assert_ok!(KittiesModule::transfer(Origin::signed(100), 2, 102));
+        assert_ok!(KittiesModule::set_price(Origin::signed(102), 2, Some(100), None, None));
+        assert_ok!(KittiesModule::buy(Origin::signed(101), 2, 100, None, None));
+        assert_ok!(KittiesModule::burn(Origin::signed(101), 2));
+
+        assert_eq!(hook_calls(), vec![
+            HookCall::Minted(100, 0),
+            HookCall::Minted(101, 1),
+            HookCall::Minted(100, 2),
+            HookCall::Transferred(100, 102, 2),
+            HookCall::Transferred(102, 101, 2),
+            HookCall::Burned(101, 2),
+        ]);
+    });
+}
+
+#[test]
+fn hooks_can_veto_transfers() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 0, Some(100), None, None));
+        REJECT_TRANSFERS_TO.with(|v| *v.borrow_mut() = Some(102));
+
+        assert_noop!(KittiesModule::transfer(Origin::signed(100), 0, 102), DispatchError::Other("transfer rejected"));
+        // the sale is reverted along with the transfer
+        assert_noop!(KittiesModule::buy(Origin::signed(102), 0, 100, None, None), DispatchError::Other("transfer rejected"));
+        assert_eq!(KittiesModule::kitty_prices(0), Some(100));
+
+        assert_ok!(KittiesModule::buy(Origin::signed(101), 0, 100, None, None));
+        assert_eq!(KittiesModule::owner_of(0), Some(101));
+        assert_eq!(hook_calls(), vec![HookCall::Minted(100, 0), HookCall::Transferred(100, 101, 0)]);
+    });
+}
+
+#[test]
+fn bridges_move_kitties_through_the_provider() {
+    new_test_ext().execute_with(|| {
+        type KittyBridge = Bridge<KittiesModule>;
+        let kitty_id = KittyBridge::bring_in(&100, Kitty([1; 16])).unwrap();
+        assert_eq!(KittiesModule::owner_of(kitty_id), Some(100));
+        assert_eq!(KittiesModule::owned_kitties_count(100), 1);
+        assert!(matches!(last_event(), Event::kitties(RawEvent::KittyCreated(100, 0, _, _))));
+        assert_noop!(KittyBridge::bring_in(&101, Kitty([1; 16])), Error::<Test>::WrongDNA);
+
+        // sending it out delists it like a transfer
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), kitty_id, Some(100), None, None));
+        assert_noop!(KittyBridge::send_out(&101, kitty_id), DispatchError::Other("not the owner"));
+        assert_ok!(KittyBridge::send_out(&100, kitty_id));
+        assert_eq!(KittiesModule::owner_of(kitty_id), Some(BRIDGE_ACCOUNT));
+        assert_eq!(KittiesModule::kitty_prices(kitty_id), None);
+        assert_eq!(KittiesModule::owned_kitties_count(100), 0);
+        assert!(matches!(last_event(), Event::kitties(RawEvent::KittyTransferred(100, BRIDGE_ACCOUNT, 0, _))));
+
+        // a locked kitty stays put
+        assert_ok!(<KittiesModule as LockableKitties<u64>>::lock(kitty_id, *b"bridge  "));
+        assert_noop!(KittyBridge::bring_back(kitty_id, &101), Error::<Test>::KittyLocked);
+        assert_noop!(KittyBridge::retire(kitty_id), Error::<Test>::KittyLocked);
+        assert_ok!(<KittiesModule as LockableKitties<u64>>::unlock(kitty_id, *b"bridge  "));
+
+        assert_ok!(KittyBridge::bring_back(kitty_id, &101));
+        assert_eq!(KittiesModule::owner_of(kitty_id), Some(101));
+        assert_ok!(KittyBridge::send_out(&101, kitty_id));
+        assert_ok!(KittyBridge::retire(kitty_id));
+        assert_eq!(KittiesModule::owner_of(kitty_id), None);
+        assert_eq!(KittiesModule::owned_kitties_count(BRIDGE_ACCOUNT), 0);
+        assert!(matches!(last_event(), Event::kitties(RawEvent::KittyBurned(BRIDGE_ACCOUNT, 0, _))));
+        assert_eq!(hook_calls(), vec![
+            HookCall::Minted(100, 0),
+            HookCall::Transferred(100, BRIDGE_ACCOUNT, 0),
+            HookCall::Transferred(BRIDGE_ACCOUNT, 101, 0),
+            HookCall::Transferred(101, BRIDGE_ACCOUNT, 0),
+            HookCall::Burned(BRIDGE_ACCOUNT, 0),
+        ]);
+        assert_ok!(KittiesModule::do_try_state());
+    });
+}
+
+#[test]
+fn siring_offers_end_on_transfer() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(101)));
+        assert_ok!(KittiesModule::set_siring_price(Origin::signed(101), 0, Some(100)));
+        assert_ok!(KittiesModule::transfer(Origin::signed(101), 0, 102));
+        assert_eq!(KittiesModule::siring_price(0), None);
+    });
+}
+
+#[test]
+fn breeding_proposals_need_the_partner() {
+    new_test_ext().execute_with(|| {
+        // a female of 100 and a male of 101
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::create(Origin::signed(101)));
+        assert_noop!(
+            KittiesModule::propose_breeding(Origin::signed(100), 1, 101, 1, 100),
+            Error::<Test>::KittenNotFound
+        );
+
+        assert_ok!(KittiesModule::propose_breeding(Origin::signed(100), 0, 101, 1, 102));
+        assert!(matches!(last_event(), Event::kitties(RawEvent::BreedingProposed(100, 101, 0, _))));
+        assert_noop!(KittiesModule::accept_breeding(Origin::signed(102), 0), Error::<Test>::NotBreedingPartner);
+        assert_noop!(KittiesModule::accept_breeding(Origin::signed(101), 1), Error::<Test>::BreedingProposalNotFound);
+
+        System::set_extrinsic_index(1);
+        assert_ok!(KittiesModule::accept_breeding(Origin::signed(101), 0));
+        assert_eq!(KittiesModule::owner_of(2), Some(102));
+        assert_eq!(KittiesModule::kitty_parents(2), Some((0, 1)));
+        assert!(matches!(last_event(), Event::kitties(RawEvent::BreedingAccepted(0, 2, _))));
+        assert_eq!(KittiesModule::breeding_proposal(0), None);
+    });
+}
+
+#[test]
+fn breeding_proposals_can_be_cancelled() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::create(Origin::signed(101)));
+        assert_ok!(KittiesModule::propose_breeding(Origin::signed(100), 0, 101, 1, 100));
+        assert_noop!(KittiesModule::cancel_breeding(Origin::signed(101), 0), Error::<Test>::NotBreedingProposer);
+        assert_ok!(KittiesModule::cancel_breeding(Origin::signed(100), 0));
+        assert!(matches!(last_event(), Event::kitties(RawEvent::BreedingProposalCancelled(0, _))));
+        assert_noop!(KittiesModule::accept_breeding(Origin::signed(101), 0), Error::<Test>::BreedingProposalNotFound);
+
+        // voided once a kitty changes hands, although the call fails
+        assert_ok!(KittiesModule::propose_breeding(Origin::signed(100), 0, 101, 1, 100));
+        assert_ok!(KittiesModule::transfer(Origin::signed(100), 0, 102));
+        assert_eq!(
+            KittiesModule::accept_breeding(Origin::signed(101), 1),
+            Err(Error::<Test>::BreedingProposalStale.into())
+        );
+        assert_eq!(KittiesModule::breeding_proposal(1), None);
+        assert!(matches!(last_event(), Event::kitties(RawEvent::BreedingProposalCancelled(1, _))));
+    });
+}
+
+#[test]
+fn accepted_breeding_checks_genders() {
+    new_test_ext().execute_with(|| {
+        // males of 100 and 101
+        System::set_extrinsic_index(1);
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::create(Origin::signed(101)));
+        assert_ok!(KittiesModule::propose_breeding(Origin::signed(100), 0, 101, 1, 100));
+        assert_noop!(KittiesModule::accept_breeding(Origin::signed(101), 0), Error::<Test>::SameGenderBreed);
+        assert!(KittiesModule::breeding_proposal(0).is_some());
+    });
+}
+
+#[test]
+fn offers_reserve_funds_until_accepted() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_noop!(KittiesModule::make_offer(Origin::signed(100), 0, 100, 10), Error::<Test>::InvalidOffer);
+        assert_noop!(KittiesModule::make_offer(Origin::signed(101), 0, 100, 0), Error::<Test>::InvalidOffer);
+        assert_noop!(KittiesModule::make_offer(Origin::signed(101), 1, 100, 10), Error::<Test>::KittenNotFound);
+
+        assert_ok!(KittiesModule::make_offer(Origin::signed(101), 0, 100, 10));
+        assert_eq!(Balances::reserved_balance(101), 100);
+        // a new offer replaces the earlier one
+        assert_ok!(KittiesModule::make_offer(Origin::signed(101), 0, 300, 10));
+        assert_eq!(Balances::reserved_balance(101), 300);
+        assert_eq!(KittiesModule::offer(0, 101), Some(Offer { amount: 300, expiry: 10, nonce: 1 }));
+        assert_ok!(KittiesModule::make_offer(Origin::signed(102), 0, 200, 10));
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 0, Some(1_000), None, None));
+
+        assert_noop!(KittiesModule::accept_offer(Origin::signed(102), 0, 101, 1), Error::<Test>::KittenNotFound);
+        assert_ok!(KittiesModule::accept_offer(Origin::signed(100), 0, 101, 1));
+
+        assert!(KittiesModule::kitties(&101, 0).is_some());
+        assert_eq!(Balances::free_balance(100), 10_300);
+        assert_eq!(Balances::free_balance(101), 9_700);
+        assert_eq!(Balances::reserved_balance(101), 0);
+        assert_eq!(KittiesModule::offer(0, 101), None);
+        assert_eq!(KittiesModule::kitty_prices(0), None);
+        assert!(matches!(last_event(), Event::kitties(RawEvent::KittySold(100, 101, 0, 300, None, 0, 0, _, _))));
+
+        // the other offer stays for the new owner
+        assert_eq!(Balances::reserved_balance(102), 200);
+        assert_ok!(KittiesModule::accept_offer(Origin::signed(101), 0, 102, 2));
+        assert!(KittiesModule::kitties(&102, 0).is_some());
+        assert_eq!(Balances::reserved_balance(102), 0);
+        // the creator got a royalty on the resale
+        assert_eq!(Balances::free_balance(100), 10_320);
+        assert_eq!(Balances::free_balance(101), 9_880);
+        assert_eq!(Balances::free_balance(102), 9_800);
+    });
+}
+
+#[test]
+fn stale_offer_acceptances_are_rejected() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::make_offer(Origin::signed(101), 0, 300, 10));
+        assert!(matches!(last_event(), Event::kitties(RawEvent::OfferMade(101, 0, 300, 10, 0, _))));
+
+        // the bidder lowers the offer before the owner's acceptance of the first one lands
+        assert_ok!(KittiesModule::make_offer(Origin::signed(101), 0, 100, 10));
+        assert_noop!(KittiesModule::accept_offer(Origin::signed(100), 0, 101, 0), Error::<Test>::OfferChanged);
+
+        // withdrawn and made again at the old price, still a different offer
+        assert_ok!(KittiesModule::cancel_offer(Origin::signed(101), 0, 101));
+        assert_ok!(KittiesModule::make_offer(Origin::signed(101), 0, 300, 10));
+        assert_noop!(KittiesModule::accept_offer(Origin::signed(100), 0, 101, 0), Error::<Test>::OfferChanged);
+        assert_noop!(KittiesModule::accept_offer(Origin::signed(100), 0, 101, 1), Error::<Test>::OfferChanged);
+
+        assert_ok!(KittiesModule::accept_offer(Origin::signed(100), 0, 101, 2));
+        assert!(KittiesModule::kitties(&101, 0).is_some());
+        // a retry of the same acceptance finds nothing left to match
+        assert_noop!(KittiesModule::accept_offer(Origin::signed(100), 0, 101, 2), Error::<Test>::KittenNotFound);
+    });
+}
+
+#[test]
+fn offers_can_be_dropped_once_expired() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::make_offer(Origin::signed(101), 0, 100, 5));
+        assert_noop!(KittiesModule::cancel_offer(Origin::signed(102), 0, 101), Error::<Test>::OfferNotExpired);
+
+        System::set_block_number(6);
+        assert_noop!(KittiesModule::accept_offer(Origin::signed(100), 0, 101, 0), Error::<Test>::OfferExpired);
+        assert_ok!(KittiesModule::cancel_offer(Origin::signed(102), 0, 101));
+        assert_eq!(Balances::reserved_balance(101), 0);
+        assert_eq!(Balances::free_balance(101), 10_000);
+        assert!(matches!(last_event(), Event::kitties(RawEvent::OfferCancelled(101, 0, _))));
+        assert_noop!(KittiesModule::cancel_offer(Origin::signed(101), 0, 101), Error::<Test>::OfferNotFound);
+
+        // the bidder can withdraw any time
+        assert_ok!(KittiesModule::make_offer(Origin::signed(101), 0, 100, 20));
+        assert_ok!(KittiesModule::cancel_offer(Origin::signed(101), 0, 101));
+        assert_eq!(Balances::reserved_balance(101), 0);
+    });
+}
+
+#[test]
+fn offers_are_released_when_the_bidder_gets_the_kitty() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::make_offer(Origin::signed(101), 0, 100, 10));
+        let _ = Balances::slash_reserved(&101, 50);
+        assert_noop!(KittiesModule::accept_offer(Origin::signed(100), 0, 101, 0), Error::<Test>::OfferUnderfunded);
+
+        assert_ok!(KittiesModule::transfer(Origin::signed(100), 0, 101));
+        assert_eq!(KittiesModule::offer(0, 101), None);
+        assert_eq!(Balances::reserved_balance(101), 0);
+        assert_eq!(Balances::free_balance(101), 9_950);
+    });
+}
+
+#[test]
+fn swaps_exchange_kitties_and_tokens() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::create(Origin::signed(101)));
+        assert_noop!(KittiesModule::propose_swap(Origin::signed(100), 0, 100, 0, 0, 0), Error::<Test>::InvalidSwap);
+        assert_noop!(KittiesModule::propose_swap(Origin::signed(100), 1, 101, 0, 0, 0), Error::<Test>::KittenNotFound);
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 0, Some(500), None, None));
+        assert_ok!(KittiesModule::set_price(Origin::signed(101), 1, Some(500), None, None));
+
+        assert_ok!(KittiesModule::propose_swap(Origin::signed(100), 0, 101, 1, 0, 50));
+        assert_noop!(KittiesModule::accept_swap(Origin::signed(100), 0), Error::<Test>::NotSwapCounterparty);
+        assert_ok!(KittiesModule::accept_swap(Origin::signed(101), 0));
+
+        assert!(KittiesModule::kitties(&101, 0).is_some());
+        assert!(KittiesModule::kitties(&100, 1).is_some());
+        assert_eq!(Balances::free_balance(100), 10_050);
+        assert_eq!(Balances::free_balance(101), 9_950);
+        assert_eq!(KittiesModule::kitty_prices(0), None);
+        assert_eq!(KittiesModule::kitty_prices(1), None);
+        assert_eq!(KittiesModule::swap_proposal(0), None);
+        assert!(matches!(last_event(), Event::kitties(RawEvent::KittiesSwapped(0, 100, 0, 101, 1, _))));
+    });
+}
+
+#[test]
+fn swaps_are_voided_when_a_kitty_moves() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::create(Origin::signed(101)));
+        assert_ok!(KittiesModule::propose_swap(Origin::signed(100), 0, 101, 1, 0, 0));
+
+        // the proposer gives its kitty away before the counterparty accepts
+        assert_ok!(KittiesModule::transfer(Origin::signed(100), 0, 102));
+        assert_eq!(KittiesModule::accept_swap(Origin::signed(101), 0), Err(Error::<Test>::SwapStale.into()));
+        assert_eq!(KittiesModule::swap_proposal(0), None);
+        assert!(matches!(last_event(), Event::kitties(RawEvent::SwapCancelled(0, _))));
+        assert!(KittiesModule::kitties(&101, 1).is_some());
+        assert!(KittiesModule::kitties(&102, 0).is_some());
+
+        // either party can cancel
+        assert_ok!(KittiesModule::propose_swap(Origin::signed(102), 0, 101, 1, 10, 0));
+        assert_noop!(KittiesModule::cancel_swap(Origin::signed(100), 1), Error::<Test>::NotSwapParty);
+        assert_ok!(KittiesModule::cancel_swap(Origin::signed(101), 1));
+        assert_noop!(KittiesModule::accept_swap(Origin::signed(101), 1), Error::<Test>::SwapNotFound);
+    });
+}
+
+#[test]
+fn gifts_are_claimed_by_the_recipient() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 0, Some(500), None, None));
+        assert_noop!(KittiesModule::gift(Origin::signed(100), 0, 100, 10), Error::<Test>::InvalidGift);
+        assert_noop!(KittiesModule::gift(Origin::signed(100), 0, 101, 0), Error::<Test>::InvalidGift);
+        assert_noop!(KittiesModule::gift(Origin::signed(101), 0, 102, 10), Error::<Test>::KittenNotFound);
+
+        assert_ok!(KittiesModule::gift(Origin::signed(100), 0, 101, 10));
+        assert!(matches!(last_event(), Event::kitties(RawEvent::KittyGifted(100, 101, 0, 10, _))));
+        assert_eq!(KittiesModule::owner_of(0), Some(KittiesModule::gift_account_id(0)));
+        assert_eq!(KittiesModule::kitty_prices(0), None);
+
+        // pending, the kitty can't be listed, bred or moved by the sender
+        assert_noop!(KittiesModule::set_price(Origin::signed(100), 0, Some(500), None, None), Error::<Test>::KittenNotFound);
+        assert!(KittiesModule::transfer(Origin::signed(100), 0, 102).is_err());
+        assert_noop!(KittiesModule::claim_gift(Origin::signed(102), 0), Error::<Test>::NotGiftRecipient);
+
+        // claimable up to and including the expiry block
+        System::set_block_number(10);
+        assert_ok!(KittiesModule::claim_gift(Origin::signed(101), 0));
+        assert!(KittiesModule::kitties(&101, 0).is_some());
+        assert_eq!(KittiesModule::pending_gift(0), None);
+        assert!(matches!(last_event(), Event::kitties(RawEvent::GiftClaimed(101, 0, _))));
+        assert_noop!(KittiesModule::claim_gift(Origin::signed(101), 0), Error::<Test>::GiftNotFound);
+    });
+}
+
+#[test]
+fn expired_gifts_go_back_to_the_sender() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::gift(Origin::signed(100), 0, 101, 10));
+
+        System::set_block_number(10);
+        assert_noop!(KittiesModule::cancel_gift(Origin::signed(102), 0), Error::<Test>::GiftNotExpired);
+        System::set_block_number(11);
+        assert_noop!(KittiesModule::claim_gift(Origin::signed(101), 0), Error::<Test>::GiftExpired);
+        assert_ok!(KittiesModule::cancel_gift(Origin::signed(102), 0));
+        assert!(KittiesModule::kitties(&100, 0).is_some());
+        assert!(matches!(last_event(), Event::kitties(RawEvent::GiftCancelled(100, 0, _))));
+
+        // the sender can take a gift back before it expires
+        assert_ok!(KittiesModule::gift(Origin::signed(100), 0, 101, 20));
+        assert_ok!(KittiesModule::cancel_gift(Origin::signed(100), 0));
+        assert!(KittiesModule::kitties(&100, 0).is_some());
+        assert_eq!(KittiesModule::owned_kitties_count(&100), 1);
+    });
+}
+
+#[test]
+fn auctions_sell_to_the_best_bid() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 0, Some(1_000), None, None));
+        assert_noop!(KittiesModule::start_auction(Origin::signed(100), 0, 100, 1), Error::<Test>::InvalidAuction);
+        assert_noop!(KittiesModule::start_auction(Origin::signed(101), 0, 100, 5), Error::<Test>::KittenNotFound);
+        assert_ok!(KittiesModule::start_auction(Origin::signed(100), 0, 100, 5));
+        assert_eq!(KittiesModule::kitty_prices(0), None);
+
+        // locked for the duration of the auction
+        assert_noop!(KittiesModule::transfer(Origin::signed(100), 0, 101), Error::<Test>::KittyLocked);
+        assert_noop!(KittiesModule::set_price(Origin::signed(100), 0, Some(10), None, None), Error::<Test>::KittyLocked);
+
+        assert_noop!(KittiesModule::bid(Origin::signed(100), 0, 200), Error::<Test>::BuyFromSelf);
+        assert_noop!(KittiesModule::bid(Origin::signed(101), 0, 99), Error::<Test>::BidTooLow);
+        assert_ok!(KittiesModule::bid(Origin::signed(101), 0, 100));
+        assert_noop!(KittiesModule::bid(Origin::signed(102), 0, 100), Error::<Test>::BidTooLow);
+        assert_ok!(KittiesModule::bid(Origin::signed(102), 0, 150));
+        // outbid, 101's bid is released
+        assert_eq!(Balances::reserved_balance(101), 0);
+        assert_eq!(Balances::reserved_balance(102), 150);
+        assert_ok!(KittiesModule::bid(Origin::signed(101), 0, 200));
+        assert_eq!(Balances::reserved_balance(101), 200);
+        assert_eq!(Balances::reserved_balance(102), 0);
+        assert!(matches!(last_event(), Event::kitties(RawEvent::AuctionBid(101, 0, 200, _))));
+
+        // bids are accepted up to and including the end block
+        KittiesModule::on_finalize(4);
+        System::set_block_number(5);
+        assert_ok!(KittiesModule::bid(Origin::signed(102), 0, 250));
+        KittiesModule::on_finalize(5);
+        System::set_block_number(6);
+        assert_eq!(KittiesModule::auction_status(0), Some(AuctionStatus::AwaitingSettlement));
+        assert_noop!(KittiesModule::bid(Origin::signed(101), 0, 300), Error::<Test>::AuctionEnded);
+        KittiesModule::on_initialize(6);
+        assert_eq!(KittiesModule::auction_status(0), None);
+        assert_noop!(KittiesModule::bid(Origin::signed(101), 0, 300), Error::<Test>::AuctionNotFound);
+
+        assert!(KittiesModule::kitties(&102, 0).is_some());
+        assert_eq!(KittiesModule::auctions_ending(5), Vec::<u32>::new());
+        assert_eq!(Balances::free_balance(100), 10_250);
+        assert_eq!(Balances::free_balance(101), 10_000);
+        assert_eq!(Balances::reserved_balance(101), 0);
+        assert_eq!(Balances::free_balance(102), 9_750);
+        assert_eq!(Balances::reserved_balance(102), 0);
+        assert_eq!(KittiesModule::activity_counter(ActivityKind::Sales), 1);
+        assert!(matches!(last_event(), Event::kitties(RawEvent::AuctionSettled(100, 102, 0, 250, 0, 0, _))));
+    });
+}
+
+#[test]
+fn auctions_without_bids_keep_the_kitty() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::start_auction(Origin::signed(100), 0, 500, 3));
+        assert_noop!(KittiesModule::bid(Origin::signed(101), 0, 499), Error::<Test>::BidTooLow);
+
+        KittiesModule::on_finalize(3);
+        System::set_block_number(4);
+        KittiesModule::on_initialize(4);
+        assert!(KittiesModule::kitties(&100, 0).is_some());
+        assert_eq!(KittiesModule::auction(0), None);
+        assert_eq!(Balances::free_balance(100), 10_000);
+        assert_eq!(Balances::free_balance(101), 10_000);
+        assert!(matches!(last_event(), Event::kitties(RawEvent::AuctionUnsold(100, 0, _))));
+
+        // unlocked again
+        assert_ok!(KittiesModule::transfer(Origin::signed(100), 0, 101));
+    });
+}
+
+#[test]
+fn underfunded_auction_bids_are_released() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::start_auction(Origin::signed(100), 0, 100, 3));
+        assert_ok!(KittiesModule::bid(Origin::signed(101), 0, 100));
+        let _ = Balances::slash_reserved(&101, 40);
+
+        KittiesModule::on_finalize(3);
+        System::set_block_number(4);
+        KittiesModule::on_initialize(4);
+        assert!(KittiesModule::kitties(&100, 0).is_some());
+        assert_eq!(Balances::free_balance(101), 9_960);
+        assert_eq!(Balances::reserved_balance(101), 0);
+        assert_eq!(KittiesModule::kitty_reserved(101), 0);
+        assert!(matches!(last_event(), Event::kitties(RawEvent::AuctionUnsold(100, 0, _))));
+    });
+}
+
+#[test]
+fn auctions_ending_together_settle_in_order() {
+    new_test_ext().execute_with(|| {
+        let _ = Balances::deposit_creating(&KittiesModule::account_id(), 1_000);
+        for kitty_id in 0..5 {
+            assert_ok!(KittiesModule::create(Origin::signed(100)));
+            assert_ok!(KittiesModule::start_auction(Origin::signed(100), kitty_id, 100, 3));
+            assert_ok!(KittiesModule::bid(Origin::signed(101 + u64::from(kitty_id % 2)), kitty_id, 100 + u64::from(kitty_id)));
+        }
+        let settled = || {
+            System::events()
+                .iter()
+                .filter_map(|r| match &r.event {
+                    Event::kitties(RawEvent::AuctionSettled(_, _, kitty_id, ..)) => Some(*kitty_id),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+
+        next_block();
+        next_block();
+        assert_eq!(KittiesModule::auction_status(4), Some(AuctionStatus::Open));
+        assert_ok!(KittiesModule::bid(Origin::signed(102), 4, 200));
+
+        // the budget settles two a block, the rest wait their turn without taking bids
+        next_block();
+        assert_eq!(settled(), vec![0, 1]);
+        assert_eq!(KittiesModule::settlement_queue_range(), (2, 5));
+        assert_eq!(KittiesModule::auction_status(1), None);
+        assert_eq!(KittiesModule::auction_status(2), Some(AuctionStatus::AwaitingSettlement));
+        assert_noop!(KittiesModule::bid(Origin::signed(102), 4, 300), Error::<Test>::AuctionEnded);
+        assert_noop!(KittiesModule::transfer(Origin::signed(100), 2, 101), Error::<Test>::KittyLocked);
+        assert_ok!(KittiesModule::do_try_state());
+
+        next_block();
+        assert_eq!(settled(), vec![0, 1, 2, 3]);
+
+        // anyone can settle the rest, for a reward
+        let free = Balances::free_balance(101);
+        let info = KittiesModule::settle_next(Origin::signed(101), 5).unwrap();
+        assert_eq!(info.actual_weight, Some(DbWeight::get().reads_writes(13 + 2, 17 + 2)));
+        assert_eq!(settled(), vec![0, 1, 2, 3, 4]);
+        assert!(matches!(last_event(), Event::kitties(RawEvent::SettlementRewardPaid(101, 1, 5, _))));
+        assert_eq!(Balances::free_balance(101), free + 5);
+        assert!(KittiesModule::kitties(&102, 4).is_some());
+        assert_eq!(KittiesModule::settlement_queue_range(), (5, 5));
+        assert_noop!(KittiesModule::settle_next(Origin::signed(101), 5), Error::<Test>::NothingToSettle);
+
+        // every auction sold, to its best bidder
+        assert!(KittiesModule::kitties(&101, 0).is_some());
+        assert!(KittiesModule::kitties(&102, 1).is_some());
+        assert!(KittiesModule::kitties(&101, 2).is_some());
+        assert!(KittiesModule::kitties(&102, 3).is_some());
+        assert_eq!(Balances::reserved_balance(101), 0);
+        assert_eq!(Balances::reserved_balance(102), 0);
+        assert_ok!(KittiesModule::do_try_state());
+    });
+}
+
+#[test]
+fn dutch_auction_price_falls_linearly() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_noop!(
+            KittiesModule::start_dutch_auction(Origin::signed(100), 0, 1_000, 100, 0),
+            Error::<Test>::InvalidDutchAuction
+        );
+        assert_noop!(
+            KittiesModule::start_dutch_auction(Origin::signed(100), 0, 100, 1_000, 10),
+            Error::<Test>::InvalidDutchAuction
+        );
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 0, Some(5_000), None, None));
+        assert_ok!(KittiesModule::start_dutch_auction(Origin::signed(100), 0, 1_000, 100, 10));
+        assert_eq!(KittiesModule::kitty_prices(0), None);
+
+        let price_at = |block| {
+            System::set_block_number(block);
+            KittiesModule::current_dutch_price(0)
+        };
+        assert_eq!(price_at(1), Some(1_000));
+        assert_eq!(price_at(2), Some(910));
+        assert_eq!(price_at(4), Some(730));
+        assert_eq!(price_at(10), Some(190));
+        assert_eq!(price_at(11), Some(100));
+        assert_eq!(price_at(50), Some(100));
+        assert_eq!(KittiesModule::current_dutch_price(1), None);
+    });
+}
+
+#[test]
+fn dutch_auctions_sell_at_the_current_price() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::start_dutch_auction(Origin::signed(100), 0, 1_000, 0, 4));
+        assert_noop!(KittiesModule::buy_dutch(Origin::signed(100), 0, 1_000), Error::<Test>::BuyFromSelf);
+
+        System::set_block_number(3);
+        assert_noop!(KittiesModule::buy_dutch(Origin::signed(101), 0, 499), Error::<Test>::PriceTooLow);
+        assert_ok!(KittiesModule::buy_dutch(Origin::signed(101), 0, 600));
+
+        assert!(KittiesModule::kitties(&101, 0).is_some());
+        assert_eq!(KittiesModule::dutch_auction(0), None);
+        assert_eq!(Balances::free_balance(100), 10_500);
+        assert_eq!(Balances::free_balance(101), 9_500);
+        assert!(matches!(last_event(), Event::kitties(RawEvent::KittySold(100, 101, 0, 500, None, 0, 0, _, _))));
+        assert_noop!(KittiesModule::buy_dutch(Origin::signed(102), 0, 1_000), Error::<Test>::NotForSale);
+    });
+}
+
+#[test]
+fn dutch_auctions_end_when_cancelled_or_moved() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::start_dutch_auction(Origin::signed(100), 0, 1_000, 0, 4));
+        assert_noop!(KittiesModule::cancel_dutch_auction(Origin::signed(101), 0), Error::<Test>::KittenNotFound);
+        assert_ok!(KittiesModule::cancel_dutch_auction(Origin::signed(100), 0));
+        assert!(matches!(last_event(), Event::kitties(RawEvent::DutchAuctionCancelled(100, 0, _))));
+        assert_noop!(KittiesModule::buy_dutch(Origin::signed(101), 0, 1_000), Error::<Test>::NotForSale);
+
+        assert_ok!(KittiesModule::start_dutch_auction(Origin::signed(100), 0, 1_000, 0, 4));
+        assert_ok!(KittiesModule::transfer(Origin::signed(100), 0, 102));
+        assert_eq!(KittiesModule::dutch_auction(0), None);
+    });
+}
+
+#[test]
+fn listings_expire_after_their_block() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_noop!(KittiesModule::set_price(Origin::signed(100), 0, Some(500), Some(0), None), Error::<Test>::ListingExpired);
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 0, Some(500), Some(3), None));
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 1, Some(500), Some(3), None));
+
+        // the expiry block is the last one the kitty can be bought in
+        System::set_block_number(3);
+        assert_ok!(KittiesModule::buy(Origin::signed(101), 0, 500, None, None));
+        assert!(KittiesModule::kitties(&101, 0).is_some());
+
+        System::set_block_number(4);
+        assert_eq!(KittiesModule::kitty_prices(1), None);
+        assert_eq!(KittiesModule::buy(Origin::signed(101), 1, 500, None, None), Err(Error::<Test>::ListingExpired.into()));
+        // removed on access, although the call fails
+        assert_eq!(KittiesModule::listing(1), None);
+        assert!(matches!(last_event(), Event::kitties(RawEvent::ListingExpiredEvent(100, 1, _))));
+        assert_eq!(Balances::free_balance(101), 9_500);
+    });
+}
+
+#[test]
+fn expired_listings_are_swept_in_bounded_chunks() {
+    new_test_ext().execute_with(|| {
+        for kitty_id in 0..4 {
+            assert_ok!(KittiesModule::create(Origin::signed(100)));
+            assert_ok!(KittiesModule::set_price(Origin::signed(100), kitty_id, Some(500), Some(2), None));
+        }
+        ListingSweepCursor::<Test>::put(2);
+        System::set_block_number(3);
+
+        // one for the block, two removals
+        KittiesModule::sweep_expired_listings(3, 3);
+        assert_eq!(ListingExpiries::<Test>::iter_prefix(2).count(), 2);
+        assert_eq!(Listings::<Test>::iter().count(), 2);
+        assert_eq!(KittiesModule::listing_sweep_cursor(), 2);
+
+        KittiesModule::sweep_expired_listings(3, 3);
+        assert_eq!(ListingExpiries::<Test>::iter_prefix(2).count(), 0);
+        assert_eq!(Listings::<Test>::iter().count(), 0);
+        assert_eq!(KittiesModule::listing_sweep_cursor(), 3);
+        assert_eq!(
+            System::events().iter().filter(|r| matches!(r.event, Event::kitties(RawEvent::ListingExpiredEvent(..)))).count(),
+            4
+        );
+
+        // a relisting without expiry leaves its old entry behind, the sweep only drops it
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 0, Some(500), Some(5), None));
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 0, Some(600), None, None));
+        KittiesModule::on_initialize(6);
+        assert_eq!(KittiesModule::kitty_prices(0), Some(600));
+        assert_eq!(ListingExpiries::<Test>::iter().count(), 0);
+        assert_eq!(KittiesModule::listing_sweep_cursor(), 6);
+    });
+}
+
+#[test]
+fn listings_get_no_expiry_on_upgrade() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        // layout of version 5
+        frame_support::storage::unhashed::put(&Listings::<Test>::hashed_key_for(0), &(100u64, 50u64));
+        StorageVersion::<DefaultInstance>::put(5);
+        System::set_block_number(7);
+
+        migrations::add_listing_expiry::<Test, DefaultInstance>();
+        assert_eq!(KittiesModule::storage_version(), 6);
+        assert_eq!(KittiesModule::listing(0), Some(Listing { seller: 100, price: 50, expires_at: None, deposit: 0, asset: None }));
+        assert_eq!(KittiesModule::listing_sweep_cursor(), 7);
+        assert_ok!(KittiesModule::do_try_state());
+    });
+}
+
+#[test]
+fn listings_get_no_deposit_on_upgrade() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        // layout of version 6
+        frame_support::storage::unhashed::put(&Listings::<Test>::hashed_key_for(0), &(100u64, 50u64, Some(9u64)));
+        StorageVersion::<DefaultInstance>::put(6);
+
+        migrations::add_listing_deposit::<Test, DefaultInstance>();
+        assert_eq!(KittiesModule::storage_version(), 7);
+        assert_eq!(KittiesModule::listing(0), Some(Listing { seller: 100, price: 50, expires_at: Some(9), deposit: 0, asset: None }));
+        assert_ok!(KittiesModule::do_try_state());
+
+        // a free listing releases nothing when removed
+        LISTING_DEPOSIT.with(|v| *v.borrow_mut() = 100);
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 0, None, None, None));
+        assert_eq!(Balances::reserved_balance(100), 0);
+    });
+}
+
+#[test]
+fn listings_are_priced_in_currency_on_upgrade() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        // layout of version 7
+        frame_support::storage::unhashed::put(&Listings::<Test>::hashed_key_for(0), &(100u64, 50u64, Some(9u64), 5u64));
+        StorageVersion::<DefaultInstance>::put(7);
+
+        migrations::add_listing_asset::<Test, DefaultInstance>();
+        assert_eq!(KittiesModule::storage_version(), 8);
+        assert_eq!(KittiesModule::listing(0), Some(Listing { seller: 100, price: 50, expires_at: Some(9), deposit: 5, asset: None }));
+    });
+}
+
+#[test]
+fn offers_get_nonces_on_upgrade() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::make_offer(Origin::signed(101), 0, 50, 9));
+        assert_ok!(KittiesModule::make_offer(Origin::signed(102), 0, 60, 9));
+        // layout of version 9
+        frame_support::storage::unhashed::put(&Offers::<Test>::hashed_key_for(0, 101), &(50u64, 9u64));
+        frame_support::storage::unhashed::put(&Offers::<Test>::hashed_key_for(0, 102), &(60u64, 9u64));
+        StorageVersion::<DefaultInstance>::put(9);
+
+        migrations::add_offer_nonce::<Test, DefaultInstance>();
+        assert_eq!(KittiesModule::storage_version(), 10);
+        assert_eq!(KittiesModule::next_offer_nonce(), 4);
+        let mut nonces: Vec<u64> = [101, 102].iter().map(|bidder| KittiesModule::offer(0, bidder).unwrap().nonce).collect();
+        nonces.sort();
+        assert_eq!(nonces, vec![2, 3]);
+        assert_eq!(KittiesModule::offer(0, 101).map(|offer| (offer.amount, offer.expiry)), Some((50, 9)));
+    });
+}
+
+#[test]
+fn bare_kitties_are_tagged_on_upgrade() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        let class_id = KittiesModule::class_id();
+        let dna = KittiesModule::kitties(&100, 0).unwrap().dna();
+        // layout of version 10, with the kitty as bare DNA
+        let key = orml_nft::Tokens::<Test>::hashed_key_for(class_id, 0);
+        frame_support::storage::unhashed::put(&key, &(Vec::<u8>::new(), 100u64, dna));
+        StorageVersion::<DefaultInstance>::put(10);
+        assert!(Nft::tokens(class_id, 0).is_none());
+
+        migrations::tag_v1_kitties::<Test, DefaultInstance>();
+        assert_eq!(Nft::tokens(class_id, 0).unwrap().data, VersionedKitty::V1(dna));
+        assert!(!Nft::tokens(class_id, 1).unwrap().data.is_v1());
+        assert_eq!(KittiesModule::storage_version(), 10);
+        migrations::mark_kitties_tagged::<Test, DefaultInstance>();
+        assert_eq!(KittiesModule::storage_version(), 11);
+        assert_ok!(KittiesModule::do_try_state());
+    });
+}
+
+#[test]
+fn listings_are_indexed_on_upgrade() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 0, Some(50), None, None));
+        ForSaleKitties::<Test>::remove(0);
+        StorageVersion::<DefaultInstance>::put(8);
+
+        migrations::index_listings::<Test, DefaultInstance>();
+        assert_eq!(KittiesModule::storage_version(), 9);
+        assert!(ForSaleKitties::<Test>::contains_key(0));
+        assert_ok!(KittiesModule::do_try_state());
+    });
+}
+
+#[test]
+fn runtime_upgrade_of_an_up_to_date_chain_changes_nothing() {
+    use frame_support::traits::OnRuntimeUpgrade;
+
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::create(Origin::signed(101)));
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 0, Some(50), Some(9), None));
+        assert_eq!(KittiesModule::storage_version(), migrations::CURRENT_VERSION);
+
+        let root = sp_io::storage::root();
+        KittiesModule::on_runtime_upgrade();
+        KittiesModule::on_runtime_upgrade();
+        assert_eq!(sp_io::storage::root(), root);
+    });
+}
+
+#[test]
+fn storage_keeps_its_prefix() {
+    new_test_ext().execute_with(|| {
+        // as written by a runtime before the trait was renamed to `Config`
+        let key = [sp_io::hashing::twox_128(b"Kitties"), sp_io::hashing::twox_128(b"Paused")].concat();
+        frame_support::storage::unhashed::put(&key, &true);
+        assert!(KittiesModule::paused());
+    });
+}
+
+#[test]
+fn instances_keep_their_own_kitties() {
+    new_test_ext().execute_with(|| {
+        assert_ne!(KittiesModule::class_id(), PuppiesModule::class_id());
+        assert_ne!(KittiesModule::account_id(), PuppiesModule::account_id());
+        assert_ne!(Listings::<Test>::hashed_key_for(0), Listings::<Test, Instance1>::hashed_key_for(0));
+
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 0, Some(50), None, None));
+        assert_eq!(PuppiesModule::total_kitties(), 0);
+        assert_eq!(PuppiesModule::owned_kitties_count(100), 0);
+        assert_eq!(Nft::next_token_id(PuppiesModule::class_id()), 0);
+
+        // the first puppy gets id 0 too, in the class of the puppies
+        assert_ok!(PuppiesModule::create(Origin::signed(100)));
+        assert!(matches!(last_event(), Event::kitties_Instance1(RawEvent::KittyCreated(100, 0, _, 0))));
+        assert_eq!(PuppiesModule::owned_kitties_count(100), 1);
+        assert_eq!(PuppiesModule::kitty_prices(0), None);
+        assert_eq!(KittiesModule::owned_kitties_count(100), 1);
+        assert_eq!(KittiesModule::kitty_prices(0), Some(50));
+        assert_eq!(KittiesModule::total_kitties(), 1);
+    });
+}
+
+#[test]
+fn kitties_for_sale_come_in_pages() {
+    new_test_ext().execute_with(|| {
+        for _ in 0..6 {
+            assert_ok!(KittiesModule::create(Origin::signed(100)));
+        }
+        for kitty_id in 0..4 {
+            assert_ok!(KittiesModule::set_price(Origin::signed(100), kitty_id, Some(u64::from(kitty_id) + 10), None, None));
+        }
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 4, Some(50), Some(3), None));
+        System::set_block_number(4);
+
+        let first = KittiesModule::for_sale(3, None);
+        assert_eq!(first.len(), 3);
+        let second = KittiesModule::for_sale(3, first.last().map(|(kitty_id, _, _)| *kitty_id));
+        // the expired listing is skipped
+        assert_eq!(second.len(), 1);
+        assert_eq!(KittiesModule::for_sale(3, second.last().map(|(kitty_id, _, _)| *kitty_id)), vec![]);
+
+        let mut all: Vec<_> = first.into_iter().chain(second).collect();
+        all.sort();
+        assert_eq!(all, vec![(0, 100, 10), (1, 100, 11), (2, 100, 12), (3, 100, 13)]);
+        assert_eq!(KittiesModule::for_sale(0, None), vec![]);
+    });
+}
+
+#[test]
+fn for_sale_index_follows_listings() {
+    new_test_ext().execute_with(|| {
+        let accounts = [100u64, 101, 102];
+        for account in accounts.iter() {
+            for _ in 0..3 {
+                assert_ok!(KittiesModule::create(Origin::signed(*account)));
+            }
+        }
+        // xorshift, so the sequence is the same on every run
+        let mut seed = 0x2545_f491u32;
+        let mut next = |bound: u32| {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            seed % bound
+        };
+        for _ in 0..300 {
+            let kitty_id = next(9);
+            let owner = match KittiesModule::owner_of(kitty_id) {
+                Some(owner) => owner,
+                None => continue,
+            };
+            let other = accounts[next(3) as usize];
+            let price = u64::from(next(100)) + 1;
+            let _ = match next(10) {
+                0..=3 => KittiesModule::set_price(Origin::signed(owner), kitty_id, Some(price), None, None).is_ok(),
+                4 => KittiesModule::set_price(Origin::signed(owner), kitty_id, None, None, None).is_ok(),
+                5..=7 => KittiesModule::buy(Origin::signed(other), kitty_id, price, None, None).is_ok(),
+                8 => KittiesModule::transfer(Origin::signed(owner), kitty_id, other).is_ok(),
+                _ => KittiesModule::burn(Origin::signed(owner), kitty_id).is_ok(),
+            };
+
+            let mut indexed: Vec<_> = ForSaleKitties::<Test>::iter().map(|(kitty_id, _)| kitty_id).collect();
+            let mut listed: Vec<_> = Listings::<Test>::iter().map(|(kitty_id, _)| kitty_id).collect();
+            indexed.sort();
+            listed.sort();
+            assert_eq!(indexed, listed);
+            let mut for_sale: Vec<_> = KittiesModule::for_sale(u32::max_value(), None).into_iter().map(|(kitty_id, _, _)| kitty_id).collect();
+            for_sale.sort();
+            assert_eq!(for_sale, listed);
+        }
+        assert_ok!(KittiesModule::do_try_state());
+    });
+}
+
+#[test]
+fn offchain_worker_submits_listing_expiries() {
+    use frame_support::traits::OffchainWorker;
+    use sp_core::offchain::{testing, OffchainExt, TransactionPoolExt};
+
+    let (offchain, _) = testing::TestOffchainExt::new();
+    let (pool, pool_state) = testing::TestTransactionPoolExt::new();
+    let mut ext = new_test_ext();
+    ext.register_extension(OffchainExt::new(offchain));
+    ext.register_extension(TransactionPoolExt::new(pool));
+    ext.execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 0, Some(500), Some(3), None));
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 1, Some(500), Some(10), None));
+
+        // listing 0 can still be bought in block 3
+        System::set_block_number(2);
+        KittiesModule::offchain_worker(2);
+        assert!(pool_state.read().transactions.is_empty());
+
+        System::set_block_number(3);
+        KittiesModule::offchain_worker(3);
+        let tx = pool_state.write().transactions.pop().unwrap();
+        assert!(pool_state.read().transactions.is_empty());
+        let tx = Extrinsic::decode(&mut &*tx).unwrap();
+        assert_eq!(tx.signature, None);
+        assert_eq!(tx.call, Call::expire_listing(0));
+
+        System::set_block_number(4);
+        assert_ok!(KittiesModule::expire_listing(Origin::none(), 0));
+        assert_eq!(KittiesModule::listing(0), None);
+        assert!(matches!(last_event(), Event::kitties(RawEvent::ListingExpiredEvent(100, 0, _))));
+    });
+}
+
+#[test]
+fn unsigned_expiries_of_live_listings_are_rejected() {
+    use frame_support::unsigned::ValidateUnsigned;
+    use sp_runtime::transaction_validity::{InvalidTransaction, TransactionSource, TransactionValidity};
+
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 0, Some(500), Some(3), None));
+        let validate = |kitty_id| KittiesModule::validate_unsigned(TransactionSource::External, &Call::expire_listing(kitty_id));
+        let stale: TransactionValidity = InvalidTransaction::Stale.into();
+        let wrong_call: TransactionValidity = InvalidTransaction::Call.into();
+
+        assert_eq!(validate(0), stale);
+        assert_eq!(validate(1), stale);
+        assert_eq!(KittiesModule::validate_unsigned(TransactionSource::External, &Call::create()), wrong_call);
+        assert_noop!(KittiesModule::expire_listing(Origin::none(), 0), Error::<Test>::ListingNotExpired);
+        assert_noop!(KittiesModule::expire_listing(Origin::signed(100), 0), DispatchError::BadOrigin);
+
+        System::set_block_number(4);
+        let valid = validate(0).unwrap();
+        assert_eq!(valid.priority, UnsignedPriority::get());
+        assert_eq!(valid.provides, vec![("KittiesExpireListing", 0u32).encode()]);
+        assert_eq!(valid.longevity, 5);
+    });
+}
+
+#[test]
+fn royalty_rounds_down_and_is_reported_with_the_sale() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::transfer(Origin::signed(100), 0, 101));
+        assert_ok!(KittiesModule::set_price(Origin::signed(101), 0, Some(339), None, None));
+        assert_ok!(KittiesModule::buy(Origin::signed(102), 0, 339, None, None));
+
+        // 10% of 339 is 33.9, the creator gets 33 and the seller the remaining 306
+        assert_eq!(Balances::free_balance(100), 10_033);
+        assert_eq!(Balances::free_balance(101), 10_306);
+        assert_eq!(Balances::free_balance(102), 9_661);
+        assert!(matches!(last_event(), Event::kitties(RawEvent::KittySold(101, 102, 0, 339, None, 0, 33, _, _))));
+
+        // auctions split the same way
+        assert_ok!(KittiesModule::start_auction(Origin::signed(102), 0, 100, 3));
+        assert_ok!(KittiesModule::bid(Origin::signed(101), 0, 100));
+        KittiesModule::on_finalize(3);
+        assert_eq!(Balances::free_balance(100), 10_043);
+        assert_eq!(Balances::free_balance(101), 10_206);
+        assert_eq!(Balances::free_balance(102), 9_751);
+        assert!(matches!(last_event(), Event::kitties(RawEvent::AuctionSettled(102, 101, 0, 100, 0, 10, _))));
+    });
+}
+
+#[test]
+fn market_fee_rounds_down_and_is_reported_with_the_sale() {
+    new_test_ext().execute_with(|| {
+        FEE_BENEFICIARY.with(|v| *v.borrow_mut() = Some(200));
+        assert_ok!(KittiesModule::set_fee_schedule(Origin::root(), vec![(0, Permill::from_percent(10))]));
+
+        // the seller created every kitty, so no royalty is due
+        for (kitty_id, price, fee) in vec![(0, 1, 0), (1, 15, 1), (2, 1000, 100)] {
+            assert_ok!(KittiesModule::create(Origin::signed(100)));
+            let seller = Balances::free_balance(100);
+            let buyer = Balances::free_balance(101);
+            let collected = Balances::free_balance(200);
+
+            assert_ok!(KittiesModule::set_price(Origin::signed(100), kitty_id, Some(price), None, None));
+            assert_ok!(KittiesModule::buy(Origin::signed(101), kitty_id, price, None, None));
+
+            assert_eq!(Balances::free_balance(100), seller + price - fee);
+            assert_eq!(Balances::free_balance(101), buyer - price);
+            assert_eq!(Balances::free_balance(200), collected + fee);
+            assert!(matches!(
+                last_event(),
+                Event::kitties(RawEvent::KittySold(100, 101, id, p, None, f, 0, _, _)) if id == kitty_id && p == price && f == fee
+            ));
+        }
+    });
+}
+
+#[test]
+fn listing_deposit_is_held_until_delisting() {
+    new_test_ext().execute_with(|| {
+        LISTING_DEPOSIT.with(|v| *v.borrow_mut() = 100);
+        for _ in 0..5 {
+            assert_ok!(KittiesModule::create(Origin::signed(100)));
+        }
+
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 0, Some(500), None, None));
+        assert_eq!(Balances::reserved_balance(100), 100);
+        assert_eq!(KittiesModule::listing(0).map(|listing| listing.deposit), Some(100));
+        // relisting keeps the deposit
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 0, Some(600), None, None));
+        assert_eq!(Balances::reserved_balance(100), 100);
+        // delisted
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 0, None, None, None));
+        assert_eq!(Balances::reserved_balance(100), 0);
+
+        // sold
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 0, Some(500), None, None));
+        assert_ok!(KittiesModule::buy(Origin::signed(101), 0, 500, None, None));
+        assert_eq!(Balances::reserved_balance(100), 0);
+        assert_eq!(Balances::free_balance(100), 10_500);
+
+        // transferred
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 1, Some(500), None, None));
+        assert_ok!(KittiesModule::transfer(Origin::signed(100), 1, 102));
+        assert_eq!(Balances::reserved_balance(100), 0);
+
+        // burned
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 2, Some(500), None, None));
+        assert_ok!(KittiesModule::burn(Origin::signed(100), 2));
+        assert_eq!(Balances::reserved_balance(100), 0);
+
+        // expired
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 3, Some(500), Some(2), None));
+        KittiesModule::on_initialize(3);
+        assert_eq!(KittiesModule::listing(3), None);
+        assert_eq!(Balances::reserved_balance(100), 0);
+
+        // auctioned instead
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 4, Some(500), None, None));
+        assert_ok!(KittiesModule::start_auction(Origin::signed(100), 4, 100, 10));
+        assert_eq!(Balances::reserved_balance(100), 0);
+
+        assert_eq!(Balances::free_balance(100), 10_500);
+        assert_eq!(KittiesModule::kitty_reserved(100), 0);
+        assert_ok!(KittiesModule::do_try_state());
+    });
+}
+
+#[test]
+fn listing_fails_cleanly_without_deposit() {
+    new_test_ext().execute_with(|| {
+        LISTING_DEPOSIT.with(|v| *v.borrow_mut() = 20_000);
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+
+        assert_noop!(
+            KittiesModule::set_price(Origin::signed(100), 0, Some(500), Some(5), None),
+            Error::<Test>::ListingDepositUnavailable
+        );
+        assert_eq!(KittiesModule::listing(0), None);
+        assert_eq!(Balances::reserved_balance(100), 0);
+    });
+}
+
+#[test]
+fn sale_history_keeps_the_latest_sales() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        for (block, seller, buyer, price) in vec![(1, 100, 101, 10), (2, 101, 102, 20), (3, 102, 100, 30), (4, 100, 101, 40)] {
+            System::set_block_number(block);
+            assert_ok!(KittiesModule::set_price(Origin::signed(seller), 0, Some(price), None, None));
+            assert_ok!(KittiesModule::buy(Origin::signed(buyer), 0, price, None, None));
+        }
+        assert_eq!(
+            KittiesModule::sale_history(0),
+            vec![(2, 101, 102, 20), (3, 102, 100, 30), (4, 100, 101, 40)]
+        );
+
+        // auction settlements are recorded too, dropping the oldest sale
+        assert_ok!(KittiesModule::start_auction(Origin::signed(101), 0, 50, 6));
+        assert_ok!(KittiesModule::bid(Origin::signed(102), 0, 50));
+        System::set_block_number(6);
+        KittiesModule::on_finalize(6);
+        assert_eq!(
+            KittiesModule::sale_history(0),
+            vec![(3, 102, 100, 30), (4, 100, 101, 40), (6, 101, 102, 50)]
+        );
+
+        assert_ok!(KittiesModule::burn(Origin::signed(102), 0));
+        assert!(KittiesModule::sale_history(0).is_empty());
+        assert!(!KittySaleHistory::<Test>::contains_key(0));
+    });
+}
+
+#[test]
+fn kitties_sell_in_the_asset_they_are_listed_in() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(KittiesModule::set_fee_schedule(Origin::root(), vec![(0, Permill::from_percent(10))]));
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::create(Origin::signed(100)));
+        assert_ok!(KittiesModule::transfer(Origin::signed(100), 0, 101));
+
+        assert_ok!(KittiesModule::set_price(Origin::signed(101), 0, Some(200), None, Some(USD)));
+        assert!(matches!(last_event(), Event::kitties(RawEvent::KittyPriceUpdated(101, 0, Some(200), Some(USD), _))));
+        assert_noop!(KittiesModule::buy(Origin::signed(102), 0, 200, None, None), Error::<Test>::WrongPaymentAsset.with_weight(DbWeight::get().reads(6)));
+        assert_noop!(KittiesModule::buy(Origin::signed(102), 0, 200, None, Some(2)), Error::<Test>::WrongPaymentAsset.with_weight(DbWeight::get().reads(6)));
+        assert_ok!(KittiesModule::buy(Origin::signed(102), 0, 200, None, Some(USD)));
+
+        // 10% fee burned and 10% royalty to the creator, both out of the seller's proceeds
+        assert_eq!(Tokens::free_balance(USD, &102), 800);
+        assert_eq!(Tokens::free_balance(USD, &101), 1_160);
+        assert_eq!(Tokens::free_balance(USD, &100), 1_020);
+        assert_eq!(Tokens::total_issuance(USD), 2_980);
+        assert_eq!(Balances::free_balance(101), 10_000);
+        assert_eq!(Balances::free_balance(102), 10_000);
+        assert!(matches!(last_event(), Event::kitties(RawEvent::KittySold(101, 102, 0, 200, Some(USD), 20, 20, _, _))));
+        assert_eq!(KittiesModule::owner_of(0), Some(102));
+        // sale statistics stay in the native currency
+        assert_eq!(KittiesModule::last_sale_price(0), None);
+        assert!(KittiesModule::sale_history(0).is_empty());
+
+        assert_ok!(KittiesModule::set_price(Origin::signed(100), 1, Some(300), None, None));
+        assert_noop!(KittiesModule::buy(Origin::signed(101), 1, 300, None, Some(USD)), Error::<Test>::WrongPaymentAsset.with_weight(DbWeight::get().reads(6)));
+        assert_ok!(KittiesModule::buy(Origin::signed(101), 1, 300, None, None));
+        assert_eq!(Balances::free_balance(100), 10_270);
+        assert_eq!(Tokens::free_balance(USD, &101), 1_160);
+        assert!(matches!(last_event(), Event::kitties(RawEvent::KittySold(100, 101, 1, 300, None, 30, 0, _, _))));
+    });
+}
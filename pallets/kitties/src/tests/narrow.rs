@@ -0,0 +1,201 @@
+//! A mock runtime with `u8` kitty ids, checking that ids past the narrow limit fail rather
+//! than wrap or truncate.
+
+use super::*;
+use frame_support::{Blake2_128Concat, StorageHasher};
+
+impl_outer_origin! {
+    pub enum Origin for Narrow where system = frame_system {}
+}
+
+impl_outer_event! {
+    pub enum Event for Narrow {
+        frame_system<T>,
+        pallet_balances<T>,
+        orml_tokens<T>,
+        kitties<T>,
+    }
+}
+
+#[derive(Clone, Eq, PartialEq)]
+pub struct Narrow;
+
+impl frame_system::Trait for Narrow {
+    type BaseCallFilter = ();
+    type Origin = Origin;
+    type Call = ();
+    type Index = u64;
+    type BlockNumber = u64;
+    type Hash = H256;
+    type Hashing = BlakeTwo256;
+    type AccountId = u64;
+    type Lookup = IdentityLookup<Self::AccountId>;
+    type Header = Header;
+    type Event = Event;
+    type BlockHashCount = BlockHashCount;
+    type MaximumBlockWeight = MaximumBlockWeight;
+    type DbWeight = DbWeight;
+    type BlockExecutionWeight = ();
+    type ExtrinsicBaseWeight = ();
+    type MaximumExtrinsicWeight = MaximumBlockWeight;
+    type MaximumBlockLength = MaximumBlockLength;
+    type AvailableBlockRatio = AvailableBlockRatio;
+    type Version = ();
+    type PalletInfo = ();
+    type AccountData = pallet_balances::AccountData<u64>;
+    type OnNewAccount = ();
+    type OnKilledAccount = ();
+    type SystemWeightInfo = ();
+}
+
+impl pallet_balances::Trait for Narrow {
+    type MaxLocks = MaxLocks;
+    type Balance = u64;
+    type Event = Event;
+    type DustRemoval = ();
+    type ExistentialDeposit = ExistentialDeposit;
+    type AccountStore = frame_system::Module<Narrow>;
+    type WeightInfo = ();
+}
+
+impl orml_tokens::Trait for Narrow {
+    type Event = Event;
+    type Balance = u64;
+    type Amount = i64;
+    type CurrencyId = u32;
+    type OnReceived = ();
+    type WeightInfo = ();
+}
+
+impl orml_nft::Trait for Narrow {
+    type ClassId = u32;
+    type TokenId = u8;
+    type ClassData = ();
+    type TokenData = VersionedKitty;
+}
+
+impl Config for Narrow {
+    type Event = Event;
+    type Randomness = MockRandom;
+    type SecureRandomness = MockRandom;
+    type ImmediateBreedRandomness = ImmediateBreedRandomness;
+    type MaxStarterKittiesPerBlock = MaxStarterKittiesPerBlock;
+    type MaxCreatePerBlock = MaxCreatePerBlock;
+    type MaxBreedPerBlock = MaxBreedPerBlock;
+    type MaxKittiesPerOwner = MaxKittiesPerOwner;
+    type MaxKittySupply = MaxKittySupply;
+    type PersonhoodProvider = PersonhoodProvider;
+    type BurnPolicy = KittyBurnPolicy;
+    type CustodyPolicy = KittyCustodyPolicy;
+    type TradeTtl = TradeTtl;
+    type MaxTradeKitties = MaxTradeKitties;
+    type PoolBreedCooldown = PoolBreedCooldown;
+    type MaxPoolKitties = MaxPoolKitties;
+    type PriceOracle = SaleMedianOracle<Narrow>;
+    type Currency = pallet_balances::Module<Narrow>;
+    type Assets = orml_tokens::Module<Narrow>;
+    type FeeHandler = ();
+    type KittyHooks = ();
+    type MaturityPeriod = MaturityPeriod;
+    type MaxBreedsPerKitty = MaxBreedsPerKitty;
+    type BreedingFee = BreedingFee;
+    type GenderPreferenceFee = GenderPreferenceFee;
+    type MutationChance = MutationChance;
+    type GenderPreferenceChance = GenderPreferenceChance;
+    type TokenDecimals = TokenDecimals;
+    type TokenSymbol = TokenSymbol;
+    type ForceOrigin = frame_system::EnsureOneOf<
+        u64,
+        frame_system::EnsureRoot<u64>,
+        frame_system::EnsureSignedBy<Governors, u64>,
+    >;
+    type MaxSeasons = MaxSeasons;
+    type AdminOrigin = frame_system::EnsureRoot<u64>;
+    type FulfillmentOrigin = frame_system::EnsureRoot<u64>;
+    type RedemptionTtl = RedemptionTtl;
+    type MaxOpenRedemptions = MaxOpenRedemptions;
+    type MinNovelty = MinNovelty;
+    type RebreedWindow = RebreedWindow;
+    type EraLength = EraLength;
+    type MaxOpenPolls = MaxOpenPolls;
+    type MaxPollVotes = MaxPollVotes;
+    type RareGeneBonus = RareGeneBonus;
+    type RevealDelay = RevealDelay;
+    type RevealWindow = RevealWindow;
+    type CreationFee = CreationFee;
+    type CreationPriceStep = CreationPriceStep;
+    type CommitFee = CommitFee;
+    type KittyDeposit = KittyDeposit;
+    type ListingDeposit = ListingDeposit;
+    type ModuleId = KittiesModuleId;
+    type CommitForfeit = CommitForfeit;
+    type RentalPeriod = RentalPeriod;
+    type AuctionSettlementBudget = AuctionSettlementBudget;
+    type SettlementReward = SettlementReward;
+    type RoyaltyRate = RoyaltyRate;
+    type MaxSaleHistory = MaxSaleHistory;
+    type VerboseMetrics = VerboseMetrics;
+    type LocksBlockBreeding = LocksBlockBreeding;
+    type GenerationMilestone = GenerationMilestone;
+    type UnsignedPriority = UnsignedPriority;
+    type WeightInfo = ();
+}
+
+impl frame_system::offchain::SendTransactionTypes<Call<Narrow>> for Narrow {
+    type OverarchingCall = Call<Narrow>;
+    type Extrinsic = sp_runtime::testing::TestXt<Call<Narrow>, ()>;
+}
+
+type NarrowKitties = Module<Narrow>;
+
+fn narrow_ext() -> sp_io::TestExternalities {
+    let mut t = frame_system::GenesisConfig::default().build_storage::<Narrow>().unwrap();
+    pallet_balances::GenesisConfig::<Narrow> { balances: vec![(100, 10_000)] }
+        .assimilate_storage(&mut t)
+        .unwrap();
+    GenesisConfig::<Narrow>::default().assimilate_storage(&mut t).unwrap();
+
+    let mut ext: sp_io::TestExternalities = t.into();
+    ext.execute_with(|| frame_system::Module::<Narrow>::set_block_number(1));
+    ext
+}
+
+#[test]
+fn kitty_ids_stop_at_the_narrow_limit() {
+    narrow_ext().execute_with(|| {
+        let class_id = NarrowKitties::class_id();
+        orml_nft::NextTokenId::<Narrow>::insert(class_id, u8::max_value() - 2);
+        assert_eq!(NarrowKitties::kitty_id_headroom(), 2);
+
+        assert_ok!(NarrowKitties::create(Origin::signed(100)));
+        assert_ok!(NarrowKitties::create(Origin::signed(100)));
+        assert!(NarrowKitties::kitties(&100, u8::max_value() - 1).is_some());
+        assert!(NarrowKitties::minting_exhausted());
+
+        // 255 is never handed out, and the ids don't wrap around to 0
+        assert_noop!(NarrowKitties::create(Origin::signed(100)), Error::<Narrow>::MintingExhausted);
+        assert_eq!(NarrowKitties::kitties(&100, u8::max_value()), None);
+        assert_eq!(NarrowKitties::kitties(&100, 0), None);
+        assert_eq!(NarrowKitties::owned_kitties_count(100), 2);
+        assert_ok!(NarrowKitties::do_try_state());
+    });
+}
+
+#[test]
+fn wider_kitty_ids_are_not_truncated() {
+    narrow_ext().execute_with(|| {
+        assert_ok!(NarrowKitties::create(Origin::signed(100)));
+
+        // a price stored under a u32 id, which only collides with kitty 0 if truncated
+        let mut key = KittyPrices::<Narrow>::final_prefix().to_vec();
+        key.extend(Blake2_128Concat::hash(&256u32.encode()));
+        frame_support::storage::unhashed::put(&key, &50u64);
+        StorageVersion::<DefaultInstance>::put(0);
+        RehashCursor::<DefaultInstance>::put(Vec::<u8>::new());
+        migrations::rehash_kitty_prices::<Narrow, DefaultInstance>(10);
+
+        assert_eq!(NarrowKitties::listing(0), None);
+        assert_eq!(frame_support::storage::unhashed::get::<u64>(&key), Some(50));
+        assert_eq!(NarrowKitties::rehash_cursor(), None);
+    });
+}
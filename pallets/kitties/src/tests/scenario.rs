@@ -0,0 +1,317 @@
+//! Scenario tests: market and breeding flows written as a chain of steps.
+//!
+//! Every step dispatches the real call against the mock runtime. After each one `Scenario`
+//! runs `do_try_state` and checks the invariants below, so a scenario only spells out what
+//! is particular to it.
+
+use super::*;
+use frame_support::traits::{OnFinalize, OnInitialize};
+
+struct Scenario {
+    ext: sp_io::TestExternalities,
+    /// Error the next call must fail with, set by `fails_with`.
+    expected_error: Option<DispatchError>,
+}
+
+impl Scenario {
+    fn new() -> Self {
+        Scenario { ext: new_test_ext(), expected_error: None }
+    }
+
+    /// Run `f`, then check every invariant.
+    fn step(mut self, f: impl FnOnce()) -> Self {
+        self.ext.execute_with(|| {
+            f();
+            check_invariants();
+        });
+        self
+    }
+
+    /// Dispatch a call. It must succeed and deposit a kitties event, or fail with the error
+    /// set by `fails_with` without changing storage.
+    fn call(mut self, call: impl FnOnce() -> DispatchResult) -> Self {
+        let expected_error = self.expected_error.take();
+        self.step(|| match expected_error {
+            Some(error) => assert_noop!(call(), error),
+            None => {
+                let events = System::events().len();
+                assert_ok!(call());
+                assert!(
+                    System::events()[events..].iter().any(|r| matches!(r.event, Event::kitties(_))),
+                    "call succeeded without a kitties event"
+                );
+            }
+        })
+    }
+
+    /// Expect the next call to fail with `error`.
+    fn fails_with(mut self, error: Error<Test>) -> Self {
+        self.expected_error = Some(error.into());
+        self
+    }
+
+    /// Dispatch the next calls as extrinsic `index` of the block, which seeds the DNA of
+    /// created and bred kitties. At seed zero index 0 creates a female, 1 a male.
+    fn at_extrinsic(self, index: u32) -> Self {
+        self.step(|| System::set_extrinsic_index(index))
+    }
+
+    fn advance_blocks(self, blocks: u64) -> Self {
+        self.step(|| {
+            for _ in 0..blocks {
+                let now = System::block_number();
+                KittiesModule::on_finalize(now);
+                System::set_block_number(now + 1);
+                KittiesModule::on_initialize(now + 1);
+            }
+        })
+    }
+
+    fn create(self, who: u64) -> Self {
+        self.call(|| KittiesModule::create(Origin::signed(who)))
+    }
+
+    fn breed(self, who: u64, first: u32, second: u32) -> Self {
+        self.call(|| KittiesModule::breed(Origin::signed(who), first, second, None))
+    }
+
+    fn breed_for(self, who: u64, first: u32, second: u32, gender: KittyGender) -> Self {
+        self.call(|| KittiesModule::breed(Origin::signed(who), first, second, Some(gender)))
+    }
+
+    fn transfer(self, from: u64, kitty_id: u32, to: u64) -> Self {
+        self.call(|| KittiesModule::transfer(Origin::signed(from), kitty_id, to).map(drop).map_err(|error| error.error))
+    }
+
+    fn list(self, who: u64, kitty_id: u32, price: u64) -> Self {
+        self.call(|| KittiesModule::set_price(Origin::signed(who), kitty_id, Some(price), None, None))
+    }
+
+    fn unlist(self, who: u64, kitty_id: u32) -> Self {
+        self.call(|| KittiesModule::set_price(Origin::signed(who), kitty_id, None, None, None))
+    }
+
+    /// Buy a listed kitty from its current owner.
+    fn buy(self, who: u64, kitty_id: u32, max_price: u64) -> Self {
+        self.call(|| KittiesModule::buy(Origin::signed(who), kitty_id, max_price, None, None).map(drop).map_err(|error| error.error))
+    }
+
+    /// Quote a bid and an ask on a kitty, valid for the next `blocks` blocks.
+    fn bid(self, who: u64, kitty_id: u32, bid: u64, ask: u64, blocks: u64) -> Self {
+        self.call(|| KittiesModule::post_quote(Origin::signed(who), kitty_id, bid, ask, System::block_number() + blocks))
+    }
+
+    fn cancel_bid(self, who: u64, kitty_id: u32) -> Self {
+        self.call(|| KittiesModule::cancel_quote(Origin::signed(who), kitty_id))
+    }
+
+    /// Sell a kitty to `quoter` at its current bid.
+    fn settle(self, kitty_id: u32, quoter: u64) -> Self {
+        self.call(|| {
+            let nonce = KittiesModule::quotes(kitty_id, quoter).map(|quote| quote.nonce).unwrap_or_default();
+            KittiesModule::hit_bid(Origin::signed(owner_of(kitty_id)), kitty_id, quoter, nonce)
+        })
+    }
+
+    fn market_fee(self, rate: Permill) -> Self {
+        self.call(|| KittiesModule::set_fee_schedule(Origin::root(), vec![(0, rate)]))
+    }
+
+    fn propose_trade(self, who: u64, counterparty: u64, give: Vec<u32>, give_tokens: u64, want: Vec<u32>) -> Self {
+        self.call(|| KittiesModule::propose_trade(Origin::signed(who), counterparty, give, give_tokens, want, 0))
+    }
+
+    fn accept_trade(self, who: u64, trade_id: TradeId) -> Self {
+        self.call(|| KittiesModule::accept_trade(Origin::signed(who), trade_id))
+    }
+
+    fn assert_owner(self, kitty_id: u32, who: u64) -> Self {
+        self.step(|| assert_eq!(owner_of(kitty_id), who, "owner of kitty {}", kitty_id))
+    }
+
+    fn assert_kitty_count(self, who: u64, count: usize) -> Self {
+        self.step(|| assert_eq!(orml_nft::TokensByOwner::<Test>::iter_prefix(who).count(), count, "kitties of {}", who))
+    }
+
+    fn assert_price(self, kitty_id: u32, price: Option<u64>) -> Self {
+        self.step(|| assert_eq!(KittiesModule::kitty_prices(kitty_id), price, "price of kitty {}", kitty_id))
+    }
+
+    fn assert_balance(self, who: u64, free: u64) -> Self {
+        self.step(|| assert_eq!(Balances::free_balance(who), free, "free balance of {}", who))
+    }
+
+    fn assert_reserved(self, who: u64, reserved: u64) -> Self {
+        self.step(|| assert_eq!(Balances::reserved_balance(who), reserved, "reserved balance of {}", who))
+    }
+
+    fn assert_activity(self, kind: ActivityKind, count: u64) -> Self {
+        self.step(|| assert_eq!(KittiesModule::activity_counter(kind), count, "{:?} counter", kind))
+    }
+
+    /// Run custom checks.
+    fn check(self, f: impl FnOnce()) -> Self {
+        self.step(f)
+    }
+}
+
+fn owner_of(kitty_id: u32) -> u64 {
+    KittiesModule::owner_of(kitty_id).expect("no such kitty")
+}
+
+/// Invariants that hold after every step of every scenario.
+fn check_invariants() {
+    assert_ok!(KittiesModule::do_try_state());
+
+    // no funds appear or vanish outside of the issuance
+    let held = frame_system::Account::<Test>::iter()
+        .fold(0u64, |sum, (_, account)| sum + account.data.free + account.data.reserved);
+    assert_eq!(held, Balances::total_issuance(), "balances out of sync with the total issuance");
+
+    // the class issuance counts every kitty
+    let class_id = KittiesModule::class_id();
+    let kitties = orml_nft::Tokens::<Test>::iter_prefix(class_id).count() as u32;
+    assert_eq!(Nft::classes(class_id).unwrap().total_issuance, kitties, "class issuance out of sync");
+
+    // listings, quotes and offers are on existing kitties, listings by their owner and the
+    // others not
+    for (kitty_id, listing) in Listings::<Test>::iter() {
+        let token = Nft::tokens(class_id, kitty_id).expect("listing of a missing kitty");
+        assert_eq!(token.owner, listing.seller, "listing by a former owner");
+    }
+    for (kitty_id, quoter, _) in Quotes::<Test>::iter() {
+        let token = Nft::tokens(class_id, kitty_id).expect("quote on a missing kitty");
+        assert_ne!(token.owner, quoter, "quote by the owner");
+    }
+    for (kitty_id, bidder, _) in Offers::<Test>::iter() {
+        let token = Nft::tokens(class_id, kitty_id).expect("offer on a missing kitty");
+        assert_ne!(token.owner, bidder, "offer by the owner");
+    }
+
+    // no slashes happen in scenarios, so pallet reserves are fully backed
+    for (who, reserved) in KittyReserves::<Test>::iter() {
+        assert!(Balances::reserved_balance(who) >= reserved, "kitty reserves of {} not backed", who);
+    }
+}
+
+#[test]
+fn bidders_compete_for_a_kitty() {
+    Scenario::new()
+        .create(100)
+        .create(100)
+        .create(100)
+        .list(100, 0, 100)
+        .list(100, 1, 200)
+        .bid(101, 2, 300, 1_000, 10)
+        .bid(102, 2, 350, 900, 3)
+        // 101 outbids, its earlier bid is released
+        .bid(101, 2, 400, 1_000, 10)
+        .assert_reserved(101, 400)
+        .assert_reserved(102, 350)
+        .settle(2, 101)
+        .assert_owner(2, 101)
+        .assert_price(2, Some(1_000))
+        .assert_balance(100, 10_400)
+        .assert_balance(101, 9_600)
+        .assert_reserved(101, 0)
+        // the losing bid stays until it is cancelled, and can't be hit once expired
+        .advance_blocks(5)
+        .fails_with(Error::<Test>::QuoteExpired)
+        .settle(2, 102)
+        .cancel_bid(102, 2)
+        .assert_balance(102, 10_000)
+        .buy(101, 0, 100)
+        .assert_owner(0, 101)
+        .assert_price(1, Some(200))
+        .assert_kitty_count(100, 1)
+        .assert_balance(100, 10_500)
+        .assert_activity(ActivityKind::Sales, 2);
+}
+
+#[test]
+fn resales_pay_the_creator_royalty() {
+    Scenario::new()
+        .create(100)
+        .list(100, 0, 1_000)
+        .fails_with(Error::<Test>::BuyFromSelf)
+        .buy(100, 0, 1_000)
+        .fails_with(Error::<Test>::PriceTooLow)
+        .buy(101, 0, 999)
+        .buy(101, 0, 1_000)
+        .assert_price(0, None)
+        .fails_with(Error::<Test>::NotForSale)
+        .buy(102, 0, 1_000)
+        .list(101, 0, 2_000)
+        .buy(102, 0, 2_000)
+        .assert_owner(0, 102)
+        // the creator sold without a royalty, then got 10% of the resale
+        .assert_balance(100, 11_200)
+        .assert_balance(101, 10_800)
+        .assert_balance(102, 8_000);
+}
+
+#[test]
+fn market_fee_is_charged_to_sellers() {
+    Scenario::new()
+        .market_fee(Permill::from_percent(10))
+        .create(100)
+        .list(100, 0, 500)
+        .unlist(100, 0)
+        .fails_with(Error::<Test>::NotForSale)
+        .buy(101, 0, 500)
+        .list(100, 0, 500)
+        .buy(101, 0, 500)
+        .assert_balance(100, 10_450)
+        .assert_balance(101, 9_500)
+        .check(|| assert_eq!(Balances::total_issuance(), 30_000 - 50));
+}
+
+#[test]
+fn breeding_across_generations() {
+    Scenario::new()
+        .create(100)
+        .at_extrinsic(1)
+        .create(100)
+        .fails_with(Error::<Test>::SameGenderBreed)
+        .breed(100, 0, 0)
+        .breed(100, 0, 1)
+        .at_extrinsic(3)
+        .breed_for(100, 0, 1, KittyGender::Female)
+        .check(|| {
+            assert_eq!(KittiesModule::generation(2), 1);
+            assert_eq!(KittiesModule::kitties(&100, 3).unwrap().gender(), KittyGender::Female);
+        })
+        .assert_balance(100, 9_990)
+        .transfer(100, 2, 101)
+        .fails_with(Error::<Test>::KittenNotFound)
+        .breed(100, 2, 1)
+        .assert_kitty_count(100, 3)
+        .assert_activity(ActivityKind::Breeds, 2);
+}
+
+#[test]
+fn bundle_trade_swaps_kitties_and_tokens() {
+    Scenario::new()
+        .create(100)
+        .create(100)
+        .create(101)
+        .propose_trade(100, 101, vec![0, 1], 0, vec![2])
+        .fails_with(Error::<Test>::KittyLocked)
+        .transfer(100, 0, 102)
+        .propose_trade(101, 100, vec![], 50, vec![1])
+        .assert_reserved(101, 50)
+        .accept_trade(101, 0)
+        .assert_owner(0, 101)
+        .assert_owner(1, 101)
+        .assert_owner(2, 100)
+        // the second trade wants a kitty 100 no longer has, it is voided although the call
+        // fails
+        .check(|| {
+            assert_eq!(
+                KittiesModule::accept_trade(Origin::signed(100), 1),
+                Err(Error::<Test>::WantedKittyUnavailable.into())
+            )
+        })
+        .assert_reserved(101, 0)
+        .assert_balance(101, 10_000);
+}
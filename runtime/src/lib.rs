@@ -16,8 +16,8 @@ use sp_runtime::traits::{
 };
 use sp_runtime::{
     create_runtime_str, generic, impl_opaque_keys,
-    transaction_validity::{TransactionSource, TransactionValidity},
-    ApplyExtrinsicResult, MultiSignature,
+    transaction_validity::{TransactionPriority, TransactionSource, TransactionValidity},
+    ApplyExtrinsicResult, ModuleId, MultiSignature,
 };
 use sp_std::prelude::*;
 #[cfg(feature = "std")]
@@ -185,7 +185,8 @@ impl frame_system::Trait for Runtime {
     ///
     /// This type is being generated by `construct_runtime!`.
     type PalletInfo = PalletInfo;
-    /// What to do if a new account is created.
+    /// What to do if a new account is created. Faucet testnets can hand out starter kitties
+    /// with `pallet_kitties::MintStarterKitty<Runtime>`.
     type OnNewAccount = ();
     /// What to do if an account is fully reaped from the system.
     type OnKilledAccount = ();
@@ -273,15 +274,154 @@ impl orml_nft::Trait for Runtime {
 	type ClassId = u32;
 	type TokenId = u32;
 	type ClassData = ();
-	type TokenData = pallet_kitties::Kitty;
+	type TokenData = pallet_kitties::VersionedKitty;
 }
 
+/// Id of a token held in `Tokens`, such as a stablecoin kitties are listed in.
+pub type CurrencyId = u32;
+
+impl orml_tokens::Trait for Runtime {
+    type Event = Event;
+    type Balance = Balance;
+    type Amount = i128;
+    type CurrencyId = CurrencyId;
+    type OnReceived = ();
+    type WeightInfo = ();
+}
+
+
+parameter_types! {
+    pub const MaturityPeriod: BlockNumber = HOURS;
+    pub const MaxBreedsPerKitty: u32 = 20;
+    pub const BreedingFee: Balance = 500;
+    pub const GenderPreferenceFee: Balance = 1_000;
+    pub const MutationChance: Perbill = Perbill::from_percent(1);
+    pub const GenderPreferenceChance: Perbill = Perbill::from_percent(50);
+    pub const TokenDecimals: u8 = 12;
+    pub const TokenSymbol: &'static str = "UNIT";
+    pub const MaxSeasons: u32 = 16;
+    pub const RedemptionTtl: BlockNumber = 7 * DAYS;
+    pub const MaxOpenRedemptions: u32 = 100;
+    pub const MinNovelty: u32 = 16;
+    pub const RebreedWindow: BlockNumber = DAYS;
+    pub const EraLength: BlockNumber = 7 * DAYS;
+    pub const MaxOpenPolls: u32 = 10;
+    pub const MaxPollVotes: u32 = 10_000;
+    pub const RareGeneBonus: Balance = 100;
+    pub const RevealDelay: BlockNumber = 1;
+    pub const RevealWindow: BlockNumber = 100;
+    pub const CreationFee: Balance = 1_000;
+    pub const CreationPriceStep: u32 = 0;
+    pub const CommitFee: Balance = 1_000;
+    pub const KittyDeposit: Balance = 1_000;
+    pub const ListingDeposit: Balance = 100;
+    pub const KittiesModuleId: ModuleId = ModuleId(*b"py/kitty");
+    pub const CommitForfeit: Perbill = Perbill::from_percent(50);
+    pub const RentalPeriod: BlockNumber = HOURS;
+    /// A twentieth of the block for settling ended auctions.
+    pub AuctionSettlementBudget: Weight = MaximumBlockWeight::get() / 20;
+    pub const SettlementReward: Balance = 10;
+    pub const RoyaltyRate: Perbill = Perbill::from_percent(5);
+    pub const MaxSaleHistory: u32 = 20;
+    pub const VerboseMetrics: bool = false;
+    pub const LocksBlockBreeding: bool = true;
+    pub const GenerationMilestone: u32 = 10;
+    pub const ImmediateBreedRandomness: bool = true;
+    pub const MaxStarterKittiesPerBlock: u32 = 5;
+    pub const MaxCreatePerBlock: u32 = 50;
+    pub const MaxBreedPerBlock: Option<u32> = None;
+    pub const MaxKittiesPerOwner: u32 = 1_000;
+    pub const MaxKittySupply: Option<u32> = None;
+    pub const KittyBurnPolicy: pallet_kitties::BurnPolicy = pallet_kitties::BurnPolicy::Delete;
+    pub const KittyCustodyPolicy: pallet_kitties::CustodyPolicy = pallet_kitties::CustodyPolicy { pool: true, gift: false };
+    pub const TradeTtl: BlockNumber = 3 * DAYS;
+    pub const MaxTradeKitties: u32 = 8;
+    pub const PoolBreedCooldown: BlockNumber = HOURS;
+    pub const MaxPoolKitties: u32 = 16;
+    pub const KittiesUnsignedPriority: TransactionPriority = TransactionPriority::max_value() / 2;
+}
+
+/// No account is verified until a personhood registry, such as `pallet-identity` judgements,
+/// is part of the runtime, so `claim_starter_kitty` stays closed.
+pub struct NoPersonhoodRegistry;
+impl frame_support::traits::Contains<AccountId> for NoPersonhoodRegistry {
+    fn sorted_members() -> Vec<AccountId> {
+        Vec::new()
+    }
+}
 
 /// Configure the template pallet in pallets/template.
-impl pallet_kitties::Trait for Runtime {
+impl pallet_kitties::Config for Runtime {
     type Event = Event;
     type Randomness = RandomnessCollectiveFlip;
+    // swap for BABE epoch randomness once the chain runs BABE
+    type SecureRandomness = pallet_kitties::ImmediateRandomness<Runtime, RandomnessCollectiveFlip>;
+    type ImmediateBreedRandomness = ImmediateBreedRandomness;
+    type MaxStarterKittiesPerBlock = MaxStarterKittiesPerBlock;
+    type MaxCreatePerBlock = MaxCreatePerBlock;
+    type MaxBreedPerBlock = MaxBreedPerBlock;
+    type MaxKittiesPerOwner = MaxKittiesPerOwner;
+    type MaxKittySupply = MaxKittySupply;
+    type PersonhoodProvider = NoPersonhoodRegistry;
+    type BurnPolicy = KittyBurnPolicy;
+    type CustodyPolicy = KittyCustodyPolicy;
+    type TradeTtl = TradeTtl;
+    type MaxTradeKitties = MaxTradeKitties;
+    type PoolBreedCooldown = PoolBreedCooldown;
+    type MaxPoolKitties = MaxPoolKitties;
+    type PriceOracle = pallet_kitties::SaleMedianOracle<Runtime>;
 	type Currency = Balances;
+	type Assets = Tokens;
+    // fees are burned; a treasury or the block author can take them instead
+    type FeeHandler = ();
+    type KittyHooks = ();
+    type MaturityPeriod = MaturityPeriod;
+    type MaxBreedsPerKitty = MaxBreedsPerKitty;
+    type BreedingFee = BreedingFee;
+    type GenderPreferenceFee = GenderPreferenceFee;
+    type MutationChance = MutationChance;
+    type GenderPreferenceChance = GenderPreferenceChance;
+    type TokenDecimals = TokenDecimals;
+    type TokenSymbol = TokenSymbol;
+    type ForceOrigin = frame_system::EnsureRoot<AccountId>;
+    type AdminOrigin = frame_system::EnsureRoot<AccountId>;
+    type MaxSeasons = MaxSeasons;
+    type FulfillmentOrigin = frame_system::EnsureRoot<AccountId>;
+    type RedemptionTtl = RedemptionTtl;
+    type MaxOpenRedemptions = MaxOpenRedemptions;
+    type MinNovelty = MinNovelty;
+    type RebreedWindow = RebreedWindow;
+    type EraLength = EraLength;
+    type MaxOpenPolls = MaxOpenPolls;
+    type MaxPollVotes = MaxPollVotes;
+    type RareGeneBonus = RareGeneBonus;
+    type RevealDelay = RevealDelay;
+    type RevealWindow = RevealWindow;
+    type CreationFee = CreationFee;
+    type CreationPriceStep = CreationPriceStep;
+    type CommitFee = CommitFee;
+    type KittyDeposit = KittyDeposit;
+    type ListingDeposit = ListingDeposit;
+    type ModuleId = KittiesModuleId;
+    type CommitForfeit = CommitForfeit;
+    type RentalPeriod = RentalPeriod;
+    type AuctionSettlementBudget = AuctionSettlementBudget;
+    type SettlementReward = SettlementReward;
+    type RoyaltyRate = RoyaltyRate;
+    type MaxSaleHistory = MaxSaleHistory;
+    type VerboseMetrics = VerboseMetrics;
+    type LocksBlockBreeding = LocksBlockBreeding;
+    type GenerationMilestone = GenerationMilestone;
+    type UnsignedPriority = KittiesUnsignedPriority;
+    type WeightInfo = weights::pallet_kitties::WeightInfo<Runtime>;
+}
+
+impl<C> frame_system::offchain::SendTransactionTypes<C> for Runtime
+where
+    Call: From<C>,
+{
+    type OverarchingCall = Call;
+    type Extrinsic = UncheckedExtrinsic;
 }
 
 // Create the runtime by composing the FRAME pallets that were previously configured.
@@ -301,13 +441,15 @@ construct_runtime!(
         Sudo: pallet_sudo::{Module, Call, Config<T>, Storage, Event<T>},
         // Include the custom logic from the template pallet in the runtime.
         TemplateModule: pallet_template::{Module, Call, Storage, Event<T>},
-        Kitties: pallet_kitties::{Module, Call, Storage, Event<T>},
+        Kitties: pallet_kitties::{Module, Call, Storage, Event<T>, Config<T>, ValidateUnsigned},
 		NFT: orml_nft::{Module,Storage},
+        Tokens: orml_tokens::{Module, Call, Storage, Event<T>},
     }
 );
 
 /// The address format for describing accounts.
 mod multiaddress;
+mod weights;
 pub type Address = multiaddress::MultiAddress<AccountId, ()>;
 /// Block header type as expected by this runtime.
 pub type Header = generic::Header<BlockNumber, BlakeTwo256>;
@@ -466,6 +608,57 @@ impl_runtime_apis! {
         }
     }
 
+    impl pallet_kitties_runtime_api::KittiesApi<Block, AccountId, u32, Balance, BlockNumber> for Runtime {
+        fn gene_scarcity() -> Vec<pallet_kitties::GeneBuckets> {
+            Kitties::gene_scarcity()
+        }
+
+        fn activity_counters() -> Vec<(pallet_kitties::ActivityKind, u64)> {
+            Kitties::activity_counters()
+        }
+
+        fn operation_cost(
+            call_kind: pallet_kitties::KittyCallKind<u32>,
+        ) -> Option<pallet_kitties::CostBreakdown<Balance>> {
+            Kitties::operation_cost(call_kind)
+        }
+
+        fn account_tax_report(
+            account: AccountId,
+            from: BlockNumber,
+            to: BlockNumber,
+        ) -> pallet_kitties::TaxReport<AccountId, u32, Balance, BlockNumber> {
+            Kitties::account_tax_report(&account, from, to)
+        }
+
+        fn offspring_rarity_distribution(
+            first: [u8; 16],
+            second: [u8; 16],
+        ) -> Vec<(pallet_kitties::RarityTier, Permill)> {
+            Kitties::offspring_rarity_distribution(first, second)
+        }
+
+        fn kitty_traits(kitty_id: u32) -> Option<Vec<pallet_kitties::KittyTrait>> {
+            Kitties::kitty_traits(kitty_id)
+        }
+
+        fn total_kitties() -> u32 {
+            Kitties::total_kitties()
+        }
+
+        fn current_dutch_price(kitty_id: u32) -> Option<Balance> {
+            Kitties::current_dutch_price(kitty_id)
+        }
+
+        fn kitties_of(owner: AccountId, limit: u32) -> Vec<(u32, [u8; 16])> {
+            Kitties::kitties_of(&owner, limit)
+        }
+
+        fn owner_of(kitty_id: u32) -> Option<AccountId> {
+            Kitties::owner_of(kitty_id)
+        }
+    }
+
     #[cfg(feature = "runtime-benchmarks")]
     impl frame_benchmarking::Benchmark<Block> for Runtime {
         fn dispatch_benchmark(
@@ -500,6 +693,7 @@ impl_runtime_apis! {
             add_benchmark!(params, batches, frame_system, SystemBench::<Runtime>);
             add_benchmark!(params, batches, pallet_balances, Balances);
             add_benchmark!(params, batches, pallet_timestamp, Timestamp);
+            add_benchmark!(params, batches, pallet_kitties, Kitties);
 
             if batches.is_empty() { return Err("Benchmark not found for this pallet.".into()) }
             Ok(batches)
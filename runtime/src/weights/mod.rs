@@ -0,0 +1,3 @@
+//! Weights of the pallets of this runtime that were benchmarked for it.
+
+pub mod pallet_kitties;
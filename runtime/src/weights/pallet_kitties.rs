@@ -0,0 +1,43 @@
+//! Weights for pallet_kitties
+//!
+//! Estimated from the storage accesses of each call until the benchmarks are run on reference
+//! hardware. Replace this file with the output of:
+//!
+//! ./target/release/node-template benchmark --chain=dev --steps=50 --repeat=20
+//! --pallet=pallet_kitties --extrinsic='*' --execution=wasm --wasm-execution=compiled
+//! --heap-pages=4096 --output=./runtime/src/weights/pallet_kitties.rs
+
+#![allow(unused_parens)]
+#![allow(unused_imports)]
+
+use frame_support::{traits::Get, weights::Weight};
+use sp_std::marker::PhantomData;
+
+pub struct WeightInfo<T>(PhantomData<T>);
+impl<T: frame_system::Trait> pallet_kitties::WeightInfo for WeightInfo<T> {
+	fn create() -> Weight {
+		(96_000_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(14 as Weight))
+			.saturating_add(T::DbWeight::get().writes(15 as Weight))
+	}
+	fn breed() -> Weight {
+		(164_000_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(27 as Weight))
+			.saturating_add(T::DbWeight::get().writes(20 as Weight))
+	}
+	fn transfer() -> Weight {
+		(72_000_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(11 as Weight))
+			.saturating_add(T::DbWeight::get().writes(12 as Weight))
+	}
+	fn set_price() -> Weight {
+		(41_000_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(6 as Weight))
+			.saturating_add(T::DbWeight::get().writes(5 as Weight))
+	}
+	fn buy() -> Weight {
+		(124_000_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(18 as Weight))
+			.saturating_add(T::DbWeight::get().writes(16 as Weight))
+	}
+}
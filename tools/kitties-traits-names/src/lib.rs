@@ -0,0 +1,69 @@
+//! Names of kitty traits, for clients only.
+//!
+//! The runtime exposes traits as numbers, see `pallet_kitties::KittyTrait`, so on-chain data
+//! doesn't depend on a language. Clients resolve them here against a versioned table per
+//! language, and should report the table version along with the names.
+
+use pallet_kitties::KittyTrait;
+
+/// Version of the name tables. Bumped whenever a name changes meaning.
+pub const TABLE_VERSION: u32 = 1;
+
+/// Names of one language.
+pub struct NameTable {
+	pub version: u32,
+	/// BCP 47 language tag.
+	pub language: &'static str,
+	/// Name of each slot, by DNA position.
+	pub slots: [&'static str; 16],
+	/// Name of each value, by `GenePool` bucket.
+	pub values: [&'static str; 16],
+	/// Name of each tier.
+	pub tiers: [&'static str; 2],
+}
+
+/// Resolved names of a trait.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct TraitName {
+	pub slot: String,
+	pub value: String,
+	pub tier: String,
+}
+
+pub const EN: NameTable = NameTable {
+	version: TABLE_VERSION,
+	language: "en",
+	slots: [
+		"Body", "Fur", "Pattern", "Eye shape", "Eye colour", "Base colour", "Highlight colour",
+		"Accent colour", "Mouth", "Ears", "Tail", "Whiskers", "Paws", "Environment", "Secret",
+		"Purrstige",
+	],
+	values: [
+		"Ash", "Smoke", "Slate", "Dove", "Sand", "Honey", "Amber", "Copper", "Rust", "Rose", "Plum",
+		"Violet", "Cobalt", "Teal", "Moss", "Gold",
+	],
+	tiers: ["Common", "Scarce"],
+};
+
+/// Every table, first one the default.
+pub const TABLES: &[NameTable] = &[EN];
+
+/// Table of `language`, falling back to the default one.
+pub fn table(language: &str) -> &'static NameTable {
+	TABLES.iter().find(|table| table.language.eq_ignore_ascii_case(language)).unwrap_or(&TABLES[0])
+}
+
+impl NameTable {
+	/// Names of `kitty_trait`. Numbers missing from the table, such as slots added to the
+	/// runtime later, are named by their number.
+	pub fn resolve(&self, kitty_trait: &KittyTrait) -> TraitName {
+		fn name(names: &[&'static str], kind: &str, index: u8) -> String {
+			names.get(index as usize).map(|name| name.to_string()).unwrap_or_else(|| format!("{} {}", kind, index))
+		}
+		TraitName {
+			slot: name(&self.slots, "Slot", kitty_trait.slot),
+			value: name(&self.values, "Value", kitty_trait.value),
+			tier: name(&self.tiers, "Tier", kitty_trait.tier),
+		}
+	}
+}
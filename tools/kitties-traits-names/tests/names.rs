@@ -0,0 +1,33 @@
+use kitties_traits_names::{table, TraitName, EN, TABLES, TABLE_VERSION};
+use pallet_kitties::KittyTrait;
+
+#[test]
+fn resolves_every_trait() {
+	for names in TABLES {
+		assert_eq!(names.version, TABLE_VERSION);
+		for slot in 0..=u8::max_value() {
+			for value in 0..=u8::max_value() {
+				let name = names.resolve(&KittyTrait { slot, value, tier: value });
+				assert!(!name.slot.is_empty() && !name.value.is_empty() && !name.tier.is_empty());
+			}
+		}
+	}
+}
+
+#[test]
+fn names_known_traits_and_numbers_the_rest() {
+	assert_eq!(
+		EN.resolve(&KittyTrait { slot: 1, value: 15, tier: 1 }),
+		TraitName { slot: "Fur".into(), value: "Gold".into(), tier: "Scarce".into() }
+	);
+	assert_eq!(
+		EN.resolve(&KittyTrait { slot: 16, value: 16, tier: 2 }),
+		TraitName { slot: "Slot 16".into(), value: "Value 16".into(), tier: "Tier 2".into() }
+	);
+}
+
+#[test]
+fn unknown_languages_fall_back_to_the_default_table() {
+	assert_eq!(table("EN").language, "en");
+	assert_eq!(table("xx").language, TABLES[0].language);
+}
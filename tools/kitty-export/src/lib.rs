@@ -0,0 +1,121 @@
+//! Export of the kitties held in a raw state dump.
+//!
+//! The dump is loaded into in-memory externalities and read back through the storage items
+//! of `pallet-kitties` and `orml-nft`, so keys and values are decoded exactly as the runtime
+//! encodes them.
+
+use std::fmt::Display;
+use std::io::{self, Write};
+
+use serde::Serialize;
+use sp_core::{bytes, storage::Storage};
+use sp_runtime::SaturatedConversion;
+use sp_state_machine::BasicExternalities;
+
+/// One exported kitty.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize)]
+pub struct KittyRecord {
+	pub id: u64,
+	pub owner: String,
+	/// DNA as 0x-prefixed hex.
+	pub dna: String,
+	pub gender: String,
+	/// Listing price as a decimal string, so it survives JSON number precision.
+	pub price: Option<String>,
+	/// NFT metadata as 0x-prefixed hex.
+	pub metadata: String,
+}
+
+/// Parse a state dump: a raw chain spec, a `{ key: value }` object, or the `[key, value]`
+/// pairs returned by the `state_getPairs` RPC, with or without the JSON-RPC envelope.
+pub fn parse_state(json: &str) -> Result<Storage, String> {
+	let value: serde_json::Value = serde_json::from_str(json).map_err(|e| e.to_string())?;
+	let pairs = value.pointer("/genesis/raw/top")
+		.or_else(|| value.get("result"))
+		.unwrap_or(&value);
+
+	let mut storage = Storage::default();
+	let mut insert = |key: &serde_json::Value, value: &serde_json::Value| -> Result<(), String> {
+		let key = key.as_str().ok_or("storage key is not a string")?;
+		let value = value.as_str().ok_or("storage value is not a string")?;
+		storage.top.insert(
+			bytes::from_hex(key).map_err(|e| format!("{}: {:?}", key, e))?,
+			bytes::from_hex(value).map_err(|e| format!("{}: {:?}", key, e))?,
+		);
+		Ok(())
+	};
+	match pairs {
+		serde_json::Value::Object(map) => {
+			for (key, value) in map {
+				insert(&serde_json::Value::String(key.clone()), value)?;
+			}
+		}
+		serde_json::Value::Array(list) => {
+			for pair in list {
+				match pair.as_array().map(|pair| pair.as_slice()) {
+					Some([key, value]) => insert(key, value)?,
+					_ => return Err("storage pair is not a [key, value] array".into()),
+				}
+			}
+		}
+		_ => return Err("unknown state dump format".into()),
+	}
+	Ok(storage)
+}
+
+/// Write the kitties of `storage` to `out` as a JSON array, one kitty at a time, optionally
+/// only those of `owner`. Returns the number of kitties written.
+pub fn export<T, W>(storage: Storage, owner: Option<&T::AccountId>, mut out: W) -> io::Result<usize>
+where
+	T: pallet_kitties::Config,
+	T::AccountId: Display,
+	W: Write,
+{
+	let mut ext = BasicExternalities::new(storage);
+	ext.execute_with(|| {
+		let class_id = pallet_kitties::Module::<T>::class_id();
+		let mut count = 0;
+
+		out.write_all(b"[")?;
+		let mut write = |kitty_id: pallet_kitties::KittyIndexOf<T>| -> io::Result<()> {
+			let info = match orml_nft::Tokens::<T>::get(class_id, kitty_id) {
+				Some(info) => info,
+				None => return Ok(()),
+			};
+			let kitty = info.data.kitty();
+			let record = KittyRecord {
+				id: kitty_id.saturated_into(),
+				owner: info.owner.to_string(),
+				dna: bytes::to_hex(&kitty.dna(), false),
+				gender: format!("{:?}", kitty.gender()),
+				price: pallet_kitties::Module::<T>::kitty_prices(kitty_id)
+					.map(|price| price.saturated_into::<u128>().to_string()),
+				metadata: bytes::to_hex(&info.metadata, false),
+			};
+			out.write_all(if count == 0 { b"\n" } else { b",\n" })?;
+			serde_json::to_writer(&mut out, &record)?;
+			count += 1;
+			Ok(())
+		};
+
+		match owner {
+			Some(owner) => {
+				for ((class, kitty_id), _) in orml_nft::TokensByOwner::<T>::iter_prefix(owner) {
+					if class == class_id {
+						write(kitty_id)?;
+					}
+				}
+			}
+			None => {
+				for (kitty_id, _) in orml_nft::Tokens::<T>::iter_prefix(class_id) {
+					write(kitty_id)?;
+				}
+			}
+		}
+		drop(write);
+
+		out.write_all(b"\n]\n")?;
+		out.flush()?;
+		Ok(count)
+	})
+}
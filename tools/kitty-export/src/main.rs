@@ -0,0 +1,51 @@
+//! Command line front end of the kitty export, using the node template runtime types.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+
+use node_template_runtime::{AccountId, Runtime};
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "kitty-export", about = "Export the kitties of a state dump to JSON.")]
+struct Opt {
+	/// State dump: a raw chain spec or the JSON output of `state_getPairs`.
+	#[structopt(parse(from_os_str))]
+	state: PathBuf,
+	/// Only export the kitties of this account (SS58 or hex).
+	#[structopt(long)]
+	owner: Option<AccountId>,
+	/// Output file, stdout if omitted.
+	#[structopt(long, short, parse(from_os_str))]
+	output: Option<PathBuf>,
+}
+
+fn main() {
+	let opt = Opt::from_args();
+
+	let json = std::fs::read_to_string(&opt.state).unwrap_or_else(|e| {
+		eprintln!("Cannot read {}: {}", opt.state.display(), e);
+		std::process::exit(1);
+	});
+	let storage = kitty_export::parse_state(&json).unwrap_or_else(|e| {
+		eprintln!("Invalid state dump: {}", e);
+		std::process::exit(1);
+	});
+
+	let out: Box<dyn Write> = match &opt.output {
+		Some(path) => Box::new(File::create(path).unwrap_or_else(|e| {
+			eprintln!("Cannot create {}: {}", path.display(), e);
+			std::process::exit(1);
+		})),
+		None => Box::new(io::stdout()),
+	};
+
+	match kitty_export::export::<Runtime, _>(storage, opt.owner.as_ref(), BufWriter::new(out)) {
+		Ok(count) => eprintln!("Exported {} kitties", count),
+		Err(e) => {
+			eprintln!("Export failed: {}", e);
+			std::process::exit(1);
+		}
+	}
+}
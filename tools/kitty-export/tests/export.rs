@@ -0,0 +1,88 @@
+use node_template_runtime::{AccountId, Runtime};
+use pallet_kitties::VersionedKitty;
+use sp_core::{bytes, storage::Storage};
+use sp_state_machine::BasicExternalities;
+
+const KITTIES: u32 = 300;
+
+fn owner(index: u32) -> AccountId {
+	AccountId::from([(index % 3) as u8; 32])
+}
+
+/// Genesis state with `KITTIES` kitties split between three owners. Every tenth kitty is
+/// listed for sale.
+fn state() -> Storage {
+	let mut storage = Storage::default();
+	pallet_kitties::GenesisConfig::<Runtime>::default().assimilate_storage(&mut storage).unwrap();
+
+	BasicExternalities::execute_with_storage(&mut storage, || {
+		let class_id = pallet_kitties::Module::<Runtime>::class_id();
+		for index in 0..KITTIES {
+			let mut dna = [0u8; 16];
+			dna[..4].copy_from_slice(&index.to_le_bytes());
+			// old layout, as on a chain that wasn't upgraded yet
+			let kitty = VersionedKitty::V1(dna);
+			let kitty_id = orml_nft::Module::<Runtime>::mint(&owner(index), class_id, vec![index as u8], kitty).unwrap();
+			if index % 10 == 0 {
+				let listing = pallet_kitties::Listing {
+					seller: owner(index),
+					price: u128::from(index) * 1_000_000_000_000_000,
+					expires_at: None,
+					deposit: 0,
+					asset: None,
+				};
+				pallet_kitties::Listings::<Runtime>::insert(kitty_id, listing);
+			}
+		}
+	});
+
+	storage
+}
+
+fn export(storage: Storage, owner: Option<&AccountId>) -> Vec<serde_json::Value> {
+	let mut out = Vec::new();
+	let count = kitty_export::export::<Runtime, _>(storage, owner, &mut out).unwrap();
+	let records: Vec<serde_json::Value> = serde_json::from_slice(&out).unwrap();
+	assert_eq!(records.len(), count);
+	records
+}
+
+#[test]
+fn exports_every_kitty() {
+	let records = export(state(), None);
+	assert_eq!(records.len(), KITTIES as usize);
+
+	let record = records.iter().find(|record| record["id"] == 20).unwrap();
+	assert_eq!(record["owner"], owner(20).to_string());
+	assert_eq!(record["dna"], "0x14000000000000000000000000000000");
+	assert_eq!(record["gender"], "Male");
+	assert_eq!(record["price"], "20000000000000000000");
+	assert_eq!(record["metadata"], "0x14");
+
+	let record = records.iter().find(|record| record["id"] == 21).unwrap();
+	assert_eq!(record["gender"], "Female");
+	assert!(record["price"].is_null());
+}
+
+#[test]
+fn filters_by_owner() {
+	let records = export(state(), Some(&owner(1)));
+	assert_eq!(records.len(), KITTIES as usize / 3);
+	assert!(records.iter().all(|record| record["owner"] == owner(1).to_string()));
+	assert!(records.iter().all(|record| record["id"].as_u64().unwrap() % 3 == 1));
+}
+
+#[test]
+fn reads_state_get_pairs_output() {
+	let storage = state();
+	let pairs: Vec<_> = storage.top.iter()
+		.map(|(key, value)| vec![bytes::to_hex(key, false), bytes::to_hex(value, false)])
+		.collect();
+	let json = serde_json::json!({ "jsonrpc": "2.0", "result": pairs, "id": 1 }).to_string();
+
+	let parsed = kitty_export::parse_state(&json).unwrap();
+	assert_eq!(parsed.top, storage.top);
+	assert_eq!(export(parsed, None).len(), KITTIES as usize);
+
+	assert!(kitty_export::parse_state("[[\"0x00\"]]").is_err());
+}